@@ -1,5 +1,6 @@
 use crate::gtp::{gtp::*, gtp_ie::*, gtpv2_types::*};
 use crate::gtp_call_flow::*;
+use crate::types::*;
 
 pub async fn make_mock_callflow()
 -> Vec<CallFlow>
@@ -13,8 +14,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 1,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.2.72".into(), // UE / MME
         dst_addr: "10.10.1.71".into(), // SGW
+        src_name: None,
+        dst_name: None,
         message: "Create Session Request".into(),
         ebi: None,
         bearer: Some(vec![ Bearer {
@@ -26,8 +30,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 2,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.3.73".into(), // PGW
+        src_name: None,
+        dst_name: None,
         message: "Create Session Request".into(),
         ebi: None,
         bearer: Some(vec![ Bearer {
@@ -46,8 +53,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 3,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.3.73".into(),
         dst_addr: "10.10.1.71".into(),
+        src_name: None,
+        dst_name: None,
         message: "Create Session Response".into(),
         ebi: None,
         bearer: Some(vec![Bearer {
@@ -66,8 +76,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 4,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.2.72".into(),
+        src_name: None,
+        dst_name: None,
         message: "Create Session Response".into(),
         ebi: None,
         bearer: Some(vec![Bearer {
@@ -87,8 +100,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 5,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.2.72".into(),
         dst_addr: "10.10.1.71".into(),
+        src_name: None,
+        dst_name: None,
         message: "Modify Bearer Request".into(),
         ebi: None,
         bearer: Some(vec![Bearer {
@@ -107,8 +123,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 6,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.2.72".into(),
+        src_name: None,
+        dst_name: None,
         message: "Modify Bearer Response".into(),
         ebi: None,
         bearer: Some(vec![Bearer {
@@ -132,8 +151,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 7,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.3.73".into(),
         dst_addr: "10.10.1.71".into(),
+        src_name: None,
+        dst_name: None,
         message: "Create Bearer Request".into(),
         ebi: Some(5), //LBi
         bearer: Some(vec![
@@ -182,8 +204,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 8,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.2.72".into(),
+        src_name: None,
+        dst_name: None,
         message: "Create Bearer Request".into(),
         ebi: Some(5), //LBi
         bearer: Some(
@@ -255,8 +280,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 9,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.2.72".into(),
         dst_addr: "10.10.1.71".into(),
+        src_name: None,
+        dst_name: None,
         message: "Create Bearer Response".into(),
         ebi: None, //LBi
         bearer: Some(
@@ -331,8 +359,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 10,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.3.73".into(),
+        src_name: None,
+        dst_name: None,
         message: "Create Bearer Response".into(),
         ebi: None, //LBi
         bearer: Some(
@@ -409,8 +440,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 21,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.2.72".into(), // UE / MME
         dst_addr: "10.10.1.71".into(), // SGW
+        src_name: None,
+        dst_name: None,
         message: "Create Session Request".into(),
         ebi: None,
         bearer: Some(vec![ Bearer {
@@ -422,8 +456,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 22,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.3.73".into(),
+        src_name: None,
+        dst_name: None,
         message: "Create Session Request".into(),
         ebi: None,
         bearer: Some(vec![ Bearer {
@@ -442,8 +479,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 23,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.3.73".into(),
         dst_addr: "10.10.1.71".into(),
+        src_name: None,
+        dst_name: None,
         message: "Create Session Response".into(),
         ebi: None,
         bearer: Some(vec![Bearer {
@@ -462,8 +502,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 24,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.2.72".into(),
+        src_name: None,
+        dst_name: None,
         message: "Create Session Response".into(),
         ebi: None,
         bearer: Some(vec![Bearer {
@@ -481,8 +524,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 25,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.2.72".into(),
         dst_addr: "10.10.1.71".into(),
+        src_name: None,
+        dst_name: None,
         message: "Modify Bearer Request".into(),
         ebi: None,
         bearer: Some(vec![Bearer {
@@ -501,8 +547,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 26,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.2.72".into(),
+        src_name: None,
+        dst_name: None,
         message: "Modify Bearer Response".into(),
         ebi: None,
         bearer: Some(vec![Bearer {
@@ -525,8 +574,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 31,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.3.73".into(),
         dst_addr: "10.10.1.71".into(),
+        src_name: None,
+        dst_name: None,
         message: "Delete Bearer Request".into(),
         ebi: Some(10),
         bearer: None,
@@ -535,8 +587,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 32,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.2.72".into(),
+        src_name: None,
+        dst_name: None,
         message: "Delete Bearer Request".into(),
         ebi: Some(10),
         bearer: None,
@@ -546,8 +601,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 33,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.2.72".into(),
         dst_addr: "10.10.1.71".into(),
+        src_name: None,
+        dst_name: None,
         message: "Delete Bearer Response".into(),
         ebi: None,
         bearer: Some (vec![
@@ -560,8 +618,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 34,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.3.73".into(),
+        src_name: None,
+        dst_name: None,
         message: "Delete Bearer Response".into(),
         ebi: None,
         bearer: Some (vec![
@@ -576,8 +637,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 35,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.2.72".into(),
         dst_addr: "10.10.1.71".into(),
+        src_name: None,
+        dst_name: None,
         message: "Delete Session Request".into(),
         ebi: None,
         bearer: None,
@@ -586,8 +650,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 36,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.3.73".into(),
+        src_name: None,
+        dst_name: None,
         message: "Delete Session Request".into(),
         ebi: None,
         bearer: None,
@@ -596,8 +663,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 37,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.3.73".into(),
         dst_addr: "10.10.1.71".into(),
+        src_name: None,
+        dst_name: None,
         message: "Delete Session Response".into(),
         ebi: None,
         bearer: None,
@@ -606,8 +676,11 @@ pub async fn make_mock_callflow()
     v.push(CallFlow {
         id: 38,
         timestamp: String::new(),
+        time: PacketTime::new(),
         src_addr: "10.10.1.71".into(),
         dst_addr: "10.10.2.72".into(),
+        src_name: None,
+        dst_name: None,
         message: "Delete Session Response".into(),
         ebi: None,
         bearer: None,
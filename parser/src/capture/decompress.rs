@@ -0,0 +1,115 @@
+use std::io::{BufRead, BufReader, Cursor, Read};
+
+use flate2::read::MultiGzDecoder;
+use lzma_rust2::XzReader;
+use ruzstd::decoding::{FrameDecoder, StreamingDecoder};
+
+pub const GZIP_MAGIC: [u8; 2]   = [0x1f, 0x8b];
+pub const ZSTD_MAGIC: [u8; 4]   = [0x28, 0xb5, 0x2f, 0xfd];
+pub const XZ_MAGIC: [u8; 6]     = [0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00];
+
+const MAGIC_PEEK_LEN: usize     = 6;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+pub fn detect_compression(head: &[u8]) -> Compression
+{
+    if head.starts_with(&GZIP_MAGIC) {
+        Compression::Gzip
+    }
+    else if head.starts_with(&ZSTD_MAGIC) {
+        Compression::Zstd
+    }
+    else if head.starts_with(&XZ_MAGIC) {
+        Compression::Xz
+    }
+    else {
+        Compression::None
+    }
+}
+
+
+/// Wrap `input` in the decoder its leading magic asks for, so a gzip, zstd or
+/// xz compressed capture reads like the plain pcap/pcapng inside it.
+pub fn decompress_reader<R: Read + Send + 'static>(mut input: R)
+-> Result<Box<dyn Read + Send>, String>
+{
+    // peek the magic, then put it back in front of the rest of the stream
+    let mut head = Vec::with_capacity(MAGIC_PEEK_LEN);
+    (&mut input).take(MAGIC_PEEK_LEN as u64).read_to_end(&mut head)
+        .map_err(|e| format!("Capture read error: {}", e))?;
+
+    let compression = detect_compression(&head);
+    let input = Cursor::new(head).chain(input);
+
+    let reader: Box<dyn Read + Send> = match compression {
+        Compression::None => Box::new(input),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(input)),
+        Compression::Zstd => Box::new(ZstdReader::new(input)),
+        Compression::Xz => Box::new(XzReader::new(input, true)),
+    };
+
+    Ok(reader)
+}
+
+
+/// zstd decoder that keeps going across concatenated frames (`zstd -T`, pzstd).
+struct ZstdReader<R: Read> {
+    source: Option<BufReader<R>>,
+    frame: Option<StreamingDecoder<BufReader<R>, FrameDecoder>>,
+}
+
+impl<R: Read> ZstdReader<R> {
+    fn new(input: R) -> Self {
+        ZstdReader {
+            source: Some(BufReader::new(input)),
+            frame: None,
+        }
+    }
+
+    /// Start decoding the next frame. Returns false at the end of input.
+    fn next_frame(&mut self) -> std::io::Result<bool> {
+        let Some(mut source) = self.source.take() else {
+            return Ok(false);
+        };
+
+        if source.fill_buf()?.is_empty() {
+            return Ok(false);
+        }
+
+        let frame = StreamingDecoder::new(source)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData,
+                format!("zstd: {}", e)))?;
+        self.frame = Some(frame);
+
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for ZstdReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if let Some(frame) = self.frame.as_mut() {
+                let n = frame.read(buf)?;
+                if n > 0 || buf.is_empty() {
+                    return Ok(n);
+                }
+
+                // frame finished: hand the source back for the next one
+                self.source = self.frame.take().map(|f| f.into_inner());
+            }
+
+            if !self.next_frame()? {
+                return Ok(0);
+            }
+        }
+    }
+}
+
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::capture::pcap::*;
+use crate::capture::pcapng::*;
+use crate::capture::reader::*;
+use crate::types::{Conversation, DecodeAs, SctpAssociation, SctpUserMessage, TcpAnalysis, TcpHandshake};
+use crate::dissect::StreamChunk;
+
+
+/// Location of one packet inside the capture file.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordIndex {
+    pub offset: u64,
+    pub len: u32,
+    pub section: usize,     // index into the pcapng section list
+}
+
+#[derive(Debug, Clone)]
+pub enum CaptureFormat {
+    Pcap(PcapHeader),
+    // (reader section number, byte order and interfaces of that section)
+    Pcapng(Vec<(usize, PcapngSection)>),
+}
+
+/// Per-file packet index built while the summary is parsed, so single packets
+/// can be decoded again by seeking instead of walking the whole capture.
+#[derive(Debug, Clone)]
+pub struct CaptureIndex {
+    pub format: CaptureFormat,
+    pub records: Vec<RecordIndex>,
+    // packet that completes a fragmented datagram -> ids of all its fragments
+    pub reassembled: HashMap<usize, Vec<usize>>,
+    // ICMP/ICMPv6 error -> packet it quotes
+    pub icmp_errors: HashMap<usize, usize>,
+    // packet whose TCP segment completed PDUs -> the payload bytes of each
+    pub tcp_pdus: HashMap<usize, Vec<Vec<StreamChunk>>>,
+    // TCP segments the stream analysis flagged
+    pub tcp_analysis: HashMap<usize, Vec<TcpAnalysis>>,
+    // TCP packet -> index of its connection in `tcp_handshakes`
+    pub tcp_streams: HashMap<usize, usize>,
+    pub tcp_handshakes: Vec<TcpHandshake>,
+    // packet whose DATA chunks completed user messages -> each message and its chunks
+    pub sctp_messages: HashMap<usize, Vec<(SctpUserMessage, Vec<StreamChunk>)>>,
+    // SCTP packet -> index of its association in `sctp_associations`
+    pub sctp_assocs: HashMap<usize, usize>,
+    pub sctp_associations: Vec<SctpAssociation>,
+    // TCP/UDP/SCTP conversations, in the order first seen
+    pub conversations: Vec<Conversation>,
+    // "Decode As" rules the summary was parsed with; re-decoding uses the same
+    pub decode_as: DecodeAs,
+}
+
+impl CaptureIndex {
+    pub fn new(format: CaptureFormat) -> Self {
+        CaptureIndex {
+            format,
+            records: Vec::new(),
+            reassembled: HashMap::new(),
+            icmp_errors: HashMap::new(),
+            tcp_pdus: HashMap::new(),
+            tcp_analysis: HashMap::new(),
+            tcp_streams: HashMap::new(),
+            tcp_handshakes: Vec::new(),
+            sctp_messages: HashMap::new(),
+            sctp_assocs: HashMap::new(),
+            sctp_associations: Vec::new(),
+            conversations: Vec::new(),
+            decode_as: DecodeAs::default(),
+        }
+    }
+
+    /// Read packet `id` (1-based, as shown in the summary) from an open capture file.
+    pub fn read_record<R: Read + Seek>(&self, file: &mut R, id: usize)
+    -> Result<CaptureRecord, String>
+    {
+        let entry = id.checked_sub(1)
+            .and_then(|i| self.records.get(i))
+            .ok_or("Packet not found".to_string())?;
+
+        file.seek(SeekFrom::Start(entry.offset))
+            .map_err(|e| format!("Failed to seek to packet {}: {}", id, e))?;
+
+        let mut buf = vec![0u8; entry.len as usize];
+        file.read_exact(&mut buf)
+            .map_err(|e| format!("Failed to read packet {}: {}", id, e))?;
+
+        let mut record = match &self.format {
+            CaptureFormat::Pcap(header) => parse_pcap_record(header, &buf)?,
+
+            CaptureFormat::Pcapng(sections) => {
+                let (_, section) = sections.get(entry.section)
+                    .ok_or("Packet section not found".to_string())?;
+
+                PcapngReader::resume(Cursor::new(buf), section)
+                    .next_record()?
+                    .ok_or("Packet not found".to_string())?
+            },
+        };
+
+        record.file_offset = entry.offset;
+        record.record_len = entry.len;
+
+        Ok(record)
+    }
+}
+
+
+/// Read the given packets (1-based ids) through the index, in the order given.
+pub fn read_indexed_records(path: &Path, index: &CaptureIndex, ids: &[usize])
+-> Result<Vec<(usize, CaptureRecord)>, String>
+{
+    let file = File::open(path)
+        .map_err(|e| format!("Failed to open capture file {}: {}", path.to_string_lossy(), e))?;
+    let mut file = BufReader::new(file);
+
+    let mut records = Vec::with_capacity(ids.len());
+    for &id in ids {
+        records.push((id, index.read_record(&mut file, id)?));
+    }
+
+    Ok(records)
+}
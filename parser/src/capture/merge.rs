@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::capture::index::CaptureIndex;
+use crate::capture::reader::*;
+use crate::capture::writer::*;
+
+const NSEC_PER_SEC: i128 = 1_000_000_000;
+
+
+/// One already uploaded capture taking part in a merge.
+pub struct MergeSource {
+    pub name: String,
+    pub path: PathBuf,
+    pub index: Arc<CaptureIndex>,
+    pub clock_offset_ns: i64,   // added to every timestamp of this capture
+}
+
+pub struct MergeStats {
+    pub packets: usize,
+    pub duplicates: usize,
+}
+
+
+fn record_time_ns(record: &CaptureRecord, clock_offset_ns: i64) -> i128
+{
+    record.ts_sec as i128 * NSEC_PER_SEC
+        + record.ts_nsec as i128
+        + clock_offset_ns as i128
+}
+
+
+/// Interface name in the merged file: the source capture, then its own interface.
+fn merged_interface_name(source: &MergeSource, record: &CaptureRecord) -> String
+{
+    if record.interface_name.is_empty() {
+        source.name.clone()
+    }
+    else {
+        format!("{}:{}", source.name, record.interface_name)
+    }
+}
+
+
+/// (shifted time, source no, packet id) of every indexed packet of the
+/// sources, in merge order. A truncated tail past the index is left out.
+fn merge_order(sources: &[MergeSource]) -> Result<Vec<(i128, usize, usize)>, String>
+{
+    let mut order = Vec::new();
+
+    for (src_no, source) in sources.iter().enumerate() {
+        let file = File::open(&source.path)
+            .map_err(|e| format!("Failed to open {}: {}", source.name, e))?;
+        let mut reader = CaptureReader::new(BufReader::new(file))?;
+
+        for id in 1..=source.index.records.len() {
+            let Some(record) = reader.next_record()? else {
+                break;
+            };
+            let ts = record_time_ns(&record, source.clock_offset_ns);
+            if ts < 0 {
+                return Err(format!("Clock offset of {} moves packet {} before 1970", source.name, id));
+            }
+            order.push((ts, src_no, id));
+        }
+    }
+
+    // ties keep upload order, then file order
+    order.sort_unstable();
+    Ok(order)
+}
+
+
+/// Write the packets of all `sources` to a new pcapng file at `out`, in
+/// timestamp order after each source's clock offset. The sources need not
+/// be in time order themselves: their timestamps are sorted first and the
+/// packets then read through each source's index. Packets with the same
+/// (shifted) timestamp and identical bytes are written only once.
+pub fn merge_captures(sources: &[MergeSource], out: &Path) -> Result<MergeStats, String>
+{
+    let order = merge_order(sources)?;
+
+    let mut inputs = sources.iter()
+        .map(|source| File::open(&source.path)
+            .map(BufReader::new)
+            .map_err(|e| format!("Failed to open {}: {}", source.name, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let file = File::create(out)
+        .map_err(|e| format!("Failed to create merged capture: {}", e))?;
+    let mut writer = PcapngWriter::new(BufWriter::new(file))?;
+
+    // (source no, source interface id) -> merged interface id
+    let mut interfaces: HashMap<(usize, u32), u32> = HashMap::new();
+    let mut stats = MergeStats { packets: 0, duplicates: 0 };
+    // packets written with the current timestamp, to catch duplicates from the other sources
+    let mut run_ts = None;
+    let mut run: Vec<(usize, Vec<u8>)> = Vec::new();
+
+    for (ts, src_no, id) in order {
+        let record = sources[src_no].index.read_record(&mut inputs[src_no], id)?;
+
+        if run_ts != Some(ts) {
+            run_ts = Some(ts);
+            run.clear();
+        }
+        if run.iter().any(|(orig_len, data)| *orig_len == record.orig_len && *data == record.data) {
+            stats.duplicates += 1;
+            continue;
+        }
+
+        let key = (src_no, record.interface_id);
+        let if_id = match interfaces.get(&key) {
+            Some(id) => *id,
+            None => {
+                let name = merged_interface_name(&sources[src_no], &record);
+                let id = writer.add_interface(record.link_type, &name)?;
+                interfaces.insert(key, id);
+                id
+            },
+        };
+
+        // merge_order() turned away anything before 1970
+        writer.write_packet(if_id, ts as u64, &record)?;
+        stats.packets += 1;
+        run.push((record.orig_len, record.data));
+    }
+
+    writer.finish()?;
+
+    Ok(stats)
+}
@@ -0,0 +1,11 @@
+pub mod decompress;
+pub mod index;
+pub mod merge;
+pub mod pcap;
+pub mod pcapng;
+pub mod reader;
+pub mod stream;
+pub mod writer;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,131 @@
+use std::io::Read;
+
+use crate::capture::reader::*;
+
+// [ https://www.tcpdump.org/manpages/pcap-savefile.5.html ]
+pub const PCAP_MAGIC_USEC: u32          = 0xa1b2c3d4;
+pub const PCAP_MAGIC_NSEC: u32          = 0xa1b23c4d;
+
+pub const PCAP_FILE_HDR_LEN: usize      = 24;
+pub const PCAP_RECORD_HDR_LEN: usize    = 16;
+
+const MAX_RECORD_LEN: usize             = 64 * 1024 * 1024;
+
+
+#[derive(Debug, Clone, Copy)]
+pub struct PcapHeader {
+    pub big_endian: bool,
+    pub nanosecond: bool,
+    pub snap_len: u32,
+    pub link_type: u16,
+}
+
+
+fn read_u32(buf: &[u8], big_endian: bool) -> u32
+{
+    let b = [buf[0], buf[1], buf[2], buf[3]];
+    if big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }
+}
+
+
+pub fn parse_pcap_header(buf: &[u8]) -> Result<PcapHeader, String>
+{
+    if buf.len() < PCAP_FILE_HDR_LEN {
+        return Err("Truncated pcap file header".to_string());
+    }
+
+    let magic = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let (big_endian, nanosecond) = match magic {
+        PCAP_MAGIC_USEC => (false, false),
+        PCAP_MAGIC_NSEC => (false, true),
+        _ if magic.swap_bytes() == PCAP_MAGIC_USEC => (true, false),
+        _ if magic.swap_bytes() == PCAP_MAGIC_NSEC => (true, true),
+        _ => return Err(format!("Unknown capture file magic 0x{:08x}", magic)),
+    };
+
+    let snap_len = read_u32(&buf[16..], big_endian);
+    // upper bits of the link type field carry FCS information
+    let link_type = (read_u32(&buf[20..], big_endian) & 0xffff) as u16;
+
+    Ok(PcapHeader {
+        big_endian,
+        nanosecond,
+        snap_len,
+        link_type,
+    })
+}
+
+
+/// Decode one record (16-byte record header followed by the packet data).
+pub fn parse_pcap_record(header: &PcapHeader, buf: &[u8]) -> Result<CaptureRecord, String>
+{
+    if buf.len() < PCAP_RECORD_HDR_LEN {
+        return Err("Truncated pcap record header".to_string());
+    }
+
+    let be = header.big_endian;
+    let ts_sec = read_u32(&buf[0..], be);
+    let ts_frac = read_u32(&buf[4..], be);
+    let incl_len = read_u32(&buf[8..], be) as usize;
+    let orig_len = read_u32(&buf[12..], be) as usize;
+
+    if buf.len() < PCAP_RECORD_HDR_LEN + incl_len {
+        return Err("Truncated pcap record".to_string());
+    }
+
+    let mut record = CaptureRecord::new();
+    record.ts_sec = ts_sec as i64;
+    record.ts_nsec = if header.nanosecond { ts_frac } else { ts_frac.saturating_mul(1000) };
+    record.orig_len = orig_len;
+    record.data = buf[PCAP_RECORD_HDR_LEN..PCAP_RECORD_HDR_LEN + incl_len].to_vec();
+    record.link_type = header.link_type;
+    record.record_len = (PCAP_RECORD_HDR_LEN + incl_len) as u32;
+
+    Ok(record)
+}
+
+
+pub struct PcapReader<R: Read> {
+    input: R,
+    header: PcapHeader,
+    position: u64,      // bytes consumed from the start of the file
+}
+
+impl<R: Read> PcapReader<R> {
+    /// The file header is expected to be read already by the caller
+    /// (it uses the magic to detect the file format).
+    pub fn new(input: R, header: PcapHeader) -> Self {
+        PcapReader {
+            input,
+            header,
+            position: PCAP_FILE_HDR_LEN as u64,
+        }
+    }
+
+    pub fn header(&self) -> &PcapHeader {
+        &self.header
+    }
+
+    pub fn next_record(&mut self) -> Result<Option<CaptureRecord>, String> {
+        let mut buf = vec![0u8; PCAP_RECORD_HDR_LEN];
+        if !read_exact_or_eof(&mut self.input, &mut buf)? {
+            return Ok(None);
+        }
+
+        let incl_len = read_u32(&buf[8..], self.header.big_endian) as usize;
+        if incl_len > MAX_RECORD_LEN {
+            return Err(format!("Bad pcap record length {}", incl_len));
+        }
+
+        buf.resize(PCAP_RECORD_HDR_LEN + incl_len, 0);
+        if !read_exact_or_eof(&mut self.input, &mut buf[PCAP_RECORD_HDR_LEN..])? && incl_len > 0 {
+            return Err("Truncated pcap record".to_string());
+        }
+
+        let mut record = parse_pcap_record(&self.header, &buf)?;
+        record.file_offset = self.position;
+        self.position += buf.len() as u64;
+
+        Ok(Some(record))
+    }
+}
@@ -0,0 +1,441 @@
+use std::io::Read;
+
+use crate::capture::reader::*;
+
+// [ draft-ietf-opsawg-pcapng ]
+pub const PCAPNG_SHB_MAGIC: u32         = 0x0A0D0D0A;
+pub const PCAPNG_BYTE_ORDER_MAGIC: u32  = 0x1A2B3C4D;
+
+pub const PCAPNG_BLOCK_IDB: u32         = 0x00000001;
+pub const PCAPNG_BLOCK_OPB: u32         = 0x00000002;
+pub const PCAPNG_BLOCK_SPB: u32         = 0x00000003;
+pub const PCAPNG_BLOCK_ISB: u32         = 0x00000005;
+pub const PCAPNG_BLOCK_EPB: u32         = 0x00000006;
+
+pub const OPT_ENDOFOPT: u16         = 0;
+pub const OPT_COMMENT: u16          = 1;
+
+pub const IF_NAME: u16              = 2;
+pub const IF_DESCRIPTION: u16       = 3;
+pub const IF_TSRESOL: u16           = 9;
+pub const IF_TSOFFSET: u16          = 14;
+
+pub const EPB_FLAGS: u16            = 2;
+pub const EPB_DROPCOUNT: u16        = 4;
+
+pub const ISB_IFDROP: u16           = 5;
+
+const MAX_BLOCK_LEN: usize      = 64 * 1024 * 1024;
+
+
+#[derive(Debug, Clone)]
+pub struct InterfaceInfo {
+    pub link_type: u16,
+    pub snap_len: u32,
+    pub name: String,
+    pub description: String,
+    pub ts_units: u64,      // timestamp units per second (if_tsresol)
+    pub ts_offset: i64,     // seconds (if_tsoffset)
+    pub if_drop: Option<u64>,
+}
+impl InterfaceInfo {
+    pub fn new() -> Self {
+        InterfaceInfo {
+            link_type: 0,
+            snap_len: 0,
+            name: String::new(),
+            description: String::new(),
+            ts_units: 1_000_000,
+            ts_offset: 0,
+            if_drop: None,
+        }
+    }
+}
+
+
+/// Byte order and interface table in effect for a run of packet blocks.
+#[derive(Debug, Clone)]
+pub struct PcapngSection {
+    pub big_endian: bool,
+    pub interfaces: Vec<InterfaceInfo>,
+}
+
+
+pub struct PcapngReader<R: Read> {
+    input: R,
+    big_endian: bool,
+    interfaces: Vec<InterfaceInfo>,
+    pending_shb: bool,
+    position: u64,      // bytes consumed from the start of the file
+    section: usize,     // bumped whenever byte order or interface table changes
+}
+
+
+fn read_u16(buf: &[u8], big_endian: bool) -> u16
+{
+    let b = [buf[0], buf[1]];
+    if big_endian { u16::from_be_bytes(b) } else { u16::from_le_bytes(b) }
+}
+
+fn read_u32(buf: &[u8], big_endian: bool) -> u32
+{
+    let b = [buf[0], buf[1], buf[2], buf[3]];
+    if big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }
+}
+
+fn read_u64(buf: &[u8], big_endian: bool) -> u64
+{
+    let mut b = [0u8; 8];
+    b.copy_from_slice(&buf[0..8]);
+    if big_endian { u64::from_be_bytes(b) } else { u64::from_le_bytes(b) }
+}
+
+fn pad4(len: usize) -> usize
+{
+    (len + 3) & !3
+}
+
+
+/// Walk an option list and hand every (code, value) pair to `f`.
+fn for_each_option<F>(mut opts: &[u8], big_endian: bool, mut f: F)
+where
+    F: FnMut(u16, &[u8]),
+{
+    while opts.len() >= 4 {
+        let code = read_u16(&opts[0..], big_endian);
+        let len = read_u16(&opts[2..], big_endian) as usize;
+
+        if code == OPT_ENDOFOPT {
+            break;
+        }
+
+        if opts.len() < 4 + len {
+            break;
+        }
+
+        f(code, &opts[4..4 + len]);
+
+        let next = 4 + pad4(len);
+        if opts.len() < next {
+            break;
+        }
+        opts = &opts[next..];
+    }
+}
+
+
+fn option_str(value: &[u8]) -> String
+{
+    String::from_utf8_lossy(value)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+
+/// if_tsresol: MSB clear -> 10^-n, MSB set -> 2^-n
+fn tsresol_to_units(value: u8) -> u64
+{
+    let exp = (value & 0x7f) as u32;
+
+    if value & 0x80 != 0 {
+        if exp >= 64 { 1 } else { 1u64 << exp }
+    }
+    else {
+        10u64.checked_pow(exp).unwrap_or(1_000_000)
+    }
+}
+
+
+fn split_timestamp(ts: u64, units: u64, offset: i64) -> (i64, u32)
+{
+    let units = if units == 0 { 1_000_000 } else { units };
+    let sec = (ts / units) as i64 + offset;
+    let frac = ts % units;
+    let nsec = ((frac as u128) * 1_000_000_000 / units as u128) as u32;
+
+    (sec, nsec)
+}
+
+
+impl<R: Read> PcapngReader<R> {
+    /// The first four bytes of the section header are expected to be consumed
+    /// already by the caller (it uses them to detect the file format).
+    pub fn new(input: R) -> Self {
+        PcapngReader {
+            input,
+            big_endian: false,
+            interfaces: Vec::new(),
+            pending_shb: true,
+            position: 4,
+            section: 0,
+        }
+    }
+
+    /// Continue reading packet blocks with a previously captured section state,
+    /// e.g. after seeking straight to an indexed block.
+    pub fn resume(input: R, section: &PcapngSection) -> Self {
+        PcapngReader {
+            input,
+            big_endian: section.big_endian,
+            interfaces: section.interfaces.clone(),
+            pending_shb: false,
+            position: 0,
+            section: 0,
+        }
+    }
+
+    pub fn section(&self) -> PcapngSection {
+        PcapngSection {
+            big_endian: self.big_endian,
+            interfaces: self.interfaces.clone(),
+        }
+    }
+
+    fn read_exact_or_eof(&mut self, buf: &mut [u8]) -> Result<bool, String> {
+        let complete = read_exact_or_eof(&mut self.input, buf)?;
+        if complete {
+            self.position += buf.len() as u64;
+        }
+
+        Ok(complete)
+    }
+
+    /// Section Header Block whose block type has already been read.
+    fn read_shb(&mut self) -> Result<(), String> {
+        let mut head = [0u8; 8];
+        if !self.read_exact_or_eof(&mut head)? {
+            return Err("Truncated pcapng section header".to_string());
+        }
+
+        let magic = u32::from_le_bytes([head[4], head[5], head[6], head[7]]);
+        self.big_endian = match magic {
+            PCAPNG_BYTE_ORDER_MAGIC => false,
+            _ if magic.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+            _ => return Err(format!("Bad pcapng byte-order magic 0x{:08x}", magic)),
+        };
+
+        let block_len = read_u32(&head[0..], self.big_endian) as usize;
+        if !(28..=MAX_BLOCK_LEN).contains(&block_len) {
+            return Err(format!("Bad pcapng section header length {}", block_len));
+        }
+
+        // remainder of the block after type, length and magic
+        let mut body = vec![0u8; block_len - 12];
+        if !self.read_exact_or_eof(&mut body)? {
+            return Err("Truncated pcapng section header".to_string());
+        }
+
+        // a new section starts with a fresh interface table
+        self.interfaces.clear();
+        self.section += 1;
+
+        Ok(())
+    }
+
+    fn parse_idb(&mut self, body: &[u8]) {
+        let be = self.big_endian;
+        let mut iface = InterfaceInfo::new();
+
+        if body.len() < 8 {
+            self.interfaces.push(iface);
+            return;
+        }
+
+        iface.link_type = read_u16(&body[0..], be);
+        iface.snap_len = read_u32(&body[4..], be);
+
+        for_each_option(&body[8..], be, |code, value| {
+            match code {
+                IF_NAME => iface.name = option_str(value),
+                IF_DESCRIPTION => iface.description = option_str(value),
+                IF_TSRESOL if !value.is_empty() => iface.ts_units = tsresol_to_units(value[0]),
+                IF_TSOFFSET if value.len() >= 8 => iface.ts_offset = read_u64(value, be) as i64,
+                _ => {},
+            }
+        });
+
+        self.interfaces.push(iface);
+        self.section += 1;
+    }
+
+    fn parse_isb(&mut self, body: &[u8]) {
+        let be = self.big_endian;
+        if body.len() < 12 {
+            return;
+        }
+
+        let if_id = read_u32(&body[0..], be) as usize;
+        let mut drops = None;
+
+        for_each_option(&body[12..], be, |code, value| {
+            if code == ISB_IFDROP && value.len() >= 8 {
+                drops = Some(read_u64(value, be));
+            }
+        });
+
+        if let (Some(iface), Some(_)) = (self.interfaces.get_mut(if_id), drops) {
+            iface.if_drop = drops;
+        }
+    }
+
+    fn make_record(&self, if_id: u32, ts: u64, data: &[u8], orig_len: u32, opts: &[u8])
+    -> CaptureRecord
+    {
+        let be = self.big_endian;
+        let iface = self.interfaces.get(if_id as usize);
+
+        let (units, offset) = match iface {
+            Some(i) => (i.ts_units, i.ts_offset),
+            None => (1_000_000, 0),
+        };
+        let (ts_sec, ts_nsec) = split_timestamp(ts, units, offset);
+
+        let mut record = CaptureRecord::new();
+        record.ts_sec = ts_sec;
+        record.ts_nsec = ts_nsec;
+        record.orig_len = orig_len as usize;
+        record.data = data.to_vec();
+        record.interface_id = if_id;
+
+        if let Some(i) = iface {
+            record.link_type = i.link_type;
+            record.interface_name = i.name.clone();
+        }
+
+        for_each_option(opts, be, |code, value| {
+            match code {
+                OPT_COMMENT => record.comments.push(option_str(value)),
+                EPB_FLAGS if value.len() >= 4 => record.flags = Some(read_u32(value, be)),
+                EPB_DROPCOUNT if value.len() >= 8 => record.drop_count = Some(read_u64(value, be)),
+                _ => {},
+            }
+        });
+
+        record
+    }
+
+    fn parse_epb(&self, body: &[u8]) -> Result<CaptureRecord, String> {
+        let be = self.big_endian;
+        if body.len() < 20 {
+            return Err("Truncated enhanced packet block".to_string());
+        }
+
+        let if_id = read_u32(&body[0..], be);
+        let ts_high = read_u32(&body[4..], be) as u64;
+        let ts_low = read_u32(&body[8..], be) as u64;
+        let cap_len = read_u32(&body[12..], be) as usize;
+        let orig_len = read_u32(&body[16..], be);
+
+        if body.len() < 20 + cap_len {
+            return Err("Enhanced packet block data overruns block".to_string());
+        }
+
+        let data = &body[20..20 + cap_len];
+        let opts = body.get(20 + pad4(cap_len)..).unwrap_or(&[]);
+
+        Ok(self.make_record(if_id, (ts_high << 32) | ts_low, data, orig_len, opts))
+    }
+
+    fn parse_opb(&self, body: &[u8]) -> Result<CaptureRecord, String> {
+        let be = self.big_endian;
+        if body.len() < 20 {
+            return Err("Truncated packet block".to_string());
+        }
+
+        let if_id = read_u16(&body[0..], be) as u32;
+        let drops = read_u16(&body[2..], be);
+        let ts_high = read_u32(&body[4..], be) as u64;
+        let ts_low = read_u32(&body[8..], be) as u64;
+        let cap_len = read_u32(&body[12..], be) as usize;
+        let orig_len = read_u32(&body[16..], be);
+
+        if body.len() < 20 + cap_len {
+            return Err("Packet block data overruns block".to_string());
+        }
+
+        let data = &body[20..20 + cap_len];
+        let opts = body.get(20 + pad4(cap_len)..).unwrap_or(&[]);
+
+        let mut record = self.make_record(if_id, (ts_high << 32) | ts_low, data, orig_len, opts);
+        if drops != 0xffff {
+            record.drop_count = Some(drops as u64);
+        }
+
+        Ok(record)
+    }
+
+    fn parse_spb(&self, body: &[u8]) -> Result<CaptureRecord, String> {
+        let be = self.big_endian;
+        if body.len() < 4 {
+            return Err("Truncated simple packet block".to_string());
+        }
+
+        let orig_len = read_u32(&body[0..], be);
+        let snap_len = match self.interfaces.first() {
+            Some(i) if i.snap_len > 0 => i.snap_len as usize,
+            _ => usize::MAX,
+        };
+        let cap_len = (orig_len as usize)
+            .min(snap_len)
+            .min(body.len() - 4);
+
+        // SPB carries no timestamp
+        Ok(self.make_record(0, 0, &body[4..4 + cap_len], orig_len, &[]))
+    }
+
+    /// Returns the next packet of the capture, skipping non-packet blocks.
+    pub fn next_record(&mut self) -> Result<Option<CaptureRecord>, String> {
+        if self.pending_shb {
+            self.pending_shb = false;
+            self.read_shb()?;
+        }
+
+        loop {
+            let block_start = self.position;
+
+            let mut head = [0u8; 4];
+            if !self.read_exact_or_eof(&mut head)? {
+                return Ok(None);
+            }
+
+            let block_type = read_u32(&head, self.big_endian);
+
+            if block_type == PCAPNG_SHB_MAGIC {
+                self.read_shb()?;
+                continue;
+            }
+
+            let mut len_buf = [0u8; 4];
+            if !self.read_exact_or_eof(&mut len_buf)? {
+                return Err("Truncated pcapng block header".to_string());
+            }
+            let block_len = read_u32(&len_buf, self.big_endian) as usize;
+
+            if !(12..=MAX_BLOCK_LEN).contains(&block_len) || !block_len.is_multiple_of(4) {
+                return Err(format!("Bad pcapng block length {}", block_len));
+            }
+
+            // body + trailing block length
+            let mut rest = vec![0u8; block_len - 8];
+            if !self.read_exact_or_eof(&mut rest)? {
+                return Err("Truncated pcapng block".to_string());
+            }
+            let body = &rest[..block_len - 12];
+
+            let mut record = match block_type {
+                PCAPNG_BLOCK_IDB => { self.parse_idb(body); continue; },
+                PCAPNG_BLOCK_ISB => { self.parse_isb(body); continue; },
+                PCAPNG_BLOCK_EPB => self.parse_epb(body)?,
+                PCAPNG_BLOCK_OPB => self.parse_opb(body)?,
+                PCAPNG_BLOCK_SPB => self.parse_spb(body)?,
+                _ => continue,
+            };
+
+            record.file_offset = block_start;
+            record.record_len = block_len as u32;
+            record.section = self.section;
+
+            return Ok(Some(record));
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::io::Read;
+
+use crate::capture::index::*;
+use crate::capture::pcap::*;
+use crate::capture::pcapng::*;
+
+
+#[derive(Debug, Clone)]
+pub struct CaptureRecord {
+    pub ts_sec: i64,
+    pub ts_nsec: u32,
+    pub orig_len: usize,
+    pub data: Vec<u8>,
+
+    pub interface_id: u32,
+    pub interface_name: String,
+    pub link_type: u16,
+    pub flags: Option<u32>,
+    pub drop_count: Option<u64>,
+    pub comments: Vec<String>,
+
+    pub file_offset: u64,   // start of the record/block in the capture file
+    pub record_len: u32,    // record header + data (pcap) or whole block (pcapng)
+    pub section: usize,     // pcapng section state the record was read with
+}
+impl CaptureRecord {
+    pub fn new() -> Self {
+        CaptureRecord {
+            ts_sec: 0,
+            ts_nsec: 0,
+            orig_len: 0,
+            data: Vec::new(),
+            interface_id: 0,
+            interface_name: String::new(),
+            link_type: 0,
+            flags: None,
+            drop_count: None,
+            comments: Vec::new(),
+            file_offset: 0,
+            record_len: 0,
+            section: 0,
+        }
+    }
+}
+
+
+/// Fill `buf` completely. Returns false on a clean end of input before the
+/// first byte, and an error if the input ends part way through.
+pub fn read_exact_or_eof<R: Read>(input: &mut R, buf: &mut [u8]) -> Result<bool, String>
+{
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => {
+                if filled == 0 {
+                    return Ok(false);
+                }
+                return Err("Truncated capture record".to_string());
+            },
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("Capture read error: {}", e)),
+        }
+    }
+
+    Ok(true)
+}
+
+
+/// pcap/pcapng reader over any byte stream: a file, a buffer or an upload in flight.
+pub enum CaptureReader<R: Read> {
+    Pcap(PcapReader<R>),
+    Pcapng(PcapngReader<R>),
+}
+
+/// In-memory captures, for tests that build the bytes by hand
+#[cfg(test)]
+impl<'a> CaptureReader<std::io::Cursor<&'a [u8]>> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, String> {
+        CaptureReader::new(std::io::Cursor::new(data))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Detects the format from the leading magic and starts reading.
+    pub fn new(mut input: R) -> Result<Self, String> {
+        let mut magic = [0u8; 4];
+        if !read_exact_or_eof(&mut input, &mut magic)? {
+            return Err("Empty capture file".to_string());
+        }
+
+        if u32::from_le_bytes(magic) == PCAPNG_SHB_MAGIC {
+            return Ok(CaptureReader::Pcapng(PcapngReader::new(input)));
+        }
+
+        let mut file_hdr = [0u8; PCAP_FILE_HDR_LEN];
+        file_hdr[..4].copy_from_slice(&magic);
+        if !read_exact_or_eof(&mut input, &mut file_hdr[4..])? {
+            return Err("Truncated pcap file header".to_string());
+        }
+        let header = parse_pcap_header(&file_hdr)?;
+
+        Ok(CaptureReader::Pcap(PcapReader::new(input, header)))
+    }
+
+    /// Empty index matching the format of this capture.
+    pub fn new_index(&self) -> CaptureIndex {
+        match self {
+            CaptureReader::Pcap(reader) => CaptureIndex::new(CaptureFormat::Pcap(*reader.header())),
+            CaptureReader::Pcapng(_) => CaptureIndex::new(CaptureFormat::Pcapng(Vec::new())),
+        }
+    }
+
+    /// Record `record` (just returned by `next_record`) in `index`.
+    pub fn index_record(&self, index: &mut CaptureIndex, record: &CaptureRecord) {
+        if let (CaptureReader::Pcapng(reader), CaptureFormat::Pcapng(sections)) =
+            (self, &mut index.format)
+            && sections.last().map(|(no, _)| *no) != Some(record.section) {
+            sections.push((record.section, reader.section()));
+        }
+
+        let section = match &index.format {
+            CaptureFormat::Pcapng(sections) => sections.len() - 1,
+            CaptureFormat::Pcap(_) => 0,
+        };
+
+        index.records.push(RecordIndex {
+            offset: record.file_offset,
+            len: record.record_len,
+            section,
+        });
+    }
+
+    pub fn next_record(&mut self) -> Result<Option<CaptureRecord>, String> {
+        match self {
+            CaptureReader::Pcap(reader) => reader.next_record(),
+            CaptureReader::Pcapng(reader) => reader.next_record(),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::{Read, Write};
+use tokio::sync::mpsc;
+
+const CHUNK_QUEUE_LEN: usize = 64;
+
+
+/// Blocking `Read` over chunks pushed from an async task, so an upload can be
+/// parsed on a blocking thread while it is still being received.
+pub struct ChunkReader {
+    rx: mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+pub fn chunk_channel() -> (mpsc::Sender<Vec<u8>>, ChunkReader)
+{
+    let (tx, rx) = mpsc::channel(CHUNK_QUEUE_LEN);
+
+    let reader = ChunkReader {
+        rx,
+        chunk: Vec::new(),
+        pos: 0,
+    };
+
+    (tx, reader)
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos >= self.chunk.len() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                },
+                // all senders dropped: end of upload
+                None => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+
+        Ok(n)
+    }
+}
+
+
+/// Copies everything read through it into `out`, so a stream can be stored
+/// exactly as the parser saw it (e.g. the decompressed capture).
+pub struct TeeReader<R: Read, W: Write> {
+    input: R,
+    out: W,
+}
+
+impl<R: Read, W: Write> TeeReader<R, W> {
+    pub fn new(input: R, out: W) -> Self {
+        TeeReader { input, out }
+    }
+}
+
+impl<R: Read, W: Write> Read for TeeReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.input.read(buf)?;
+
+        if n == 0 {
+            self.out.flush()?;
+        }
+        else {
+            self.out.write_all(&buf[..n])?;
+        }
+
+        Ok(n)
+    }
+}
@@ -0,0 +1,364 @@
+use std::io::{Cursor, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::capture::decompress::*;
+use crate::capture::index::CaptureIndex;
+use crate::capture::merge::*;
+use crate::capture::pcap::*;
+use crate::capture::pcapng::*;
+use crate::capture::reader::*;
+use crate::parse_pcap::simple_parse_pcap;
+use crate::test_util::*;
+use crate::time_format::TimeFormat;
+use crate::types::DecodeAs;
+
+
+fn read_all(data: &[u8]) -> Result<Vec<CaptureRecord>, String>
+{
+    let mut cap = CaptureReader::from_bytes(data)?;
+    let mut records = Vec::new();
+    while let Some(record) = cap.next_record()? {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn read_compressed(data: Vec<u8>) -> Result<Vec<CaptureRecord>, String>
+{
+    let mut cap = CaptureReader::new(decompress_reader(Cursor::new(data))?)?;
+    let mut records = Vec::new();
+    while let Some(record) = cap.next_record()? {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn gzip(data: &[u8], level: flate2::Compression) -> Vec<u8>
+{
+    let mut enc = flate2::write::GzEncoder::new(Vec::new(), level);
+    enc.write_all(data).unwrap();
+    enc.finish().unwrap()
+}
+
+fn three_packets() -> Vec<u8>
+{
+    pcap_file(false, false, &[(1, 0, &[0x11; 40]), (2, 0, &[0x22; 40]), (3, 0, &[0x33; 40])])
+}
+
+// a capture written to the temp dir, indexed the way an upload is
+fn merge_source(test: &str, name: &str, clock_offset_ns: i64, records: &[(u32, u32, &[u8])]) -> MergeSource
+{
+    let data = pcap_file(false, false, records);
+    let path = std::env::temp_dir().join(format!("merge-{}-{}-{}", std::process::id(), test, name));
+    std::fs::write(&path, &data).unwrap();
+
+    let mut cap = CaptureReader::from_bytes(&data).unwrap();
+    let mut index: CaptureIndex = cap.new_index();
+    while let Some(record) = cap.next_record().unwrap() {
+        cap.index_record(&mut index, &record);
+    }
+
+    MergeSource { name: name.to_string(), path, index: Arc::new(index), clock_offset_ns }
+}
+
+fn merge_out(test: &str) -> PathBuf
+{
+    std::env::temp_dir().join(format!("merge-{}-{}-out.pcapng", std::process::id(), test))
+}
+
+
+// little-endian pcapng block with its length before and after the body
+fn block(block_type: u32, body: &[u8]) -> Vec<u8>
+{
+    let mut body = body.to_vec();
+    body.resize(body.len().div_ceil(4) * 4, 0);
+    let len = (12 + body.len()) as u32;
+
+    let mut out = Vec::new();
+    out.extend(block_type.to_le_bytes());
+    out.extend(len.to_le_bytes());
+    out.extend(body);
+    out.extend(len.to_le_bytes());
+    out
+}
+
+fn option(code: u16, value: &[u8]) -> Vec<u8>
+{
+    let mut out = Vec::new();
+    out.extend(code.to_le_bytes());
+    out.extend((value.len() as u16).to_le_bytes());
+    out.extend(value);
+    out.resize(out.len().div_ceil(4) * 4, 0);
+    out
+}
+
+fn shb() -> Vec<u8>
+{
+    let mut body = Vec::new();
+    body.extend(PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+    body.extend(1u16.to_le_bytes());
+    body.extend(0u16.to_le_bytes());
+    body.extend((-1i64).to_le_bytes());
+    block(PCAPNG_SHB_MAGIC, &body)
+}
+
+fn idb(link_type: u16, snap_len: u32, opts: &[u8]) -> Vec<u8>
+{
+    let mut body = Vec::new();
+    body.extend(link_type.to_le_bytes());
+    body.extend(0u16.to_le_bytes());
+    body.extend(snap_len.to_le_bytes());
+    body.extend(opts);
+    block(PCAPNG_BLOCK_IDB, &body)
+}
+
+fn epb(if_id: u32, ts: u64, data: &[u8], opts: &[u8]) -> Vec<u8>
+{
+    let mut body = Vec::new();
+    body.extend(if_id.to_le_bytes());
+    body.extend(((ts >> 32) as u32).to_le_bytes());
+    body.extend((ts as u32).to_le_bytes());
+    body.extend((data.len() as u32).to_le_bytes());
+    body.extend((data.len() as u32).to_le_bytes());
+    body.extend(data);
+    body.resize(body.len().div_ceil(4) * 4, 0);
+    body.extend(opts);
+    block(PCAPNG_BLOCK_EPB, &body)
+}
+
+fn spb(orig_len: u32, data: &[u8]) -> Vec<u8>
+{
+    let mut body = Vec::new();
+    body.extend(orig_len.to_le_bytes());
+    body.extend(data);
+    block(PCAPNG_BLOCK_SPB, &body)
+}
+
+
+#[test]
+fn pcap_little_endian_microseconds() {
+    let file = pcap_file(false, false, &[(100, 250_000, b"abcd"), (101, 1, b"ef")]);
+    let records = read_all(&file).unwrap();
+
+    assert_eq!(records.len(), 2);
+    assert_eq!((records[0].ts_sec, records[0].ts_nsec), (100, 250_000_000));
+    assert_eq!(records[0].data, b"abcd");
+    assert_eq!(records[0].orig_len, 8);
+    assert_eq!(records[0].link_type, 1);
+    assert_eq!(records[0].file_offset, PCAP_FILE_HDR_LEN as u64);
+    assert_eq!(records[1].ts_nsec, 1_000);
+    assert_eq!(records[1].file_offset, (PCAP_FILE_HDR_LEN + PCAP_RECORD_HDR_LEN + 4) as u64);
+}
+
+#[test]
+fn pcap_big_endian_nanoseconds() {
+    let file = pcap_file(true, true, &[(7, 123_456_789, b"xyz")]);
+    let records = read_all(&file).unwrap();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!((records[0].ts_sec, records[0].ts_nsec), (7, 123_456_789));
+    assert_eq!(records[0].data, b"xyz");
+    assert_eq!(records[0].record_len, (PCAP_RECORD_HDR_LEN + 3) as u32);
+}
+
+#[test]
+fn pcap_truncated_record() {
+    let file = pcap_file(false, false, &[(1, 0, b"abcd"), (2, 0, b"efgh")]);
+    let mut cap = CaptureReader::from_bytes(&file[..file.len() - 2]).unwrap();
+
+    assert!(cap.next_record().unwrap().is_some());
+    assert!(cap.next_record().is_err());
+}
+
+#[test]
+fn pcap_truncated_header_and_bad_magic() {
+    let file = pcap_file(false, false, &[]);
+
+    assert!(CaptureReader::from_bytes(&[]).is_err());
+    assert!(CaptureReader::from_bytes(&file[..10]).is_err());
+    assert!(CaptureReader::from_bytes(&[0u8; PCAP_FILE_HDR_LEN]).is_err());
+}
+
+#[test]
+fn pcapng_epb_with_interface_options() {
+    let mut opts = option(IF_NAME, b"eth0");
+    opts.extend(option(IF_TSRESOL, &[9]));
+    opts.extend(option(OPT_ENDOFOPT, &[]));
+    let mut epb_opts = option(OPT_COMMENT, b"hello");
+    epb_opts.extend(option(EPB_FLAGS, &1u32.to_le_bytes()));
+
+    let mut file = shb();
+    file.extend(idb(1, 65535, &opts));
+    file.extend(epb(0, 5_000_000_123, b"packet", &epb_opts));
+    let records = read_all(&file).unwrap();
+
+    assert_eq!(records.len(), 1);
+    let r = &records[0];
+    assert_eq!((r.ts_sec, r.ts_nsec), (5, 123));
+    assert_eq!(r.data, b"packet");
+    assert_eq!(r.interface_name, "eth0");
+    assert_eq!(r.link_type, 1);
+    assert_eq!(r.comments, vec!["hello".to_string()]);
+    assert_eq!(r.flags, Some(1));
+    assert_eq!(r.file_offset, (shb().len() + idb(1, 65535, &opts).len()) as u64);
+}
+
+#[test]
+fn pcapng_spb_is_cut_to_the_snap_length() {
+    let mut file = shb();
+    file.extend(idb(101, 4, &[]));
+    file.extend(spb(6, b"abcdef"));
+    let records = read_all(&file).unwrap();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].data, b"abcd");
+    assert_eq!(records[0].orig_len, 6);
+    assert_eq!(records[0].link_type, 101);
+}
+
+#[test]
+fn pcapng_skips_unknown_blocks_and_keeps_interfaces_apart() {
+    let mut file = shb();
+    file.extend(idb(1, 0, &[]));
+    file.extend(idb(113, 0, &option(IF_NAME, b"any")));
+    file.extend(block(0x0bad, b"1234"));
+    file.extend(epb(1, 1_000_000, b"a", &[]));
+    file.extend(epb(0, 2_000_000, b"b", &[]));
+    let records = read_all(&file).unwrap();
+
+    assert_eq!(records.len(), 2);
+    assert_eq!((records[0].interface_id, records[0].link_type), (1, 113));
+    assert_eq!(records[0].interface_name, "any");
+    assert_eq!(records[0].ts_sec, 1);
+    assert_eq!((records[1].interface_id, records[1].link_type), (0, 1));
+}
+
+#[test]
+fn pcapng_truncated_block() {
+    let mut file = shb();
+    file.extend(idb(1, 0, &[]));
+    file.extend(epb(0, 0, b"first", &[]));
+    file.extend(epb(0, 0, b"second", &[]));
+    let mut cap = CaptureReader::from_bytes(&file[..file.len() - 6]).unwrap();
+
+    assert_eq!(cap.next_record().unwrap().unwrap().data, b"first");
+    assert!(cap.next_record().is_err());
+}
+
+#[test]
+fn pcapng_truncated_section_header() {
+    let file = shb();
+    let mut cap = CaptureReader::from_bytes(&file[..12]).unwrap();
+
+    assert!(cap.next_record().is_err());
+}
+
+#[test]
+fn compression_is_detected_from_the_magic() {
+    assert_eq!(detect_compression(&GZIP_MAGIC), Compression::Gzip);
+    assert_eq!(detect_compression(&ZSTD_MAGIC), Compression::Zstd);
+    assert_eq!(detect_compression(&XZ_MAGIC), Compression::Xz);
+    assert_eq!(detect_compression(&PCAP_MAGIC_USEC.to_le_bytes()), Compression::None);
+    assert_eq!(detect_compression(&[0x1f]), Compression::None);
+}
+
+#[test]
+fn multi_member_gzip() {
+    // pigz/bgzip style: the capture is split over several gzip members
+    let file = three_packets();
+    let mut data = gzip(&file[..50], flate2::Compression::default());
+    data.extend(gzip(&file[50..], flate2::Compression::default()));
+    let records = read_compressed(data).unwrap();
+
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[2].ts_sec, 3);
+    assert_eq!(records[2].data, [0x33; 40]);
+}
+
+#[test]
+fn multi_frame_zstd() {
+    use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+
+    let file = three_packets();
+    let mut data = compress_to_vec(&file[..70], CompressionLevel::Fastest);
+    data.extend(compress_to_vec(&file[70..], CompressionLevel::Fastest));
+    let records = read_compressed(data).unwrap();
+
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[1].data, [0x22; 40]);
+    assert_eq!(records[2].file_offset, (PCAP_FILE_HDR_LEN + 2 * (PCAP_RECORD_HDR_LEN + 40)) as u64);
+}
+
+#[test]
+fn xz() {
+    use lzma_rust2::{XzOptions, XzWriter};
+
+    let file = three_packets();
+    let mut enc = XzWriter::new(Vec::new(), XzOptions::with_preset(6)).unwrap();
+    enc.write_all(&file).unwrap();
+    let records = read_compressed(enc.finish().unwrap()).unwrap();
+
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].data, [0x11; 40]);
+}
+
+#[test]
+fn truncated_compressed_capture_keeps_the_packets_before_the_cut() {
+    // stored deflate blocks, so the cut lands inside the third record
+    let frame = eth(0x0800, &ipv4(17, &udp(40000, 5000, &[0; 16])));
+    let file = pcap_file(false, false, &[(1, 0, &frame), (2, 0, &frame), (3, 0, &frame)]);
+    let data = gzip(&file, flate2::Compression::none());
+    let cut = data[..data.len() - 8 - 20].to_vec();
+
+    let cap = CaptureReader::new(decompress_reader(Cursor::new(cut)).unwrap()).unwrap();
+    let time_fmt = TimeFormat::from_query(None, None).unwrap();
+    let parsed = simple_parse_pcap(cap, "cut.pcap.gz".to_string(), &time_fmt, &DecodeAs::default()).unwrap();
+
+    assert_eq!(parsed.packets.len(), 2);
+    assert!(parsed.truncated.is_some());
+}
+
+#[test]
+fn merge_sorts_unordered_sources_and_drops_duplicates() {
+    // a.pcap is out of order; b.pcap runs 1 s behind and repeats a.pcap's packet at 3 s
+    let sources = [
+        merge_source("sort", "a.pcap", 0, &[(1, 0, b"x"), (3, 0, b"dup"), (2, 0, b"y")]),
+        merge_source("sort", "b.pcap", 1_000_000_000, &[(0, 500_000, b"z"), (2, 0, b"w"), (2, 0, b"dup")]),
+    ];
+    let out = merge_out("sort");
+    let stats = merge_captures(&sources, &out).unwrap();
+    let merged = read_all(&std::fs::read(&out).unwrap()).unwrap();
+
+    assert_eq!((stats.packets, stats.duplicates), (5, 1));
+    let seen: Vec<_> = merged.iter()
+        .map(|r| (r.ts_sec, r.ts_nsec, r.data.as_slice(), r.interface_name.as_str()))
+        .collect();
+    assert_eq!(seen, [
+        (1, 0, &b"x"[..], "a.pcap"),
+        (1, 500_000_000, b"z", "b.pcap"),
+        (2, 0, b"y", "a.pcap"),
+        (3, 0, b"dup", "a.pcap"),
+        (3, 0, b"w", "b.pcap"),
+    ]);
+
+    for path in sources.iter().map(|s| &s.path).chain([&out]) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[test]
+fn merge_refuses_an_offset_before_1970_without_writing() {
+    let sources = [
+        merge_source("offset", "a.pcap", 0, &[(1, 0, b"x")]),
+        merge_source("offset", "b.pcap", -5_000_000_000, &[(10, 0, b"y"), (2, 0, b"z")]),
+    ];
+    let out = merge_out("offset");
+
+    assert!(merge_captures(&sources, &out).is_err());
+    assert!(!out.exists());
+
+    for source in &sources {
+        let _ = std::fs::remove_file(&source.path);
+    }
+}
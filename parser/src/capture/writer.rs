@@ -0,0 +1,121 @@
+use std::io::Write;
+
+use crate::capture::pcapng::*;
+use crate::capture::reader::*;
+
+const PCAPNG_BLOCK_HDR_LEN: usize   = 8;     // block type + block total length
+const PCAPNG_BLOCK_TRAILER_LEN: usize = 4;
+const TSRESOL_NSEC: u8              = 9;     // if_tsresol: 10^-9 s
+
+
+/// Little-endian pcapng writer with nanosecond timestamps on every interface.
+pub struct PcapngWriter<W: Write> {
+    out: W,
+    interfaces: u32,
+}
+
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8])
+{
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    body.resize(body.len().next_multiple_of(4), 0);
+}
+
+fn push_end_of_options(body: &mut Vec<u8>)
+{
+    body.extend_from_slice(&OPT_ENDOFOPT.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Starts the file with a single section header.
+    pub fn new(out: W) -> Result<Self, String> {
+        let mut writer = PcapngWriter {
+            out,
+            interfaces: 0,
+        };
+
+        let mut body = Vec::new();
+        body.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());        // major version
+        body.extend_from_slice(&0u16.to_le_bytes());        // minor version
+        body.extend_from_slice(&(-1i64).to_le_bytes());     // section length unknown
+        writer.write_block(PCAPNG_SHB_MAGIC, &body)?;
+
+        Ok(writer)
+    }
+
+    fn write_block(&mut self, block_type: u32, body: &[u8]) -> Result<(), String> {
+        let total = (PCAPNG_BLOCK_HDR_LEN + body.len() + PCAPNG_BLOCK_TRAILER_LEN) as u32;
+
+        let mut block = Vec::with_capacity(total as usize);
+        block.extend_from_slice(&block_type.to_le_bytes());
+        block.extend_from_slice(&total.to_le_bytes());
+        block.extend_from_slice(body);
+        block.extend_from_slice(&total.to_le_bytes());
+
+        self.out.write_all(&block)
+            .map_err(|e| format!("Failed to write capture: {}", e))
+    }
+
+    /// Declare an interface and return its id for `write_packet`.
+    pub fn add_interface(&mut self, link_type: u16, name: &str) -> Result<u32, String> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&link_type.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());        // reserved
+        body.extend_from_slice(&0u32.to_le_bytes());        // no snap length limit
+
+        if !name.is_empty() {
+            push_option(&mut body, IF_NAME, name.as_bytes());
+        }
+        push_option(&mut body, IF_TSRESOL, &[TSRESOL_NSEC]);
+        push_end_of_options(&mut body);
+
+        self.write_block(PCAPNG_BLOCK_IDB, &body)?;
+
+        self.interfaces += 1;
+        Ok(self.interfaces - 1)
+    }
+
+    /// Write `record` as an enhanced packet block stamped with `ts_nsec`
+    /// (nanoseconds since the epoch), keeping its comments, flags and drop count.
+    pub fn write_packet(&mut self, if_id: u32, ts_nsec: u64, record: &CaptureRecord)
+    -> Result<(), String>
+    {
+        let mut body = Vec::with_capacity(20 + record.data.len() + 16);
+        body.extend_from_slice(&if_id.to_le_bytes());
+        body.extend_from_slice(&((ts_nsec >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(ts_nsec as u32).to_le_bytes());
+        body.extend_from_slice(&(record.data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(record.orig_len as u32).to_le_bytes());
+        body.extend_from_slice(&record.data);
+        body.resize(body.len().next_multiple_of(4), 0);
+
+        let has_options = !record.comments.is_empty()
+            || record.flags.is_some()
+            || record.drop_count.is_some();
+
+        for comment in &record.comments {
+            push_option(&mut body, OPT_COMMENT, comment.as_bytes());
+        }
+        if let Some(flags) = record.flags {
+            push_option(&mut body, EPB_FLAGS, &flags.to_le_bytes());
+        }
+        if let Some(drops) = record.drop_count {
+            push_option(&mut body, EPB_DROPCOUNT, &drops.to_le_bytes());
+        }
+        if has_options {
+            push_end_of_options(&mut body);
+        }
+
+        self.write_block(PCAPNG_BLOCK_EPB, &body)
+    }
+
+    pub fn finish(mut self) -> Result<W, String> {
+        self.out.flush()
+            .map_err(|e| format!("Failed to write capture: {}", e))?;
+
+        Ok(self.out)
+    }
+}
@@ -0,0 +1,389 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+
+use crate::types::*;
+use crate::dissect::FlowTuple;
+use crate::ip::port::*;
+use crate::l4::{tcp::*, tcp_stream::*};
+use crate::nodes::*;
+
+// segments per direction waiting for an ACK; the oldest stop counting beyond this
+const MAX_UNACKED: usize    = 256;
+// frames of datagrams not yet reassembled
+const MAX_UNPLACED: usize   = 4096;
+
+// how two conversations compare for one sort key
+type ConvOrder = Box<dyn Fn(&Conversation, &Conversation) -> Ordering>;
+
+
+// a segment sent at `time_ns`, acknowledged once the ACK reaches `end`
+struct Unacked {
+    end: u32,
+    time_ns: i64,
+    retransmitted: bool,    // its ACK may answer either copy, so it gives no sample
+}
+
+struct TcpRtt {
+    unacked: [VecDeque<Unacked>; 2],
+    retransmissions: usize,
+    samples: usize,
+    sum_ns: i64,
+    min_ns: Option<i64>,
+    max_ns: Option<i64>,
+}
+impl TcpRtt {
+    fn new() -> Self {
+        TcpRtt {
+            unacked: [VecDeque::new(), VecDeque::new()],
+            retransmissions: 0,
+            samples: 0,
+            sum_ns: 0,
+            min_ns: None,
+            max_ns: None,
+        }
+    }
+
+    // `dir` is 0 from A, 1 from B
+    fn observe(&mut self, dir: usize, time_ns: i64, seg: &TcpSegment, retransmission: bool) {
+        // the ACK answers what the other side sent; the newest segment it covers is the sample
+        if seg.flags & TCP_FLAG_ACK != 0 {
+            let other = &mut self.unacked[1 - dir];
+            let mut acked = None;
+            while let Some(front) = other.front()
+                && seq_diff(seg.ack, front.end) >= 0 {
+                acked = other.pop_front();
+            }
+            if let Some(acked) = acked.filter(|u| !u.retransmitted) {
+                let rtt = time_ns - acked.time_ns;
+                self.samples += 1;
+                self.sum_ns += rtt;
+                self.min_ns = Some(self.min_ns.map_or(rtt, |m| m.min(rtt)));
+                self.max_ns = Some(self.max_ns.map_or(rtt, |m| m.max(rtt)));
+            }
+        }
+
+        let syn_fin = (seg.flags & TCP_FLAG_SYN != 0) as u32 + (seg.flags & TCP_FLAG_FIN != 0) as u32;
+        let seq_len = seg.payload_len as u32 + syn_fin;
+        if seq_len == 0 {
+            return;
+        }
+
+        let queue = &mut self.unacked[dir];
+        if retransmission {
+            self.retransmissions += 1;
+            for sent in queue.iter_mut().filter(|u| seq_diff(u.end, seg.seq) > 0) {
+                sent.retransmitted = true;
+            }
+            return;
+        }
+        if queue.len() >= MAX_UNACKED {
+            queue.pop_front();
+        }
+        queue.push_back(Unacked { end: seg.seq.wrapping_add(seq_len), time_ns, retransmitted: false });
+    }
+
+    fn finish(self) -> TcpConversation {
+        TcpConversation {
+            retransmissions: self.retransmissions,
+            rtt_samples: self.samples,
+            rtt_min_ns: self.min_ns,
+            rtt_avg_ns: (self.samples > 0).then(|| self.sum_ns / self.samples as i64),
+            rtt_max_ns: self.max_ns,
+        }
+    }
+}
+
+
+// both directions map to the same key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ConvKey {
+    protocol: u8,
+    ends: [(IpAddr, u16); 2],
+}
+
+struct ConvState {
+    conv: Conversation,
+    a: (IpAddr, u16),
+    first_ns: i64,
+    last_ns: i64,
+    tcp: Option<TcpRtt>,
+}
+
+
+/// Collects TCP/UDP/SCTP conversations from the 5-tuple of each packet while
+/// the summary is parsed. Tunnelled traffic counts for its innermost flow.
+pub struct ConversationTracker {
+    convs: Vec<ConvState>,
+    keys: HashMap<ConvKey, usize>,
+    unplaced: HashMap<usize, usize>,    // fragment frame -> its length, until its datagram is complete
+}
+
+impl ConversationTracker {
+    pub fn new() -> Self {
+        ConversationTracker {
+            convs: Vec::new(),
+            keys: HashMap::new(),
+            unplaced: HashMap::new(),
+        }
+    }
+
+    /// Packet `pkt`, `frame_len` bytes on the wire. `seg` is its TCP segment, if any.
+    pub fn observe(&mut self, pkt: &PacketSummary, flow: &FlowTuple, frame_len: usize, seg: Option<&TcpSegment>) {
+        let protocol = flow.protocol as usize;
+        if ![PROTO_TYPE_TCP, PROTO_TYPE_UDP, PROTO_TYPE_SCTP].contains(&protocol) {
+            return;
+        }
+        let (Some(src), Some(dst)) = (flow.src_addr, flow.dst_addr) else {
+            return;
+        };
+
+        // a fragment without the transport header; counted with its datagram
+        if pkt.l4_type.is_empty() {
+            if self.unplaced.len() >= MAX_UNPLACED {
+                self.unplaced.clear();
+            }
+            self.unplaced.insert(pkt.id, frame_len);
+            return;
+        }
+
+        let src = (src, flow.src_port);
+        let dst = (dst, flow.dst_port);
+        let key = ConvKey { protocol: flow.protocol, ends: if src <= dst { [src, dst] } else { [dst, src] } };
+        let time_ns = pkt.time.relative_ns;
+
+        let next_id = self.convs.len();
+        let id = *self.keys.entry(key).or_insert(next_id);
+        if id == next_id {
+            self.convs.push(ConvState {
+                conv: Conversation {
+                    id,
+                    protocol: protocol_to_str(protocol).unwrap_or_default(),
+                    addr_a: src.0.to_string(),
+                    port_a: src.1,
+                    name_a: None,
+                    addr_b: dst.0.to_string(),
+                    port_b: dst.1,
+                    name_b: None,
+                    packets_a_to_b: 0,
+                    bytes_a_to_b: 0,
+                    packets_b_to_a: 0,
+                    bytes_b_to_a: 0,
+                    packets: 0,
+                    bytes: 0,
+                    first_packet: pkt.id,
+                    last_packet: pkt.id,
+                    first_seen: pkt.ts.clone(),
+                    last_seen: pkt.ts.clone(),
+                    start_ns: time_ns,
+                    duration_ns: 0,
+                    bps_a_to_b: None,
+                    bps_b_to_a: None,
+                    tcp: None,
+                },
+                a: src,
+                first_ns: time_ns,
+                last_ns: time_ns,
+                tcp: (protocol == PROTO_TYPE_TCP).then(TcpRtt::new),
+            });
+        }
+        let state = &mut self.convs[id];
+
+        let mut packets = 1;
+        let mut bytes = frame_len;
+        for frag_id in pkt.fragments.iter().filter(|&&f| f != pkt.id) {
+            if let Some(len) = self.unplaced.remove(frag_id) {
+                packets += 1;
+                bytes += len;
+            }
+        }
+
+        let from_a = src == state.a;
+        let conv = &mut state.conv;
+        if from_a {
+            conv.packets_a_to_b += packets;
+            conv.bytes_a_to_b += bytes;
+        }
+        else {
+            conv.packets_b_to_a += packets;
+            conv.bytes_b_to_a += bytes;
+        }
+        conv.last_packet = pkt.id;
+        conv.last_seen = pkt.ts.clone();
+        state.last_ns = time_ns;
+
+        if let (Some(rtt), Some(seg)) = (state.tcp.as_mut(), seg) {
+            let retransmission = pkt.tcp_analysis.contains(&TcpAnalysis::Retransmission);
+            rtt.observe(!from_a as usize, time_ns, seg, retransmission);
+        }
+    }
+
+    /// Every conversation, in the order first seen
+    pub fn finish(self) -> Vec<Conversation> {
+        self.convs.into_iter().map(|state| {
+            let mut conv = state.conv;
+            conv.packets = conv.packets_a_to_b + conv.packets_b_to_a;
+            conv.bytes = conv.bytes_a_to_b + conv.bytes_b_to_a;
+            conv.duration_ns = state.last_ns - state.first_ns;
+
+            let bps = |bytes: usize| (conv.duration_ns > 0)
+                .then(|| bytes as f64 * 8.0 * 1e9 / conv.duration_ns as f64);
+            conv.bps_a_to_b = bps(conv.bytes_a_to_b);
+            conv.bps_b_to_a = bps(conv.bytes_b_to_a);
+            conv.tcp = state.tcp.map(TcpRtt::finish);
+            conv
+        }).collect()
+    }
+}
+
+
+/// Conversations matching every filter given in `query`, sorted as asked,
+/// with the endpoints named from `nodes`.
+pub fn query_conversations(convs: &[Conversation], query: &ConversationQuery, nodes: Option<&NodeDirectory>)
+-> Result<Vec<Conversation>, String>
+{
+    let protocol = query.protocol.as_deref().map(str::to_ascii_uppercase);
+    if let Some(p) = protocol.as_deref()
+        && !["TCP", "UDP", "SCTP"].contains(&p) {
+        return Err(format!("Unknown protocol \"{}\" (tcp, udp or sctp)", p.to_ascii_lowercase()));
+    }
+    let subnet = query.addr.as_deref().map(parse_subnet).transpose()?;
+
+    let in_addr = |addr: &str| match (subnet, addr.parse::<IpAddr>()) {
+        (Some((net, prefix)), Ok(addr)) => in_subnet(net, prefix, addr),
+        (Some(_), Err(_)) => false,
+        (None, _) => true,
+    };
+
+    let mut result: Vec<Conversation> = convs.iter()
+        .filter(|c| protocol.as_ref().is_none_or(|p| c.protocol == *p))
+        .filter(|c| in_addr(&c.addr_a) || in_addr(&c.addr_b))
+        .filter(|c| query.port.is_none_or(|p| c.port_a == p || c.port_b == p))
+        .cloned()
+        .collect();
+
+    sort_conversations(&mut result, query.sort.as_deref(), query.order.as_deref())?;
+
+    if let Some(nodes) = nodes {
+        for conv in &mut result {
+            conv.name_a = nodes.name_of(&conv.addr_a);
+            conv.name_b = nodes.name_of(&conv.addr_b);
+        }
+    }
+
+    Ok(result)
+}
+
+
+/// Sort by "id", "addr", "packets", "bytes", "start", "duration", "bps",
+/// "rtt" or "retransmissions"; missing values sort first.
+pub fn sort_conversations(convs: &mut [Conversation], sort: Option<&str>, order: Option<&str>)
+-> Result<(), String>
+{
+    let desc = match order.unwrap_or("asc") {
+        "asc" => false,
+        "desc" => true,
+        other => return Err(format!("Unknown order \"{}\" (asc or desc)", other)),
+    };
+
+    let by_f64 = |key: fn(&Conversation) -> Option<f64>| move |a: &Conversation, b: &Conversation| {
+        match (key(a), key(b)) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            (x, y) => x.is_some().cmp(&y.is_some()),
+        }
+    };
+    let cmp: ConvOrder = match sort.unwrap_or("id") {
+        "id" => Box::new(|a, b| a.id.cmp(&b.id)),
+        "addr" => Box::new(|a, b| {
+            let ends = |c: &Conversation| (c.addr_a.parse::<IpAddr>().ok(), c.port_a,
+                c.addr_b.parse::<IpAddr>().ok(), c.port_b);
+            ends(a).cmp(&ends(b))
+        }),
+        "packets" => Box::new(|a, b| a.packets.cmp(&b.packets)),
+        "bytes" => Box::new(|a, b| a.bytes.cmp(&b.bytes)),
+        "start" => Box::new(|a, b| a.start_ns.cmp(&b.start_ns)),
+        "duration" => Box::new(|a, b| a.duration_ns.cmp(&b.duration_ns)),
+        "bps" => Box::new(by_f64(|c| match (c.bps_a_to_b, c.bps_b_to_a) {
+            (Some(x), Some(y)) => Some(x + y),
+            _ => None,
+        })),
+        "rtt" => Box::new(by_f64(|c| c.tcp.as_ref()?.rtt_avg_ns.map(|ns| ns as f64))),
+        "retransmissions" => Box::new(by_f64(|c| c.tcp.as_ref().map(|t| t.retransmissions as f64))),
+        other => return Err(format!("Unknown sort \"{}\" (id, addr, packets, bytes, start, \
+            duration, bps, rtt or retransmissions)", other)),
+    };
+
+    // ties stay in capture order either way
+    convs.sort_by(|a, b| {
+        let ord = cmp(a, b);
+        if desc { ord.reverse() } else { ord }.then(a.id.cmp(&b.id))
+    });
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::reader::CaptureReader;
+    use crate::parse_pcap::simple_parse_pcap;
+    use crate::time_format::TimeFormat;
+    use crate::test_util::*;
+
+    const CLIENT: u16 = 40000;
+    const SERVER: u16 = 5000;
+    const SYN_ACK: u8 = TCP_FLAG_SYN | TCP_FLAG_ACK;
+    const PSH_ACK: u8 = 0x08 | TCP_FLAG_ACK;
+
+    // (milliseconds, from the client, seq, ack, flags, payload length)
+    fn parse(segments: &[(u32, bool, u32, u32, u8, usize)]) -> Vec<Conversation>
+    {
+        let frames: Vec<_> = segments.iter().map(|&(ms, from_client, seq, ack, flags, len)| {
+            let payload = vec![0xab; len];
+            let frame = match from_client {
+                true => eth(0x0800, &ipv4(PROTO_TYPE_TCP as u8, &tcp(CLIENT, SERVER, seq, ack, flags, &payload))),
+                false => eth(0x0800, &ipv4_reply(PROTO_TYPE_TCP as u8, &tcp(SERVER, CLIENT, seq, ack, flags, &payload))),
+            };
+            (ms * 1000, frame)
+        }).collect();
+        let records: Vec<_> = frames.iter().map(|(us, f)| (1, *us, f.as_slice())).collect();
+
+        let file = pcap_file(false, false, &records);
+        let time_fmt = TimeFormat::from_query(None, None).unwrap();
+        let parsed = simple_parse_pcap(CaptureReader::from_bytes(&file).unwrap(), "tcp.pcap".to_string(),
+            &time_fmt, &DecodeAs::default()).unwrap();
+        parsed.index.conversations
+    }
+
+    #[test]
+    fn tcp_conversation_counts_both_directions_and_rtt() {
+        let convs = parse(&[
+            (0, true, 100, 0, TCP_FLAG_SYN, 0),
+            (10, false, 500, 101, SYN_ACK, 0),
+            (20, true, 101, 501, TCP_FLAG_ACK, 0),
+            (30, true, 101, 501, PSH_ACK, 10),
+            (60, false, 501, 111, PSH_ACK, 20),
+            (70, true, 111, 521, TCP_FLAG_ACK, 0),
+        ]);
+
+        assert_eq!(convs.len(), 1);
+        let conv = &convs[0];
+        assert_eq!((conv.protocol.as_str(), conv.addr_a.as_str(), conv.port_a), ("TCP", "10.0.0.1", CLIENT));
+        assert_eq!((conv.addr_b.as_str(), conv.port_b), ("10.0.0.2", SERVER));
+
+        // 58 bytes on the wire per frame: Ethernet, IPv4 and TCP headers,
+        // and the 4 bytes pcap_file() leaves out of the capture
+        assert_eq!((conv.packets_a_to_b, conv.bytes_a_to_b), (4, 4 * 58 + 10));
+        assert_eq!((conv.packets_b_to_a, conv.bytes_b_to_a), (2, 2 * 58 + 20));
+        assert_eq!((conv.packets, conv.first_packet, conv.last_packet), (6, 1, 6));
+        assert_eq!(conv.duration_ns, 70_000_000);
+
+        // SYN, SYN/ACK, request and response are each acknowledged once
+        let tcp = conv.tcp.as_ref().unwrap();
+        assert_eq!((tcp.rtt_samples, tcp.retransmissions), (4, 0));
+        assert_eq!(tcp.rtt_min_ns, Some(10_000_000));
+        assert_eq!(tcp.rtt_avg_ns, Some(15_000_000));
+        assert_eq!(tcp.rtt_max_ns, Some(30_000_000));
+    }
+}
@@ -0,0 +1,252 @@
+pub mod registry;
+
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+use serde::Serialize;
+
+use crate::types::*;
+use crate::ip::reassembly::Fragment;
+use crate::l4::tcp_stream::TcpSegment;
+use crate::l4::sctp_stream::SctpPacket;
+use crate::dissect::registry::DissectorTable;
+
+
+/// How much of a packet a consumer needs decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Depth {
+    Summary,    // addresses, ports, protocol and description for the packet list
+    Flow,       // plus application headers and IEs, for call flow analysis
+    Detail,     // every field of every layer, for the packet detail view
+}
+
+
+/// Key a dissector is registered under, and how a layer names the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerKey {
+    Ethertype(u16),
+    IpProto(u8),
+    UdpPort { src: u16, dst: u16 },
+    TcpPort { src: u16, dst: u16 },
+    SctpPpid(u32),
+}
+
+/// Where decoding continues after a layer.
+#[derive(Debug, Clone, Copy)]
+pub struct NextLayer {
+    pub key: LayerKey,
+    pub hdr_len: usize,     // bytes of the current layer before the next one starts
+    pub len: Option<usize>, // whole layer as its header declares it; bytes past it are padding
+}
+
+
+/// Innermost addresses and ports, as typed values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FlowTuple {
+    pub protocol: u8,
+    pub src_addr: Option<IpAddr>,
+    pub dst_addr: Option<IpAddr>,
+    pub src_port: u16,
+    pub dst_port: u16,
+}
+impl FlowTuple {
+    pub fn new() -> Self {
+        FlowTuple {
+            protocol: 0,
+            src_addr: None,
+            dst_addr: None,
+            src_port: 0,
+            dst_port: 0,
+        }
+    }
+}
+
+/// Bytes of one packet that went into a message reassembled across packets
+/// (TCP PDU, SCTP user message).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamChunk {
+    pub packet_id: usize,
+    pub start: usize,   // offset in the frame, or in the datagram the packet completed
+    pub len: usize,
+}
+
+/// One decoded layer and the bytes it covers in the frame.
+#[derive(Debug, Clone, Serialize)]
+pub struct LayerSpan {
+    pub protocol: &'static str,
+    pub offset: usize,
+    pub len: usize,
+    pub reassembled: bool,  // offset is into the reassembled datagram, not the frame
+}
+
+
+/// Everything decoded from one frame. Every depth fills `summary` and `flow`;
+/// `detail.app` is filled from `Depth::Flow` and the rest of `detail` only at
+/// `Depth::Detail`.
+pub struct Dissection {
+    pub depth: Depth,
+    pub summary: PacketSummary,
+    pub detail: PacketDetail,
+    pub flow: FlowTuple,
+    pub layers: Vec<LayerSpan>,
+    pub offset: usize,      // frame offset of the layer being dissected
+    pub fragment: Option<Fragment>,
+    pub segment: Option<TcpSegment>,    // TCP payload, for the stream it belongs to
+    pub sctp: Option<SctpPacket>,       // SCTP chunks, for the association they belong to
+    pub complete: bool,     // the layer below declared a length and all of it was captured
+    pub in_quote: bool,     // decoding the packet quoted by an ICMP error
+    pub quoted_flow: Option<FlowTuple>,     // flow of the packet an ICMP error quotes
+    pub transaction: Option<u32>,   // GTPv2-C/PFCP sequence number, pairs a request with what answers it
+    pub table: Arc<DissectorTable>, // the file's table, for layers decoded out of line (ICMP quotes)
+}
+impl Dissection {
+    pub fn new(depth: Depth, table: Arc<DissectorTable>) -> Self {
+        Dissection {
+            depth,
+            summary: PacketSummary::new(),
+            detail: PacketDetail::new(),
+            flow: FlowTuple::new(),
+            layers: Vec::new(),
+            offset: 0,
+            fragment: None,
+            segment: None,
+            sctp: None,
+            complete: false,
+            in_quote: false,
+            quoted_flow: None,
+            transaction: None,
+            table,
+        }
+    }
+
+    pub fn wants_detail(&self) -> bool {
+        self.depth >= Depth::Detail
+    }
+
+    pub fn wants_app(&self) -> bool {
+        self.depth >= Depth::Flow
+    }
+
+    /// Flags the packet when a layer's checksum did not match.
+    pub fn check_checksum(&mut self, status: ChecksumStatus) -> ChecksumStatus {
+        if status == ChecksumStatus::Bad {
+            self.summary.bad_checksum = true;
+        }
+        status
+    }
+
+    /// Records `err` against the current layer. Dissectors call this directly
+    /// for problems that do not stop decoding, e.g. a bad IPv4 option.
+    pub fn mark_malformed(&mut self, protocol: &str, err: &DecodeError) {
+        self.summary.malformed = true;
+        self.detail.malformed.push(Malformed {
+            protocol: protocol.to_string(),
+            offset: self.offset + err.offset(),
+            reason: err.to_string(),
+        });
+    }
+}
+
+
+/// Why a layer could not be decoded. Offsets and byte counts are relative
+/// to the start of the layer; `dissect_frame` turns them into frame offsets.
+#[derive(Debug, Clone)]
+pub enum DecodeError {
+    /// a field at `offset` runs past the `available` captured bytes
+    Truncated { offset: usize, needed: Option<usize>, available: usize },
+    /// a field holds a value the protocol does not allow
+    Invalid { offset: usize, reason: String },
+}
+
+impl DecodeError {
+    pub fn offset(&self) -> usize {
+        match self {
+            DecodeError::Truncated { offset, .. } => *offset,
+            DecodeError::Invalid { offset, .. } => *offset,
+        }
+    }
+
+    pub fn invalid(offset: usize, reason: impl Into<String>) -> Self {
+        DecodeError::Invalid { offset, reason: reason.into() }
+    }
+
+    /// Same error, seen from `by` bytes earlier in the packet.
+    pub fn shifted(self, by: usize) -> Self {
+        match self {
+            DecodeError::Truncated { offset, needed, available } => DecodeError::Truncated {
+                offset: offset + by,
+                needed: needed.map(|n| n + by),
+                available: available + by,
+            },
+            DecodeError::Invalid { offset, reason } => DecodeError::Invalid { offset: offset + by, reason },
+        }
+    }
+
+    /// Maps a nom failure inside `data` to the offset where it stopped.
+    /// Every nom parser here works on complete input, so a failure means
+    /// the bytes ran out.
+    pub fn from_nom(data: &[u8], err: nom::Err<nom::error::Error<&[u8]>>) -> Self {
+        let offset = match err {
+            nom::Err::Error(e) | nom::Err::Failure(e) => data.len() - e.input.len(),
+            nom::Err::Incomplete(_) => data.len(),
+        };
+
+        DecodeError::Truncated { offset, needed: None, available: data.len() }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated { needed: Some(needed), available, .. } =>
+                write!(f, "truncated: needs {} bytes, {} captured", needed, available),
+            DecodeError::Truncated { offset, needed: None, available } =>
+                write!(f, "truncated: field at byte {} runs past {} captured bytes", offset, available),
+            DecodeError::Invalid { reason, .. } => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// Fails with `Truncated` unless `data` holds at least `len` bytes.
+pub fn need(data: &[u8], len: usize) -> Result<(), DecodeError> {
+    if data.len() < len {
+        return Err(DecodeError::Truncated { offset: data.len(), needed: Some(len), available: data.len() });
+    }
+    Ok(())
+}
+
+
+/// End of a GTPv2-C or PFCP message whose length field holds `msg_len`.
+/// The field leaves out the first 4 octets: flags, message type and itself.
+pub fn gtp_msg_end(msg_len: u16) -> usize {
+    msg_len as usize + 4
+}
+
+
+/// Decoder for one protocol layer. Implementations live next to the protocol
+/// they decode and are registered in a `DissectorTable`.
+pub trait Dissector: Send + Sync {
+    /// Protocol name shown in layer lists, e.g. "IPv4"
+    fn name(&self) -> &'static str;
+
+    /// Decode the header at the start of `data` into `ctx`, as deep as
+    /// `ctx.depth` asks. Returns the next layer, or None if this is the last one.
+    /// Whatever was decoded before an error stays in `ctx`.
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError>;
+
+    /// Whether `data` looks like this protocol, for traffic on ports nothing
+    /// is registered for. Only asked when heuristics are turned on.
+    fn heuristic(&self, _data: &[u8]) -> bool {
+        false
+    }
+
+    /// Length of the message at the start of a TCP byte stream, or None
+    /// until enough of it has arrived to tell. Protocols without their own
+    /// framing take whatever is there.
+    fn pdu_len(&self, data: &[u8]) -> Option<usize> {
+        Some(data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use crate::types::{DecodeAs, SctpUserMessage, TcpPdu};
+use crate::dissect::*;
+use crate::l2::{ethernet::*, link::*};
+use crate::ip::{ipv4::*, ipv6::*, port::*, reassembly::*};
+use crate::l4::{tcp::*, tcp_stream::*, udp::*, sctp::*, sctp_stream::*, icmp::*, icmpv6::*};
+use crate::gtp::{gtp::*, gtpu::*};
+use crate::pfcp::pfcp::*;
+use crate::tunnel::{gre::*, erspan::*, vxlan::*};
+
+// guards against tunnels that point back at themselves
+const MAX_LAYERS: usize = 16;
+
+
+// inclusive port range and the dissector it is decoded as
+type PortRange = (u16, u16, Arc<dyn Dissector>);
+
+/// Dissectors keyed by ethertype, IP protocol number, UDP/TCP port and SCTP PPID.
+#[derive(Clone)]
+pub struct DissectorTable {
+    ethertypes: HashMap<u16, Arc<dyn Dissector>>,
+    ip_protos: HashMap<u8, Arc<dyn Dissector>>,
+    udp_ports: HashMap<u16, Arc<dyn Dissector>>,
+    tcp_ports: HashMap<u16, Arc<dyn Dissector>>,
+    sctp_ppids: HashMap<u32, Arc<dyn Dissector>>,
+    // "Decode As" ranges, tried before the well-known ports
+    udp_ranges: Vec<PortRange>,
+    tcp_ranges: Vec<PortRange>,
+    udp_heuristics: Vec<Arc<dyn Dissector>>,
+    use_heuristics: bool,
+    // TCP payloads are left to a `TcpReassembler` instead of decoded per segment
+    tcp_streams: bool,
+}
+
+impl DissectorTable {
+    pub fn new() -> Self {
+        DissectorTable {
+            ethertypes: HashMap::new(),
+            ip_protos: HashMap::new(),
+            udp_ports: HashMap::new(),
+            tcp_ports: HashMap::new(),
+            sctp_ppids: HashMap::new(),
+            udp_ranges: Vec::new(),
+            tcp_ranges: Vec::new(),
+            udp_heuristics: Vec::new(),
+            use_heuristics: false,
+            tcp_streams: false,
+        }
+    }
+
+    pub fn register_ethertype(&mut self, ethertype: u16, dissector: Arc<dyn Dissector>) {
+        self.ethertypes.insert(ethertype, dissector);
+    }
+
+    pub fn register_ip_proto(&mut self, proto: u8, dissector: Arc<dyn Dissector>) {
+        self.ip_protos.insert(proto, dissector);
+    }
+
+    pub fn register_udp_port(&mut self, port: u16, dissector: Arc<dyn Dissector>) {
+        self.udp_ports.insert(port, dissector);
+    }
+
+    #[allow(dead_code)]
+    pub fn register_tcp_port(&mut self, port: u16, dissector: Arc<dyn Dissector>) {
+        self.tcp_ports.insert(port, dissector);
+    }
+
+    /// Upper layer of SCTP user messages carrying `ppid`
+    #[allow(dead_code)]
+    pub fn register_sctp_ppid(&mut self, ppid: u32, dissector: Arc<dyn Dissector>) {
+        self.sctp_ppids.insert(ppid, dissector);
+    }
+
+    pub fn register_udp_range(&mut self, first: u16, last: u16, dissector: Arc<dyn Dissector>) {
+        self.udp_ranges.push((first, last, dissector));
+    }
+
+    pub fn register_tcp_range(&mut self, first: u16, last: u16, dissector: Arc<dyn Dissector>) {
+        self.tcp_ranges.push((first, last, dissector));
+    }
+
+    /// `dissector.heuristic()` is asked about UDP payloads on unknown ports
+    pub fn register_udp_heuristic(&mut self, dissector: Arc<dyn Dissector>) {
+        self.udp_heuristics.push(dissector);
+    }
+
+    /// Stop at the TCP header; the payload is decoded by `follow_tcp_stream`
+    /// once the PDUs it carries are complete.
+    pub fn set_tcp_streams(&mut self, on: bool) {
+        self.tcp_streams = on;
+    }
+
+    /// Ports are tried destination first, then source, so responses sent back
+    /// to an ephemeral port are still recognised. `data` is the payload the
+    /// key came from, for heuristic dissectors.
+    pub fn lookup(&self, key: &LayerKey, data: &[u8]) -> Option<&Arc<dyn Dissector>> {
+        match *key {
+            LayerKey::Ethertype(t) => self.ethertypes.get(&t),
+            LayerKey::IpProto(p) => self.ip_protos.get(&p),
+            LayerKey::UdpPort { src, dst } =>
+                lookup_port(&self.udp_ranges, &self.udp_ports, src, dst)
+                    .or_else(|| self.lookup_heuristic(data)),
+            LayerKey::TcpPort { src, dst } =>
+                lookup_port(&self.tcp_ranges, &self.tcp_ports, src, dst),
+            LayerKey::SctpPpid(ppid) => self.sctp_ppids.get(&ppid),
+        }
+    }
+
+    fn lookup_heuristic(&self, data: &[u8]) -> Option<&Arc<dyn Dissector>> {
+        if !self.use_heuristics {
+            return None;
+        }
+        self.udp_heuristics.iter().find(|d| d.heuristic(data))
+    }
+
+    /// Names a "Decode As" rule can use: every dissector registered on a port.
+    pub fn port_protocols(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self.udp_ports.values()
+            .chain(self.tcp_ports.values())
+            .map(|d| d.name())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    fn port_dissector(&self, name: &str) -> Option<Arc<dyn Dissector>> {
+        self.udp_ports.values()
+            .chain(self.tcp_ports.values())
+            .find(|d| d.name().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    /// This table with a file's "Decode As" rules and heuristics applied.
+    pub fn with_decode_as(&self, decode_as: &DecodeAs) -> Result<DissectorTable, String> {
+        let mut table = self.clone();
+
+        for rule in &decode_as.rules {
+            let dissector = self.port_dissector(&rule.protocol)
+                .ok_or_else(|| format!("Unknown protocol \"{}\" (one of {})",
+                    rule.protocol, self.port_protocols().join(", ")))?;
+
+            let last = rule.port_end.unwrap_or(rule.port);
+            if last < rule.port {
+                return Err(format!("Port range {}-{} is empty", rule.port, last));
+            }
+
+            match rule.transport.to_ascii_lowercase().as_str() {
+                "udp" => table.register_udp_range(rule.port, last, dissector),
+                "tcp" => table.register_tcp_range(rule.port, last, dissector),
+                other => return Err(format!("Unknown transport \"{}\" (udp or tcp)", other)),
+            }
+        }
+        table.use_heuristics = decode_as.heuristics;
+
+        Ok(table)
+    }
+}
+
+
+fn lookup_port<'a>(ranges: &'a [PortRange], ports: &'a HashMap<u16, Arc<dyn Dissector>>,
+    src: u16, dst: u16)
+-> Option<&'a Arc<dyn Dissector>>
+{
+    let in_range = |port: u16| ranges.iter()
+        .find(|(first, last, _)| (*first..=*last).contains(&port))
+        .map(|(_, _, d)| d);
+
+    in_range(dst)
+        .or_else(|| in_range(src))
+        .or_else(|| ports.get(&dst))
+        .or_else(|| ports.get(&src))
+}
+
+
+/// The built-in protocols. A protocol registered here is decoded the same way
+/// for the summary, the packet detail and the call flow.
+pub fn default_table() -> &'static Arc<DissectorTable>
+{
+    static TABLE: OnceLock<Arc<DissectorTable>> = OnceLock::new();
+
+    TABLE.get_or_init(|| {
+        let mut table = DissectorTable::new();
+
+        let ipv4: Arc<dyn Dissector> = Arc::new(Ipv4Dissector);
+        table.register_ethertype(NEXT_HDR_IPV4 as u16, ipv4.clone());
+        table.register_ip_proto(PROTO_TYPE_IPINIP as u8, ipv4);
+        let ipv6: Arc<dyn Dissector> = Arc::new(Ipv6Dissector);
+        table.register_ethertype(NEXT_HDR_IPV6 as u16, ipv6.clone());
+        table.register_ip_proto(PROTO_TYPE_IPV6 as u8, ipv6);
+
+        table.register_ip_proto(PROTO_TYPE_TCP as u8, Arc::new(TcpDissector));
+        table.register_ip_proto(PROTO_TYPE_UDP as u8, Arc::new(UdpDissector));
+        table.register_ip_proto(PROTO_TYPE_SCTP as u8, Arc::new(SctpDissector));
+        table.register_ip_proto(PROTO_TYPE_ICMP as u8, Arc::new(IcmpDissector));
+        table.register_ip_proto(PROTO_TYPE_ICMPV6 as u8, Arc::new(Icmpv6Dissector));
+
+        // tunnels; the payload is looked up again, so they nest
+        table.register_ip_proto(PROTO_TYPE_GRE as u8, Arc::new(GreDissector));
+        table.register_ethertype(ETHERTYPE_TEB as u16, Arc::new(EthernetDissector));
+        table.register_ethertype(ETHERTYPE_ERSPAN_II as u16, Arc::new(ErspanDissector { erspan_type: 2 }));
+        table.register_ethertype(ETHERTYPE_ERSPAN_III as u16, Arc::new(ErspanDissector { erspan_type: 3 }));
+        table.register_udp_port(L4_PORT_VXLAN, Arc::new(VxlanDissector));
+        table.register_udp_port(L4_PORT_GTPU, Arc::new(GtpuDissector));
+
+        let gtpv2: Arc<dyn Dissector> = Arc::new(Gtpv2Dissector);
+        table.register_udp_port(L4_PORT_GTPV2, gtpv2.clone());
+        table.register_udp_heuristic(gtpv2);
+        let pfcp: Arc<dyn Dissector> = Arc::new(PfcpDissector);
+        table.register_udp_port(L4_PORT_PFCP, pfcp.clone());
+        table.register_udp_heuristic(pfcp);
+
+        Arc::new(table)
+    })
+}
+
+
+/// Decode a frame captured with `link_type` down to `depth`.
+/// Fragments are left in `ctx.fragment` undecoded; see `dissect_frame_reassembled`.
+pub fn dissect_frame(table: &Arc<DissectorTable>, link_type: u16, data: &[u8], depth: Depth)
+-> Dissection
+{
+    let mut ctx = Dissection::new(depth, table.clone());
+    ctx.detail.l2.frame_len = data.len();
+
+    let Some((ethertype, offset)) = parse_l2(link_type, data, &mut ctx.detail.l2) else {
+        // unsupported link types are simply not decoded; a known one that
+        // does not parse is a broken frame
+        if link_type_to_str(link_type).is_some() {
+            let err = DecodeError::invalid(0, "link layer header is truncated or unrecognised");
+            ctx.mark_malformed("Link", &err);
+        }
+        return ctx;
+    };
+
+    ctx.layers.push(LayerSpan {
+        protocol: "Link",
+        offset: 0,
+        len: offset,
+        reassembled: false,
+    });
+
+    dissect_layers(table, &mut ctx, data, offset, LayerKey::Ethertype(ethertype as u16), false);
+
+    ctx
+}
+
+
+/// Like `dissect_frame`, for frames handed over in capture order. Fragments
+/// are collected in `frags`; the frame that completes a datagram also gets the
+/// reassembled payload decoded, with the fragment ids in its summary.
+pub fn dissect_frame_reassembled(table: &Arc<DissectorTable>, frags: &mut Reassembler,
+    packet_id: usize, link_type: u16, data: &[u8], depth: Depth)
+-> (Dissection, Option<Reassembled>)
+{
+    let mut ctx = dissect_frame(table, link_type, data, depth);
+
+    let Some(frag) = ctx.fragment.take() else {
+        return (ctx, None);
+    };
+    let Some(payload) = data.get(frag.payload_at..frag.payload_at + frag.payload_len) else {
+        return (ctx, None);
+    };
+    let Some(datagram) = frags.add(packet_id, &frag, payload) else {
+        return (ctx, None);
+    };
+
+    ctx.summary.fragments = datagram.packet_ids.clone();
+    ctx.detail.fragments = datagram.packet_ids.clone();
+    ctx.flow.protocol = datagram.protocol;
+
+    dissect_layers(table, &mut ctx, &datagram.payload, 0, LayerKey::IpProto(datagram.protocol), true);
+
+    (ctx, Some(datagram))
+}
+
+
+/// TCP payload of the segment in `ctx`. `data` is what its offsets point
+/// into: the frame, or the datagram it was reassembled into.
+pub fn segment_payload<'a>(ctx: &Dissection, data: &'a [u8]) -> &'a [u8]
+{
+    ctx.segment.as_ref()
+        .and_then(|seg| data.get(seg.payload_at..seg.payload_at + seg.payload_len))
+        .unwrap_or_default()
+}
+
+
+/// Hand the TCP segment found in `ctx` to `streams` and decode the PDUs it
+/// completed, with the analysis of the segment in the summary. `data` is as
+/// for `segment_payload`; None if the packet has no TCP segment.
+pub fn follow_tcp_stream(table: &DissectorTable, streams: &mut TcpReassembler,
+    packet_id: usize, time_ns: i64, ctx: &mut Dissection, data: &[u8])
+-> Option<SegmentResult>
+{
+    let payload = segment_payload(ctx, data);
+    let seg = ctx.segment.take()?;
+
+    let key = LayerKey::TcpPort { src: ctx.flow.src_port, dst: ctx.flow.dst_port };
+    let framing = table.lookup(&key, payload).map(|d| d.as_ref());
+    let result = streams.add(packet_id, time_ns, &ctx.flow, &seg, payload, framing);
+
+    ctx.summary.tcp_analysis = result.analysis.clone();
+    let pdus: Vec<&[u8]> = result.pdus.iter().map(|p| &p.data[..]).collect();
+    let info: Vec<TcpPdu> = result.pdus.iter().map(|p| pdu_info(&p.chunks)).collect();
+    dissect_stream_pdus(table, ctx, &pdus, info);
+
+    Some(result)
+}
+
+
+/// Decode PDUs reassembled from the TCP stream of the segment in `ctx`, in
+/// stream order, above the segment's own layers.
+pub fn dissect_stream_pdus(table: &DissectorTable, ctx: &mut Dissection, pdus: &[&[u8]], info: Vec<TcpPdu>)
+{
+    let key = LayerKey::TcpPort { src: ctx.flow.src_port, dst: ctx.flow.dst_port };
+    let messages: Vec<_> = pdus.iter().map(|pdu| (key, *pdu)).collect();
+
+    dissect_messages(table, ctx, &messages);
+    ctx.detail.tcp_pdus = info;
+}
+
+
+/// Hand the SCTP chunks found in `ctx` to `assocs` and decode the user
+/// messages they completed by their PPID. `data` is as for
+/// `segment_payload`; None if the packet is not SCTP.
+pub fn follow_sctp(table: &DissectorTable, assocs: &mut SctpReassembler,
+    packet_id: usize, ctx: &mut Dissection, data: &[u8])
+-> Option<SctpResult>
+{
+    let pkt = ctx.sctp.take()?;
+    let result = assocs.add(packet_id, &ctx.flow, &pkt, data);
+
+    let messages: Vec<(u32, &[u8])> = result.messages.iter().map(|m| (m.ppid, &m.data[..])).collect();
+    let info: Vec<SctpUserMessage> = result.assoc
+        .map(|assoc| result.messages.iter().map(|m| message_info(assoc, m)).collect())
+        .unwrap_or_default();
+    dissect_sctp_messages(table, ctx, &messages, info);
+
+    Some(result)
+}
+
+
+/// Decode SCTP user messages, given with their PPID, above the packet's own layers.
+pub fn dissect_sctp_messages(table: &DissectorTable, ctx: &mut Dissection,
+    messages: &[(u32, &[u8])], info: Vec<SctpUserMessage>)
+{
+    let messages: Vec<_> = messages.iter().map(|(ppid, msg)| (LayerKey::SctpPpid(*ppid), *msg)).collect();
+
+    dissect_messages(table, ctx, &messages);
+    ctx.detail.sctp_messages = info;
+}
+
+
+// several messages in one packet are listed together; the transport's own
+// description stays when none of them has one
+fn dissect_messages(table: &DissectorTable, ctx: &mut Dissection, messages: &[(LayerKey, &[u8])])
+{
+    let original = std::mem::take(&mut ctx.summary.description);
+    let mut descriptions = Vec::new();
+
+    for (key, msg) in messages {
+        ctx.summary.description.clear();
+        dissect_layers(table, ctx, msg, 0, *key, true);
+        if !ctx.summary.description.is_empty() {
+            descriptions.push(ctx.summary.description.clone());
+        }
+    }
+
+    ctx.summary.description = if descriptions.is_empty() { original } else { descriptions.join("; ") };
+}
+
+
+/// Decode the packet quoted by an ICMP/ICMPv6 error, which usually stops
+/// after a few bytes of its transport header. Only the summary is decoded,
+/// and errors quoted inside it are not followed.
+pub fn dissect_quoted(table: &Arc<DissectorTable>, data: &[u8]) -> Dissection
+{
+    let mut ctx = Dissection::new(Depth::Summary, table.clone());
+    ctx.in_quote = true;
+
+    let ethertype = match data.first().map(|b| b >> 4) {
+        Some(6) => NEXT_HDR_IPV6,
+        _ => NEXT_HDR_IPV4,
+    };
+    dissect_layers(table, &mut ctx, data, 0, LayerKey::Ethertype(ethertype as u16), false);
+
+    ctx
+}
+
+
+fn dissect_layers(table: &DissectorTable, ctx: &mut Dissection, data: &[u8],
+    mut offset: usize, mut key: LayerKey, reassembled: bool)
+{
+    let mut end = data.len();
+    // a reassembled datagram is whole; a frame may have been cut by the snaplen
+    let mut complete = reassembled;
+    let first = ctx.layers.len();
+
+    while ctx.layers.len() - first <= MAX_LAYERS {
+        let Some(rest) = data.get(offset..end) else {
+            break;
+        };
+        let Some(dissector) = table.lookup(&key, rest) else {
+            break;
+        };
+
+        ctx.offset = offset;
+        ctx.complete = complete;
+        let next = match dissector.dissect(rest, ctx) {
+            Ok(next) => next,
+            Err(e) => {
+                // keep the layers decoded so far and stop this frame here
+                ctx.mark_malformed(dissector.name(), &e);
+                ctx.layers.push(LayerSpan {
+                    protocol: dissector.name(),
+                    offset,
+                    len: rest.len(),
+                    reassembled,
+                });
+                break;
+            },
+        };
+
+        // trailing padding (e.g. short Ethernet frames) is not passed upwards
+        let len = next.and_then(|n| n.len).unwrap_or(rest.len()).min(rest.len());
+
+        ctx.layers.push(LayerSpan {
+            protocol: dissector.name(),
+            offset,
+            len: next.map(|n| n.hdr_len).unwrap_or(len),
+            reassembled,
+        });
+
+        match next {
+            Some(NextLayer { key: LayerKey::TcpPort { .. }, .. }) if table.tcp_streams => break,
+            Some(next) => {
+                // layers without a length field (TCP) inherit it from below
+                if let Some(declared) = next.len {
+                    complete = declared <= rest.len();
+                }
+                end = offset + len;
+                offset += next.hdr_len;
+                key = next.key;
+            },
+            None => break,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::dissect::*;
+use crate::dissect::registry::*;
+use crate::ip::port::*;
+
+
+// GTPv2-C Echo Request, sequence number 1
+const ECHO_REQUEST: [u8; 8] = [0x40, 0x01, 0x00, 0x04, 0x00, 0x00, 0x01, 0x00];
+
+fn rule(transport: &str, port: u16, port_end: Option<u16>, protocol: &str) -> DecodeAsRule
+{
+    DecodeAsRule { transport: transport.to_string(), port, port_end, protocol: protocol.to_string() }
+}
+
+fn udp_lookup(table: &DissectorTable, src: u16, dst: u16, data: &[u8]) -> Option<&'static str>
+{
+    table.lookup(&LayerKey::UdpPort { src, dst }, data).map(|d| d.name())
+}
+
+
+#[test]
+fn decode_as_rule_beats_the_default_port() {
+    let decode_as = DecodeAs {
+        rules: vec![rule("udp", L4_PORT_GTPV2, None, "PFCP"), rule("UDP", 40000, Some(40010), "GTPv2-C")],
+        heuristics: false,
+    };
+    let table = default_table().with_decode_as(&decode_as).unwrap();
+
+    assert_eq!(udp_lookup(default_table(), 40000, L4_PORT_GTPV2, &[]), Some("GTPv2-C"));
+    assert_eq!(udp_lookup(&table, 40000, L4_PORT_GTPV2, &[]), Some("PFCP"));
+    // a rule on the source port still comes before the destination's default
+    assert_eq!(udp_lookup(&table, 40010, L4_PORT_PFCP, &[]), Some("GTPv2-C"));
+    assert_eq!(udp_lookup(&table, 40011, L4_PORT_PFCP, &[]), Some("PFCP"));
+    // the rule is for UDP only
+    assert!(table.lookup(&LayerKey::TcpPort { src: 40000, dst: L4_PORT_GTPV2 }, &[]).is_none());
+}
+
+#[test]
+fn heuristics_run_only_when_enabled() {
+    let heuristics = DecodeAs { rules: Vec::new(), heuristics: true };
+    let table = default_table().with_decode_as(&heuristics).unwrap();
+
+    assert_eq!(udp_lookup(default_table(), 40000, 5000, &ECHO_REQUEST), None);
+    assert_eq!(udp_lookup(&table, 40000, 5000, &ECHO_REQUEST), Some("GTPv2-C"));
+    // a length that does not match the datagram is not taken for GTPv2-C
+    assert_eq!(udp_lookup(&table, 40000, 5000, &ECHO_REQUEST[..7]), None);
+    // a registered port is not second-guessed
+    assert_eq!(udp_lookup(&table, 40000, L4_PORT_PFCP, &ECHO_REQUEST), Some("PFCP"));
+}
+
+#[test]
+fn decode_as_rejects_bad_rules() {
+    let bad = [
+        rule("udp", 5000, None, "NoSuchProtocol"),
+        rule("udp", 5000, Some(4999), "PFCP"),
+        rule("sctp", 5000, None, "PFCP"),
+    ];
+    for rule in bad {
+        let decode_as = DecodeAs { rules: vec![rule.clone()], heuristics: false };
+        assert!(default_table().with_decode_as(&decode_as).is_err(), "{:?}", rule);
+    }
+}
@@ -2,9 +2,11 @@ use std:: path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Instant;
 use std::collections::HashMap;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 
 use crate::types::*;
+use crate::capture::index::*;
+use crate::nodes::*;
 
 static FILE_ID_GEN: AtomicU64 = AtomicU64::new(1);
 
@@ -23,7 +25,10 @@ pub struct FileContext {
     pub uuid: String,
     pub original_name: PathBuf,
     pub packets: Vec<PacketSummary>,
+    pub index: Arc<CaptureIndex>,
     pub parsed_at: Instant,
+    pub inferred: Vec<InferredNode>,
+    pub aliases: Vec<NodeAlias>,
 }
 
 
@@ -39,14 +44,18 @@ impl PcapFiles {
         }
     }
 
-    pub fn insert_file(&self, uuid:String, original_name:PathBuf, packets: Vec<PacketSummary>) -> FileId {
+    pub fn insert_file(&self, uuid:String, original_name:PathBuf, packets: Vec<PacketSummary>,
+        index: CaptureIndex, inferred: Vec<InferredNode>) -> FileId {
         let file_id = FileId::new();
 
         let ctx = FileContext {
             uuid,
             original_name,
             packets,
+            index: Arc::new(index),
             parsed_at: Instant::now(),
+            inferred,
+            aliases: Vec::new(),
         };
 
         self.files.write().unwrap().insert(file_id, ctx);
@@ -54,6 +63,54 @@ impl PcapFiles {
         file_id
     }
 
+    /// Swap in the result of parsing a file again, e.g. with new "Decode As" rules.
+    /// The file's aliases are kept and applied to the new summaries, which are returned.
+    pub fn replace_parsed(&self, file_id: FileId, mut packets: Vec<PacketSummary>,
+        index: CaptureIndex, inferred: Vec<InferredNode>) -> Option<(Vec<PacketSummary>, Vec<NetworkNode>)> {
+        let mut files = self.files.write().unwrap();
+        let ctx = files.get_mut(&file_id)?;
+
+        // aliases were checked when they were set
+        let nodes = NodeDirectory::new(&ctx.aliases, &inferred).ok()?;
+        nodes.name_packets(&mut packets);
+
+        ctx.packets = packets;
+        ctx.index = Arc::new(index);
+        ctx.parsed_at = Instant::now();
+        ctx.inferred = inferred;
+
+        Some((ctx.packets.clone(), nodes.nodes(&ctx.packets)))
+    }
+
+    /// Replace a file's alias map and rename its summaries with it
+    pub fn set_aliases(&self, file_id: FileId, aliases: Vec<NodeAlias>)
+    -> Result<(Vec<PacketSummary>, Vec<NetworkNode>), String> {
+        let mut files = self.files.write().unwrap();
+        let ctx = files.get_mut(&file_id).ok_or("file not found".to_string())?;
+
+        let nodes = NodeDirectory::new(&aliases, &ctx.inferred)?;
+        nodes.name_packets(&mut ctx.packets);
+        ctx.aliases = aliases;
+
+        Ok((ctx.packets.clone(), nodes.nodes(&ctx.packets)))
+    }
+
+    /// Aliases and inferred roles of a file, for naming addresses
+    pub fn node_directory(&self, file_id: FileId) -> Option<NodeDirectory> {
+        let files = self.files.read().ok()?;
+        let ctx = files.get(&file_id)?;
+
+        NodeDirectory::new(&ctx.aliases, &ctx.inferred).ok()
+    }
+
+    /// Known nodes of a file
+    pub fn nodes(&self, file_id: FileId) -> Option<Vec<NetworkNode>> {
+        let nodes = self.node_directory(file_id)?;
+        let files = self.files.read().ok()?;
+
+        Some(nodes.nodes(&files.get(&file_id)?.packets))
+    }
+
     pub fn get_file_name ( &self, file_id: FileId)
     // -> Option<PathBuf>
     -> Option<FileContext>
@@ -64,6 +121,31 @@ impl PcapFiles {
         result 
     }
 
+    /// Cache key, stored path and packet index of a file, without cloning its summaries
+    pub fn get_file_location ( &self, file_id: FileId)
+    -> Option<(String, PathBuf, Arc<CaptureIndex>)>
+    {
+        let files = self.files.read().ok()?;
+        let ctx = files.get(&file_id)?;
+
+        Some((ctx.uuid.clone(), ctx.original_name.clone(), ctx.index.clone()))
+    }
+
+    /// Ids of the summary rows matching `pred`
+    pub fn find_packet_ids<F>( &self, file_id: FileId, pred: F)
+    -> Option<Vec<usize>>
+    where
+        F: Fn(&PacketSummary) -> bool,
+    {
+        let files = self.files.read().ok()?;
+        let ctx = files.get(&file_id)?;
+
+        Some(ctx.packets.iter()
+            .filter(|p| pred(p))
+            .map(|p| p.id)
+            .collect())
+    }
+
     pub fn get_packet ( &self, file_id: FileId, packet_id: usize)
     -> Option<Vec<PacketSummary>>
     {
@@ -6,6 +6,7 @@ use nom::{
 };
 
 use crate::types::*;
+use crate::dissect::*;
 use crate::gtp::gtp_ie::*;
 use crate::gtp::gtpv2_types::*;
 
@@ -42,16 +43,6 @@ impl GtpHeader {
 }
 
 
-pub fn get_msg_type_from_gtpc<'a>(input: &'a [u8])
-    -> IResult<&'a [u8], String>
-{
-    let (input, _flags) = be_u8(input)?;
-    let (input, msg_type) = be_u8(input)?;
-
-    Ok((input, GTPV2_MSG_TYPES[msg_type as usize].to_string()))
-}
-
-
 fn head_parser<'a>(input: &'a[u8])
     -> IResult<&'a[u8], GtpHeader>
 {
@@ -104,7 +95,7 @@ fn head_parser<'a>(input: &'a[u8])
 }
 
 
-pub fn parse_gtpc<'a>(input: &'a [u8], packet: &'a mut PacketSummary)
+pub fn parse_gtpc<'a>(input: &'a [u8], packet: &mut PacketSummary)
     -> IResult<&'a[u8], GtpHeader>
 {
     let (rest, head) = head_parser(input)?;
@@ -116,99 +107,6 @@ pub fn parse_gtpc<'a>(input: &'a [u8], packet: &'a mut PacketSummary)
     Ok((rest, head))
 }
 
-pub fn get_gtp_hdr_len(input: &[u8])
-    -> usize
-{
-    // let (input, flags) = be_u8(input)?;
-    let flags = input[0];
-    let t_flag = ((flags >> 3) & 0x01) == 1;
-    let mp_flag = ((flags >> 2) & 0x01) == 1;
-
-    let mut len = 8; // Base header length
-
-    if t_flag {
-        len += 4; // TEID field length
-    }
-    if mp_flag {
-        len += 1; // Message Priority field length
-    }
-
-    len
-}
-
-
-pub fn get_gtp_teid<'a>(input: &'a [u8])
-    -> IResult<&'a[u8], u32>
-{
-    let (input, flags) = be_u8(input)?;
-    let t_flag = ((flags >> 3) & 0x01) == 1;
-
-    let (input, _) = be_u8(input)?;
-    let (input, _) = be_u16(input)?;
-
-    let (input, teid) = if t_flag {
-        let (input, teid) = be_u32(input)?;
-            (input, Some(teid))
-    }
-    else {
-        (input, None)
-    };
-    Ok (( input, teid.unwrap()))
-}
-
-pub fn get_gtp_header(input: & [u8])
-    -> IResult<&[u8], GtpHeader>
-{
-    let (input, flags) = be_u8(input)?;
-    let version = (flags >> 5) & 0x07;
-    let p_flag = ((flags >> 4) & 0x01) == 1;
-    let t_flag = ((flags >> 3) & 0x01) == 1;
-    let mp_flag = ((flags >> 2) & 0x01) == 1;
-
-    let (input, msg_type) = be_u8(input)?;
-    let (input, msg_len) = be_u16(input)?;
-
-    let (input, teid) = if t_flag {
-        let (input, teid) = be_u32(input)?;
-        (input, Some(teid))
-    }
-    else {
-        (input, None)
-    };
-
-    let (input, seq_bytes) = take(3usize)(input)?;
-    let seq = ((seq_bytes[0] as u32) << 16)
-                    | ((seq_bytes[1] as u32) << 8)
-                    | (seq_bytes[2] as u32);
-
-    let (input, mp) = if mp_flag {
-        let (input, m) = be_u8(input)?;
-        let m = (m >> 4) & 0x0f;
-        (input, Some(m))
-    }
-    else {
-        (input, None)
-    };
-
-    let (input, _spare) = be_u8(input)?;
-
-    let mut add = 0;
-    if !teid.is_none() {
-        add += 4;
-    }
-
-    add += 4;
-    let (remaining, _) = take((msg_len-add) as usize)(input)?;
-
-    Ok((remaining, GtpHeader {
-        version,
-        p_flag, t_flag, mp_flag,
-        msg_type, msg_len,
-        teid, seq, mp,
-    }))
-}
-
-
 pub fn parse_gtpc_detail<'a>(input: &'a [u8])//, packet: &'a mut PacketDetail)
     -> IResult<&'a[u8], GtpInfo>
 {
@@ -227,8 +125,65 @@ pub fn parse_gtpc_detail<'a>(input: &'a [u8])//, packet: &'a mut PacketDetail)
         seq:            head.seq,
         mp:             if head.mp_flag {head.mp} else {None},
         ies:            Vec::new(),
-        raw:            input[..head.msg_len as usize].to_vec(),
+        raw:            input[..gtp_msg_end(head.msg_len).min(input.len())].to_vec(),
     };
 
     Ok (( rest, info))
-}
\ No newline at end of file
+}
+
+
+pub struct Gtpv2Dissector;
+
+impl Dissector for Gtpv2Dissector {
+    fn name(&self) -> &'static str {
+        "GTPv2-C"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        ctx.summary.protocol = "GTP2-C".to_string();
+        let (rest, head) = parse_gtpc(data, &mut ctx.summary)
+            .map_err(|e| DecodeError::from_nom(data, e))?;
+        ctx.transaction = Some(head.seq);
+
+        if ctx.wants_app()
+            && let Ok((_, gtpinfo)) = parse_gtpc_detail(data) {
+            ctx.detail.app = AppLayerInfo::GTP(gtpinfo);
+        }
+
+        let msg_end = gtp_msg_end(head.msg_len);
+        need(data, msg_end)?;
+
+        if ctx.wants_app() {
+            let ies_at = data.len() - rest.len();
+            if let AppLayerInfo::GTP(gtpinfo) = &mut ctx.detail.app {
+                let ies = rest.get(..msg_end.saturating_sub(ies_at)).unwrap_or_default();
+                parse_all_ies(ies, &mut gtpinfo.ies).map_err(|e| e.shifted(ies_at))?;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Version 2, spare bits clear, a known message type and a length that
+    /// covers the whole datagram (or leaves room for a piggybacked message).
+    fn heuristic(&self, data: &[u8]) -> bool {
+        let Some(&[flags, msg_type, len_hi, len_lo]) = data.get(..4) else {
+            return false;
+        };
+        let p_flag = flags & 0x10 != 0;
+        let t_flag = flags & 0x08 != 0;
+        let msg_end = gtp_msg_end(u16::from_be_bytes([len_hi, len_lo]));
+        let hdr_len = if t_flag { 12 } else { 8 };
+
+        let name = GTPV2_MSG_TYPES[msg_type as usize];
+        let known = name != "Reserved" && name != "Unknown";
+        let len_ok = if p_flag { msg_end <= data.len() } else { msg_end == data.len() };
+
+        flags >> 5 == 2 && flags & 0x03 == 0 && known && msg_end >= hdr_len && len_ok
+    }
+
+    fn pdu_len(&self, data: &[u8]) -> Option<usize> {
+        let len = data.get(2..4)?;
+        Some(gtp_msg_end(u16::from_be_bytes([len[0], len[1]])))
+    }
+}
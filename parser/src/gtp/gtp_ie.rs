@@ -2,12 +2,13 @@ use std::net::{Ipv4Addr, Ipv6Addr};
 use serde::Serialize;
 use nom::{
     IResult,
-    number::complete::{be_u8, be_u16, be_u32},
+    number::complete::{be_u8, be_u16},
     bytes::complete::take,
 };
 use std::convert::TryInto;
 
 use crate::gtp::gtpv2_types::*;
+use crate::dissect::DecodeError;
 use crate::pfcp::{pfcp_ie::*, types::*};
 
 #[derive(Debug, Clone, Serialize)]
@@ -557,7 +558,16 @@ pub fn decode_bearer_tft (input: &[u8])
                 _ => 0,
             };
 
-            let comp_value = &pf_content[comp_offset..(comp_offset + real_len)];
+            let Some(comp_value) = pf_content.get(comp_offset..(comp_offset + real_len)) else {
+                component_list.push(PacketFilterComponentList {
+                    pf_type_id: comp_type,
+                    components: (PacketFilterComponent::Unknown {
+                        t: comp_type,
+                        data: pf_content[comp_offset..].to_vec(),
+                    }),
+                });
+                break;
+            };
             // let comp_value = &pf_content[comp_offset..(comp_offset + comp_len)];
 
             /*
@@ -724,8 +734,8 @@ pub fn decode_fteid<T>(input: &[u8])
 pub fn decode_bearerqos<T>(input: &[u8])
     -> Result<IeValue<T>, String>
 {
-    if input.is_empty() {
-        return Err("input is empty".into());
+    if input.len() < 22 {
+        return Err("Bearer QoS IE: length must be 22".into());
     }
     let mut pos = 0;
 
@@ -780,8 +790,8 @@ pub fn decode_bearerqos<T>(input: &[u8])
 pub fn decode_ambr<T>(input: &[u8])
     -> Result<IeValue<T>, String>
 {
-    if input.is_empty() {
-        return Err("input is empty".into());
+    if input.len() < 8 {
+        return Err("AMBR IE: length must be 8".into());
     }
     let mut pos = 0;
 
@@ -853,6 +863,9 @@ pub fn decode_apn<T>(input: &[u8])
 pub fn decode_ipv4<T>(input: &[u8])
     -> Result<IeValue<T>, String>
 {
+    if input.len() < 4 {
+        return Err("IP Address IE: length must be 4".into());
+    }
     let v = Ipv4Addr::from_octets([
         input[0], input[1], input[2], input[3]
     ]);
@@ -1009,14 +1022,16 @@ pub fn find_ie_ebi(ies: &Vec<GtpIe>)
 fn parse_ie(input: &[u8])
     -> IResult<&[u8], GtpIe>
 {
-    let ie_type = input[0];
-    let ie_len = u16::from_be_bytes([input[1], input[2]]) as usize;
-    let ie_inst = input[3] & 0x0f;
-    let total_len = 4+ie_len;
+    let start = input;
+    let (input, ie_type) = be_u8(input)?;
+    let (input, ie_len) = be_u16(input)?;
+    let (mut input, inst) = be_u8(input)?;
+    let ie_len = ie_len as usize;
+    let ie_inst = inst & 0x0f;
 
-    let raw = input[..total_len].to_vec();
-
-    let (mut input, _) = be_u32(input)?;
+    // 선언된 길이만큼 데이터가 없으면 여기서 에러
+    let (_, _) = take(ie_len)(input)?;
+    let raw = start[..4 + ie_len].to_vec();
 
     let (type_str, is_group) = GTPV2_IE_TYPES.get(ie_type as usize).map(|(s, g)| (s.to_string(), *g)).unwrap_or_else(|| ("Out of bound".to_string(), false));
 
@@ -1112,26 +1127,28 @@ fn parse_ie(input: &[u8])
 }
 
 
-pub fn parse_all_ies(mut input: &[u8])
-    -> Result<Vec<GtpIe>, String>
+/// Decodes IEs into `ies` until `input` is used up. On a broken IE the ones
+/// before it are kept and the error offset is relative to `input`.
+pub fn parse_all_ies(input: &[u8], ies: &mut Vec<GtpIe>)
+    -> Result<(), DecodeError>
 {
-    let mut result = Vec::new();
-
-    while !input.is_empty () {
-        match parse_ie(input) {
-            Ok((rest, ie)) => {
-                result.push(ie);
-                input = rest;
-                if input.len() < 4 {
+    let mut rest = input;
+
+    while !rest.is_empty () {
+        match parse_ie(rest) {
+            Ok((next, ie)) => {
+                ies.push(ie);
+                rest = next;
+                if rest.len() < 4 {
                     break;
                 }
             },
 
             Err(e) => {
-                return Err(format!("IE parse error: {}",e));
+                return Err(DecodeError::from_nom(input, e));
             }
         }
     }
 
-    Ok(result)
-}
\ No newline at end of file
+    Ok(())
+}
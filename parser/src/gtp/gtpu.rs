@@ -0,0 +1,365 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::types::*;
+use crate::dissect::*;
+use crate::l2::ethernet::*;
+
+pub const GTPU_HDR_LEN: usize       = 8;
+// sequence number, N-PDU number and next extension header type
+const GTPU_OPT_LEN: usize           = 4;
+
+const GTPU_FLAG_PT: u8              = 0x10;
+const GTPU_FLAG_E: u8               = 0x04;
+const GTPU_FLAG_S: u8               = 0x02;
+const GTPU_FLAG_PN: u8              = 0x01;
+
+pub const GTPU_ECHO_REQUEST: u8     = 1;
+pub const GTPU_ECHO_RESPONSE: u8    = 2;
+pub const GTPU_ERROR_INDICATION: u8 = 26;
+pub const GTPU_SUPPORTED_EXT: u8    = 31;
+pub const GTPU_TUNNEL_STATUS: u8    = 253;
+pub const GTPU_END_MARKER: u8       = 254;
+pub const GTPU_G_PDU: u8            = 255;
+
+const GTPU_EXT_NONE: u8             = 0x00;
+const GTPU_EXT_LONG_PDCP_OLD: u8    = 0x03;
+const GTPU_EXT_SCI: u8              = 0x20;
+const GTPU_EXT_UDP_PORT: u8         = 0x40;
+const GTPU_EXT_RAN_CONTAINER: u8    = 0x81;
+const GTPU_EXT_LONG_PDCP: u8        = 0x82;
+const GTPU_EXT_XW_RAN_CONTAINER: u8 = 0x83;
+const GTPU_EXT_NR_RAN_CONTAINER: u8 = 0x84;
+const GTPU_EXT_PDU_SESSION: u8      = 0x85;
+const GTPU_EXT_PDCP: u8             = 0xc0;
+
+const GTPU_IE_RECOVERY: u8          = 14;
+const GTPU_IE_TEID_DATA_I: u8       = 16;
+const GTPU_IE_GSN_ADDRESS: u8       = 133;
+const GTPU_IE_EXT_HDR_LIST: u8      = 141;
+const GTPU_IE_PRIVATE_EXT: u8       = 255;
+
+
+pub fn gtpu_msg_to_str(msg_type: u8) -> String
+{
+    match msg_type {
+        GTPU_ECHO_REQUEST       => "Echo Request".to_string(),
+        GTPU_ECHO_RESPONSE      => "Echo Response".to_string(),
+        GTPU_ERROR_INDICATION   => "Error Indication".to_string(),
+        GTPU_SUPPORTED_EXT      => "Supported Extension Headers Notification".to_string(),
+        GTPU_TUNNEL_STATUS      => "Tunnel Status".to_string(),
+        GTPU_END_MARKER         => "End Marker".to_string(),
+        GTPU_G_PDU              => "G-PDU".to_string(),
+        _                       => format!("Unknown ({})", msg_type),
+    }
+}
+
+fn gtpu_ext_to_str(ext_type: u8) -> String
+{
+    match ext_type {
+        GTPU_EXT_LONG_PDCP_OLD      => "Long PDCP PDU Number".to_string(),
+        GTPU_EXT_SCI                => "Service Class Indicator".to_string(),
+        GTPU_EXT_UDP_PORT           => "UDP Port".to_string(),
+        GTPU_EXT_RAN_CONTAINER      => "RAN Container".to_string(),
+        GTPU_EXT_LONG_PDCP          => "Long PDCP PDU Number".to_string(),
+        GTPU_EXT_XW_RAN_CONTAINER   => "Xw RAN Container".to_string(),
+        GTPU_EXT_NR_RAN_CONTAINER   => "NR RAN Container".to_string(),
+        GTPU_EXT_PDU_SESSION        => "PDU Session Container".to_string(),
+        GTPU_EXT_PDCP               => "PDCP PDU Number".to_string(),
+        _                           => format!("Unknown (0x{:02x})", ext_type),
+    }
+}
+
+fn gtpu_ie_to_str(ie_type: u8) -> String
+{
+    match ie_type {
+        GTPU_IE_RECOVERY        => "Recovery".to_string(),
+        GTPU_IE_TEID_DATA_I     => "Tunnel Endpoint Identifier Data I".to_string(),
+        GTPU_IE_GSN_ADDRESS     => "GTP-U Peer Address".to_string(),
+        GTPU_IE_EXT_HDR_LIST    => "Extension Header Type List".to_string(),
+        GTPU_IE_PRIVATE_EXT     => "Private Extension".to_string(),
+        _                       => format!("Unknown ({})", ie_type),
+    }
+}
+
+// TS 38.415 PDU types
+fn pdu_session_type_to_str(pdu_type: u8) -> String
+{
+    match pdu_type {
+        0   => "DL PDU Session Information".to_string(),
+        1   => "UL PDU Session Information".to_string(),
+        _   => format!("Unknown ({})", pdu_type),
+    }
+}
+
+
+// content of one extension header, between its length and next type octets
+fn gtpu_ext_value(ext_type: u8, content: &[u8]) -> Result<GtpuExtValue, String>
+{
+    let short = || format!("{} is too short", gtpu_ext_to_str(ext_type));
+
+    let value = match ext_type {
+        GTPU_EXT_PDU_SESSION => {
+            let &[first, second, ..] = content else {
+                return Err(short());
+            };
+            let pdu_type = first >> 4;
+            // only the downlink carries RQI and, with the PPP flag, the PPI
+            let (rqi, ppi) = if pdu_type == 0 {
+                let ppi = (second & 0x80 != 0).then(|| content.get(2).map(|b| b >> 5)).flatten();
+                (Some(second & 0x40 != 0), ppi)
+            }
+            else {
+                (None, None)
+            };
+            GtpuExtValue::PduSession {
+                pdu_type,
+                pdu_type_str: pdu_session_type_to_str(pdu_type),
+                qfi: second & 0x3f,
+                rqi,
+                ppi,
+            }
+        },
+        GTPU_EXT_UDP_PORT => {
+            let &[hi, lo, ..] = content else {
+                return Err(short());
+            };
+            GtpuExtValue::UdpPort(u16::from_be_bytes([hi, lo]))
+        },
+        GTPU_EXT_PDCP => {
+            let &[hi, lo, ..] = content else {
+                return Err(short());
+            };
+            GtpuExtValue::PdcpPduNumber(u16::from_be_bytes([hi, lo]) as u32 & 0x7fff)
+        },
+        GTPU_EXT_LONG_PDCP | GTPU_EXT_LONG_PDCP_OLD => {
+            let &[b0, b1, b2, ..] = content else {
+                return Err(short());
+            };
+            GtpuExtValue::PdcpPduNumber(u32::from_be_bytes([0, b0, b1, b2]) & 0x3ffff)
+        },
+        _ => GtpuExtValue::Raw(content.to_vec()),
+    };
+
+    Ok(value)
+}
+
+
+/// Follow the extension header chain starting at `pos`, with `next` the type
+/// of the first one. Returns where the chain ends, which is at most `end`.
+fn parse_gtpu_ext_headers(data: &[u8], mut pos: usize, mut next: u8, end: usize, exts: &mut Vec<GtpuExtHeader>)
+-> Result<usize, DecodeError>
+{
+    while next != GTPU_EXT_NONE {
+        need(data, pos + 1)?;
+        let length = data[pos] as usize * 4;
+        if length == 0 {
+            return Err(DecodeError::invalid(pos, "GTP-U extension header length is zero"));
+        }
+        if pos + length > end {
+            return Err(DecodeError::invalid(pos, "GTP-U extension header runs past the message length"));
+        }
+        need(data, pos + length)?;
+
+        let ext_type = next;
+        let value = gtpu_ext_value(ext_type, &data[pos + 1..pos + length - 1])
+            .map_err(|reason| DecodeError::invalid(pos, reason))?;
+        exts.push(GtpuExtHeader {
+            ext_type,
+            name: gtpu_ext_to_str(ext_type),
+            length,
+            value,
+        });
+
+        next = data[pos + length - 1];
+        pos += length;
+    }
+
+    Ok(pos)
+}
+
+
+/// Information elements of the signalling messages (Echo, Error Indication,
+/// Supported Extension Headers Notification). Types below 128 are fixed length.
+fn parse_gtpu_ies(data: &[u8], ies: &mut Vec<GtpuIe>) -> Result<(), DecodeError>
+{
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let ie_type = data[pos];
+        let (value_at, len) = match ie_type {
+            GTPU_IE_RECOVERY => (pos + 1, 1),
+            GTPU_IE_TEID_DATA_I => (pos + 1, 4),
+            // the one TLV with a single octet length
+            GTPU_IE_EXT_HDR_LIST => {
+                need(data, pos + 2)?;
+                (pos + 2, data[pos + 1] as usize)
+            },
+            t if t >= 128 => {
+                need(data, pos + 3)?;
+                (pos + 3, u16::from_be_bytes([data[pos + 1], data[pos + 2]]) as usize)
+            },
+            t => return Err(DecodeError::invalid(pos, format!("unknown GTP-U IE type {}", t))),
+        };
+        need(data, value_at + len)?;
+        let body = &data[value_at..value_at + len];
+
+        let value = match ie_type {
+            GTPU_IE_RECOVERY => format!("Restart Counter {}", body[0]),
+            GTPU_IE_TEID_DATA_I => format!("0x{:08x}", u32::from_be_bytes([body[0], body[1], body[2], body[3]])),
+            GTPU_IE_GSN_ADDRESS => match body.len() {
+                4 => IpAddr::V4(Ipv4Addr::new(body[0], body[1], body[2], body[3])).to_string(),
+                16 => <[u8; 16]>::try_from(body).map(|b| IpAddr::V6(Ipv6Addr::from(b)).to_string())
+                    .unwrap_or_default(),
+                n => format!("{} bytes", n),
+            },
+            GTPU_IE_EXT_HDR_LIST => body.iter().map(|&t| gtpu_ext_to_str(t)).collect::<Vec<_>>().join(", "),
+            _ => format!("{} bytes", len),
+        };
+        ies.push(GtpuIe {
+            ie_type,
+            name: gtpu_ie_to_str(ie_type),
+            value,
+        });
+
+        pos = value_at + len;
+    }
+
+    Ok(())
+}
+
+
+// header and extension headers into `gtpu`; returns where the T-PDU or the IEs start
+fn parse_gtpu(data: &[u8], gtpu: &mut GtpuInfo) -> Result<usize, DecodeError>
+{
+    need(data, GTPU_HDR_LEN)?;
+
+    let flags = data[0];
+    gtpu.flags = flags;
+    gtpu.version = flags >> 5;
+    gtpu.pt_flag = flags & GTPU_FLAG_PT != 0;
+    gtpu.e_flag = flags & GTPU_FLAG_E != 0;
+    gtpu.s_flag = flags & GTPU_FLAG_S != 0;
+    gtpu.pn_flag = flags & GTPU_FLAG_PN != 0;
+    gtpu.msg_type = data[1];
+    gtpu.msg_type_str = gtpu_msg_to_str(gtpu.msg_type);
+    gtpu.msg_len = u16::from_be_bytes([data[2], data[3]]);
+    gtpu.teid = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+
+    if gtpu.version != 1 {
+        return Err(DecodeError::invalid(0, format!("GTP version {} on the GTP-U port", gtpu.version)));
+    }
+    if !gtpu.pt_flag {
+        return Err(DecodeError::invalid(0, "GTP-U PT flag is not set (GTP')"));
+    }
+
+    let msg_end = GTPU_HDR_LEN + gtpu.msg_len as usize;
+    if flags & (GTPU_FLAG_E | GTPU_FLAG_S | GTPU_FLAG_PN) == 0 {
+        return Ok(GTPU_HDR_LEN);
+    }
+
+    // the optional fields are there as soon as one flag is set; the flags say which are meaningful
+    let opt_end = GTPU_HDR_LEN + GTPU_OPT_LEN;
+    if opt_end > msg_end {
+        return Err(DecodeError::invalid(2, "GTP-U length leaves no room for the optional fields"));
+    }
+    need(data, opt_end)?;
+    gtpu.seq = gtpu.s_flag.then(|| u16::from_be_bytes([data[8], data[9]]));
+    gtpu.npdu = gtpu.pn_flag.then_some(data[10]);
+
+    if !gtpu.e_flag {
+        return Ok(opt_end);
+    }
+    parse_gtpu_ext_headers(data, opt_end, data[11], msg_end, &mut gtpu.ext_headers)
+}
+
+
+pub struct GtpuDissector;
+
+impl Dissector for GtpuDissector {
+    fn name(&self) -> &'static str {
+        "GTP-U"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        let mut gtpu = GtpuInfo::new();
+        // the inner UDP/TCP header replaces the outer ports in the flow
+        gtpu.src_port = ctx.flow.src_port;
+        gtpu.dst_port = ctx.flow.dst_port;
+
+        let parsed = parse_gtpu(data, &mut gtpu);
+        let hdr_len = *parsed.as_ref().unwrap_or(&data.len().min(GTPU_HDR_LEN));
+        let msg_end = GTPU_HDR_LEN + gtpu.msg_len as usize;
+        gtpu.raw.extend_from_slice(&data[..hdr_len]);
+
+        let signalling = matches!(gtpu.msg_type, GTPU_ECHO_REQUEST | GTPU_ECHO_RESPONSE
+            | GTPU_ERROR_INDICATION | GTPU_SUPPORTED_EXT | GTPU_TUNNEL_STATUS);
+        let ies = if parsed.is_ok() && signalling {
+            let body = data.get(hdr_len..msg_end.min(data.len())).unwrap_or_default();
+            parse_gtpu_ies(body, &mut gtpu.ies).map_err(|e| e.shifted(hdr_len))
+        }
+        else {
+            Ok(())
+        };
+
+        ctx.summary.protocol = "GTP-U".to_string();
+        ctx.summary.description = gtpu_summary(&gtpu);
+        // path management messages use TEID 0
+        if gtpu.msg_type == GTPU_G_PDU || gtpu.msg_type == GTPU_END_MARKER {
+            ctx.summary.tunnel_teid = Some(gtpu.teid);
+        }
+
+        let msg_type = gtpu.msg_type;
+        if ctx.wants_detail() {
+            ctx.detail.l3.push(Layer3Info::Gtpu(gtpu));
+        }
+
+        let hdr_len = parsed?;
+        ies?;
+
+        // the T-PDU is whatever the PDU session carries; only IP is decoded further
+        let ethertype = match data.get(hdr_len).map(|b| b >> 4) {
+            Some(4) if msg_type == GTPU_G_PDU => NEXT_HDR_IPV4,
+            Some(6) if msg_type == GTPU_G_PDU => NEXT_HDR_IPV6,
+            _ => return Ok(None),
+        };
+
+        Ok(Some(NextLayer {
+            key: LayerKey::Ethertype(ethertype as u16),
+            hdr_len,
+            len: Some(msg_end),
+        }))
+    }
+}
+
+
+// message name, then what tells one tunnel or peer from another
+fn gtpu_summary(gtpu: &GtpuInfo) -> String
+{
+    let mut parts = vec![format!("GTP-U {}", gtpu.msg_type_str)];
+
+    for ext in &gtpu.ext_headers {
+        if let GtpuExtValue::PduSession { pdu_type, qfi, .. } = ext.value {
+            let dir = match pdu_type {
+                0 => " (DL)",
+                1 => " (UL)",
+                _ => "",
+            };
+            parts.push(format!("QFI {}{}", qfi, dir));
+        }
+    }
+
+    if let Some(seq) = gtpu.seq
+        && gtpu.msg_type != GTPU_G_PDU {
+        parts.push(format!("seq {}", seq));
+    }
+
+    for ie in &gtpu.ies {
+        match ie.ie_type {
+            GTPU_IE_TEID_DATA_I => parts.push(format!("TEID {}", ie.value)),
+            GTPU_IE_GSN_ADDRESS => parts.push(format!("peer {}", ie.value)),
+            _ => {},
+        }
+    }
+
+    parts.join(", ")
+}
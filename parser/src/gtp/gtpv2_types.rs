@@ -215,6 +215,55 @@ pub const GTPV2C_IE_PRIVATE_EXTENSION: u8 =						255;
 pub const GTPV2C_IE_TYPE_MAX: u8 =								255;
 
 
+// F-TEID Interface Types (TS 29.274 8.22)
+pub const GTPV2C_IFACE_S1U_ENODEB_GTPU: u8 =					0;
+pub const GTPV2C_IFACE_S1U_SGW_GTPU: u8 =						1;
+pub const GTPV2C_IFACE_S5S8_SGW_GTPU: u8 =						4;
+pub const GTPV2C_IFACE_S5S8_PGW_GTPU: u8 =						5;
+pub const GTPV2C_IFACE_S5S8_SGW_GTPC: u8 =						6;
+pub const GTPV2C_IFACE_S5S8_PGW_GTPC: u8 =						7;
+pub const GTPV2C_IFACE_S11_MME_GTPC: u8 =						10;
+pub const GTPV2C_IFACE_S11S4_SGW_GTPC: u8 =						11;
+pub const GTPV2C_IFACE_S10_MME_GTPC: u8 =						12;
+pub const GTPV2C_IFACE_S3_MME_GTPC: u8 =						13;
+pub const GTPV2C_IFACE_S4_SGW_GTPU: u8 =						16;
+pub const GTPV2C_IFACE_S2B_EPDG_GTPC: u8 =						30;
+pub const GTPV2C_IFACE_S2B_PGW_GTPC: u8 =						32;
+pub const GTPV2C_IFACE_S2B_U_PGW_GTPU: u8 =						33;
+pub const GTPV2C_IFACE_S2A_PGW_GTPC: u8 =						36;
+pub const GTPV2C_IFACE_S2A_PGW_GTPU: u8 =						37;
+pub const GTPV2C_IFACE_S11_MME_GTPU: u8 =						38;
+pub const GTPV2C_IFACE_S11_SGW_GTPU: u8 =						39;
+pub const GTPV2C_IFACE_N26_AMF_GTPC: u8 =						40;
+
+/// Network function that owns an F-TEID of `iface_type`
+pub fn fteid_iface_to_role(iface_type: u8) -> Option<&'static str>
+{
+    match iface_type {
+        GTPV2C_IFACE_S1U_ENODEB_GTPU    => Some("eNodeB"),
+        GTPV2C_IFACE_S11_MME_GTPC |
+        GTPV2C_IFACE_S10_MME_GTPC |
+        GTPV2C_IFACE_S3_MME_GTPC |
+        GTPV2C_IFACE_S11_MME_GTPU       => Some("MME"),
+        GTPV2C_IFACE_S5S8_SGW_GTPC |
+        GTPV2C_IFACE_S11S4_SGW_GTPC     => Some("SGW-C"),
+        GTPV2C_IFACE_S1U_SGW_GTPU |
+        GTPV2C_IFACE_S5S8_SGW_GTPU |
+        GTPV2C_IFACE_S4_SGW_GTPU |
+        GTPV2C_IFACE_S11_SGW_GTPU       => Some("SGW-U"),
+        GTPV2C_IFACE_S5S8_PGW_GTPC |
+        GTPV2C_IFACE_S2B_PGW_GTPC |
+        GTPV2C_IFACE_S2A_PGW_GTPC       => Some("PGW-C"),
+        GTPV2C_IFACE_S5S8_PGW_GTPU |
+        GTPV2C_IFACE_S2B_U_PGW_GTPU |
+        GTPV2C_IFACE_S2A_PGW_GTPU       => Some("PGW-U"),
+        GTPV2C_IFACE_S2B_EPDG_GTPC      => Some("ePDG"),
+        GTPV2C_IFACE_N26_AMF_GTPC       => Some("AMF"),
+        _ => None,
+    }
+}
+
+
 // GTPv2 Error Types
 pub const 	GTPV2C_CAUSE_RESERVED	: u8 =							1;
 pub const 	GTPV2C_CAUSE_LOCAL_DETACH	: u8 =						2;
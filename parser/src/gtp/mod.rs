@@ -1,3 +1,6 @@
 pub mod gtp;
 pub mod gtp_ie;
-pub mod gtpv2_types;
\ No newline at end of file
+pub mod gtpu;
+pub mod gtpv2_types;
+#[cfg(test)]
+mod tests;
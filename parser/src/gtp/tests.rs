@@ -0,0 +1,123 @@
+use crate::types::*;
+use crate::dissect::{*, registry::*};
+use crate::gtp::gtpu::*;
+use crate::ip::port::*;
+use crate::l2::link::*;
+use crate::test_util::*;
+
+
+const FLAGS_V1_PT: u8 = 0x30;
+const FLAG_E: u8 = 0x04;
+const FLAG_S: u8 = 0x02;
+
+// GTP-U header; `opt` is the sequence number, N-PDU number and extension
+// headers when a flag asks for them, and counts towards the length like `body`
+fn gtpu(flags: u8, msg_type: u8, teid: u32, opt: &[u8], body: &[u8]) -> Vec<u8>
+{
+    let mut out = vec![flags, msg_type];
+    out.extend(((opt.len() + body.len()) as u16).to_be_bytes());
+    out.extend(teid.to_be_bytes());
+    out.extend(opt);
+    out.extend(body);
+    out
+}
+
+fn dissect_gtpu(data: &[u8]) -> (Dissection, Result<Option<NextLayer>, DecodeError>)
+{
+    let mut ctx = Dissection::new(Depth::Detail, default_table().clone());
+    let next = GtpuDissector.dissect(data, &mut ctx);
+    (ctx, next)
+}
+
+fn gtpu_info(ctx: &Dissection) -> &GtpuInfo
+{
+    match ctx.detail.l3.last() {
+        Some(Layer3Info::Gtpu(gtpu)) => gtpu,
+        other => panic!("expected GTP-U, got {:?}", other),
+    }
+}
+
+
+#[test]
+fn gtpu_g_pdu_with_extension_headers() {
+    let inner = ipv4(PROTO_TYPE_UDP as u8, &udp(1000, 2000, b"data"));
+    // seq, N-PDU, next type; PDU Session Container (DL, RQI, QFI 5) then PDCP PDU Number
+    let opt = [0, 0, 0, 0x85, 1, 0x00, 0x45, 0xc0, 1, 0x80, 0x2a, 0x00];
+    let data = gtpu(FLAGS_V1_PT | FLAG_E, GTPU_G_PDU, 0x1234_5678, &opt, &inner);
+
+    let (ctx, next) = dissect_gtpu(&data);
+    let next = next.unwrap().unwrap();
+    assert_eq!(next.key, LayerKey::Ethertype(0x0800));
+    assert_eq!((next.hdr_len, next.len), (20, Some(data.len())));
+    assert_eq!(ctx.summary.tunnel_teid, Some(0x1234_5678));
+    assert_eq!(ctx.summary.description, "GTP-U G-PDU, QFI 5 (DL)");
+
+    let gtpu = gtpu_info(&ctx);
+    assert!(gtpu.e_flag && !gtpu.s_flag);
+    assert_eq!((gtpu.seq, gtpu.npdu), (None, None));
+    assert_eq!(gtpu.ext_headers.len(), 2);
+    assert_eq!((gtpu.ext_headers[0].ext_type, gtpu.ext_headers[0].length), (0x85, 4));
+    assert!(matches!(gtpu.ext_headers[0].value,
+        GtpuExtValue::PduSession { pdu_type: 0, qfi: 5, rqi: Some(true), ppi: None, .. }));
+    assert!(matches!(gtpu.ext_headers[1].value, GtpuExtValue::PdcpPduNumber(0x2a)));
+}
+
+#[test]
+fn gtpu_extension_header_errors() {
+    // length zero
+    let data = gtpu(FLAGS_V1_PT | FLAG_E, GTPU_G_PDU, 1, &[0, 0, 0, 0x85, 0, 0, 0, 0], &[]);
+    let (ctx, next) = dissect_gtpu(&data);
+    assert!(matches!(next, Err(DecodeError::Invalid { offset: 12, .. })));
+    // the header is still shown, with what was decoded before the error
+    assert_eq!(gtpu_info(&ctx).teid, 1);
+
+    // two 4-octet units in a message with room for one
+    let data = gtpu(FLAGS_V1_PT | FLAG_E, GTPU_G_PDU, 1, &[0, 0, 0, 0x40, 2, 0x08, 0x68, 0], &[]);
+    assert!(matches!(dissect_gtpu(&data).1, Err(DecodeError::Invalid { offset: 12, .. })));
+
+    // cut off in the capture
+    let data = gtpu(FLAGS_V1_PT | FLAG_E, GTPU_G_PDU, 1, &[0, 0, 0, 0x40, 1, 0x08, 0x68, 0], &[]);
+    assert!(matches!(dissect_gtpu(&data[..14]).1, Err(DecodeError::Truncated { .. })));
+
+    // an uplink PDU Session Container has no RQI
+    let data = gtpu(FLAGS_V1_PT | FLAG_E, GTPU_G_PDU, 1, &[0, 0, 0, 0x85, 1, 0x10, 0x49, 0], &[]);
+    let (ctx, next) = dissect_gtpu(&data);
+    assert!(next.is_ok());
+    assert!(matches!(gtpu_info(&ctx).ext_headers[0].value,
+        GtpuExtValue::PduSession { pdu_type: 1, qfi: 9, rqi: None, .. }));
+}
+
+#[test]
+fn gtpu_echo_response_with_recovery() {
+    let data = gtpu(FLAGS_V1_PT | FLAG_S, GTPU_ECHO_RESPONSE, 0, &[0, 7, 0, 0], &[14, 3]);
+
+    let (ctx, next) = dissect_gtpu(&data);
+    assert!(next.unwrap().is_none());
+    assert_eq!(ctx.summary.tunnel_teid, None);
+    assert!(ctx.summary.description.contains("seq 7"));
+    let gtpu = gtpu_info(&ctx);
+    assert_eq!(gtpu.ies.len(), 1);
+    assert_eq!(gtpu.ies[0].value, "Restart Counter 3");
+}
+
+#[test]
+fn gtpu_rejects_other_versions() {
+    // GTP' has the PT flag cleared, GTPv2-C is version 2
+    assert!(dissect_gtpu(&gtpu(0x20, GTPU_G_PDU, 1, &[], &[])).1.is_err());
+    assert!(dissect_gtpu(&gtpu(0x48, GTPU_G_PDU, 1, &[], &[])).1.is_err());
+    assert!(matches!(dissect_gtpu(&[0x30, 0xff, 0]).1, Err(DecodeError::Truncated { .. })));
+}
+
+#[test]
+fn gtpu_t_pdu_is_dissected_as_the_inner_flow() {
+    let inner = ipv4(PROTO_TYPE_UDP as u8, &udp(1000, 2000, b"data"));
+    let opt = [0, 0, 0, 0x85, 1, 0x10, 0x09, 0x00];
+    let tunnel = gtpu(FLAGS_V1_PT | FLAG_E, GTPU_G_PDU, 0xabcd, &opt, &inner);
+    let frame = ipv4(PROTO_TYPE_UDP as u8, &udp(L4_PORT_GTPU, L4_PORT_GTPU, &tunnel));
+
+    let ctx = dissect_frame(default_table(), LINKTYPE_RAW, &frame, Depth::Flow);
+    assert_eq!(ctx.summary.tunnel_teid, Some(0xabcd));
+    assert_eq!((ctx.flow.src_port, ctx.flow.dst_port), (1000, 2000));
+    let protocols: Vec<_> = ctx.layers.iter().map(|l| l.protocol).collect();
+    assert_eq!(protocols, vec!["Link", "IPv4", "UDP", "GTP-U", "IPv4", "UDP"]);
+}
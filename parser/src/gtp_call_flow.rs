@@ -1,14 +1,15 @@
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr};
 use std::vec;
 use serde::Serialize;
-use std::path::PathBuf;
-use pcap::Capture;
+use std::path::Path;
 
-use crate::ip::{ipv4::*, port::*};
-use crate::l4::udp::*;
-use crate::gtp::{gtp::*, gtp_ie::*, gtpv2_types::*};
+use crate::ip::port::*;
+use crate::dissect::*;
+use crate::gtp::{gtp_ie::*, gtpv2_types::*};
 use crate::types::*;
 use crate::parse_pcap::*;
+use crate::capture::index::*;
+use crate::time_format::*;
 use crate::call_flow_test::*;
 
 
@@ -30,8 +31,11 @@ impl Bearer {
 pub struct CallFlow{
     pub id: usize,
     pub timestamp: String,
+    pub time: PacketTime,
     pub src_addr: String,
     pub dst_addr: String,
+    pub src_name: Option<String>,   // node alias or inferred role, filled in by the handler
+    pub dst_name: Option<String>,
     pub message: String,
     pub ebi: Option<u8>,
     pub bearer: Option<Vec<Bearer>>,
@@ -41,8 +45,11 @@ impl CallFlow{
         CallFlow {
             id: 0,
             timestamp: String::new(),
+            time: PacketTime::new(),
             src_addr: String::new(),
             dst_addr: String::new(),
+            src_name: None,
+            dst_name: None,
             message: String::new(),
             ebi: None,
             bearer: None,
@@ -54,8 +61,8 @@ impl CallFlow{
 #[derive(Serialize, Debug, Clone)]
 pub struct Ip5Tuple {
     pub protocol: u8,
-    pub src_addr: Ipv4Addr,
-    pub dst_addr: Ipv4Addr,
+    pub src_addr: IpAddr,
+    pub dst_addr: IpAddr,
     pub src_port: u16,
     pub dst_port: u16,
 }
@@ -63,8 +70,8 @@ impl Ip5Tuple{
     pub fn new() -> Self {
         Ip5Tuple {
             protocol: 0,
-            src_addr: Ipv4Addr::new(0, 0, 0, 0),
-            dst_addr: Ipv4Addr::new(0, 0, 0, 0),
+            src_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            dst_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
             src_port: 0,
             dst_port: 0,
         }
@@ -74,7 +81,10 @@ impl Ip5Tuple{
 #[derive(Serialize, Debug)]
 struct OwnedPacket {
     idx: i32,
-    data: Vec<u8>,
+    ts_sec: i64,
+    ts_nsec: u32,
+    tuple: Ip5Tuple,        // innermost IPv4/IPv6 addresses and UDP ports
+    gtp: Option<GtpInfo>,   // GTPv2-C header, None for any other packet
     ies: Vec<GtpIe>,
 }
 
@@ -100,8 +110,9 @@ impl SessCtxInfo {
 
 #[derive(Serialize, Debug)]
 struct NodeInfo {
-    addr: Ipv4Addr,
+    addr: IpAddr,
     port: u16,
+    fteid_addrs: Vec<IpAddr>,   // other addresses of this node, from its own sender F-TEIDs
     s11_seq: u32,
     s5s8_seq: u32,
     // msg_type: u8,
@@ -111,8 +122,9 @@ struct NodeInfo {
 impl NodeInfo {
     pub fn new() -> Self {
         NodeInfo {
-            addr: Ipv4Addr::new(0, 0, 0, 0),
+            addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
             port: 0,
+            fteid_addrs: Vec::new(),
             s11_seq: 0,
             s5s8_seq: 0,
             status: 0,
@@ -123,28 +135,52 @@ impl NodeInfo {
 
 #[derive(Debug, Clone)]
 struct TargetInfo {
-    tuple: Ip5Tuple,
-    teid: u32,
     imsi: String,
 }
 
-fn load_pcap(path: &PathBuf) -> Result<Vec<OwnedPacket>, String> {
-    let mut cap = Capture::from_file(path)
-        .map_err(|e| e.to_string())?;
+/// 5-tuple of a dissected IPv4 or IPv6 packet; empty for anything else.
+fn flow_to_5tuple(flow: &FlowTuple) -> Ip5Tuple
+{
+    match (flow.src_addr, flow.dst_addr) {
+        (Some(src_addr), Some(dst_addr)) => Ip5Tuple {
+            protocol: flow.protocol,
+            src_addr,
+            dst_addr,
+            src_port: flow.src_port,
+            dst_port: flow.dst_port,
+        },
+        _ => Ip5Tuple::new(),
+    }
+}
+
+
+/// Read only the requested packets, seeking through the file's index, and
+/// decode them as deep as the call flow needs.
+fn load_pcap(path: &Path, index: &CaptureIndex, ids: &[usize]) -> Result<Vec<OwnedPacket>, String> {
+    let records = dissect_indexed(path, index, ids, Depth::Flow)?;
 
-    let mut idx: i32 = 1;
     let mut packets = Vec::new();
 
-    while let Ok(pkt) = cap.next_packet() {
+    for (id, pkt, dissection) in records {
+
+        let (gtp, ies) = match dissection.detail.app {
+            AppLayerInfo::GTP(mut gtp) => {
+                let ies = std::mem::take(&mut gtp.ies);
+                (Some(gtp), ies)
+            },
+            _ => (None, Vec::new()),
+        };
+
         packets.push (
             OwnedPacket {
-                idx,
-                data: pkt.data.to_vec(),
-                ies: Vec::new()
+                idx: id as i32,
+                ts_sec: pkt.ts_sec,
+                ts_nsec: pkt.ts_nsec,
+                tuple: flow_to_5tuple(&dissection.flow),
+                gtp,
+                ies,
             }
         );
-
-        idx += 1;
     }
 
     Ok(packets)
@@ -161,14 +197,11 @@ senario_analysis(target:TargetInfo, vec_packets: Vec<OwnedPacket>, nodes: &mut V
     let mut resp_node = NodeInfo::new();
     let mut third_node = NodeInfo::new();
 
-    for mut pkt in vec_packets.into_iter() {
-
-        let mut offset = MIN_ETH_HDR_LEN + IP_HDR_LEN + UDP_HDR_LEN;
+    for pkt in vec_packets.into_iter() {
 
-        let (_, hdr) = get_gtp_header(&pkt.data[offset..]).map_err(|e| {
-            eprintln!("GTP Message Type parse error: {:?}", e);
-            e
-        }).unwrap();
+        let Some(hdr) = pkt.gtp.as_ref() else {
+            continue;
+        };
 
         let msg_type = hdr.msg_type;
         let seq = hdr.seq;
@@ -179,12 +212,9 @@ senario_analysis(target:TargetInfo, vec_packets: Vec<OwnedPacket>, nodes: &mut V
         // hdr.teid.unwrap();
 
 
-        let tuple = extract_5tuple(&pkt).await;
+        let tuple = pkt.tuple.clone();
 
-        offset += get_gtp_hdr_len(&pkt.data[offset..]);
-        let ies = parse_all_ies(&pkt.data[offset..]).unwrap_or_default();
-
-        pkt.ies = ies.clone();
+        let ies = pkt.ies.clone();
 
         match msg_type {
             GTPV2C_CREATE_SESSION_REQ => {
@@ -204,6 +234,7 @@ senario_analysis(target:TargetInfo, vec_packets: Vec<OwnedPacket>, nodes: &mut V
                     init_node_info(&mut init_node, tuple.src_addr, tuple.src_port, msg_type,
                         seq, 0, &imsi);
                     init_fteid_info(&mut init_node, fteid_teid, 0, 0, 0);
+                    add_fteid_addrs(&mut init_node, &ies);
 
                     //Second Node
                     init_node_info(&mut resp_node, tuple.dst_addr, tuple.dst_port, msg_type,
@@ -217,8 +248,11 @@ senario_analysis(target:TargetInfo, vec_packets: Vec<OwnedPacket>, nodes: &mut V
                 //If Second Create Session Request Packet
                 else if init_node.status == 1 && resp_node.status == 1 {
 
-                    if resp_node.addr == tuple.src_addr &&
-                       tuple.dst_addr != init_node.addr {
+                    // the SGW may talk to the PGW from another address, even
+                    // another address family, than the one the MME used
+                    if (has_addr(&resp_node, tuple.src_addr) ||
+                        is_s5s8_sgw_request(&ies, &init_node, &tuple)) &&
+                       !has_addr(&init_node, tuple.dst_addr) {
 
                         println!(" [mme]  [sgw] -> [pgw] ");
                         //Third Node check
@@ -226,6 +260,7 @@ senario_analysis(target:TargetInfo, vec_packets: Vec<OwnedPacket>, nodes: &mut V
                             0, seq, &imsi);
                         init_fteid_info(&mut third_node, 0, 0,
                             0, fteid_teid);
+                        add_fteid_addrs(&mut resp_node, &ies);
 
                         //Second Node Update
                         update_node_info(&mut resp_node, 0, seq,
@@ -239,7 +274,7 @@ senario_analysis(target:TargetInfo, vec_packets: Vec<OwnedPacket>, nodes: &mut V
             },
 
             GTPV2C_CREATE_SESSION_RSP => {
-                let fteid_teid = extract_fteid(ies).await;
+                let fteid_teid = extract_fteid(ies.clone()).await;
                 if init_node.status == 0 {
                     continue;
                 }
@@ -257,6 +292,7 @@ senario_analysis(target:TargetInfo, vec_packets: Vec<OwnedPacket>, nodes: &mut V
                                     0, fteid_teid, 0, 0);
                                 update_node_info(&mut resp_node, 0, 0,
                                     0, 0, 0, fteid_teid);
+                                add_fteid_addrs(&mut third_node, &ies);
                                 filtered_packets.push(pkt);
                                 continue;
                             }
@@ -279,6 +315,7 @@ senario_analysis(target:TargetInfo, vec_packets: Vec<OwnedPacket>, nodes: &mut V
                             update_node_info(&mut init_node, 0, 0,
                                 0, 0,
                                 fteid_teid, 0);
+                            add_fteid_addrs(&mut resp_node, &ies);
 
                             filtered_packets.push(pkt);
                                 continue;
@@ -552,56 +589,6 @@ senario_analysis(target:TargetInfo, vec_packets: Vec<OwnedPacket>, nodes: &mut V
 }
 
 
-fn get_seq_from_header (packet: &OwnedPacket) -> u32
-{
-
-    let offset: usize = MIN_ETH_HDR_LEN + IP_HDR_LEN + UDP_HDR_LEN;
-
-    let (_, hdr) = get_gtp_header(&packet.data[offset..]).map_err(|e| {
-        eprintln!("GTP Header parse error: {:?}", e);
-        e
-    }).unwrap();
-
-
-    hdr.seq
-}
-
-fn get_teid_from_header (packet: &OwnedPacket) -> u32
-{
-
-    let offset: usize = MIN_ETH_HDR_LEN + IP_HDR_LEN + UDP_HDR_LEN;
-
-    let (_, hdr) = get_gtp_header(&packet.data[offset..]).map_err(|e| {
-        eprintln!("GTP Message Type parse error: {:?}", e);
-        e
-    }).unwrap();
-
-    match hdr.teid {
-        None => {
-            println!("No TEID in GTP Header");
-            return 0;
-        },
-        Some(teid) => teid,
-    }
-}
-
-fn get_msg_type_from_header (packet: &OwnedPacket) -> u8
-{
-
-    let offset: usize = MIN_ETH_HDR_LEN + IP_HDR_LEN + UDP_HDR_LEN;
-
-    let (_, hdr) = get_gtp_header(&packet.data[offset..]).map_err(|e| {
-        eprintln!("GTP Message Type parse error: {:?}", e);
-        e
-    }).unwrap();
-
-    // println!("GTP Message Type: {}", hdr.msg_type);
-
-    hdr.msg_type
-}
-
-
-
 async fn
 extract_imsi( ies: Vec<GtpIe>)
 -> String
@@ -687,108 +674,12 @@ async fn extract_bearer_ctx(ies: Vec<GtpIe>)
 }
 
 
-async fn
-extract_5tuple(packet: &OwnedPacket)
--> Ip5Tuple
-{
-    let mut offset: usize = 0;
-    let mut tuple = Ip5Tuple::new();
-
-    offset += MIN_ETH_HDR_LEN;
-
-    (tuple.src_addr, tuple.dst_addr) = get_ip_addr(&packet.data[offset..]);
-
-    let proto = get_next_proto(
-                &packet.data[offset..]);
-
-    tuple.protocol = proto as u8;
-
-    if PROTO_TYPE_UDP != proto as usize {
-        return Ip5Tuple::new();
-    }
-
-    offset += IP_HDR_LEN;
-
-    (tuple.src_port, tuple.dst_port) = get_udp_port(&packet.data[offset..]);
-
-    tuple
-}
-
-
-fn filter_by_imsi(target_imsi: &str, packets: Vec<OwnedPacket>)
-->Vec<OwnedPacket>
-{
-    let mut imsi_filtered_packets = Vec::new();
-
-    for pkt in packets.into_iter() {
-        let mut offset: usize = MIN_ETH_HDR_LEN + IP_HDR_LEN + UDP_HDR_LEN;
-        let hdr_size = get_gtp_hdr_len(&pkt.data[offset..]);
-        offset += hdr_size;
-        let ies = parse_all_ies(&pkt.data[offset..]);
-
-        let imsi = match ies {
-            Ok(ies) => {
-                let t = find_ie_imsi(&ies);
-                match t {
-                    Ok(imsi_value) => {
-                        imsi_value
-                    },
-                    Err(_) => String::new(),
-                }
-            },
-
-            Err(_) => String::new(),
-        };
-
-        if imsi == target_imsi {
-            imsi_filtered_packets.push(pkt);
-        }
-
-    }
-
-    imsi_filtered_packets
-}
-
-async fn filter_by_teid(orig_teid:u32, filtered_packets: Vec<OwnedPacket>)
-->Vec<OwnedPacket>
-{
-    let offset: usize = MIN_ETH_HDR_LEN + IP_HDR_LEN + UDP_HDR_LEN;
-
-    filtered_packets.into_iter().filter(|pkt| {
-        match get_gtp_teid(&pkt.data[offset..]) {
-            Ok ((_, teid)) => teid == orig_teid,
-            Err(_) => false,
-        }
-    }).collect()
-
-}
-
+/// GTPv2-C over UDP on IPv4 or IPv6, as decoded by the dissector pipeline
 pub fn check_gtp(packet: &OwnedPacket)
 -> bool
 {
-    let mut offset: usize = MIN_ETH_HDR_LEN;
-    let mut next_type = 0;
-
-    if packet.data.len() >= MIN_ETH_HDR_LEN {
-        next_type = parse_ethernet(&packet.data);
-    }
-    if next_type != 0x0800 {
-        return false
-    }
-
-    // --- Parse Layer 3 ---
-    let next_proto = get_next_proto(&packet.data[offset..]);
-    if next_proto != PROTO_TYPE_UDP {
-        return false;
-    }
-
-    offset += IP_HDR_LEN;
-    let (_, dst_port) = get_udp_port(&packet.data[offset..]);
-    if dst_port != L4_PORT_GTPV2 {
-        return false;
-    }
-
-    true
+    packet.gtp.is_some() &&
+    packet.tuple.protocol as usize == PROTO_TYPE_UDP
 }
 
 
@@ -811,51 +702,6 @@ async fn filtered_as_gtp(vec_packets: Vec<OwnedPacket>)
     Ok(filtered_packets)
 }
 
-fn checked_by_5tuple(tuple: &Ip5Tuple, packet: &OwnedPacket)
--> bool
-{
-    let mut offset: usize = MIN_ETH_HDR_LEN;
-
-    let (src_addr, dst_addr) = get_ip_addr(&packet.data[offset..]);
-    let proto = get_next_proto(&packet.data[offset..]);
-
-    if (tuple.src_addr == src_addr || tuple.src_addr == dst_addr) &&
-       (tuple.dst_addr == src_addr || tuple.dst_addr == dst_addr) &&
-       tuple.protocol as usize == proto {
-        
-        offset += IP_HDR_LEN;
-
-        let (src_port, dst_port) = get_udp_port(&packet.data[offset..]);
-
-        if ( tuple.src_port == src_port || tuple.src_port == dst_port ) &&
-           ( tuple.dst_port == src_port || tuple.dst_port == dst_port ) {
-            return true;   
-        }
-    }
-
-    false
-}
-
-async fn filter_by_5tuple(tuple: Ip5Tuple, vec_packets: Vec<OwnedPacket>)
-->Result<Vec<OwnedPacket>, String>
-{
-    let mut filtered_packets = Vec::new();
-
-    for pkt in vec_packets.into_iter() {
-
-		if checked_by_5tuple(&tuple, &pkt) {
-            filtered_packets.push(pkt);
-        }
-    }
-
-    if filtered_packets.is_empty() {
-        return Err("No packets matched the 5-tuple".to_string());
-    }
-
-    Ok(filtered_packets)
-}
-
-
 fn init_fteid_info(node: &mut NodeInfo,
     my_s11_teid:u32, my_s5s8_teid:u32, peer_s11_teid:u32, peer_s5s8_teid:u32)
 {
@@ -875,7 +721,7 @@ fn init_fteid_info(node: &mut NodeInfo,
 }
 
 fn init_node_info(node: &mut NodeInfo,
-    addr: Ipv4Addr, port: u16,
+    addr: IpAddr, port: u16,
     msg_type:u8,
     s11_seq:u32, s5s8_seq:u32,
     imsi:&str)
@@ -961,11 +807,54 @@ fn is_s11_seq_match( node: &NodeInfo, seq:u32)
     false
 }
 
+/// A node is known by the address it was first seen on and by the addresses
+/// in its own sender F-TEIDs, which may be of the other address family.
+fn has_addr( node: &NodeInfo, addr: IpAddr )
+-> bool
+{
+    node.addr == addr || node.fteid_addrs.contains(&addr)
+}
+
+/// Addresses of the sender F-TEID, the first F-TEID of the message
+fn sender_fteid_addrs(ies: &Vec<GtpIe>)
+-> Vec<IpAddr>
+{
+    let Some(fteid) = find_ie_fteid(ies).ok().and_then(|list| list.into_iter().next()) else {
+        return Vec::new();
+    };
+
+    [fteid.ipv4, fteid.ipv6].into_iter()
+        .flatten()
+        .filter_map(|addr| addr.parse().ok())
+        .collect()
+}
+
+fn add_fteid_addrs(node: &mut NodeInfo, ies: &Vec<GtpIe>)
+{
+    for addr in sender_fteid_addrs(ies) {
+        if !has_addr(node, addr) {
+            node.fteid_addrs.push(addr);
+        }
+    }
+}
+
+/// Create Session Request sent by the SGW on S5/S8, recognised by its
+/// sender F-TEID rather than by the address the MME sent it to.
+fn is_s5s8_sgw_request(ies: &Vec<GtpIe>, init_node: &NodeInfo, tuple: &Ip5Tuple)
+-> bool
+{
+    let is_sgw_fteid = find_ie_fteid(ies).ok()
+        .and_then(|list| list.into_iter().next())
+        .is_some_and(|fteid| fteid.iface_type == GTPV2C_IFACE_S5S8_SGW_GTPC);
+
+    is_sgw_fteid && !has_addr(init_node, tuple.src_addr)
+}
+
 fn
-check_node ( node: &NodeInfo, addr: Ipv4Addr, port: u16 )
+check_node ( node: &NodeInfo, addr: IpAddr, port: u16 )
 -> bool
 {
-    if node.addr == addr && node.port == port {
+    if has_addr(node, addr) && node.port == port {
         return true;
     }
 
@@ -976,7 +865,7 @@ fn
 is_match_node( node: &NodeInfo, tuple: &Ip5Tuple )
 -> bool
 {
-    if ( node.addr == tuple.src_addr || node.addr == tuple.dst_addr) &&
+    if ( has_addr(node, tuple.src_addr) || has_addr(node, tuple.dst_addr)) &&
        ( node.port == tuple.src_port || node.port == tuple.dst_port) {
         return true;
     }
@@ -984,28 +873,27 @@ is_match_node( node: &NodeInfo, tuple: &Ip5Tuple )
     false
 }
 
+/// Relative times count from the first packet of the capture, as in the summary;
+/// deltas are between consecutive call flow messages.
 async fn
-make_data( flow_packets: Vec<OwnedPacket>)
+make_data( flow_packets: Vec<OwnedPacket>, time_fmt: &TimeFormat, mut times: TimeTracker)
 -> Result<Vec<CallFlow>, String>
 {
     let mut call_flow= Vec::new();
 
     for pkt in flow_packets {
-        let mut offset: usize = 0;
         let mut cf = CallFlow::new();
 
         cf.id = pkt.idx as usize;
+        cf.timestamp = time_fmt.format(pkt.ts_sec, pkt.ts_nsec);
+        cf.time = times.next(pkt.ts_sec, pkt.ts_nsec);
 
-        offset += MIN_ETH_HDR_LEN;
-
-        let (src_addr, dst_addr) = get_ip_addr(&pkt.data[offset..]);
-
-        cf.src_addr.push_str(&src_addr.to_string());
-        cf.dst_addr.push_str(&dst_addr.to_string());
-
-        offset += IP_HDR_LEN+UDP_HDR_LEN;
+        cf.src_addr.push_str(&pkt.tuple.src_addr.to_string());
+        cf.dst_addr.push_str(&pkt.tuple.dst_addr.to_string());
 
-        let (_, message) = get_msg_type_from_gtpc (&pkt.data[offset..]).map_err(|e| format!("Error: {:?}", e))?;
+        let message = pkt.gtp.as_ref()
+            .map(|hdr| hdr.msg_type_str.clone())
+            .ok_or("Not a GTPv2-C message".to_string())?;
 
         cf.message.push_str(&message);
 
@@ -1037,35 +925,29 @@ make_data( flow_packets: Vec<OwnedPacket>)
 
 
 pub async fn
-make_call_flow (path: &PathBuf, id: usize)
+make_call_flow (path: &Path, index: &CaptureIndex, ids: &[usize], id: usize,
+    time_fmt: &TimeFormat)
 -> Result<Vec<CallFlow>, String>
 {
-    let mut offset = MIN_ETH_HDR_LEN + IP_HDR_LEN + UDP_HDR_LEN;
-
-    //1. convert whole packets of pcap to vec
-    let vec_packets = load_pcap(path)?;
+    //1. read the candidate packets of pcap to vec
+    let vec_packets = load_pcap(path, index, ids)?;
 
     //2. find the packet by id
-    let packet = vec_packets.get(id-1).ok_or("Packet not found".to_string())?;
-
-    //2.1 parse all IEs
-    offset += get_gtp_hdr_len(&packet.data[offset..]);
-    let ies = parse_all_ies(&packet.data[offset..]).unwrap_or_default();
-
-    //3. extract 5-tuple from previous found packet
-    let tuple = extract_5tuple(&packet).await;
+    let packet = vec_packets.iter()
+        .find(|p| p.idx as usize == id)
+        .ok_or("Packet not found".to_string())?;
 
-    offset = MIN_ETH_HDR_LEN + IP_HDR_LEN + UDP_HDR_LEN;
+    if packet.gtp.is_none() {
+        return Err("Packet is not a GTPv2-C message".to_string());
+    }
 
-    //4. extract TEID from previous found packet
-    let (_, teid) = get_gtp_teid(&packet.data[offset..]).map_err(|e| format!("GTP-C parse error: {:?}", e))?;
+    //2.1 parse all IEs
+    let ies = packet.ies.clone();
 
-    //4.1 extract IMSI from previous found packet
+    //3. extract IMSI from previous found packet
     let target_imsi = extract_imsi(ies).await;
 
     let target = TargetInfo {
-        tuple : tuple.clone(),
-        teid,
         imsi: target_imsi.clone(),
     };
 
@@ -1093,7 +975,13 @@ make_call_flow (path: &PathBuf, id: usize)
 
     //7. Make Call Flow raw data
 #[cfg(not(feature = "mock"))]
-    let call_flow = make_data( packets).await;
+    let call_flow = {
+        let times = match read_indexed_records(path, index, &[1])?.first() {
+            Some((_, first)) => TimeTracker::with_base(first.ts_sec, first.ts_nsec),
+            None => TimeTracker::new(),
+        };
+        make_data( packets, time_fmt, times).await
+    };
 
     //8. Only for Mock Test
 #[cfg(feature = "mock")]
@@ -1,5 +1,5 @@
-use std:: path::PathBuf;
-use tokio::io::AsyncWriteExt;
+use std::io::{BufReader, BufWriter, Read};
+use std::path::Path;
 use tokio::fs;
 use uuid::Uuid;
 use axum_extra::extract::multipart::Field;
@@ -14,26 +14,28 @@ use std::time::{ Duration, Instant};
 
 use crate::*;
 use crate::parse_pcap::*;
-use crate::types::{Cache, FileInfo, PacketQuery};
+use crate::types::{Cache, ConversationQuery, DecodeAs, FileInfo, FileQuery, PacketQuery, ParsedResult, TimeQuery};
+use crate::time_format::*;
 use crate::file_manage::*;
+use crate::conversations::*;
+use crate::dissect::registry::*;
+use crate::capture::{decompress::*, merge::*, reader::*, stream::*};
+use tokio::sync::mpsc;
 
 async fn upload_file(
     cache: &Cache,
+    uuid: &str,
+    tmp_path: &Path,
     original_name: &str,// file_data: &[u8]
     field: Field,
-) -> Result<String, (StatusCode, String)>
+    parser_tx: mpsc::Sender<Vec<u8>>,
+) -> Result<(), (StatusCode, String)>
 {
-
-    // UUID로 내부 파일 이름 생성
-    let uuid = Uuid::new_v4().to_string();
-    let tmp_filename = format!("web_parser-{}.pcap", uuid);
-    let tmp_path = std::env::temp_dir().join(tmp_filename);
-
     // println!("[cache saved] {}", tmp_path.display());
 
     // 파일을 디스크에 쓴다 (비동기)
     if let Err(e) =
-        save_field_to_file(field, &tmp_path).await {
+        save_field_to_file(field, parser_tx).await {
             let msg = format!("Failed to save uploaded file: {}", e);
             let ret = (StatusCode::INTERNAL_SERVER_ERROR, msg);//.into_response();
             return Err(ret);
@@ -43,40 +45,64 @@ async fn upload_file(
     // 캐시에 등록
     let info = FileInfo {
         // uuid: uuid.clone(),
-        path: tmp_path.clone(),
+        path: tmp_path.to_path_buf(),
         original_name: original_name.to_string(),
         last_used: Instant::now(),
     };
 
-    cache.write().await.insert(uuid.clone(), info);
+    cache.write().await.insert(uuid.to_string(), info);
 
-    Ok(uuid) // 캐시 접근용 키 반환
+    Ok(())
 }
 
 
 /// Multipart field를 파일로 저장 (streaming)
+/// Chunks go to the parser task, which undoes any gzip/zstd/xz compression and
+/// writes the plain capture to disk while parsing it, so the stored file matches
+/// the packet index.
 async fn save_field_to_file(
     mut field: axum_extra::extract::multipart::Field,
-    path: &PathBuf)
+    parser_tx: mpsc::Sender<Vec<u8>>)
 -> Result<(), Box<dyn std::error::Error + Send + Sync>>
 {
-    let mut f = fs::File::create(path).await?;
-
     while let Some(chunk) = field.chunk().await? {
-        f.write_all(&chunk).await?;
+        // the parser has given up on a bad capture; nothing left to store
+        if parser_tx.send(chunk.to_vec()).await.is_err() {
+            break;
+        }
     }
 
-    f.flush().await?;
     Ok(())
 }
 
+
+/// Store the (decompressed) capture at `path` and parse it in one pass.
+fn store_and_parse<R: Read + Send + 'static>(input: R, path: &Path, name: String,
+    time_fmt: &TimeFormat)
+-> Result<ParsedResult, String>
+{
+    let input = decompress_reader(input)?;
+
+    let file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to save uploaded file: {}", e))?;
+
+    let cap = CaptureReader::new(TeeReader::new(input, BufWriter::new(file)))?;
+    simple_parse_pcap(cap, name, time_fmt, &DecodeAs::default())
+}
+
 pub async fn handle_parse_summary(
     State(state): State<Arc<AppState>>,
+    Query(time_query): Query<TimeQuery>,
     mut multipart: Multipart)
 -> Response
 {
     let cache = &state.cache;
 
+    let time_fmt = match TimeFormat::from_time_query(&time_query) {
+        Ok(fmt) => fmt,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
     while let Some(field) = multipart.next_field().await.unwrap_or(None) {
 
         let name = field.name().map(|s| s.to_string()).unwrap_or_default();
@@ -89,34 +115,31 @@ pub async fn handle_parse_summary(
         let orig_filename = field.file_name().map(|s| s.to_string());
         let name = orig_filename.unwrap();
 
-        /* 캐쉬에 등록 */
-        let result = upload_file( &cache, &name, field,);
-
-        let uuid = match result.await {
-            Ok(uuid) => uuid,
-            Err((code, msg)) => {
-                return (code, msg).into_response();
-            }
-        };
-
-        // 임시 파일 경로 생성
-        let cache_read = cache.read().await; // std::sync::RwLock
-
-        let tmp_path = // let cache_read = cache.read().await;
-            cache_read
-                .get(&uuid)
-                .map(|info| info.path.clone())
-                .unwrap_or_else(|| std::env::temp_dir().join(&uuid));
+        // 파서는 보통 sync(블로킹)이므로 spawn_blocking 사용
+        // The upload is parsed while it streams in, not re-read from disk.
+        // UUID로 내부 파일 이름 생성
+        let uuid = Uuid::new_v4().to_string();
+        let tmp_filename = format!("web_parser-{}.pcap", uuid);
+        let tmp_path = std::env::temp_dir().join(tmp_filename);
+
+        let (parser_tx, chunk_reader) = chunk_channel();
+        let parse_name = name.clone();
+        let store_path = tmp_path.clone();
+        let parse_fmt = time_fmt.clone();
+
+        let parse_task =
+            tokio::task::spawn_blocking(move || {
+                store_and_parse(chunk_reader, &store_path, parse_name, &parse_fmt)
+            });
 
+        /* 캐쉬에 등록 */
+        let result = upload_file(cache, &uuid, &tmp_path, &name, field, parser_tx);
 
-        // 파서는 보통 sync(블로킹)이므로 spawn_blocking 사용
-        let tmp_path_clone = tmp_path.clone();
+        if let Err((code, msg)) = result.await {
+            return (code, msg).into_response();
+        }
 
-        let parse_result =
-            // tokio::task::spawn_blocking(move || parse_file(&tmp_path_clone)).await;
-            tokio::spawn(async move {
-                simple_parse_pcap(&tmp_path_clone).await
-            }).await;
+        let parse_result = parse_task.await;
 
         // parse 결과 처리
         match parse_result {
@@ -124,7 +147,7 @@ pub async fn handle_parse_summary(
                 // let _ = tokio::fs::remove_file(&tmp_path).await;
                 // let msg = Json(parsed);
                 let file_id = state.pcaps.insert_file(uuid, tmp_path.clone(),
-                    parsed.clone().packets);
+                    parsed.packets.clone(), parsed.index.clone(), parsed.inferred.clone());
 
                 let resp = serde_json::json!({
                     "file_id": file_id.0,
@@ -162,8 +185,8 @@ pub async fn handle_single_packet (
 
     let pcaps = &state.pcaps;
 
-    let (uuid, file_name) = match pcaps.get_file_name(file_id){
-        Some(pkt) => (pkt.uuid, pkt.original_name),
+    let (uuid, file_name, index) = match pcaps.get_file_location(file_id){
+        Some(loc) => loc,
         None => {
             return (
                 StatusCode::NOT_FOUND,
@@ -177,7 +200,7 @@ pub async fn handle_single_packet (
     let parse_result =
         tokio::spawn(async move {
             //5. parsing하기
-            parse_single_packet(&file_name, packet_id).await
+            parse_single_packet(&file_name, &index, packet_id).await
         }).await;
 
     if let Some(info) = cache.write().await.get_mut(&uuid) {
@@ -234,11 +257,16 @@ handle_callflow(
     let packet_id = req.packet_id;
     // let packet_summary = state.pcaps;
 
+    let time_fmt = match TimeFormat::from_query(req.tz.as_deref(), req.ts_format.as_deref()) {
+        Ok(fmt) => fmt,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
     // let cache = &state.cache;
     let pcaps = &state.pcaps;
 
-    let (_, file_name) = match pcaps.get_file_name(file_id){
-        Some(pkt) => (pkt.uuid, pkt.original_name),
+    let (_, file_name, index) = match pcaps.get_file_location(file_id){
+        Some(loc) => loc,
         None => {
             return (
                 StatusCode::NOT_FOUND,
@@ -247,13 +275,28 @@ handle_callflow(
         }
     };
 
+    // only GTPv2-C packets take part in a call flow, so only those are read back.
+    // The summary already reflects the file's "Decode As" rules.
+    let gtp_ids = pcaps.find_packet_ids(file_id, |p| {
+        p.id == packet_id || p.protocol == "GTP2-C"
+    }).unwrap_or_default();
+
+    let node_names = pcaps.node_directory(file_id);
+
     let flow_result =
         tokio::spawn(async move {
-            make_call_flow(&file_name, packet_id).await
+            make_call_flow(&file_name, &index, &gtp_ids, packet_id, &time_fmt).await
         }).await;
 
     match flow_result {
-        Ok(Ok(call_flow)) => {
+        Ok(Ok(mut call_flow)) => {
+            if let Some(names) = node_names {
+                for msg in call_flow.iter_mut() {
+                    msg.src_name = names.name_of(&msg.src_addr);
+                    msg.dst_name = names.name_of(&msg.dst_addr);
+                }
+            }
+
             let msg = Json(call_flow);
             return (StatusCode::OK, msg).into_response()
         }
@@ -272,6 +315,259 @@ handle_callflow(
 }
 
 
+/// Merge already uploaded captures (e.g. S11 and S5/S8 probes) into a new file id.
+pub async fn
+handle_merge(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<MergeRequest>)
+-> Response
+{
+    if req.files.is_empty() {
+        return (StatusCode::BAD_REQUEST, "No files to merge").into_response();
+    }
+
+    let time_fmt = match TimeFormat::from_query(req.tz.as_deref(), req.ts_format.as_deref()) {
+        Ok(fmt) => fmt,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let cache = &state.cache;
+    let pcaps = &state.pcaps;
+
+    let mut sources = Vec::with_capacity(req.files.len());
+    for file in &req.files {
+        let (uuid, path, index) = match pcaps.get_file_location(FileId(file.file_id)) {
+            Some(loc) => loc,
+            None => {
+                let msg = format!("file {} not found", file.file_id);
+                return (StatusCode::NOT_FOUND, msg).into_response();
+            }
+        };
+
+        let name = match cache.write().await.get_mut(&uuid) {
+            Some(info) => {
+                info.last_used = Instant::now();
+                info.original_name.clone()
+            },
+            None => format!("file-{}", file.file_id),
+        };
+
+        sources.push(MergeSource {
+            name,
+            path,
+            index,
+            clock_offset_ns: file.clock_offset_ns,
+        });
+    }
+
+    let merged_name = req.name.clone().unwrap_or_else(|| {
+        sources.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(" + ")
+    });
+
+    let uuid = Uuid::new_v4().to_string();
+    let tmp_path = std::env::temp_dir().join(format!("web_parser-{}.pcapng", uuid));
+    let merge_path = tmp_path.clone();
+    let parse_name = merged_name.clone();
+
+    let merge_result =
+        tokio::task::spawn_blocking(move || {
+            let stats = merge_captures(&sources, &merge_path)?;
+
+            let file = std::fs::File::open(&merge_path)
+                .map_err(|e| format!("Failed to open merged capture: {}", e))?;
+            let cap = CaptureReader::new(BufReader::new(file))?;
+
+            Ok::<_, String>((stats, simple_parse_pcap(cap, parse_name, &time_fmt, &DecodeAs::default())?))
+        }).await;
+
+    match merge_result {
+        Ok(Ok((stats, parsed))) => {
+            // 캐시에 등록 (TTL cleanup이 병합 파일도 지운다)
+            cache.write().await.insert(uuid.clone(), FileInfo {
+                path: tmp_path.clone(),
+                original_name: merged_name,
+                last_used: Instant::now(),
+            });
+
+            let file_id = pcaps.insert_file(uuid, tmp_path,
+                parsed.packets.clone(), parsed.index.clone(), parsed.inferred.clone());
+
+            let resp = serde_json::json!({
+                "file_id": file_id.0,
+                "duplicates": stats.duplicates,
+                "packets": parsed,
+            });
+
+            (StatusCode::OK, Json(resp)).into_response()
+        }
+
+        Ok(Err(e)) => {
+            let _ = fs::remove_file(&tmp_path).await;
+            let msg = format!("Merge error: {}", e);
+            (StatusCode::BAD_REQUEST, msg).into_response()
+        }
+
+        Err(join_err) => {
+            let _ = fs::remove_file(&tmp_path).await;
+            let msg = format!("Internal error: {}", join_err);
+            (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response()
+        }
+    }
+}
+
+
+/// Current "Decode As" rules of a file and the protocols a rule can name.
+pub async fn
+handle_get_decode_as(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FileQuery>)
+-> Response
+{
+    let Some((_, _, index)) = state.pcaps.get_file_location(FileId(query.file_id)) else {
+        return (StatusCode::NOT_FOUND, "file not found").into_response();
+    };
+
+    let resp = serde_json::json!({
+        "file_id": query.file_id,
+        "decode_as": index.decode_as,
+        "protocols": default_table().port_protocols(),
+    });
+
+    (StatusCode::OK, Json(resp)).into_response()
+}
+
+
+/// Replace a file's "Decode As" rules and parse it again with them. The file
+/// keeps its id; the new summary is returned like an upload's.
+pub async fn
+handle_decode_as(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<DecodeAsRequest>)
+-> Response
+{
+    let file_id = FileId(req.file_id);
+
+    let time_fmt = match TimeFormat::from_query(req.tz.as_deref(), req.ts_format.as_deref()) {
+        Ok(fmt) => fmt,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let decode_as = DecodeAs {
+        rules: req.rules,
+        heuristics: req.heuristics,
+    };
+    // reject bad rules before reading the file
+    if let Err(e) = default_table().with_decode_as(&decode_as) {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+
+    let cache = &state.cache;
+    let pcaps = &state.pcaps;
+
+    let Some((uuid, path, _)) = pcaps.get_file_location(file_id) else {
+        return (StatusCode::NOT_FOUND, "file not found").into_response();
+    };
+
+    let name = match cache.write().await.get_mut(&uuid) {
+        Some(info) => {
+            info.last_used = Instant::now();
+            info.original_name.clone()
+        },
+        None => format!("file-{}", req.file_id),
+    };
+
+    let parse_result =
+        tokio::task::spawn_blocking(move || {
+            let file = std::fs::File::open(&path)
+                .map_err(|e| format!("Failed to open capture file: {}", e))?;
+            let cap = CaptureReader::new(BufReader::new(file))?;
+
+            simple_parse_pcap(cap, name, &time_fmt, &decode_as)
+        }).await;
+
+    match parse_result {
+        Ok(Ok(mut parsed)) => {
+            let Some((packets, nodes)) = pcaps.replace_parsed(file_id, parsed.packets,
+                parsed.index.clone(), parsed.inferred.clone()) else {
+                return (StatusCode::NOT_FOUND, "file not found").into_response();
+            };
+            parsed.packets = packets;
+            parsed.nodes = nodes;
+
+            let resp = serde_json::json!({
+                "file_id": req.file_id,
+                "packets": parsed,
+            });
+
+            (StatusCode::OK, Json(resp)).into_response()
+        }
+
+        Ok(Err(e)) => {
+            let msg = format!("Parser error: {}", e);
+            (StatusCode::BAD_REQUEST, msg).into_response()
+        }
+
+        Err(join_err) => {
+            let msg = format!("Internal error: {}", join_err);
+            (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response()
+        }
+    }
+}
+
+
+/// Nodes of a file: addresses with an alias or an inferred role
+pub async fn
+handle_nodes(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FileQuery>)
+-> Response
+{
+    let Some(nodes) = state.pcaps.nodes(FileId(query.file_id)) else {
+        return (StatusCode::NOT_FOUND, "file not found").into_response();
+    };
+
+    let resp = serde_json::json!({
+        "file_id": query.file_id,
+        "nodes": nodes,
+    });
+
+    (StatusCode::OK, Json(resp)).into_response()
+}
+
+
+/// Replace a file's node alias map. The renamed summaries are returned with
+/// the node list.
+pub async fn
+handle_node_aliases(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<NodeAliasRequest>)
+-> Response
+{
+    let file_id = FileId(req.file_id);
+
+    if state.pcaps.get_file_location(file_id).is_none() {
+        return (StatusCode::NOT_FOUND, "file not found").into_response();
+    }
+
+    match state.pcaps.set_aliases(file_id, req.aliases) {
+        Ok((packets, nodes)) => {
+            let resp = serde_json::json!({
+                "file_id": req.file_id,
+                "nodes": nodes,
+                "packets": {
+                    "total_packets": packets.len(),
+                    "packets": packets,
+                },
+            });
+
+            (StatusCode::OK, Json(resp)).into_response()
+        }
+
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+
 pub async fn
 handle_cleanup(
     State(state): State<Arc<AppState>>)
@@ -303,4 +599,31 @@ handle_cleanup(
         StatusCode::OK,
         format!("cleanup done: {} files removed", removed_count)
     )
-}
\ No newline at end of file
+}
+
+
+/// TCP/UDP/SCTP conversations of a file, filtered and sorted as the query asks
+pub async fn
+handle_conversations(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ConversationQuery>)
+-> Response
+{
+    let file_id = FileId(query.file_id);
+    let Some((_, _, index)) = state.pcaps.get_file_location(file_id) else {
+        return (StatusCode::NOT_FOUND, "file not found").into_response();
+    };
+    let nodes = state.pcaps.node_directory(file_id);
+
+    match query_conversations(&index.conversations, &query, nodes.as_ref()) {
+        Ok(conversations) => {
+            let resp = serde_json::json!({
+                "file_id": query.file_id,
+                "total": index.conversations.len(),
+                "conversations": conversations,
+            });
+            (StatusCode::OK, Json(resp)).into_response()
+        },
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
@@ -0,0 +1,105 @@
+use std::net::IpAddr;
+
+use crate::types::ChecksumStatus;
+
+
+/// 16 bit one's complement sum of `data` added to `sum`, not yet folded
+pub fn ones_complement_sum(data: &[u8], mut sum: u32) -> u32
+{
+    let mut chunks = data.chunks_exact(2);
+    for c in &mut chunks {
+        sum += u16::from_be_bytes([c[0], c[1]]) as u32;
+    }
+    // an odd last byte is padded with zero
+    if let [last] = chunks.remainder() {
+        sum += (*last as u32) << 8;
+    }
+
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum
+}
+
+
+/// Sum of the UDP/TCP/ICMPv6 pseudo header (RFC 768, RFC 8200 8.1)
+pub fn pseudo_header_sum(src: IpAddr, dst: IpAddr, protocol: u8, len: usize) -> u32
+{
+    let mut sum = 0;
+
+    match (src, dst) {
+        (IpAddr::V4(s), IpAddr::V4(d)) => {
+            sum = ones_complement_sum(&s.octets(), sum);
+            sum = ones_complement_sum(&d.octets(), sum);
+            sum += protocol as u32;
+            sum += len as u32 & 0xffff;
+        },
+        (IpAddr::V6(s), IpAddr::V6(d)) => {
+            sum = ones_complement_sum(&s.octets(), sum);
+            sum = ones_complement_sum(&d.octets(), sum);
+            sum = ones_complement_sum(&(len as u32).to_be_bytes(), sum);
+            sum += protocol as u32;
+        },
+        _ => {},
+    }
+
+    sum
+}
+
+
+/// A checksum field is right when everything it covers, itself included,
+/// sums to 0xffff.
+pub fn checksum_status(data: &[u8], pseudo_sum: u32) -> ChecksumStatus
+{
+    if ones_complement_sum(data, pseudo_sum) == 0xffff {
+        ChecksumStatus::Good
+    }
+    else {
+        ChecksumStatus::Bad
+    }
+}
+
+
+/// Checksum of a UDP/TCP segment carried by the IP layer in `ctx.flow`.
+/// Segments that were not captured whole cannot be checked.
+pub fn l4_checksum_status(data: &[u8], complete: bool,
+    src: Option<IpAddr>, dst: Option<IpAddr>, protocol: u8)
+-> ChecksumStatus
+{
+    let (Some(src), Some(dst)) = (src, dst) else {
+        return ChecksumStatus::Unverified;
+    };
+    if !complete {
+        return ChecksumStatus::Unverified;
+    }
+
+    checksum_status(data, pseudo_header_sum(src, dst, protocol, data.len()))
+}
+
+
+// CRC32c (Castagnoli), reflected polynomial
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x82f6_3b78 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC32c of `data`, as SCTP uses it (RFC 9260 Appendix A)
+pub fn crc32c(data: &[u8]) -> u32
+{
+    let mut crc = !0u32;
+    for &b in data {
+        crc = CRC32C_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
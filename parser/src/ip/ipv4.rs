@@ -1,50 +1,140 @@
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr};
 use crate::ip::port::*;
 use crate::types::*;
+use crate::dissect::*;
+use crate::ip::reassembly::*;
+use crate::ip::checksum::*;
 
-pub fn parse_ipv4_simple(ip_hdr: &[u8], mut packet: &mut PacketSummary) -> usize
-{
-    let mut offset: usize = 0;
+// [ https://www.iana.org/assignments/ip-parameters ]
+pub const IP_FLAG_MF: u8        = 0x1;
 
-    offset += 2; //IHL(1byte) + Service Field(1byte)
-    offset += 2; //Total Length (2bytes)
-    offset += 2; //ID Field (2 bytes)
-    offset += 2; //Fragment flag and offset (2bytes)
-    offset += 1; //Time to Live (1byte)
+pub const IPOPT_EOL: u8         = 0;
+pub const IPOPT_NOP: u8         = 1;
+pub const IPOPT_RR: u8          = 7;
+pub const IPOPT_TS: u8          = 68;
+pub const IPOPT_SEC: u8         = 130;
+pub const IPOPT_LSRR: u8        = 131;
+pub const IPOPT_SID: u8         = 136;
+pub const IPOPT_SSRR: u8        = 137;
+pub const IPOPT_RA: u8          = 148;
 
-    let next_hdr = ip_hdr.get(offset).copied().unwrap_or(0) as usize;
 
-    if let Some(v) = protocol_to_str(next_hdr) {
-        packet.protocol.push_str(&v);
-    }
-    else {
-        eprintln!("[parse_ipv4_simple] Unknown protocol type {}", next_hdr);
+pub fn ipv4_option_to_str(opt_type: u8) -> String
+{
+    match opt_type {
+        IPOPT_EOL   => "End of Option List".to_string(),
+        IPOPT_NOP   => "No-Operation".to_string(),
+        IPOPT_RR    => "Record Route".to_string(),
+        IPOPT_TS    => "Timestamp".to_string(),
+        IPOPT_SEC   => "Security".to_string(),
+        IPOPT_LSRR  => "Loose Source Route".to_string(),
+        IPOPT_SID   => "Stream ID".to_string(),
+        IPOPT_SSRR  => "Strict Source Route".to_string(),
+        IPOPT_RA    => "Router Alert".to_string(),
+        _           => format!("Unknown ({})", opt_type),
     }
+}
 
-    offset += 1; //Next Protocol (1byte)
-    offset += 2; //Header Checksum Field (2bytes)
 
-    let mut src_addr = Ipv4Addr::new(0,0,0,0);
-    if let Ok(octets) = ip_hdr[offset..offset+4].try_into() {
-        src_addr = Ipv4Addr::from_octets(octets);
-    }
-    else {
-        eprintln!("Failure to read Src Addr");
+fn ipv4_option_value(opt_type: u8, body: &[u8]) -> Ipv4OptionValue
+{
+    let addr_at = |b: &[u8]| Ipv4Addr::new(b[0], b[1], b[2], b[3]).to_string();
+
+    match opt_type {
+        IPOPT_RR | IPOPT_LSRR | IPOPT_SSRR if !body.is_empty() => Ipv4OptionValue::Route {
+            pointer: body[0],
+            addrs: body[1..].chunks_exact(4).map(addr_at).collect(),
+        },
+
+        IPOPT_TS if body.len() >= 2 => {
+            let flag = body[1] & 0x0f;
+            // flag 0: timestamps only, 1 and 3: address + timestamp pairs
+            let entries = if flag == 0 {
+                body[2..].chunks_exact(4)
+                    .map(|c| Ipv4Timestamp {
+                        addr: None,
+                        timestamp: u32::from_be_bytes([c[0], c[1], c[2], c[3]]),
+                    })
+                    .collect()
+            }
+            else {
+                body[2..].chunks_exact(8)
+                    .map(|c| Ipv4Timestamp {
+                        addr: Some(addr_at(c)),
+                        timestamp: u32::from_be_bytes([c[4], c[5], c[6], c[7]]),
+                    })
+                    .collect()
+            };
+            Ipv4OptionValue::Timestamp {
+                pointer: body[0],
+                overflow: body[1] >> 4,
+                flag,
+                entries,
+            }
+        },
+
+        IPOPT_RA if body.len() == 2 =>
+            Ipv4OptionValue::RouterAlert(u16::from_be_bytes([body[0], body[1]])),
+
+        _ if body.is_empty() => Ipv4OptionValue::None,
+        _ => Ipv4OptionValue::Raw(body.to_vec()),
     }
-    offset += 4;
+}
 
-    let mut dst_addr = Ipv4Addr::new(0,0,0,0);
-    if let Ok(octets) = ip_hdr[offset..offset+4].try_into() {
-        dst_addr = Ipv4Addr::from_octets(octets);
-    }
-    else {
-        eprintln!("Failure to read Src Addr");
-    }
 
-    packet.src_ip.push_str(&src_addr.to_string());
-    packet.dst_ip.push_str(&dst_addr.to_string());
+/// Decode the options area between the fixed header and IHL into `list`.
+/// Offsets in the error are relative to `opts`.
+pub fn parse_ipv4_options(opts: &[u8], list: &mut Vec<Ipv4Option>)
+-> Result<(), DecodeError>
+{
+    let mut pos = 0;
+
+    while pos < opts.len() {
+        let opt_type = opts[pos];
+
+        let mut opt = Ipv4Option {
+            opt_type,
+            name: ipv4_option_to_str(opt_type),
+            copied: opt_type & 0x80 != 0,
+            class: (opt_type >> 5) & 0x03,
+            number: opt_type & 0x1f,
+            length: 1,
+            value: Ipv4OptionValue::None,
+            raw: vec![opt_type],
+        };
+
+        // single octet options
+        if opt_type == IPOPT_EOL || opt_type == IPOPT_NOP {
+            list.push(opt);
+            pos += 1;
+            if opt_type == IPOPT_EOL {
+                break;      // the rest is padding
+            }
+            continue;
+        }
+
+        let Some(&len) = opts.get(pos + 1) else {
+            return Err(DecodeError::Truncated { offset: pos + 1, needed: Some(pos + 2), available: opts.len() });
+        };
+        if len < 2 {
+            return Err(DecodeError::invalid(pos + 1,
+                format!("option {} length {} is below 2", opt.name, len)));
+        }
+
+        let end = pos + len as usize;
+        let Some(body) = opts.get(pos + 2..end) else {
+            return Err(DecodeError::Truncated { offset: pos, needed: Some(end), available: opts.len() });
+        };
+
+        opt.length = len;
+        opt.value = ipv4_option_value(opt_type, body);
+        opt.raw = opts[pos..end].to_vec();
+        list.push(opt);
+
+        pos = end;
+    }
 
-    next_hdr
+    Ok(())
 }
 
 
@@ -75,18 +165,6 @@ pub fn get_ip_addr(ip_hdr: &[u8])
 }
 
 
-pub fn get_next_proto(ip_hdr: &[u8])
--> usize
-{
-    let mut offset: usize = 0;
-    offset += 9;
-
-    let next_hdr = ip_hdr[offset] as usize;
-
-    next_hdr
-}
-
-
 pub fn parse_ipv4( ip_hdr: &[u8], ip: &mut IpInfo)
 -> usize
 {
@@ -95,7 +173,7 @@ pub fn parse_ipv4( ip_hdr: &[u8], ip: &mut IpInfo)
     let version_ihl = ip_hdr[offset];
     let version = (version_ihl & 0xf0)>>4;
     let ihl = (version_ihl & 0x0f) as usize * 4;
-    if ihl != IP_HDR_LEN {
+    if ihl < IP_HDR_LEN || ip_hdr.len() < ihl {
         return 0;
     }
 
@@ -117,15 +195,12 @@ pub fn parse_ipv4( ip_hdr: &[u8], ip: &mut IpInfo)
     ]);
     offset += 2; //ID Field (2 bytes)
 
-    let frag : u16 = u16::from_ne_bytes([
+    let frag : u16 = u16::from_be_bytes([
         ip_hdr[offset], ip_hdr[offset+1]
     ]);
 
-    let frag_flag: u8 = ((frag & 0x40)>>5) as u8;
-    let mut frag_offset: u16 = 0;
-    if frag_flag == 0x02 {
-        frag_offset = frag & 0x1f;
-    }
+    let frag_flag: u8 = (frag >> 13) as u8;
+    let frag_offset: u16 = (frag & 0x1fff) * 8;
     offset += 2; //Fragment flag and offset (2bytes)
 
     let ttl = ip_hdr[offset];
@@ -180,4 +255,84 @@ pub fn parse_ipv4( ip_hdr: &[u8], ip: &mut IpInfo)
     ip.next.push_str(&str_proto.to_string());
 
     next_hdr
-}
\ No newline at end of file
+}
+
+
+pub struct Ipv4Dissector;
+
+impl Dissector for Ipv4Dissector {
+    fn name(&self) -> &'static str {
+        "IPv4"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        need(data, IP_HDR_LEN)?;
+
+        let version = data[0] >> 4;
+        if version != 4 {
+            return Err(DecodeError::invalid(0, format!("IP version {} in an IPv4 header", version)));
+        }
+        let ihl = (data[0] & 0x0f) as usize * 4;
+        if ihl < IP_HDR_LEN {
+            return Err(DecodeError::invalid(0, format!("header length {} is below {}", ihl, IP_HDR_LEN)));
+        }
+        need(data, ihl)?;
+
+        let mut ip = IpInfo::new();
+        let next_hdr = parse_ipv4(data, &mut ip);
+        ip.checksum_status = ctx.check_checksum(checksum_status(&data[..ihl], 0));
+
+        // a bad option is reported, the layers above are still decoded
+        if let Err(e) = parse_ipv4_options(&data[IP_HDR_LEN..ihl], &mut ip.options) {
+            ctx.mark_malformed(self.name(), &e.shifted(IP_HDR_LEN));
+        }
+
+        // an inner IP header (tunnel) replaces the outer addresses
+        let (src_addr, dst_addr) = get_ip_addr(data);
+        ctx.flow.protocol = ip.protocol;
+        ctx.flow.src_addr = Some(IpAddr::V4(src_addr));
+        ctx.flow.dst_addr = Some(IpAddr::V4(dst_addr));
+
+        ctx.summary.src_ip = ip.src_addr.clone();
+        ctx.summary.dst_ip = ip.dst_addr.clone();
+        ctx.summary.protocol = protocol_to_str(next_hdr).unwrap_or_default();
+
+        let hdr_len = ip.ihl as usize;
+        // 0 is seen with TCP segmentation offload, the captured length is used then
+        let total_len = (ip.total_length as usize >= hdr_len).then_some(ip.total_length as usize);
+
+        let more = ip.flags & IP_FLAG_MF != 0;
+        if more || ip.fragment_offset != 0 {
+            let end = total_len.unwrap_or(data.len()).min(data.len());
+            ctx.summary.description = format!("Fragmented IP protocol (proto={} {}, off={}, ID={:04x})",
+                ip.next, ip.protocol, ip.fragment_offset, ip.id);
+            ctx.fragment = Some(Fragment {
+                key: FragKey {
+                    src: IpAddr::V4(src_addr),
+                    dst: IpAddr::V4(dst_addr),
+                    id: ip.id as u32,
+                    protocol: ip.protocol,
+                },
+                offset: ip.fragment_offset as usize,
+                more,
+                payload_at: ctx.offset + hdr_len,
+                payload_len: end - hdr_len,
+            });
+        }
+
+        if ctx.wants_detail() {
+            ctx.detail.l3.push(Layer3Info::IP(ip));
+        }
+
+        // the payload of a fragment is decoded once its datagram is reassembled
+        if ctx.fragment.is_some() {
+            return Ok(None);
+        }
+
+        Ok(Some(NextLayer {
+            key: LayerKey::IpProto(next_hdr as u8),
+            hdr_len,
+            len: total_len,
+        }))
+    }
+}
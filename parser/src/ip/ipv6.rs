@@ -1,81 +1,203 @@
-use std::net::Ipv6Addr;
-use crate::{ip::port::*, types::Ip6Info};
+use std::net::{IpAddr, Ipv6Addr};
+use crate::ip::port::*;
 use crate::types::*;
+use crate::dissect::*;
+use crate::ip::reassembly::*;
 
-fn parse_ipv6_ext(mut next_hdr: usize, packet: &[u8]) {
-    let mut offset = 0;
+pub const IP6_FRAG_HDR_LEN: usize = 8;
+const IP6_EXT_MIN_LEN: usize      = 8;
+const ESP_HDR_LEN: usize          = 8;
+const ROUTING_TYPE_SRH: u8        = 4;
 
-    // extension header가 계속 있는 동안 반복
-    while let Some(name) = v6_ext_hdr_to_str(next_hdr) {
-        println!("Found extension header: {}", name);
 
-         if packet.len() < offset + 2 {
-            println!("Malformed packet");
-            return;
-        }
+fn ip6_option_to_str(opt_type: u8) -> String
+{
+    match opt_type {
+        0x00 => "Pad1".to_string(),
+        0x01 => "PadN".to_string(),
+        0x05 => "Router Alert".to_string(),
+        0x07 => "CALIPSO".to_string(),
+        0xc2 => "Jumbo Payload".to_string(),
+        0xc9 => "Home Address".to_string(),
+        _    => format!("Unknown (0x{:02x})", opt_type),
+    }
+}
+
 
-        let ext_hdr_type = packet[offset];
-        // 실제 헤더 길이 가져오기 (필드는 offset+1 위치에 있음)
-        let hdr_len = packet[offset + 1] as usize;
-        let hdr_total = (hdr_len + 1) * 8;
+/// Option TLVs after the first two octets of a HbH / DestOpts header
+fn parse_ip6_options(data: &[u8]) -> Vec<Ip6Option>
+{
+    let mut options = Vec::new();
+    let mut pos = 0;
 
-        if packet.len() < offset + hdr_total {
-            println!("Truncated extension header");
-            return;
+    while pos < data.len() {
+        let opt_type = data[pos];
+        // Pad1 has no length octet
+        if opt_type == 0 {
+            options.push(Ip6Option { opt_type, name: ip6_option_to_str(opt_type), data: Vec::new() });
+            pos += 1;
+            continue;
         }
 
-        println!("{} {}", ext_hdr_type, hdr_total);
+        let Some(&len) = data.get(pos + 1) else {
+            break;
+        };
+        let end = (pos + 2 + len as usize).min(data.len());
+        options.push(Ip6Option {
+            opt_type,
+            name: ip6_option_to_str(opt_type),
+            data: data[pos + 2..end].to_vec(),
+        });
+        pos = end;
+    }
 
-        offset += hdr_total;
+    options
+}
 
-        next_hdr = packet[offset] as usize; // 다음 헤더 값 업데이트
+
+fn parse_srh(data: &[u8]) -> Option<Srv6Header>
+{
+    // last entry, flags, tag follow the 4 common routing header octets
+    if data.len() < 8 {
+        return None;
     }
 
-    println!("No more extension headers. Next protocol = {}", next_hdr);
+    let segments = data[8..].chunks_exact(16)
+        .take(data[4] as usize + 1)
+        .map(|c| {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(c);
+            Ipv6Addr::from(octets).to_string()
+        })
+        .collect();
+
+    Some(Srv6Header {
+        last_entry: data[4],
+        flags: data[5],
+        tag: u16::from_be_bytes([data[6], data[7]]),
+        segments,
+    })
 }
 
 
-pub fn parse_ipv6_simple(ip_hdr: &[u8], packet: &mut PacketSummary) -> usize
+/// Walk the extension headers starting with `next_hdr` at the start of
+/// `data` (the IPv6 payload) into `chain`. Stops at the upper layer, at a
+/// fragment that is not the whole datagram, and at ESP.
+/// Returns the upper layer protocol and where it starts in `data`.
+pub fn parse_ipv6_ext(mut next_hdr: u8, data: &[u8], chain: &mut Vec<Ip6ExtHeader>)
+-> Result<(u8, usize), DecodeError>
 {
-    let mut offset: usize = 0;
+    let mut offset = 0;
 
-    offset += 4;
-    offset += 2;
+    // extension header가 계속 있는 동안 반복
+    while v6_ext_hdr_to_str(next_hdr as usize).is_some() {
+        let hdr = &data[offset..];
 
-    let next_hdr = ip_hdr[offset] as usize;
-    offset += 1;
-    offset += 1;
+        let ext_type = next_hdr as usize;
+        let (ext, hdr_len, next) = match ext_type {
+            V6_EXT_HOP_BY_HOP | V6_EXT_DEST_OPTS => {
+                need(hdr, 2).map_err(|e| e.shifted(offset))?;
+                let hdr_len = (hdr[1] as usize + 1) * 8;
+                need(hdr, hdr_len).map_err(|e| e.shifted(offset))?;
 
-    // let mut str_proto = String::new();
-    if let Some(v) = protocol_to_str(next_hdr) {
-        // str_proto = v;
-        packet.protocol.push_str(&v);
-    }
-    else {
-        eprintln!("[parse_ipv6_simple] Unknown protocol type {}", next_hdr);
-    }
+                let options = parse_ip6_options(&hdr[2..hdr_len]);
+                let ext = if ext_type == V6_EXT_HOP_BY_HOP {
+                    Ip6ExtHeader::HopByHop { next: hdr[0], hdr_len, options }
+                }
+                else {
+                    Ip6ExtHeader::DestOptions { next: hdr[0], hdr_len, options }
+                };
+                (ext, hdr_len, hdr[0])
+            },
 
-    let mut src_addr6 = Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0);
-    if let Ok(v) = ip_hdr[offset..offset+15].try_into() {
-        src_addr6  = Ipv6Addr::from_octets(v);
-    }
-    else {
-        eprintln!("failure to read src addr")
-    }
-    offset += 16;
+            V6_EXT_ROUTING => {
+                need(hdr, IP6_EXT_MIN_LEN).map_err(|e| e.shifted(offset))?;
+                let hdr_len = (hdr[1] as usize + 1) * 8;
+                need(hdr, hdr_len).map_err(|e| e.shifted(offset))?;
 
-    let mut dst_addr6 = Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0);
-    if let Ok(v) = ip_hdr[offset..offset+15].try_into() {
-        dst_addr6  = Ipv6Addr::from_octets(v);
-    }
-    else {
-        eprintln!("failure to read dst addr")
+                let routing_type = hdr[2];
+                let srv6 = if routing_type == ROUTING_TYPE_SRH {
+                    parse_srh(&hdr[..hdr_len])
+                }
+                else {
+                    None
+                };
+                let ext = Ip6ExtHeader::Routing {
+                    next: hdr[0],
+                    hdr_len,
+                    routing_type,
+                    segments_left: hdr[3],
+                    srv6,
+                    data: hdr[4..hdr_len].to_vec(),
+                };
+                (ext, hdr_len, hdr[0])
+            },
+
+            V6_EXT_FRAGMENT => {
+                need(hdr, IP6_FRAG_HDR_LEN).map_err(|e| e.shifted(offset))?;
+                let off_flags = u16::from_be_bytes([hdr[2], hdr[3]]);
+                let ext = Ip6ExtHeader::Fragment {
+                    next: hdr[0],
+                    offset: off_flags & 0xfff8,
+                    more: off_flags & 0x0001 != 0,
+                    id: u32::from_be_bytes([hdr[4], hdr[5], hdr[6], hdr[7]]),
+                };
+                (ext, IP6_FRAG_HDR_LEN, hdr[0])
+            },
+
+            V6_EXT_AH => {
+                need(hdr, 12).map_err(|e| e.shifted(offset))?;
+                // AH length is in 4-octet units, minus 2
+                let hdr_len = (hdr[1] as usize + 2) * 4;
+                need(hdr, hdr_len).map_err(|e| e.shifted(offset))?;
+
+                let ext = Ip6ExtHeader::Ah {
+                    next: hdr[0],
+                    hdr_len,
+                    spi: u32::from_be_bytes([hdr[4], hdr[5], hdr[6], hdr[7]]),
+                    seq: u32::from_be_bytes([hdr[8], hdr[9], hdr[10], hdr[11]]),
+                    icv: hdr[12..hdr_len].to_vec(),
+                };
+                (ext, hdr_len, hdr[0])
+            },
+
+            V6_EXT_ESP => {
+                need(hdr, ESP_HDR_LEN).map_err(|e| e.shifted(offset))?;
+                chain.push(Ip6ExtHeader::Esp {
+                    spi: u32::from_be_bytes([hdr[0], hdr[1], hdr[2], hdr[3]]),
+                    seq: u32::from_be_bytes([hdr[4], hdr[5], hdr[6], hdr[7]]),
+                });
+                return Ok((next_hdr, offset + ESP_HDR_LEN));
+            },
+
+            // Mobility, HIP, Shim6: the chain is not followed further
+            _ => break,
+        };
+
+        chain.push(ext);
+        offset += hdr_len;
+        next_hdr = next;
+
+        // only the first fragment has the upper layer header, and only in part
+        if let Some(Ip6ExtHeader::Fragment { offset: frag_off, more, .. }) = chain.last()
+            && (*frag_off != 0 || *more) {
+            break;
+        }
     }
 
-    packet.src_ip.push_str(&src_addr6.to_string());
-    packet.dst_ip.push_str(&dst_addr6.to_string());
+    Ok((next_hdr, offset))
+}
 
-    next_hdr
+
+/// Source and destination of a header already checked to be IP6_HDR_LEN long
+pub fn get_ip6_addr(ip_hdr: &[u8]) -> (Ipv6Addr, Ipv6Addr)
+{
+    let mut src = [0u8; 16];
+    let mut dst = [0u8; 16];
+    src.copy_from_slice(&ip_hdr[8..24]);
+    dst.copy_from_slice(&ip_hdr[24..40]);
+
+    (Ipv6Addr::from(src), Ipv6Addr::from(dst))
 }
 
 
@@ -100,14 +222,13 @@ pub fn parse_ipv6(ip_hdr: &[u8], ip: &mut Ip6Info) -> usize
 
     let next_hdr = ip_hdr[offset] as usize;
 
-    let str_proto = protocol_to_str(next_hdr).unwrap_or_default(); 
     offset += 1;
 
     let hl = ip_hdr[offset];
     offset += 1;
 
     let mut src_addr6 = Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0);
-    if let Ok(v) = ip_hdr[offset..offset+15].try_into() {
+    if let Ok(v) = ip_hdr[offset..offset+16].try_into() {
         src_addr6  = Ipv6Addr::from_octets(v);
     }
     else {
@@ -116,17 +237,12 @@ pub fn parse_ipv6(ip_hdr: &[u8], ip: &mut Ip6Info) -> usize
     offset += 16;
 
     let mut dst_addr6 = Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0);
-    if let Ok(v) = ip_hdr[offset..offset+15].try_into() {
+    if let Ok(v) = ip_hdr[offset..offset+16].try_into() {
         dst_addr6  = Ipv6Addr::from_octets(v);
     }
     else {
         eprintln!("failure to read dst addr")
     }
-    offset += 16;
-
-    if str_proto.is_empty() {
-        parse_ipv6_ext(next_hdr, &ip_hdr[offset..]);
-    }
 
     ip.version = version;
     ip.tc = tc;
@@ -138,4 +254,88 @@ pub fn parse_ipv6(ip_hdr: &[u8], ip: &mut Ip6Info) -> usize
     ip.dst_addr.push_str(&dst_addr6.to_string());
 
     next_hdr
-}
\ No newline at end of file
+}
+
+
+pub struct Ipv6Dissector;
+
+impl Dissector for Ipv6Dissector {
+    fn name(&self) -> &'static str {
+        "IPv6"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        need(data, IP6_HDR_LEN)?;
+
+        let version = data[0] >> 4;
+        if version != 6 {
+            return Err(DecodeError::invalid(0, format!("IP version {} in an IPv6 header", version)));
+        }
+
+        let mut ip6 = Ip6Info::new();
+        parse_ipv6(data, &mut ip6);
+
+        // payload length 0 means a jumbogram, the captured length is used then
+        let len = (ip6.pl != 0).then_some(IP6_HDR_LEN + ip6.pl as usize);
+        let end = len.unwrap_or(data.len()).min(data.len());
+
+        let chain = parse_ipv6_ext(ip6.next, &data[IP6_HDR_LEN..end], &mut ip6.ext_headers)
+            .map_err(|e| e.shifted(IP6_HDR_LEN));
+        let (upper, ext_len) = chain.as_ref().copied().unwrap_or((ip6.next, 0));
+        let hdr_len = IP6_HDR_LEN + ext_len;
+        ip6.upper_proto = upper;
+
+        let (src, dst) = get_ip6_addr(data);
+        ctx.flow.protocol = upper;
+        ctx.flow.src_addr = Some(IpAddr::V6(src));
+        ctx.flow.dst_addr = Some(IpAddr::V6(dst));
+
+        ctx.summary.src_ip = ip6.src_addr.clone();
+        ctx.summary.dst_ip = ip6.dst_addr.clone();
+        ctx.summary.protocol = protocol_to_str(upper as usize)
+            .or_else(|| v6_ext_hdr_to_str(upper as usize))
+            .unwrap_or_default();
+
+        match ip6.ext_headers.last() {
+            Some(Ip6ExtHeader::Fragment { next, offset, more, id }) if *offset != 0 || *more => {
+                ctx.summary.description = format!("IPv6 fragment (nxt={} {}, off={}, ID=0x{:08x})",
+                    ctx.summary.protocol, next, offset, id);
+                ctx.fragment = Some(Fragment {
+                    key: FragKey {
+                        src: IpAddr::V6(src),
+                        dst: IpAddr::V6(dst),
+                        id: *id,
+                        protocol: *next,
+                    },
+                    offset: *offset as usize,
+                    more: *more,
+                    payload_at: ctx.offset + hdr_len,
+                    payload_len: end - hdr_len,
+                });
+            },
+            Some(Ip6ExtHeader::Esp { spi, seq }) => {
+                ctx.summary.protocol = "ESP".to_string();
+                ctx.summary.description = format!("ESP (SPI=0x{:08x}, seq={})", spi, seq);
+            },
+            _ => {},
+        }
+
+        let stop = chain.is_err() || ctx.fragment.is_some()
+            || matches!(ip6.ext_headers.last(), Some(Ip6ExtHeader::Esp { .. }));
+
+        if ctx.wants_detail() {
+            ctx.detail.l3.push(Layer3Info::IP6(ip6));
+        }
+
+        chain?;
+        if stop {
+            return Ok(None);
+        }
+
+        Ok(Some(NextLayer {
+            key: LayerKey::IpProto(upper),
+            hdr_len,
+            len,
+        }))
+    }
+}
@@ -1,3 +1,7 @@
 pub mod ipv4;
 pub mod ipv6;
-pub mod port;
\ No newline at end of file
+pub mod checksum;
+pub mod port;
+pub mod reassembly;
+#[cfg(test)]
+mod tests;
@@ -2,7 +2,17 @@ pub const PROTO_TYPE_ICMP: usize    = 1;
 pub const PROTO_TYPE_IPINIP: usize  = 4;
 pub const PROTO_TYPE_TCP: usize     = 6;
 pub const PROTO_TYPE_UDP: usize     = 17;
+pub const PROTO_TYPE_IPV6: usize    = 41;
+pub const PROTO_TYPE_GRE: usize     = 47;
 pub const PROTO_TYPE_ICMPV6: usize  = 58;
+pub const PROTO_TYPE_SCTP: usize    = 132;
+
+pub const V6_EXT_HOP_BY_HOP: usize  = 0;
+pub const V6_EXT_ROUTING: usize     = 43;
+pub const V6_EXT_FRAGMENT: usize    = 44;
+pub const V6_EXT_ESP: usize         = 50;
+pub const V6_EXT_AH: usize          = 51;
+pub const V6_EXT_DEST_OPTS: usize   = 60;
 
 pub const L4_PORT_FTP_DATA: u16     = 20;
 pub const L4_PORT_FTP_CTRL: u16     = 21;
@@ -11,17 +21,26 @@ pub const L4_PORT_DHCP_SVR: u16     = 67;
 pub const L4_PORT_DHCP_CLI: u16     = 68;
 pub const L4_PORT_HTTP: u16         = 80;
 pub const L4_PORT_GTPV2: u16        = 2123;
+pub const L4_PORT_GTPU: u16         = 2152;
+pub const L4_PORT_VXLAN: u16        = 4789;
 pub const L4_PORT_PFCP: u16         = 8805;
 
+/// UDP ports of the tunnels we decapsulate, which may leave the UDP checksum
+/// at zero over IPv6 (RFC 6935)
+pub fn is_tunnel_port(port: u16) -> bool
+{
+    matches!(port, L4_PORT_GTPU | L4_PORT_VXLAN)
+}
+
 pub fn v6_ext_hdr_to_str(ext_hdr: usize) -> Option<String>
 {
     match ext_hdr {
-        0	=> Some("Hop-by-Hop Extension Header".to_string()),
-        43	=> Some("Routing Extension Header".to_string()),
-        44	=> Some("Fragment Extension Header".to_string()),
-        51	=> Some("Authentication Header (AH) Extension Header".to_string()),
-        50	=> Some("Encapsulating Security Payload (ESP) Extension Header".to_string()),
-        60	=> Some("Destination Extension Header".to_string()),
+        V6_EXT_HOP_BY_HOP	=> Some("Hop-by-Hop Extension Header".to_string()),
+        V6_EXT_ROUTING	=> Some("Routing Extension Header".to_string()),
+        V6_EXT_FRAGMENT	=> Some("Fragment Extension Header".to_string()),
+        V6_EXT_AH	=> Some("Authentication Header (AH) Extension Header".to_string()),
+        V6_EXT_ESP	=> Some("Encapsulating Security Payload (ESP) Extension Header".to_string()),
+        V6_EXT_DEST_OPTS	=> Some("Destination Extension Header".to_string()),
         135	=> Some("Mobility Extension Header".to_string()),
         139	=> Some("Host Identity Protocol Extension Header".to_string()),
         140	=> Some("Shim6 Protocol Extension Header".to_string()),
@@ -37,7 +56,10 @@ pub fn protocol_to_str(next_hdr: usize) -> Option<String>
         PROTO_TYPE_IPINIP   => Some("IP in IP".to_string()),
         PROTO_TYPE_TCP   => Some("TCP".to_string()),
         PROTO_TYPE_UDP  => Some("UDP".to_string()),
+        PROTO_TYPE_IPV6  => Some("IPv6".to_string()),
+        PROTO_TYPE_GRE  => Some("GRE".to_string()),
         PROTO_TYPE_ICMPV6  => Some("ICMPv6".to_string()),
+        PROTO_TYPE_SCTP  => Some("SCTP".to_string()),
         _   => None,
     }
 
@@ -53,6 +75,8 @@ pub fn port_to_str(port: u16) -> Option<String>
         L4_PORT_DHCP_CLI   => Some("DHCP".to_string()),
         L4_PORT_HTTP       => Some("HTTP".to_string()),
         L4_PORT_GTPV2      => Some("GTPv2-C".to_string()),
+        L4_PORT_GTPU       => Some("GTP-U".to_string()),
+        L4_PORT_VXLAN      => Some("VXLAN".to_string()),
         // 5G
         L4_PORT_PFCP       => Some("PFCP".to_string()),
         _                         => None,
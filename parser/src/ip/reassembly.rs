@@ -0,0 +1,133 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+
+// datagrams still waiting for fragments; the oldest is dropped beyond this
+const MAX_PENDING: usize        = 1024;
+// IPv4 total length and IPv6 payload length are 16 bit
+const MAX_DATAGRAM: usize       = 65535;
+
+
+/// Identifies the fragments of one datagram.
+/// IPv4: (src, dst, ID, protocol), IPv6: (src, dst, Fragment header ID, next header)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FragKey {
+    pub src: IpAddr,
+    pub dst: IpAddr,
+    pub id: u32,
+    pub protocol: u8,
+}
+
+/// A fragment found while dissecting a frame
+#[derive(Debug, Clone)]
+pub struct Fragment {
+    pub key: FragKey,
+    pub offset: usize,          // byte offset of the payload in the datagram
+    pub more: bool,             // MF / M flag
+    pub payload_at: usize,      // frame offset of the fragment payload
+    pub payload_len: usize,
+}
+
+/// A datagram put back together from its fragments
+#[derive(Debug, Clone)]
+pub struct Reassembled {
+    pub protocol: u8,
+    pub payload: Vec<u8>,
+    pub packet_ids: Vec<usize>,     // in arrival order, the last one completed it
+}
+
+
+struct Pending {
+    parts: Vec<(usize, Vec<u8>)>,
+    total: Option<usize>,
+    packet_ids: Vec<usize>,
+}
+
+
+/// Collects fragments across a capture until their datagram is complete.
+pub struct Reassembler {
+    pending: HashMap<FragKey, Pending>,
+    order: VecDeque<FragKey>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Reassembler {
+            pending: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Add the fragment of packet `packet_id`. Returns the datagram once every
+    /// byte from 0 to the end of the last fragment has been seen.
+    pub fn add(&mut self, packet_id: usize, frag: &Fragment, payload: &[u8])
+    -> Option<Reassembled>
+    {
+        let end = frag.offset + payload.len();
+        if end > MAX_DATAGRAM {
+            return None;
+        }
+
+        if !self.pending.contains_key(&frag.key) {
+            if self.order.len() >= MAX_PENDING
+                && let Some(oldest) = self.order.pop_front() {
+                self.pending.remove(&oldest);
+            }
+            self.order.push_back(frag.key);
+            self.pending.insert(frag.key, Pending {
+                parts: Vec::new(),
+                total: None,
+                packet_ids: Vec::new(),
+            });
+        }
+
+        let entry = self.pending.get_mut(&frag.key)?;
+        entry.parts.push((frag.offset, payload.to_vec()));
+        entry.packet_ids.push(packet_id);
+        if !frag.more {
+            entry.total = Some(end);
+        }
+
+        let total = entry.total?;
+        if !is_complete(&mut entry.parts, total) {
+            return None;
+        }
+
+        let entry = self.pending.remove(&frag.key)?;
+        self.order.retain(|k| *k != frag.key);
+
+        // overlapping bytes keep the copy with the lowest offset, then the earliest
+        let mut payload = vec![0u8; total];
+        let mut filled = vec![false; total];
+        for (offset, data) in entry.parts.iter() {
+            for (i, b) in data.iter().enumerate() {
+                let at = offset + i;
+                if at < total && !filled[at] {
+                    payload[at] = *b;
+                    filled[at] = true;
+                }
+            }
+        }
+
+        Some(Reassembled {
+            protocol: frag.key.protocol,
+            payload,
+            packet_ids: entry.packet_ids,
+        })
+    }
+}
+
+
+fn is_complete(parts: &mut [(usize, Vec<u8>)], total: usize) -> bool
+{
+    parts.sort_by_key(|(offset, _)| *offset);
+
+    let mut covered = 0;
+    for (offset, data) in parts.iter() {
+        if *offset > covered {
+            return false;
+        }
+        covered = covered.max(offset + data.len());
+    }
+
+    covered >= total
+}
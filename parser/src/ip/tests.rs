@@ -0,0 +1,311 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::types::*;
+use crate::dissect::*;
+use crate::dissect::registry::*;
+use crate::ip::{ipv6::*, port::*, reassembly::*};
+use crate::l2::link::*;
+use crate::test_util::*;
+
+
+const PROTO_TCP: u8 = PROTO_TYPE_TCP as u8;
+const PROTO_UDP: u8 = PROTO_TYPE_UDP as u8;
+
+fn l4_checksum(packet: &[u8]) -> (ChecksumStatus, Dissection)
+{
+    let ctx = dissect_frame(default_table(), LINKTYPE_RAW, packet, Depth::Detail);
+    let status = match &ctx.detail.l4 {
+        Layer4Info::UDP(udp) => udp.checksum_status,
+        Layer4Info::TCP(tcp) => tcp.checksum_status,
+        Layer4Info::ICMP(icmp) => icmp.checksum_status,
+        other => panic!("expected UDP, TCP or ICMP, got {:?}", other),
+    };
+    (status, ctx)
+}
+
+// extension header with its next header and 8-octet length unit, padded to 8 octets
+fn ext(next: u8, body: &[u8]) -> Vec<u8>
+{
+    let len = (2 + body.len()).div_ceil(8) * 8;
+    let mut out = vec![next, (len / 8 - 1) as u8];
+    out.extend(body);
+    out.resize(len, 0);
+    out
+}
+
+
+#[test]
+fn ipv6_ext_chain_up_to_udp() {
+    // Router Alert, then PadN to the end of the header
+    let hbh = ext(43, &[0x05, 0x02, 0x00, 0x00, 0x01, 0x00]);
+    let mut srh = vec![4, 1, 1, 0x80, 0x00, 0x2a];
+    srh.extend([0x20, 0x01, 0x0d, 0xb8].iter().chain(&[0; 11]).chain(&[1]));
+    srh.extend([0x20, 0x01, 0x0d, 0xb8].iter().chain(&[0; 11]).chain(&[2]));
+    let routing = ext(60, &srh);
+    let dest = ext(PROTO_UDP, &[0x00, 0x01, 0x02, 0x00, 0x00, 0x00]);
+    let data = [hbh.clone(), routing.clone(), dest.clone(), vec![0xaa; 8]].concat();
+    let mut chain = Vec::new();
+
+    let (upper, at) = parse_ipv6_ext(0, &data, &mut chain).unwrap();
+    assert_eq!((upper, at), (PROTO_UDP, hbh.len() + routing.len() + dest.len()));
+    assert_eq!(chain.len(), 3);
+
+    let Ip6ExtHeader::HopByHop { next, hdr_len, options } = &chain[0] else {
+        panic!("expected Hop-by-Hop, got {:?}", chain[0]);
+    };
+    assert_eq!((*next, *hdr_len), (43, 8));
+    let names: Vec<_> = options.iter().map(|o| o.name.as_str()).collect();
+    assert_eq!(names, vec!["Router Alert", "PadN"]);
+    assert_eq!(options[0].data, vec![0x00, 0x00]);
+
+    let Ip6ExtHeader::Routing { routing_type, segments_left, srv6: Some(srv6), hdr_len, .. } = &chain[1] else {
+        panic!("expected an SRH, got {:?}", chain[1]);
+    };
+    assert_eq!((*routing_type, *segments_left, *hdr_len), (4, 1, 40));
+    assert_eq!((srv6.last_entry, srv6.flags, srv6.tag), (1, 0x80, 0x2a));
+    assert_eq!(srv6.segments, vec!["2001:db8::1".to_string(), "2001:db8::2".to_string()]);
+
+    let Ip6ExtHeader::DestOptions { options, .. } = &chain[2] else {
+        panic!("expected Destination Options, got {:?}", chain[2]);
+    };
+    // Pad1 has no length octet
+    assert_eq!(options.iter().map(|o| o.opt_type).collect::<Vec<_>>(), vec![0, 1, 0]);
+}
+
+#[test]
+fn ipv6_ext_chain_atomic_fragment_continues() {
+    let data = [ipv6_fragment_header(PROTO_TCP, 0, false, 0x01020304), vec![0; 20]].concat();
+    let mut chain = Vec::new();
+
+    assert_eq!(parse_ipv6_ext(44, &data, &mut chain).unwrap(), (PROTO_TCP, 8));
+    assert!(matches!(chain[0], Ip6ExtHeader::Fragment { offset: 0, more: false, id: 0x01020304, .. }));
+}
+
+#[test]
+fn ipv6_ext_chain_stops_at_a_fragment() {
+    // the first fragment's Destination Options header is not walked
+    let data = [ipv6_fragment_header(60, 0, true, 7), ext(PROTO_UDP, &[])].concat();
+    let mut chain = Vec::new();
+    assert_eq!(parse_ipv6_ext(44, &data, &mut chain).unwrap(), (60, 8));
+    assert_eq!(chain.len(), 1);
+
+    let data = [ipv6_fragment_header(PROTO_UDP, 1448, false, 7), vec![0; 8]].concat();
+    let mut chain = Vec::new();
+    assert_eq!(parse_ipv6_ext(44, &data, &mut chain).unwrap(), (PROTO_UDP, 8));
+    assert!(matches!(chain[0], Ip6ExtHeader::Fragment { offset: 1448, more: false, .. }));
+}
+
+#[test]
+fn ipv6_ext_chain_ah_and_esp() {
+    // AH length counts 4-octet units minus 2: 4 means 24 octets, a 12-octet ICV
+    let mut ah = vec![50, 4, 0, 0];
+    ah.extend(0x1000u32.to_be_bytes());
+    ah.extend(9u32.to_be_bytes());
+    ah.extend([0xcc; 12]);
+    let mut esp = 0x2000u32.to_be_bytes().to_vec();
+    esp.extend(3u32.to_be_bytes());
+    esp.extend([0xee; 16]);
+    let data = [ah, esp].concat();
+    let mut chain = Vec::new();
+
+    assert_eq!(parse_ipv6_ext(51, &data, &mut chain).unwrap(), (50, 32));
+    let Ip6ExtHeader::Ah { next, hdr_len, spi, seq, icv } = &chain[0] else {
+        panic!("expected AH, got {:?}", chain[0]);
+    };
+    assert_eq!((*next, *hdr_len, *spi, *seq, icv.len()), (50, 24, 0x1000, 9, 12));
+    assert!(matches!(chain[1], Ip6ExtHeader::Esp { spi: 0x2000, seq: 3 }));
+}
+
+#[test]
+fn ipv6_ext_chain_truncated() {
+    // Destination Options claims 16 octets after an 8-octet Hop-by-Hop header
+    let data = [ext(60, &[]), vec![PROTO_UDP, 1, 0, 0, 0, 0, 0, 0]].concat();
+    let mut chain = Vec::new();
+
+    let err = parse_ipv6_ext(0, &data, &mut chain).unwrap_err();
+    assert!(matches!(err, DecodeError::Truncated { needed: Some(24), available: 16, .. }));
+    assert_eq!(err.offset(), 16);
+    assert_eq!(chain.len(), 1);
+
+    let mut chain = Vec::new();
+    assert!(parse_ipv6_ext(44, &[PROTO_UDP, 0, 0], &mut chain).is_err());
+}
+
+
+fn key(id: u32) -> FragKey
+{
+    FragKey {
+        src: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+        dst: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+        id,
+        protocol: PROTO_UDP,
+    }
+}
+
+fn fragment(id: u32, offset: usize, more: bool) -> Fragment
+{
+    Fragment { key: key(id), offset, more, payload_at: 0, payload_len: 0 }
+}
+
+
+#[test]
+fn reassembly_out_of_order() {
+    let mut frags = Reassembler::new();
+
+    assert!(frags.add(1, &fragment(1, 16, false), &[3; 4]).is_none());
+    assert!(frags.add(2, &fragment(1, 8, true), &[2; 8]).is_none());
+    let datagram = frags.add(3, &fragment(1, 0, true), &[1; 8]).unwrap();
+
+    assert_eq!(datagram.protocol, PROTO_UDP);
+    assert_eq!(datagram.payload, [[1; 8].as_slice(), &[2; 8], &[3; 4]].concat());
+    assert_eq!(datagram.packet_ids, vec![1, 2, 3]);
+}
+
+#[test]
+fn reassembly_overlap_keeps_the_earliest_copy() {
+    let mut frags = Reassembler::new();
+
+    assert!(frags.add(1, &fragment(1, 0, true), &[1; 16]).is_none());
+    let datagram = frags.add(2, &fragment(1, 8, false), &[2; 16]).unwrap();
+
+    assert_eq!(datagram.payload, [[1; 16].as_slice(), &[2; 8]].concat());
+}
+
+#[test]
+fn reassembly_waits_for_the_gap_and_keeps_datagrams_apart() {
+    let mut frags = Reassembler::new();
+
+    assert!(frags.add(1, &fragment(1, 0, true), &[1; 8]).is_none());
+    assert!(frags.add(2, &fragment(2, 16, false), &[2; 8]).is_none());
+    // datagram 1 still lacks bytes 8..16, datagram 2 lacks 0..16
+    assert!(frags.add(3, &fragment(1, 16, false), &[1; 8]).is_none());
+    assert!(frags.add(4, &fragment(2, 0, true), &[2; 16]).is_some_and(|d| d.packet_ids == vec![2, 4]));
+    assert!(frags.add(5, &fragment(1, 8, true), &[1; 8]).is_some_and(|d| d.packet_ids == vec![1, 3, 5]));
+}
+
+#[test]
+fn reassembly_drops_a_datagram_over_64k() {
+    let mut frags = Reassembler::new();
+
+    assert!(frags.add(1, &fragment(1, 0, true), &[0; 8]).is_none());
+    assert!(frags.add(2, &fragment(1, 65528, false), &[0; 16]).is_none());
+}
+
+#[test]
+fn ipv4_fragments_are_dissected_once_reassembled() {
+    let sent = udp(40000, 5000, &[7; 32]);
+    let last = ipv4_fragment(0x1234, 24, false, PROTO_UDP, &sent[24..]);
+    let first = ipv4_fragment(0x1234, 0, true, PROTO_UDP, &sent[..24]);
+    let mut frags = Reassembler::new();
+
+    let (ctx, datagram) = dissect_frame_reassembled(default_table(), &mut frags, 1,
+        LINKTYPE_RAW, &last, Depth::Detail);
+    assert!(datagram.is_none());
+    assert!(ctx.summary.description.starts_with("Fragmented IP protocol"));
+    assert_eq!(ctx.flow.dst_port, 0);
+
+    let (ctx, datagram) = dissect_frame_reassembled(default_table(), &mut frags, 2,
+        LINKTYPE_RAW, &first, Depth::Detail);
+    let datagram = datagram.unwrap();
+    assert_eq!(datagram.payload, sent);
+    assert_eq!(datagram.packet_ids, vec![1, 2]);
+    assert_eq!(ctx.summary.fragments, vec![1, 2]);
+    assert_eq!((ctx.flow.src_port, ctx.flow.dst_port), (40000, 5000));
+}
+
+#[test]
+fn ipv6_fragments_are_dissected_once_reassembled() {
+    let sent = udp(40000, 5000, &[7; 40]);
+    let first = ipv6(44, &[ipv6_fragment_header(PROTO_UDP, 0, true, 0xabcd), sent[..32].to_vec()].concat());
+    let last = ipv6(44, &[ipv6_fragment_header(PROTO_UDP, 32, false, 0xabcd), sent[32..].to_vec()].concat());
+    let mut frags = Reassembler::new();
+
+    let (_, datagram) = dissect_frame_reassembled(default_table(), &mut frags, 1,
+        LINKTYPE_RAW, &first, Depth::Flow);
+    assert!(datagram.is_none());
+
+    let (ctx, datagram) = dissect_frame_reassembled(default_table(), &mut frags, 2,
+        LINKTYPE_RAW, &last, Depth::Flow);
+    assert_eq!(datagram.unwrap().payload, sent);
+    assert_eq!(ctx.flow.protocol, PROTO_UDP);
+    assert_eq!(ctx.flow.dst_port, 5000);
+}
+
+
+#[test]
+fn ipv4_header_checksum() {
+    let mut packet = ipv4(PROTO_UDP, &udp(40000, 5000, &[1, 2, 3]));
+    let ctx = dissect_frame(default_table(), LINKTYPE_RAW, &packet, Depth::Detail);
+    assert!(matches!(&ctx.detail.l3[0], Layer3Info::IP(ip) if ip.checksum_status == ChecksumStatus::Good));
+    assert!(!ctx.summary.bad_checksum);
+
+    packet[8] -= 1;     // TTL, as a router would without fixing the checksum
+    let ctx = dissect_frame(default_table(), LINKTYPE_RAW, &packet, Depth::Detail);
+    assert!(matches!(&ctx.detail.l3[0], Layer3Info::IP(ip) if ip.checksum_status == ChecksumStatus::Bad));
+    assert!(ctx.summary.bad_checksum);
+}
+
+#[test]
+fn udp_and_tcp_checksums_cover_the_pseudo_header() {
+    for (protocol, segment, field) in [(PROTO_UDP, udp(40000, 5000, &[1, 2, 3]), 6), (PROTO_TCP, tcp(40000, 5000, 1, 0, 0x18, &[4, 5, 6]), 16)] {
+        for mut packet in [ipv4(protocol, &segment), ipv6(protocol, &segment)] {
+            fill_l4_checksum(&mut packet, field);
+            let (status, ctx) = l4_checksum(&packet);
+            assert_eq!(status, ChecksumStatus::Good, "protocol {} over IPv{}", protocol, packet[0] >> 4);
+            assert!(!ctx.summary.bad_checksum);
+
+            // same segment, other destination: only the pseudo header changes
+            let dst_last = if packet[0] >> 4 == 4 { 19 } else { 39 };
+            packet[dst_last] = 3;
+            if packet[0] >> 4 == 4 {
+                packet[10..12].fill(0);
+                let sum = internet_checksum(&packet[..20]);
+                packet[10..12].copy_from_slice(&sum.to_be_bytes());
+            }
+            let (status, ctx) = l4_checksum(&packet);
+            assert_eq!(status, ChecksumStatus::Bad, "protocol {} over IPv{}", protocol, packet[0] >> 4);
+            assert!(ctx.summary.bad_checksum);
+        }
+    }
+}
+
+#[test]
+fn zero_udp_checksum() {
+    // optional over IPv4
+    let (status, ctx) = l4_checksum(&ipv4(PROTO_UDP, &udp(40000, 5000, &[1])));
+    assert_eq!(status, ChecksumStatus::Unverified);
+    assert!(!ctx.summary.bad_checksum && !ctx.summary.malformed);
+
+    // not allowed over IPv6 ...
+    let (status, ctx) = l4_checksum(&ipv6(PROTO_UDP, &udp(40000, 5000, &[1])));
+    assert_eq!(status, ChecksumStatus::Bad);
+    assert!(ctx.summary.bad_checksum && ctx.summary.malformed);
+    assert_eq!(ctx.detail.malformed[0].protocol, "UDP");
+    assert_eq!(ctx.detail.malformed[0].offset, 40 + 6);
+
+    // ... except for the tunnels of RFC 6935
+    let vxlan = [vec![0x08, 0, 0, 0, 0, 0, 0x2a, 0], eth(0x0800, &ipv4(PROTO_UDP, &udp(1, 2, &[])))].concat();
+    let ctx = dissect_frame(default_table(), LINKTYPE_RAW, &ipv6(PROTO_UDP, &udp(50000, L4_PORT_VXLAN, &vxlan)), Depth::Detail);
+    assert!(ctx.detail.l3.iter().any(|l3| matches!(l3, Layer3Info::Vxlan(_))));
+    assert!(!ctx.summary.bad_checksum && !ctx.summary.malformed);
+}
+
+#[test]
+fn icmp_checksums() {
+    // ICMP covers the message only, ICMPv6 adds the pseudo header
+    let mut echo = vec![8, 0, 0, 0, 0x12, 0x34, 0, 1, 0xaa, 0xbb];
+    let sum = internet_checksum(&echo);
+    echo[2..4].copy_from_slice(&sum.to_be_bytes());
+    let mut packet = ipv4(PROTO_TYPE_ICMP as u8, &echo);
+    assert_eq!(l4_checksum(&packet).0, ChecksumStatus::Good);
+
+    *packet.last_mut().unwrap() ^= 0xff;
+    assert_eq!(l4_checksum(&packet).0, ChecksumStatus::Bad);
+
+    let mut packet = ipv6(PROTO_TYPE_ICMPV6 as u8, &[128, 0, 0, 0, 0x12, 0x34, 0, 1, 0xaa]);
+    fill_l4_checksum(&mut packet, 2);
+    assert_eq!(l4_checksum(&packet).0, ChecksumStatus::Good);
+
+    packet[39] = 3;         // destination address, in the pseudo header only
+    assert_eq!(l4_checksum(&packet).0, ChecksumStatus::Bad);
+}
@@ -0,0 +1,99 @@
+use crate::types::*;
+use crate::dissect::*;
+use crate::l2::{link::*, mpls::*, oui::*, vlan::*};
+use crate::tunnel::erspan::*;
+
+pub const NEXT_HDR_IPV4: usize = 0x0800;
+pub const NEXT_HDR_IPV6: usize = 0x86dd;
+pub const ETHERTYPE_ARP: usize = 0x0806;
+pub const ETHERTYPE_TEB: usize = 0x6558;    // Transparent Ethernet Bridging, Ethernet in a tunnel
+pub const ETHERTYPE_LLDP: usize = 0x88cc;
+
+
+pub fn ethertype_to_str(ethertype: usize) -> Option<String>
+{
+    match ethertype {
+        NEXT_HDR_IPV4   => Some("IPv4".to_string()),
+        NEXT_HDR_IPV6   => Some("IPv6".to_string()),
+        ETHERTYPE_ARP   => Some("ARP".to_string()),
+        ETHERTYPE_TEB   => Some("Transparent Ethernet Bridging".to_string()),
+        ETHERTYPE_LLDP  => Some("LLDP".to_string()),
+        ETHERTYPE_VLAN  => Some("802.1Q VLAN".to_string()),
+        ETHERTYPE_QINQ |
+        ETHERTYPE_QINQ_OLD => Some("802.1ad QinQ".to_string()),
+        ETHERTYPE_MPLS_UC => Some("MPLS Unicast".to_string()),
+        ETHERTYPE_MPLS_MC => Some("MPLS Multicast".to_string()),
+        0x8863          => Some("PPPoE Discovery".to_string()),
+        0x8864          => Some("PPPoE Session".to_string()),
+        ETHERTYPE_ERSPAN_II  => Some("ERSPAN Type II".to_string()),
+        ETHERTYPE_ERSPAN_III => Some("ERSPAN Type III".to_string()),
+        _               => None,
+    }
+}
+
+
+/// aa:bb:cc:dd:ee:ff
+pub fn mac_to_string(mac: &[u8]) -> String
+{
+    mac.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":")
+}
+
+
+/// Source address of a link layer that only carries the sender's (SLL, SLL2)
+pub fn set_src_mac(l2: &mut Layer2Info, mac: &[u8])
+{
+    if l2.src_mac.is_none() {
+        l2.src_mac = Some(mac_to_string(mac));
+        l2.src_vendor = mac_vendor(mac);
+    }
+}
+
+
+/// Ethernet II header: destination MAC, source MAC, ethertype.
+/// The outermost header's addresses are kept when a pseudowire nests another one.
+pub fn parse_ethernet(data: &[u8], l2: &mut Layer2Info) -> usize
+{
+    let dst = &data[0..6];
+    let src = &data[6..12];
+
+    if l2.dst_mac.is_none() {
+        l2.dst_mac = Some(mac_to_string(dst));
+        l2.dst_vendor = mac_vendor(dst);
+    }
+    set_src_mac(l2, src);
+
+    u16::from_be_bytes([data[12], data[13]]) as usize
+}
+
+
+/// Ethernet frame carried in a tunnel (GRE, ERSPAN, VXLAN), with its VLAN
+/// tags and MPLS labels. The outermost link layer is decoded by `parse_l2`.
+pub struct EthernetDissector;
+
+impl Dissector for EthernetDissector {
+    fn name(&self) -> &'static str {
+        "Ethernet"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        need(data, MIN_ETH_HDR_LEN)?;
+
+        let mut l2 = Layer2Info::new();
+        l2.frame_len = data.len();
+        let parsed = parse_l2(LINKTYPE_ETHERNET, data, &mut l2);
+
+        if ctx.wants_detail() {
+            ctx.detail.l3.push(Layer3Info::Ethernet(l2));
+        }
+
+        let Some((ethertype, hdr_len)) = parsed else {
+            return Err(DecodeError::invalid(12, "VLAN or MPLS header is truncated or unrecognised"));
+        };
+
+        Ok(Some(NextLayer {
+            key: LayerKey::Ethertype(ethertype as u16),
+            hdr_len,
+            len: None,
+        }))
+    }
+}
@@ -0,0 +1,191 @@
+use crate::l2::{ethernet::*, mpls::*, vlan::*};
+use crate::types::*;
+
+// [ https://www.tcpdump.org/linktypes.html ]
+pub const LINKTYPE_NULL: u16            = 0;
+pub const LINKTYPE_ETHERNET: u16        = 1;
+pub const LINKTYPE_RAW_BSD: u16         = 12;
+pub const LINKTYPE_RAW_BSDOS: u16       = 14;
+pub const LINKTYPE_RAW: u16             = 101;
+pub const LINKTYPE_LOOP: u16            = 108;
+pub const LINKTYPE_LINUX_SLL: u16       = 113;
+pub const LINKTYPE_IPV4: u16            = 228;
+pub const LINKTYPE_IPV6: u16            = 229;
+pub const LINKTYPE_LINUX_SLL2: u16      = 276;
+
+pub const SLL_HDR_LEN: usize            = 16;
+pub const SLL2_HDR_LEN: usize           = 20;
+pub const NULL_HDR_LEN: usize           = 4;
+pub const PW_CW_LEN: usize              = 4;    // pseudowire control word
+
+// BSD AF_INET6 differs per OS (NetBSD/OpenBSD, FreeBSD, Darwin)
+const BSD_AF_INET: u32                  = 2;
+const BSD_AF_INET6: [u32; 4]            = [10, 24, 28, 30];
+
+
+pub fn link_type_to_str(link_type: u16) -> Option<String>
+{
+    match link_type {
+        LINKTYPE_NULL           => Some("BSD Loopback".to_string()),
+        LINKTYPE_ETHERNET       => Some("Ethernet".to_string()),
+        LINKTYPE_RAW_BSD |
+        LINKTYPE_RAW_BSDOS |
+        LINKTYPE_RAW            => Some("Raw IP".to_string()),
+        LINKTYPE_LOOP           => Some("OpenBSD Loopback".to_string()),
+        LINKTYPE_LINUX_SLL      => Some("Linux Cooked (SLL)".to_string()),
+        LINKTYPE_IPV4           => Some("Raw IPv4".to_string()),
+        LINKTYPE_IPV6           => Some("Raw IPv6".to_string()),
+        LINKTYPE_LINUX_SLL2     => Some("Linux Cooked v2 (SLL2)".to_string()),
+        _                       => None,
+    }
+}
+
+
+/// Guess the ethertype of a bare IP header from its version nibble.
+fn ip_version_to_type(data: &[u8]) -> Option<usize>
+{
+    match data.first().map(|b| b >> 4) {
+        Some(4) => Some(NEXT_HDR_IPV4),
+        Some(6) => Some(NEXT_HDR_IPV6),
+        _ => None,
+    }
+}
+
+
+fn bsd_family_to_type(family: u32) -> Option<usize>
+{
+    if family == BSD_AF_INET {
+        Some(NEXT_HDR_IPV4)
+    }
+    else if BSD_AF_INET6.contains(&family) {
+        Some(NEXT_HDR_IPV6)
+    }
+    else {
+        None
+    }
+}
+
+
+/// Peel VLAN tags and MPLS labels until a network layer ethertype is reached.
+fn parse_l2_tags(mut ethertype: usize, data: &[u8], mut offset: usize,
+    l2: &mut Layer2Info)
+-> Option<(usize, usize)>
+{
+    loop {
+        if is_vlan_type(ethertype) {
+            let (tag, next_type) = parse_vlan_tag(ethertype, data.get(offset..)?)?;
+            l2.vlans.push(tag);
+            offset += VLAN_TAG_LEN;
+            ethertype = next_type;
+            continue;
+        }
+
+        if is_mpls_type(ethertype) {
+            offset += parse_mpls_stack(data.get(offset..)?, &mut l2.mpls)?;
+
+            // MPLS does not signal its payload: IP is told apart by the version
+            // nibble, a zero nibble is a pseudowire control word before Ethernet
+            let payload = data.get(offset..)?;
+            if let Some(t) = ip_version_to_type(payload) {
+                return Some((t, offset));
+            }
+
+            if payload.len() >= PW_CW_LEN + MIN_ETH_HDR_LEN && payload[0] >> 4 == 0 {
+                offset += PW_CW_LEN;
+                ethertype = parse_ethernet(&data[offset..], l2);
+                offset += MIN_ETH_HDR_LEN;
+                continue;
+            }
+
+            return None;
+        }
+
+        return Some((ethertype, offset));
+    }
+}
+
+
+/// Decode the layer 2 header of a frame according to the capture's datalink type,
+/// including any VLAN tags and MPLS labels, into `l2`.
+/// Returns the ethertype of the next layer and the offset where it starts.
+pub fn parse_l2(link_type: u16, data: &[u8], l2: &mut Layer2Info) -> Option<(usize, usize)>
+{
+    l2.link_type = link_type;
+
+    let (ethertype, offset) = parse_l2_base(link_type, data, l2)?;
+    let (ethertype, offset) = parse_l2_tags(ethertype, data, offset, l2)?;
+
+    l2.ethertype = ethertype as u16;
+    l2.ethertype_str = ethertype_to_str(ethertype);
+
+    Some((ethertype, offset))
+}
+
+
+fn parse_l2_base(link_type: u16, data: &[u8], l2: &mut Layer2Info) -> Option<(usize, usize)>
+{
+    match link_type {
+        LINKTYPE_ETHERNET => {
+            if data.len() < MIN_ETH_HDR_LEN {
+                return None;
+            }
+            Some((parse_ethernet(data, l2), MIN_ETH_HDR_LEN))
+        },
+
+        LINKTYPE_RAW | LINKTYPE_RAW_BSD | LINKTYPE_RAW_BSDOS => {
+            ip_version_to_type(data).map(|t| (t, 0))
+        },
+
+        LINKTYPE_IPV4 => Some((NEXT_HDR_IPV4, 0)),
+        LINKTYPE_IPV6 => Some((NEXT_HDR_IPV6, 0)),
+
+        LINKTYPE_NULL => {
+            if data.len() < NULL_HDR_LEN {
+                return None;
+            }
+            // family is in the byte order of the capturing host
+            let raw = [data[0], data[1], data[2], data[3]];
+            let family = if data[0] == 0 && data[1] == 0 {
+                u32::from_be_bytes(raw)
+            }
+            else {
+                u32::from_le_bytes(raw)
+            };
+            bsd_family_to_type(family).map(|t| (t, NULL_HDR_LEN))
+        },
+
+        LINKTYPE_LOOP => {
+            if data.len() < NULL_HDR_LEN {
+                return None;
+            }
+            let family = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+            bsd_family_to_type(family).map(|t| (t, NULL_HDR_LEN))
+        },
+
+        LINKTYPE_LINUX_SLL => {
+            if data.len() < SLL_HDR_LEN {
+                return None;
+            }
+            // sender's link layer address, up to 8 bytes
+            let addr_len = u16::from_be_bytes([data[4], data[5]]) as usize;
+            if addr_len == 6 {
+                set_src_mac(l2, &data[6..12]);
+            }
+            let proto = u16::from_be_bytes([data[14], data[15]]) as usize;
+            Some((proto, SLL_HDR_LEN))
+        },
+
+        LINKTYPE_LINUX_SLL2 => {
+            if data.len() < SLL2_HDR_LEN {
+                return None;
+            }
+            if data[11] == 6 {
+                set_src_mac(l2, &data[12..18]);
+            }
+            let proto = u16::from_be_bytes([data[0], data[1]]) as usize;
+            Some((proto, SLL2_HDR_LEN))
+        },
+
+        _ => None,
+    }
+}
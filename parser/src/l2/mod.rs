@@ -0,0 +1,8 @@
+pub mod ethernet;
+pub mod link;
+pub mod mpls;
+pub mod oui;
+pub mod vlan;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,47 @@
+use crate::types::*;
+
+pub const ETHERTYPE_MPLS_UC: usize      = 0x8847;
+pub const ETHERTYPE_MPLS_MC: usize      = 0x8848;
+
+pub const MPLS_LABEL_LEN: usize         = 4;
+
+
+pub fn is_mpls_type(ethertype: usize) -> bool
+{
+    matches!(ethertype, ETHERTYPE_MPLS_UC | ETHERTYPE_MPLS_MC)
+}
+
+
+/// Decode an MPLS label stack up to and including the bottom-of-stack entry.
+/// Returns the number of bytes consumed, or None if the stack is truncated.
+pub fn parse_mpls_stack(data: &[u8], labels: &mut Vec<MplsLabel>) -> Option<usize>
+{
+    let mut offset = 0;
+
+    loop {
+        if data.len() < offset + MPLS_LABEL_LEN {
+            return None;
+        }
+
+        let entry = u32::from_be_bytes([
+            data[offset], data[offset+1],
+            data[offset+2], data[offset+3],
+        ]);
+        offset += MPLS_LABEL_LEN;
+
+        let label = MplsLabel {
+            label: entry >> 12,
+            tc: ((entry >> 9) & 0x07) as u8,
+            s: (entry & 0x100) != 0,
+            ttl: (entry & 0xff) as u8,
+        };
+        let bottom = label.s;
+        labels.push(label);
+
+        if bottom {
+            break;
+        }
+    }
+
+    Some(offset)
+}
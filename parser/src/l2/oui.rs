@@ -0,0 +1,93 @@
+// Offline subset of the IEEE MA-L registry: vendors commonly found in
+// mobile core, data centre and lab networks. Sorted by OUI.
+static OUI_VENDORS: [(u32, &str); 55] = [
+    (0x00000C, "Cisco"),
+    (0x0000F0, "Samsung"),
+    (0x000142, "Cisco"),
+    (0x0001D7, "F5 Networks"),
+    (0x0002C9, "Mellanox"),
+    (0x000393, "Apple"),
+    (0x000496, "Extreme Networks"),
+    (0x00051E, "Brocade"),
+    (0x000569, "VMware"),
+    (0x000585, "Juniper Networks"),
+    (0x00090F, "Fortinet"),
+    (0x000C29, "VMware"),
+    (0x001018, "Broadcom"),
+    (0x0010DB, "Juniper Networks"),
+    (0x00121E, "Juniper Networks"),
+    (0x00155D, "Microsoft Hyper-V"),
+    (0x0015EB, "ZTE"),
+    (0x00163E, "Xen"),
+    (0x001882, "Huawei"),
+    (0x0019C6, "ZTE"),
+    (0x001B17, "Palo Alto Networks"),
+    (0x001B21, "Intel"),
+    (0x001C73, "Arista Networks"),
+    (0x001E67, "Intel"),
+    (0x002590, "Super Micro Computer"),
+    (0x00259E, "Huawei"),
+    (0x005056, "VMware"),
+    (0x00D0F6, "Alcatel-Lucent"),
+    (0x00E04C, "Realtek"),
+    (0x00E0FC, "Huawei"),
+    (0x080027, "VirtualBox"),
+    (0x18A99B, "Dell"),
+    (0x246E96, "Dell"),
+    (0x248A07, "Mellanox"),
+    (0x24A43C, "Ubiquiti"),
+    (0x286ED4, "Huawei"),
+    (0x28993A, "Arista Networks"),
+    (0x2C6BF5, "Juniper Networks"),
+    (0x3CFDFE, "Intel"),
+    (0x444CA8, "Arista Networks"),
+    (0x50C7BF, "TP-Link"),
+    (0x525400, "QEMU/KVM"),
+    (0x5C260A, "Dell"),
+    (0x6805CA, "Intel"),
+    (0x7CFE90, "Mellanox"),
+    (0x848F69, "Dell"),
+    (0x90E2BA, "Intel"),
+    (0xA0369F, "Intel"),
+    (0xB8599F, "Mellanox"),
+    (0xB8CA3A, "Dell"),
+    (0xD4AE52, "Dell"),
+    (0xE4434B, "Dell"),
+    (0xEC0D9A, "Mellanox"),
+    (0xF48E38, "Dell"),
+    (0xF8BC12, "Dell"),
+];
+
+
+/// Vendor of a MAC address from its OUI. Addresses outside the table are
+/// described by their group and locally administered bits, if set.
+pub fn mac_vendor(mac: &[u8]) -> Option<String>
+{
+    let [a, b, c, ..] = *mac else {
+        return None;
+    };
+    let oui = u32::from_be_bytes([0, a, b, c]);
+
+    if let Ok(i) = OUI_VENDORS.binary_search_by_key(&oui, |(o, _)| *o) {
+        return Some(OUI_VENDORS[i].1.to_string());
+    }
+
+    if mac.iter().all(|&b| b == 0xff) {
+        Some("Broadcast".to_string())
+    }
+    else if a == 0x01 && b == 0x00 && c == 0x5e {
+        Some("IPv4 Multicast".to_string())
+    }
+    else if a == 0x33 && b == 0x33 {
+        Some("IPv6 Multicast".to_string())
+    }
+    else if a & 0x01 != 0 {
+        Some("Multicast".to_string())
+    }
+    else if a & 0x02 != 0 {
+        Some("Locally administered".to_string())
+    }
+    else {
+        None
+    }
+}
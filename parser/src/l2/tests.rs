@@ -0,0 +1,76 @@
+use crate::types::*;
+use crate::l2::{link::*, mpls::*, vlan::*};
+use crate::test_util::*;
+
+
+const IPV4_START: [u8; 4] = [0x45, 0x00, 0x00, 0x14];
+const IPV6_START: [u8; 4] = [0x60, 0x00, 0x00, 0x00];
+
+
+#[test]
+fn single_vlan_tag() {
+    let frame = eth(ETHERTYPE_VLAN as u16, &[vlan_tag(5, true, 100, 0x0800), IPV4_START.to_vec()].concat());
+    let mut l2 = Layer2Info::new();
+
+    assert_eq!(parse_l2(LINKTYPE_ETHERNET, &frame, &mut l2), Some((0x0800, 18)));
+    assert_eq!(l2.vlans.len(), 1);
+    let tag = &l2.vlans[0];
+    assert_eq!((tag.tpid, tag.pcp, tag.dei, tag.vid), (0x8100, 5, true, 100));
+    assert_eq!(l2.ethertype, 0x0800);
+    assert_eq!(l2.src_mac.as_deref(), Some("66:77:88:99:aa:bb"));
+}
+
+#[test]
+fn qinq_keeps_the_outer_tag_first() {
+    let tags = [vlan_tag(0, false, 10, ETHERTYPE_VLAN as u16), vlan_tag(3, false, 4094, 0x86dd)].concat();
+    let frame = eth(ETHERTYPE_QINQ as u16, &[tags, IPV6_START.to_vec()].concat());
+    let mut l2 = Layer2Info::new();
+
+    assert_eq!(parse_l2(LINKTYPE_ETHERNET, &frame, &mut l2), Some((0x86dd, 22)));
+    let vids: Vec<_> = l2.vlans.iter().map(|t| (t.tpid, t.vid)).collect();
+    assert_eq!(vids, vec![(0x88a8, 10), (0x8100, 4094)]);
+}
+
+#[test]
+fn truncated_vlan_tag() {
+    let frame = eth(ETHERTYPE_VLAN as u16, &[0x00, 0x64, 0x08]);
+    let mut l2 = Layer2Info::new();
+
+    assert_eq!(parse_l2(LINKTYPE_ETHERNET, &frame, &mut l2), None);
+}
+
+#[test]
+fn mpls_stack_before_ipv4_and_ipv6() {
+    let stack = [mpls_label(16, 0, false, 64), mpls_label(1048575, 7, true, 1)].concat();
+    let frame = eth(ETHERTYPE_MPLS_UC as u16, &[stack.clone(), IPV4_START.to_vec()].concat());
+    let mut l2 = Layer2Info::new();
+
+    assert_eq!(parse_l2(LINKTYPE_ETHERNET, &frame, &mut l2), Some((0x0800, 22)));
+    let labels: Vec<_> = l2.mpls.iter().map(|l| (l.label, l.tc, l.s, l.ttl)).collect();
+    assert_eq!(labels, vec![(16, 0, false, 64), (1048575, 7, true, 1)]);
+
+    let frame = eth(ETHERTYPE_MPLS_UC as u16, &[stack, IPV6_START.to_vec()].concat());
+    let mut l2 = Layer2Info::new();
+    assert_eq!(parse_l2(LINKTYPE_ETHERNET, &frame, &mut l2), Some((0x86dd, 22)));
+}
+
+#[test]
+fn mpls_pseudowire_carries_a_tagged_frame() {
+    let inner = eth(ETHERTYPE_VLAN as u16, &[vlan_tag(0, false, 7, 0x0800), IPV4_START.to_vec()].concat());
+    let payload = [mpls_label(100, 0, true, 255), vec![0; 4], inner].concat();
+    let frame = eth(ETHERTYPE_MPLS_UC as u16, &payload);
+    let mut l2 = Layer2Info::new();
+
+    // outer Ethernet 14, label 4, control word 4, inner Ethernet 14, tag 4
+    assert_eq!(parse_l2(LINKTYPE_ETHERNET, &frame, &mut l2), Some((0x0800, 40)));
+    assert_eq!(l2.mpls.len(), 1);
+    assert_eq!(l2.vlans[0].vid, 7);
+}
+
+#[test]
+fn mpls_stack_without_bottom_of_stack() {
+    let mut labels = Vec::new();
+
+    assert_eq!(parse_mpls_stack(&[mpls_label(1, 0, false, 1), mpls_label(2, 0, false, 1)].concat(), &mut labels), None);
+    assert_eq!(parse_mpls_stack(&[0x00, 0x01, 0x01], &mut labels), None);
+}
@@ -0,0 +1,35 @@
+use crate::types::*;
+
+pub const ETHERTYPE_VLAN: usize         = 0x8100;   // 802.1Q C-Tag
+pub const ETHERTYPE_QINQ: usize         = 0x88a8;   // 802.1ad S-Tag
+pub const ETHERTYPE_QINQ_OLD: usize     = 0x9100;   // pre-standard QinQ
+
+pub const VLAN_TAG_LEN: usize           = 4;
+
+
+pub fn is_vlan_type(ethertype: usize) -> bool
+{
+    matches!(ethertype, ETHERTYPE_VLAN | ETHERTYPE_QINQ | ETHERTYPE_QINQ_OLD)
+}
+
+
+/// Decode one 802.1Q tag. `data` starts at the TCI, right after the TPID.
+/// Returns the tag and the ethertype that follows it.
+pub fn parse_vlan_tag(tpid: usize, data: &[u8]) -> Option<(VlanTag, usize)>
+{
+    if data.len() < VLAN_TAG_LEN {
+        return None;
+    }
+
+    let tci = u16::from_be_bytes([data[0], data[1]]);
+    let next_type = u16::from_be_bytes([data[2], data[3]]) as usize;
+
+    let tag = VlanTag {
+        tpid: tpid as u16,
+        pcp: (tci >> 13) as u8,
+        dei: (tci & 0x1000) != 0,
+        vid: tci & 0x0fff,
+    };
+
+    Some((tag, next_type))
+}
@@ -1,27 +1,119 @@
 use crate::types::*;
+use crate::dissect::{*, registry::*};
+use crate::ip::checksum::*;
+use crate::ip::port::*;
 
-pub const ICMP_ECHO_REQ: u8     = 0;
-pub const ICMP_ECHO_RSP: u8     = 8;
+pub const ICMP_ECHO_REPLY: u8       = 0;
+pub const ICMP_DEST_UNREACH: u8     = 3;
+pub const ICMP_SOURCE_QUENCH: u8    = 4;
+pub const ICMP_REDIRECT: u8         = 5;
+pub const ICMP_ECHO_REQ: u8         = 8;
+pub const ICMP_ROUTER_ADVERT: u8    = 9;
+pub const ICMP_ROUTER_SOLICIT: u8   = 10;
+pub const ICMP_TIME_EXCEEDED: u8    = 11;
+pub const ICMP_PARAM_PROBLEM: u8    = 12;
+pub const ICMP_TIMESTAMP_REQ: u8    = 13;
+pub const ICMP_TIMESTAMP_REPLY: u8  = 14;
 
-pub fn parse_icmp_simple (icmp: &[u8], packet: &mut PacketSummary)
--> u16
+const ICMP_FRAG_NEEDED: u8          = 4;    // Destination Unreachable code
+
+
+fn icmp_type_to_str(icmp_type: u8) -> Option<&'static str>
+{
+    match icmp_type {
+        ICMP_ECHO_REPLY         => Some("Echo Reply"),
+        ICMP_DEST_UNREACH       => Some("Destination Unreachable"),
+        ICMP_SOURCE_QUENCH      => Some("Source Quench"),
+        ICMP_REDIRECT           => Some("Redirect"),
+        ICMP_ECHO_REQ           => Some("Echo Request"),
+        ICMP_ROUTER_ADVERT      => Some("Router Advertisement"),
+        ICMP_ROUTER_SOLICIT     => Some("Router Solicitation"),
+        ICMP_TIME_EXCEEDED      => Some("Time Exceeded"),
+        ICMP_PARAM_PROBLEM      => Some("Parameter Problem"),
+        ICMP_TIMESTAMP_REQ      => Some("Timestamp Request"),
+        ICMP_TIMESTAMP_REPLY    => Some("Timestamp Reply"),
+        _                       => None,
+    }
+}
+
+fn icmp_code_to_str(icmp_type: u8, code: u8) -> Option<&'static str>
+{
+    let codes: &[&str] = match icmp_type {
+        ICMP_DEST_UNREACH => &[
+            "Network unreachable", "Host unreachable", "Protocol unreachable",
+            "Port unreachable", "Fragmentation needed", "Source route failed",
+            "Destination network unknown", "Destination host unknown",
+            "Source host isolated", "Network administratively prohibited",
+            "Host administratively prohibited", "Network unreachable for TOS",
+            "Host unreachable for TOS", "Communication administratively prohibited",
+            "Host precedence violation", "Precedence cutoff in effect",
+        ],
+        ICMP_REDIRECT => &[
+            "Redirect for network", "Redirect for host",
+            "Redirect for TOS and network", "Redirect for TOS and host",
+        ],
+        ICMP_TIME_EXCEEDED => &["TTL exceeded in transit", "Fragment reassembly time exceeded"],
+        ICMP_PARAM_PROBLEM => &["Pointer indicates the error", "Missing a required option", "Bad length"],
+        _ => &[],
+    };
+
+    codes.get(code as usize).copied()
+}
+
+/// Message types that quote the packet that caused them
+fn is_icmp_error(icmp_type: u8) -> bool
 {
-    let icmp_type = u8::from_be_bytes([icmp[0]]);
+    matches!(icmp_type, ICMP_DEST_UNREACH | ICMP_SOURCE_QUENCH | ICMP_REDIRECT
+        | ICMP_TIME_EXCEEDED | ICMP_PARAM_PROBLEM)
+}
 
-    let mut desc = String::new();
-    if icmp_type == ICMP_ECHO_REQ {
-        desc = "Echo Request".to_string();
+
+/// Decode the packet quoted after the 8 byte header of an ICMP/ICMPv6 error.
+/// Its flow is left in `ctx.quoted_flow` so the capture pass can find it.
+pub fn decode_quoted(data: &[u8], ctx: &mut Dissection) -> Option<IcmpQuoted>
+{
+    if ctx.in_quote {
+        return None;
     }
-    else if icmp_type == ICMP_ECHO_RSP {
-        desc = "Echo Response".to_string();
+    let quote = data.get(ICMP_HDR_LEN..).filter(|q| !q.is_empty())?;
+
+    let inner = dissect_quoted(&ctx.table, quote);
+    let (Some(src), Some(dst)) = (inner.flow.src_addr, inner.flow.dst_addr) else {
+        return None;
+    };
+
+    let quoted = IcmpQuoted {
+        src_addr: src.to_string(),
+        dst_addr: dst.to_string(),
+        protocol: inner.flow.protocol,
+        src_port: inner.flow.src_port,
+        dst_port: inner.flow.dst_port,
+        description: inner.summary.description,
+        original_packet: None,
+    };
+    ctx.quoted_flow = Some(inner.flow);
+    ctx.transaction = inner.transaction;
+
+    Some(quoted)
+}
+
+/// Summary text of an ICMP/ICMPv6 message, with the packet an error quotes
+pub fn icmp_summary(type_str: &str, code_str: &str, quoted: Option<&IcmpQuoted>) -> String
+{
+    let mut desc = type_str.to_string();
+    if !code_str.is_empty() {
+        desc.push_str(&format!(" ({})", code_str));
     }
 
-    packet.src_port = 0;
-    packet.dst_port = 0;
-    packet.description = desc;
-    packet.l4_type = "ICMP".to_string();
+    if let Some(q) = quoted {
+        let proto = protocol_to_str(q.protocol as usize).unwrap_or(q.protocol.to_string());
+        desc.push_str(&format!(" for {} {}:{} > {}:{}", proto, q.src_addr, q.src_port, q.dst_addr, q.dst_port));
+        if !q.description.is_empty() {
+            desc.push_str(&format!(" [{}]", q.description));
+        }
+    }
 
-    0
+    desc
 }
 
 
@@ -55,3 +147,55 @@ pub fn parse_single_icmp(icmp_buf: &[u8], icmp: &mut IcmpInfo)
 
     0
 }
+
+
+pub struct IcmpDissector;
+
+impl Dissector for IcmpDissector {
+    fn name(&self) -> &'static str {
+        "ICMP"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        need(data, ICMP_HDR_LEN)?;
+
+        let (icmp_type, code) = (data[0], data[1]);
+        let type_str = icmp_type_to_str(icmp_type).map(str::to_string)
+            .unwrap_or(format!("Type {}", icmp_type));
+        let code_str = icmp_code_to_str(icmp_type, code).unwrap_or_default();
+
+        let quoted = match is_icmp_error(icmp_type) {
+            true => decode_quoted(data, ctx),
+            false => None,
+        };
+
+        ctx.summary.src_port = 0;
+        ctx.summary.dst_port = 0;
+        ctx.summary.description = icmp_summary(&type_str, code_str, quoted.as_ref());
+        ctx.summary.l4_type = "ICMP".to_string();
+
+        // covers the whole message, no pseudo header
+        let status = match ctx.complete {
+            true => ctx.check_checksum(checksum_status(data, 0)),
+            false => ChecksumStatus::Unverified,
+        };
+
+        if ctx.wants_detail() {
+            let mut icmp = IcmpInfo::new();
+            parse_single_icmp(data, &mut icmp);
+            icmp.type_str = type_str;
+            icmp.code_str = code_str.to_string();
+            icmp.checksum_status = status;
+            icmp.message = match (icmp_type, code) {
+                (ICMP_DEST_UNREACH, ICMP_FRAG_NEEDED) =>
+                    IcmpMessage::PacketTooBig { mtu: u16::from_be_bytes([data[6], data[7]]) as u32 },
+                (ICMP_PARAM_PROBLEM, _) => IcmpMessage::ParamProblem { pointer: data[4] as u32 },
+                _ => IcmpMessage::None,
+            };
+            icmp.quoted = quoted;
+            ctx.detail.l4 = Layer4Info::ICMP(icmp);
+        }
+
+        Ok(None)
+    }
+}
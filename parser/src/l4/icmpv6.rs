@@ -0,0 +1,282 @@
+use std::net::Ipv6Addr;
+
+use crate::types::*;
+use crate::dissect::*;
+use crate::ip::checksum::*;
+use crate::ip::port::*;
+use crate::l2::ethernet::*;
+use crate::l4::icmp::*;
+
+pub const ICMPV6_DEST_UNREACH: u8       = 1;
+pub const ICMPV6_PACKET_TOO_BIG: u8     = 2;
+pub const ICMPV6_TIME_EXCEEDED: u8      = 3;
+pub const ICMPV6_PARAM_PROBLEM: u8      = 4;
+pub const ICMPV6_ECHO_REQ: u8           = 128;
+pub const ICMPV6_ECHO_REPLY: u8         = 129;
+pub const ICMPV6_MLD_QUERY: u8          = 130;
+pub const ICMPV6_MLD_REPORT: u8         = 131;
+pub const ICMPV6_MLD_DONE: u8           = 132;
+pub const ICMPV6_ROUTER_SOLICIT: u8     = 133;
+pub const ICMPV6_ROUTER_ADVERT: u8      = 134;
+pub const ICMPV6_NEIGHBOR_SOLICIT: u8   = 135;
+pub const ICMPV6_NEIGHBOR_ADVERT: u8    = 136;
+pub const ICMPV6_REDIRECT: u8           = 137;
+pub const ICMPV6_MLDV2_REPORT: u8       = 143;
+
+// Neighbor Discovery options (RFC 4861 4.6, RFC 8106)
+pub const ND_OPT_SOURCE_LLADDR: u8      = 1;
+pub const ND_OPT_TARGET_LLADDR: u8      = 2;
+pub const ND_OPT_PREFIX_INFO: u8        = 3;
+pub const ND_OPT_REDIRECTED_HDR: u8     = 4;
+pub const ND_OPT_MTU: u8                = 5;
+pub const ND_OPT_RDNSS: u8              = 25;
+
+const ND_OPT_UNIT: usize                = 8;
+const ND_PREFIX_INFO_LEN: usize         = 32;
+const IP6_ADDR_LEN: usize               = 16;
+
+// where the options start in each ND message
+const RS_OPTS_AT: usize                 = 8;
+const RA_OPTS_AT: usize                 = 16;
+const NS_OPTS_AT: usize                 = 24;
+const REDIRECT_OPTS_AT: usize           = 40;
+
+
+fn icmpv6_type_to_str(icmp_type: u8) -> Option<&'static str>
+{
+    match icmp_type {
+        ICMPV6_DEST_UNREACH     => Some("Destination Unreachable"),
+        ICMPV6_PACKET_TOO_BIG   => Some("Packet Too Big"),
+        ICMPV6_TIME_EXCEEDED    => Some("Time Exceeded"),
+        ICMPV6_PARAM_PROBLEM    => Some("Parameter Problem"),
+        ICMPV6_ECHO_REQ         => Some("Echo Request"),
+        ICMPV6_ECHO_REPLY       => Some("Echo Reply"),
+        ICMPV6_MLD_QUERY        => Some("Multicast Listener Query"),
+        ICMPV6_MLD_REPORT       => Some("Multicast Listener Report"),
+        ICMPV6_MLD_DONE         => Some("Multicast Listener Done"),
+        ICMPV6_ROUTER_SOLICIT   => Some("Router Solicitation"),
+        ICMPV6_ROUTER_ADVERT    => Some("Router Advertisement"),
+        ICMPV6_NEIGHBOR_SOLICIT => Some("Neighbor Solicitation"),
+        ICMPV6_NEIGHBOR_ADVERT  => Some("Neighbor Advertisement"),
+        ICMPV6_REDIRECT         => Some("Redirect"),
+        ICMPV6_MLDV2_REPORT     => Some("Multicast Listener Report v2"),
+        _                       => None,
+    }
+}
+
+fn icmpv6_code_to_str(icmp_type: u8, code: u8) -> Option<&'static str>
+{
+    let codes: &[&str] = match icmp_type {
+        ICMPV6_DEST_UNREACH => &[
+            "No route to destination", "Administratively prohibited",
+            "Beyond scope of source address", "Address unreachable",
+            "Port unreachable", "Source address failed ingress/egress policy",
+            "Reject route to destination",
+        ],
+        ICMPV6_TIME_EXCEEDED => &["Hop limit exceeded in transit", "Fragment reassembly time exceeded"],
+        ICMPV6_PARAM_PROBLEM => &[
+            "Erroneous header field", "Unrecognized Next Header type",
+            "Unrecognized IPv6 option",
+        ],
+        _ => &[],
+    };
+
+    codes.get(code as usize).copied()
+}
+
+fn nd_option_to_str(opt_type: u8) -> String
+{
+    match opt_type {
+        ND_OPT_SOURCE_LLADDR    => "Source Link-Layer Address".to_string(),
+        ND_OPT_TARGET_LLADDR    => "Target Link-Layer Address".to_string(),
+        ND_OPT_PREFIX_INFO      => "Prefix Information".to_string(),
+        ND_OPT_REDIRECTED_HDR   => "Redirected Header".to_string(),
+        ND_OPT_MTU              => "MTU".to_string(),
+        ND_OPT_RDNSS            => "Recursive DNS Server".to_string(),
+        _                       => format!("Unknown ({})", opt_type),
+    }
+}
+
+
+fn be32(data: &[u8], at: usize) -> u32
+{
+    u32::from_be_bytes([data[at], data[at+1], data[at+2], data[at+3]])
+}
+
+fn ipv6_at(data: &[u8], at: usize) -> Result<String, DecodeError>
+{
+    need(data, at + IP6_ADDR_LEN)?;
+    let octets: [u8; IP6_ADDR_LEN] = data[at..at + IP6_ADDR_LEN].try_into().unwrap();
+    Ok(Ipv6Addr::from(octets).to_string())
+}
+
+
+fn nd_option_value(opt_type: u8, body: &[u8]) -> NdOptionValue
+{
+    match opt_type {
+        ND_OPT_SOURCE_LLADDR | ND_OPT_TARGET_LLADDR => NdOptionValue::LinkLayerAddr(mac_to_string(body)),
+
+        ND_OPT_PREFIX_INFO if body.len() + 2 >= ND_PREFIX_INFO_LEN => {
+            let octets: [u8; IP6_ADDR_LEN] = body[14..30].try_into().unwrap();
+            NdOptionValue::PrefixInfo {
+                prefix_len: body[0],
+                on_link: body[1] & 0x80 != 0,
+                autonomous: body[1] & 0x40 != 0,
+                valid_lifetime: be32(body, 2),
+                preferred_lifetime: be32(body, 6),
+                prefix: Ipv6Addr::from(octets).to_string(),
+            }
+        },
+
+        ND_OPT_MTU if body.len() >= 6 => NdOptionValue::Mtu(be32(body, 2)),
+
+        ND_OPT_RDNSS if body.len() >= 6 => NdOptionValue::Rdnss {
+            lifetime: be32(body, 2),
+            servers: body[6..].chunks_exact(IP6_ADDR_LEN)
+                .map(|a| Ipv6Addr::from(<[u8; IP6_ADDR_LEN]>::try_from(a).unwrap()).to_string())
+                .collect(),
+        },
+
+        _ => NdOptionValue::Raw(body.to_vec()),
+    }
+}
+
+/// Decode the Neighbor Discovery options in `opts`. The length of every
+/// option is counted in 8 byte units, and zero is invalid.
+pub fn parse_nd_options(opts: &[u8], options: &mut Vec<NdOption>) -> Result<(), DecodeError>
+{
+    let mut pos = 0;
+
+    while pos < opts.len() {
+        need(opts, pos + 2)?;
+        let opt_type = opts[pos];
+        let length = opts[pos + 1] as usize * ND_OPT_UNIT;
+        if length == 0 {
+            return Err(DecodeError::invalid(pos + 1, format!("ND option {} has length 0", opt_type)));
+        }
+        need(opts, pos + length)?;
+
+        options.push(NdOption {
+            opt_type,
+            name: nd_option_to_str(opt_type),
+            length,
+            value: nd_option_value(opt_type, &opts[pos + 2..pos + length]),
+        });
+
+        pos += length;
+    }
+
+    Ok(())
+}
+
+
+/// Type specific fields and where the ND options start, if the type has any
+fn parse_icmpv6_message(data: &[u8]) -> Result<(IcmpMessage, Option<usize>), DecodeError>
+{
+    let message = match data[0] {
+        ICMPV6_PACKET_TOO_BIG => (IcmpMessage::PacketTooBig { mtu: be32(data, 4) }, None),
+        ICMPV6_PARAM_PROBLEM => (IcmpMessage::ParamProblem { pointer: be32(data, 4) }, None),
+
+        ICMPV6_ROUTER_SOLICIT => (IcmpMessage::RouterSolicit, Some(RS_OPTS_AT)),
+
+        ICMPV6_ROUTER_ADVERT => {
+            need(data, RA_OPTS_AT)?;
+            (IcmpMessage::RouterAdvert {
+                hop_limit: data[4],
+                managed: data[5] & 0x80 != 0,
+                other: data[5] & 0x40 != 0,
+                router_lifetime: u16::from_be_bytes([data[6], data[7]]),
+                reachable_time: be32(data, 8),
+                retrans_timer: be32(data, 12),
+            }, Some(RA_OPTS_AT))
+        },
+
+        ICMPV6_NEIGHBOR_SOLICIT => (IcmpMessage::NeighborSolicit {
+            target: ipv6_at(data, 8)?,
+        }, Some(NS_OPTS_AT)),
+
+        ICMPV6_NEIGHBOR_ADVERT => (IcmpMessage::NeighborAdvert {
+            router: data[4] & 0x80 != 0,
+            solicited: data[4] & 0x40 != 0,
+            override_flag: data[4] & 0x20 != 0,
+            target: ipv6_at(data, 8)?,
+        }, Some(NS_OPTS_AT)),
+
+        ICMPV6_REDIRECT => (IcmpMessage::Redirect {
+            target: ipv6_at(data, 8)?,
+            dest: ipv6_at(data, 24)?,
+        }, Some(REDIRECT_OPTS_AT)),
+
+        _ => (IcmpMessage::None, None),
+    };
+
+    Ok(message)
+}
+
+
+pub struct Icmpv6Dissector;
+
+impl Dissector for Icmpv6Dissector {
+    fn name(&self) -> &'static str {
+        "ICMPv6"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        need(data, ICMP_HDR_LEN)?;
+
+        let (icmp_type, code) = (data[0], data[1]);
+        let type_str = icmpv6_type_to_str(icmp_type).map(str::to_string)
+            .unwrap_or(format!("Type {}", icmp_type));
+        let code_str = icmpv6_code_to_str(icmp_type, code).unwrap_or_default();
+
+        // error messages have the high bit clear
+        let quoted = match icmp_type < ICMPV6_ECHO_REQ {
+            true => decode_quoted(data, ctx),
+            false => None,
+        };
+
+        let (message, opts_at) = parse_icmpv6_message(data)?;
+
+        let mut desc = icmp_summary(&type_str, code_str, quoted.as_ref());
+        match &message {
+            IcmpMessage::PacketTooBig { mtu } => desc.push_str(&format!(", MTU {}", mtu)),
+            IcmpMessage::NeighborSolicit { target } |
+            IcmpMessage::NeighborAdvert { target, .. } => desc.push_str(&format!(" for {}", target)),
+            _ => {},
+        }
+
+        ctx.summary.src_port = 0;
+        ctx.summary.dst_port = 0;
+        ctx.summary.description = desc;
+        ctx.summary.l4_type = "ICMPv6".to_string();
+
+        // the pseudo header is included, as for UDP and TCP
+        let status = l4_checksum_status(data, ctx.complete,
+            ctx.flow.src_addr, ctx.flow.dst_addr, PROTO_TYPE_ICMPV6 as u8);
+        let status = ctx.check_checksum(status);
+
+        // a bad option is reported, the options before it are kept
+        let mut nd_options = Vec::new();
+        if let Some(at) = opts_at {
+            let opts = data.get(at..).unwrap_or_default();
+            if let Err(e) = parse_nd_options(opts, &mut nd_options) {
+                ctx.mark_malformed(self.name(), &e.shifted(at));
+            }
+        }
+
+        if ctx.wants_detail() {
+            let mut icmp = IcmpInfo::new();
+            parse_single_icmp(data, &mut icmp);
+            icmp.version = 6;
+            icmp.type_str = type_str;
+            icmp.code_str = code_str.to_string();
+            icmp.checksum_status = status;
+            icmp.message = message;
+            icmp.nd_options = nd_options;
+            icmp.quoted = quoted;
+            ctx.detail.l4 = Layer4Info::ICMP(icmp);
+        }
+
+        Ok(None)
+    }
+}
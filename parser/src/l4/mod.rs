@@ -1,3 +1,9 @@
 pub mod tcp;
+pub mod tcp_stream;
 pub mod udp;
-pub mod icmp;
\ No newline at end of file
+pub mod sctp;
+pub mod sctp_stream;
+pub mod icmp;
+pub mod icmpv6;
+#[cfg(test)]
+mod tests;
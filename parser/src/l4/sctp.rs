@@ -0,0 +1,421 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::types::*;
+use crate::dissect::*;
+use crate::ip::checksum::*;
+use crate::l4::sctp_stream::*;
+
+pub const SCTP_HDR_LEN: usize           = 12;
+const SCTP_CHUNK_HDR_LEN: usize         = 4;
+
+// [ https://www.iana.org/assignments/sctp-parameters ]
+pub const SCTP_DATA: u8                 = 0;
+pub const SCTP_INIT: u8                 = 1;
+pub const SCTP_INIT_ACK: u8             = 2;
+pub const SCTP_SACK: u8                 = 3;
+pub const SCTP_HEARTBEAT: u8            = 4;
+pub const SCTP_HEARTBEAT_ACK: u8        = 5;
+pub const SCTP_ABORT: u8                = 6;
+pub const SCTP_SHUTDOWN: u8             = 7;
+pub const SCTP_SHUTDOWN_ACK: u8         = 8;
+pub const SCTP_ERROR: u8                = 9;
+pub const SCTP_COOKIE_ECHO: u8          = 10;
+pub const SCTP_COOKIE_ACK: u8           = 11;
+pub const SCTP_SHUTDOWN_COMPLETE: u8    = 14;
+pub const SCTP_AUTH: u8                 = 15;
+pub const SCTP_I_DATA: u8               = 64;
+pub const SCTP_ASCONF_ACK: u8           = 128;
+pub const SCTP_RE_CONFIG: u8            = 130;
+pub const SCTP_PAD: u8                  = 132;
+pub const SCTP_FORWARD_TSN: u8          = 192;
+pub const SCTP_ASCONF: u8               = 193;
+pub const SCTP_I_FORWARD_TSN: u8        = 194;
+
+// DATA / I-DATA flags
+const SCTP_FLAG_END: u8                 = 0x01;
+const SCTP_FLAG_BEGIN: u8               = 0x02;
+const SCTP_FLAG_UNORDERED: u8           = 0x04;
+// ABORT / SHUTDOWN COMPLETE: the verification tag is the sender's own
+const SCTP_FLAG_T: u8                   = 0x01;
+
+pub const SCTP_PARAM_IPV4: u16          = 5;
+pub const SCTP_PARAM_IPV6: u16          = 6;
+pub const SCTP_PARAM_HOST_NAME: u16     = 11;
+
+pub const SCTP_PPID_M3UA: u32           = 3;
+pub const SCTP_PPID_S1AP: u32           = 18;
+pub const SCTP_PPID_X2AP: u32           = 27;
+pub const SCTP_PPID_DIAMETER: u32       = 46;
+pub const SCTP_PPID_NGAP: u32           = 60;
+pub const SCTP_PPID_XNAP: u32           = 61;
+pub const SCTP_PPID_F1AP: u32           = 62;
+
+
+pub fn sctp_chunk_to_str(chunk_type: u8) -> String
+{
+    match chunk_type {
+        SCTP_DATA               => "DATA".to_string(),
+        SCTP_INIT               => "INIT".to_string(),
+        SCTP_INIT_ACK           => "INIT_ACK".to_string(),
+        SCTP_SACK               => "SACK".to_string(),
+        SCTP_HEARTBEAT          => "HEARTBEAT".to_string(),
+        SCTP_HEARTBEAT_ACK      => "HEARTBEAT_ACK".to_string(),
+        SCTP_ABORT              => "ABORT".to_string(),
+        SCTP_SHUTDOWN           => "SHUTDOWN".to_string(),
+        SCTP_SHUTDOWN_ACK       => "SHUTDOWN_ACK".to_string(),
+        SCTP_ERROR              => "ERROR".to_string(),
+        SCTP_COOKIE_ECHO        => "COOKIE_ECHO".to_string(),
+        SCTP_COOKIE_ACK         => "COOKIE_ACK".to_string(),
+        12                      => "ECNE".to_string(),
+        13                      => "CWR".to_string(),
+        SCTP_SHUTDOWN_COMPLETE  => "SHUTDOWN_COMPLETE".to_string(),
+        SCTP_AUTH               => "AUTH".to_string(),
+        SCTP_I_DATA             => "I-DATA".to_string(),
+        SCTP_ASCONF_ACK         => "ASCONF_ACK".to_string(),
+        SCTP_RE_CONFIG          => "RE-CONFIG".to_string(),
+        SCTP_PAD                => "PAD".to_string(),
+        SCTP_FORWARD_TSN        => "FORWARD_TSN".to_string(),
+        SCTP_ASCONF             => "ASCONF".to_string(),
+        SCTP_I_FORWARD_TSN      => "I-FORWARD_TSN".to_string(),
+        _                       => format!("Unknown ({})", chunk_type),
+    }
+}
+
+fn sctp_param_to_str(param_type: u16) -> String
+{
+    match param_type {
+        1                       => "Heartbeat Info".to_string(),
+        SCTP_PARAM_IPV4         => "IPv4 Address".to_string(),
+        SCTP_PARAM_IPV6         => "IPv6 Address".to_string(),
+        7                       => "State Cookie".to_string(),
+        8                       => "Unrecognized Parameter".to_string(),
+        9                       => "Cookie Preservative".to_string(),
+        SCTP_PARAM_HOST_NAME    => "Host Name Address".to_string(),
+        12                      => "Supported Address Types".to_string(),
+        0x8000                  => "ECN Capable".to_string(),
+        0x8002                  => "Random".to_string(),
+        0x8003                  => "Chunk List".to_string(),
+        0x8004                  => "Requested HMAC Algorithm".to_string(),
+        0x8008                  => "Supported Extensions".to_string(),
+        0xc000                  => "Forward TSN Supported".to_string(),
+        0xc006                  => "Adaptation Layer Indication".to_string(),
+        _                       => format!("Unknown ({})", param_type),
+    }
+}
+
+fn sctp_cause_to_str(code: u16) -> String
+{
+    match code {
+        1   => "Invalid Stream Identifier".to_string(),
+        2   => "Missing Mandatory Parameter".to_string(),
+        3   => "Stale Cookie Error".to_string(),
+        4   => "Out of Resource".to_string(),
+        5   => "Unresolvable Address".to_string(),
+        6   => "Unrecognized Chunk Type".to_string(),
+        7   => "Invalid Mandatory Parameter".to_string(),
+        8   => "Unrecognized Parameters".to_string(),
+        9   => "No User Data".to_string(),
+        10  => "Cookie Received While Shutting Down".to_string(),
+        11  => "Restart of an Association with New Addresses".to_string(),
+        12  => "User Initiated Abort".to_string(),
+        13  => "Protocol Violation".to_string(),
+        _   => format!("Unknown ({})", code),
+    }
+}
+
+pub fn sctp_ppid_to_str(ppid: u32) -> Option<String>
+{
+    match ppid {
+        1                   => Some("IUA".to_string()),
+        2                   => Some("M2UA".to_string()),
+        SCTP_PPID_M3UA      => Some("M3UA".to_string()),
+        4                   => Some("SUA".to_string()),
+        5                   => Some("M2PA".to_string()),
+        7                   => Some("H.248".to_string()),
+        SCTP_PPID_S1AP      => Some("S1AP".to_string()),
+        19                  => Some("RUA".to_string()),
+        20                  => Some("HNBAP".to_string()),
+        24                  => Some("SBc-AP".to_string()),
+        25                  => Some("NBAP".to_string()),
+        SCTP_PPID_X2AP      => Some("X2AP".to_string()),
+        29                  => Some("LCS-AP".to_string()),
+        43                  => Some("M2AP".to_string()),
+        44                  => Some("M3AP".to_string()),
+        SCTP_PPID_DIAMETER  => Some("Diameter".to_string()),
+        47                  => Some("Diameter DTLS".to_string()),
+        SCTP_PPID_NGAP      => Some("NGAP".to_string()),
+        SCTP_PPID_XNAP      => Some("XnAP".to_string()),
+        SCTP_PPID_F1AP      => Some("F1AP".to_string()),
+        64                  => Some("E1AP".to_string()),
+        _                   => None,
+    }
+}
+
+
+// TLVs padded to 4 octets, as INIT parameters and error causes are
+fn parse_sctp_params(data: &[u8], name_of: fn(u16) -> String, addrs: &mut Vec<IpAddr>)
+-> Vec<SctpParam>
+{
+    let mut params = Vec::new();
+    let mut pos = 0;
+
+    while let Some(hdr) = data.get(pos..pos + 4) {
+        let param_type = u16::from_be_bytes([hdr[0], hdr[1]]);
+        let length = u16::from_be_bytes([hdr[2], hdr[3]]);
+        let Some(body) = data.get(pos + 4..pos + length as usize) else {
+            break;
+        };
+
+        let addr = match (param_type, body.len()) {
+            (SCTP_PARAM_IPV4, 4) => Some(IpAddr::V4(Ipv4Addr::new(body[0], body[1], body[2], body[3]))),
+            (SCTP_PARAM_IPV6, 16) => <[u8; 16]>::try_from(body).ok().map(|b| IpAddr::V6(Ipv6Addr::from(b))),
+            _ => None,
+        };
+        addrs.extend(addr);
+
+        let value = match param_type {
+            SCTP_PARAM_HOST_NAME => Some(String::from_utf8_lossy(body).trim_end_matches('\0').to_string()),
+            _ => addr.map(|a| a.to_string()),
+        };
+
+        params.push(SctpParam {
+            param_type,
+            name: name_of(param_type),
+            length,
+            value,
+        });
+
+        if length < 4 {
+            break;
+        }
+        pos += (length as usize).next_multiple_of(4);
+    }
+
+    params
+}
+
+
+/// Decode one chunk; DATA and I-DATA are also added to `pkt` for reassembly.
+/// `payload_at` is where `body` starts in the frame or datagram.
+fn sctp_chunk_value(chunk_type: u8, flags: u8, body: &[u8], payload_at: usize, pkt: &mut SctpPacket)
+-> Result<SctpChunkValue, DecodeError>
+{
+    let be16 = |at: usize| u16::from_be_bytes([body[at], body[at + 1]]);
+    let be32 = |at: usize| u32::from_be_bytes([body[at], body[at + 1], body[at + 2], body[at + 3]]);
+    let unordered = flags & SCTP_FLAG_UNORDERED != 0;
+    let begin = flags & SCTP_FLAG_BEGIN != 0;
+    let end = flags & SCTP_FLAG_END != 0;
+
+    let value = match chunk_type {
+        SCTP_DATA => {
+            need(body, 12)?;
+            let (tsn, stream_id, stream_seq, ppid) = (be32(0), be16(4), be16(6), be32(8));
+            pkt.data.push(SctpDataChunk {
+                tsn, stream_id, unordered, begin, end,
+                msg_id: stream_seq as u32,
+                fsn: None,
+                ppid: Some(ppid),
+                payload_at: payload_at + 12,
+                payload_len: body.len() - 12,
+            });
+            SctpChunkValue::Data {
+                tsn, stream_id, stream_seq, ppid,
+                ppid_str: sctp_ppid_to_str(ppid),
+                unordered, begin, end,
+                data_len: body.len() - 12,
+            }
+        },
+
+        SCTP_I_DATA => {
+            need(body, 16)?;
+            let (tsn, stream_id, mid, ppid_fsn) = (be32(0), be16(4), be32(8), be32(12));
+            let (ppid, fsn) = if begin { (Some(ppid_fsn), 0) } else { (None, ppid_fsn) };
+            pkt.data.push(SctpDataChunk {
+                tsn, stream_id, unordered, begin, end,
+                msg_id: mid,
+                fsn: Some(fsn),
+                ppid,
+                payload_at: payload_at + 16,
+                payload_len: body.len() - 16,
+            });
+            SctpChunkValue::IData {
+                tsn, stream_id, mid, ppid, fsn,
+                unordered, begin, end,
+                data_len: body.len() - 16,
+            }
+        },
+
+        SCTP_INIT | SCTP_INIT_ACK => {
+            need(body, 16)?;
+            let mut addrs = Vec::new();
+            let params = parse_sctp_params(&body[16..], sctp_param_to_str, &mut addrs);
+            pkt.init = Some(SctpInit {
+                ack: chunk_type == SCTP_INIT_ACK,
+                initiate_tag: be32(0),
+                addrs: addrs.clone(),
+            });
+            SctpChunkValue::Init {
+                initiate_tag: be32(0),
+                a_rwnd: be32(4),
+                out_streams: be16(8),
+                in_streams: be16(10),
+                initial_tsn: be32(12),
+                addresses: addrs.iter().map(|a| a.to_string()).collect(),
+                params,
+            }
+        },
+
+        SCTP_SACK => {
+            need(body, 12)?;
+            let gaps = be16(8) as usize;
+            let dups = be16(10) as usize;
+            need(body, 12 + gaps * 4 + dups * 4)?;
+            SctpChunkValue::Sack {
+                cum_tsn: be32(0),
+                a_rwnd: be32(4),
+                gap_blocks: (0..gaps)
+                    .map(|i| SctpGapBlock { start: be16(12 + i * 4), end: be16(14 + i * 4) })
+                    .collect(),
+                dup_tsns: (0..dups).map(|i| be32(12 + gaps * 4 + i * 4)).collect(),
+            }
+        },
+
+        SCTP_HEARTBEAT | SCTP_HEARTBEAT_ACK => SctpChunkValue::Heartbeat { info_len: body.len() },
+
+        SCTP_SHUTDOWN => {
+            need(body, 4)?;
+            pkt.shutdown = true;
+            SctpChunkValue::Shutdown { cum_tsn: be32(0) }
+        },
+
+        SCTP_ABORT | SCTP_ERROR => {
+            if chunk_type == SCTP_ABORT {
+                pkt.abort = true;
+                pkt.tag_reflected |= flags & SCTP_FLAG_T != 0;
+            }
+            SctpChunkValue::Error { causes: parse_sctp_params(body, sctp_cause_to_str, &mut Vec::new()) }
+        },
+
+        SCTP_SHUTDOWN_ACK | SCTP_SHUTDOWN_COMPLETE => {
+            pkt.shutdown = true;
+            pkt.tag_reflected |= chunk_type == SCTP_SHUTDOWN_COMPLETE && flags & SCTP_FLAG_T != 0;
+            SctpChunkValue::None
+        },
+
+        _ if body.is_empty() => SctpChunkValue::None,
+        _ => SctpChunkValue::Raw(body.to_vec()),
+    };
+
+    Ok(value)
+}
+
+
+/// Decode every chunk after the common header into `sctp`. Offsets in the
+/// error are relative to the SCTP header.
+pub fn parse_sctp_chunks(data: &[u8], base: usize, sctp: &mut SctpInfo, pkt: &mut SctpPacket)
+-> Result<(), DecodeError>
+{
+    let mut pos = SCTP_HDR_LEN;
+
+    while pos < data.len() {
+        need(&data[pos..], SCTP_CHUNK_HDR_LEN).map_err(|e| e.shifted(pos))?;
+
+        let chunk_type = data[pos];
+        let flags = data[pos + 1];
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]);
+        if (length as usize) < SCTP_CHUNK_HDR_LEN {
+            return Err(DecodeError::invalid(pos + 2,
+                format!("chunk {} length {} is below {}", sctp_chunk_to_str(chunk_type), length, SCTP_CHUNK_HDR_LEN)));
+        }
+
+        let end = pos + length as usize;
+        let Some(body) = data.get(pos + SCTP_CHUNK_HDR_LEN..end) else {
+            return Err(DecodeError::Truncated { offset: pos, needed: Some(end), available: data.len() });
+        };
+
+        let value = sctp_chunk_value(chunk_type, flags, body, base + pos + SCTP_CHUNK_HDR_LEN, pkt)
+            .map_err(|e| e.shifted(pos + SCTP_CHUNK_HDR_LEN))?;
+
+        sctp.chunks.push(SctpChunk {
+            chunk_type,
+            name: sctp_chunk_to_str(chunk_type),
+            flags,
+            length,
+            value,
+        });
+
+        // chunks are padded to 4 octets; the last one may not be
+        pos = end.next_multiple_of(4);
+    }
+
+    Ok(())
+}
+
+
+pub struct SctpDissector;
+
+impl Dissector for SctpDissector {
+    fn name(&self) -> &'static str {
+        "SCTP"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        need(data, SCTP_HDR_LEN)?;
+
+        let mut sctp = SctpInfo::new();
+        sctp.src_port = u16::from_be_bytes([data[0], data[1]]);
+        sctp.dst_port = u16::from_be_bytes([data[2], data[3]]);
+        sctp.vtag = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        sctp.checksum = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+
+        // CRC32c over the whole packet with the checksum field zeroed, sent little endian
+        let status = if ctx.complete {
+            let mut copy = data.to_vec();
+            copy[8..12].fill(0);
+            if crc32c(&copy) == u32::from_le_bytes([data[8], data[9], data[10], data[11]]) {
+                ChecksumStatus::Good
+            }
+            else {
+                ChecksumStatus::Bad
+            }
+        }
+        else {
+            ChecksumStatus::Unverified
+        };
+        sctp.checksum_status = ctx.check_checksum(status);
+
+        ctx.flow.src_port = sctp.src_port;
+        ctx.flow.dst_port = sctp.dst_port;
+
+        ctx.summary.src_port = sctp.src_port;
+        ctx.summary.dst_port = sctp.dst_port;
+        ctx.summary.l4_type = "SCTP".to_string();
+        ctx.summary.length = data.len() - SCTP_HDR_LEN;
+
+        let mut pkt = SctpPacket::new(sctp.vtag);
+        let parsed = parse_sctp_chunks(data, ctx.offset, &mut sctp, &mut pkt);
+
+        // DATA is named after what it carries, e.g. "DATA (S1AP)"
+        ctx.summary.description = sctp.chunks.iter()
+            .map(|c| match &c.value {
+                SctpChunkValue::Data { ppid_str: Some(ppid), .. } => format!("{} ({})", c.name, ppid),
+                SctpChunkValue::IData { ppid: Some(ppid), .. } => match sctp_ppid_to_str(*ppid) {
+                    Some(ppid) => format!("{} ({})", c.name, ppid),
+                    None => c.name.clone(),
+                },
+                _ => c.name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        if !ctx.in_quote {
+            ctx.sctp = Some(pkt);
+        }
+        if ctx.wants_detail() {
+            ctx.detail.l4 = Layer4Info::Sctp(sctp);
+        }
+        parsed?;
+
+        // user messages are passed on by `follow_sctp`, once complete
+        Ok(None)
+    }
+}
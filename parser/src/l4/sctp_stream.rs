@@ -0,0 +1,450 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+
+use crate::types::{SctpAssociation, SctpUserMessage};
+use crate::dissect::{FlowTuple, StreamChunk};
+use crate::l4::sctp::sctp_ppid_to_str;
+
+// associations followed at once; the oldest is dropped beyond this
+const MAX_ASSOCIATIONS: usize   = 4096;
+// fragment bytes held per direction; past this they are dropped
+const MAX_PENDING: usize        = 1 << 22;
+// TSNs remembered per direction to spot retransmitted DATA
+const MAX_SEEN_TSNS: usize      = 4096;
+
+
+/// A DATA or I-DATA chunk found while dissecting a frame
+#[derive(Debug, Clone)]
+pub struct SctpDataChunk {
+    pub tsn: u32,
+    pub stream_id: u16,
+    pub msg_id: u32,            // SSN for DATA, MID for I-DATA
+    pub fsn: Option<u32>,       // I-DATA only; 0 on the first fragment
+    pub ppid: Option<u32>,      // I-DATA carries it on the first fragment only
+    pub unordered: bool,
+    pub begin: bool,
+    pub end: bool,
+    pub payload_at: usize,      // offset of the user data in the frame, or in the reassembled datagram
+    pub payload_len: usize,
+}
+
+/// INIT or INIT ACK
+#[derive(Debug, Clone)]
+pub struct SctpInit {
+    pub ack: bool,
+    pub initiate_tag: u32,
+    pub addrs: Vec<IpAddr>,     // IPv4/IPv6 Address parameters
+}
+
+/// What the association tracker needs from one SCTP packet
+#[derive(Debug, Clone)]
+pub struct SctpPacket {
+    pub vtag: u32,
+    pub tag_reflected: bool,    // T bit of ABORT/SHUTDOWN COMPLETE: the sender's own tag
+    pub init: Option<SctpInit>,
+    pub data: Vec<SctpDataChunk>,
+    pub abort: bool,
+    pub shutdown: bool,         // SHUTDOWN, SHUTDOWN ACK or SHUTDOWN COMPLETE
+}
+impl SctpPacket {
+    pub fn new(vtag: u32) -> Self {
+        SctpPacket {
+            vtag,
+            tag_reflected: false,
+            init: None,
+            data: Vec::new(),
+            abort: false,
+            shutdown: false,
+        }
+    }
+}
+
+/// A user message put back together from the chunks of one or more packets
+#[derive(Debug, Clone)]
+pub struct SctpMessage {
+    pub stream_id: u16,
+    pub ppid: u32,
+    pub unordered: bool,
+    pub data: Vec<u8>,
+    pub chunks: Vec<StreamChunk>,   // in fragment order, the last one completed it
+}
+
+/// What the packet detail shows of a message of association `assoc_id`
+pub fn message_info(assoc_id: usize, msg: &SctpMessage) -> SctpUserMessage
+{
+    let mut packet_ids: Vec<usize> = msg.chunks.iter().map(|c| c.packet_id).collect();
+    packet_ids.dedup();
+
+    SctpUserMessage {
+        assoc_id,
+        stream_id: msg.stream_id,
+        ppid: msg.ppid,
+        ppid_str: sctp_ppid_to_str(msg.ppid),
+        unordered: msg.unordered,
+        len: msg.data.len(),
+        packet_ids,
+    }
+}
+
+/// What one packet meant for its association
+#[derive(Debug, Default)]
+pub struct SctpResult {
+    pub assoc: Option<usize>,
+    pub messages: Vec<SctpMessage>,
+}
+
+
+// fragments of one message share a key; DATA fragments are numbered by TSN,
+// I-DATA ones by FSN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FragKey {
+    stream_id: u16,
+    unordered: bool,
+    i_data: bool,
+    msg_id: Option<u32>,    // unordered DATA has no usable SSN
+}
+
+struct Frag {
+    packet_id: usize,
+    payload_at: usize,
+    ppid: Option<u32>,
+    begin: bool,
+    end: bool,
+    data: Vec<u8>,
+}
+
+// DATA sent one way: fragments waiting for the rest of their message
+struct Direction {
+    pending: HashMap<FragKey, HashMap<u32, Frag>>,
+    pending_len: usize,
+    seen: HashSet<u32>,
+    seen_order: VecDeque<u32>,
+}
+impl Direction {
+    fn new() -> Self {
+        Direction {
+            pending: HashMap::new(),
+            pending_len: 0,
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+        }
+    }
+
+    // false for a TSN already received
+    fn first_seen(&mut self, tsn: u32) -> bool {
+        if !self.seen.insert(tsn) {
+            return false;
+        }
+        self.seen_order.push_back(tsn);
+        if self.seen_order.len() > MAX_SEEN_TSNS
+            && let Some(old) = self.seen_order.pop_front() {
+            self.seen.remove(&old);
+        }
+        true
+    }
+
+    fn add(&mut self, packet_id: usize, chunk: &SctpDataChunk, payload: &[u8]) -> Option<SctpMessage> {
+        if !self.first_seen(chunk.tsn) {
+            return None;
+        }
+
+        let frag = Frag {
+            packet_id,
+            payload_at: chunk.payload_at,
+            ppid: chunk.ppid,
+            begin: chunk.begin,
+            end: chunk.end,
+            data: payload.to_vec(),
+        };
+        let message = |frags: Vec<Frag>| {
+            let first = frags.first()?;
+            Some(SctpMessage {
+                stream_id: chunk.stream_id,
+                ppid: first.ppid.unwrap_or(0),
+                unordered: chunk.unordered,
+                data: frags.iter().flat_map(|f| f.data.iter().copied()).collect(),
+                chunks: frags.iter()
+                    .map(|f| StreamChunk { packet_id: f.packet_id, start: f.payload_at, len: f.data.len() })
+                    .collect(),
+            })
+        };
+
+        if chunk.begin && chunk.end {
+            return message(vec![frag]);
+        }
+
+        if self.pending_len + payload.len() > MAX_PENDING {
+            self.pending.clear();
+            self.pending_len = 0;
+        }
+        self.pending_len += payload.len();
+
+        let key = FragKey {
+            stream_id: chunk.stream_id,
+            unordered: chunk.unordered,
+            i_data: chunk.fsn.is_some(),
+            msg_id: (chunk.fsn.is_some() || !chunk.unordered).then_some(chunk.msg_id),
+        };
+        let n = chunk.fsn.unwrap_or(chunk.tsn);
+        let frags = self.pending.entry(key).or_default();
+        frags.insert(n, frag);
+
+        // the message is complete once the fragments run unbroken from B to E
+        let mut first = n;
+        while !frags.get(&first)?.begin {
+            first = first.wrapping_sub(1);
+        }
+        let mut last = n;
+        while !frags.get(&last)?.end {
+            last = last.wrapping_add(1);
+        }
+
+        let count = last.wrapping_sub(first) as usize + 1;
+        let taken: Vec<Frag> = (0..count)
+            .filter_map(|i| frags.remove(&first.wrapping_add(i as u32)))
+            .collect();
+        if frags.is_empty() {
+            self.pending.remove(&key);
+        }
+        self.pending_len -= taken.iter().map(|f| f.data.len()).sum::<usize>();
+
+        message(taken)
+    }
+}
+
+
+struct Association {
+    info: SctpAssociation,
+    initiator_addrs: BTreeSet<IpAddr>,
+    responder_addrs: BTreeSet<IpAddr>,
+    from_initiator: Direction,
+    from_responder: Direction,
+}
+impl Association {
+    fn new(id: usize, initiator_port: u16, responder_port: u16) -> Self {
+        Association {
+            info: SctpAssociation {
+                id,
+                initiator_port,
+                responder_port,
+                initiator_tag: None,
+                responder_tag: None,
+                initiator_addrs: Vec::new(),
+                responder_addrs: Vec::new(),
+                init: None,
+                init_ack: None,
+                shutdown: None,
+                abort: None,
+                messages: 0,
+                state: String::new(),
+            },
+            initiator_addrs: BTreeSet::new(),
+            responder_addrs: BTreeSet::new(),
+            from_initiator: Direction::new(),
+            from_responder: Direction::new(),
+        }
+    }
+
+    fn ports_match(&self, from_initiator: bool, src_port: u16, dst_port: u16) -> bool {
+        let (i, r) = (self.info.initiator_port, self.info.responder_port);
+        if from_initiator { (src_port, dst_port) == (i, r) } else { (src_port, dst_port) == (r, i) }
+    }
+
+    // the tag a packet sent by one side carries
+    fn tag_from(&self, from_initiator: bool, reflected: bool) -> Option<u32> {
+        if from_initiator != reflected { self.info.responder_tag } else { self.info.initiator_tag }
+    }
+
+    fn direction_by_tag(&self, src_port: u16, dst_port: u16, pkt: &SctpPacket) -> Option<bool> {
+        [true, false].into_iter().find(|&from_initiator| {
+            self.ports_match(from_initiator, src_port, dst_port)
+                && self.tag_from(from_initiator, pkt.tag_reflected) == Some(pkt.vtag)
+        })
+    }
+
+    // only while the tag that direction carries is still unknown
+    fn direction_by_addr(&self, src: (IpAddr, u16), dst: (IpAddr, u16), pkt: &SctpPacket) -> Option<bool> {
+        let by = |from_initiator: bool| {
+            let (sender, receiver) = if from_initiator {
+                (&self.initiator_addrs, &self.responder_addrs)
+            }
+            else {
+                (&self.responder_addrs, &self.initiator_addrs)
+            };
+            self.ports_match(from_initiator, src.1, dst.1)
+                && self.tag_from(from_initiator, pkt.tag_reflected).is_none()
+                && (sender.contains(&src.0) || receiver.contains(&dst.0))
+        };
+        match (by(true), by(false)) {
+            (true, false) => Some(true),
+            (false, true) => Some(false),
+            _ => None,
+        }
+    }
+
+    fn observe(&mut self, packet_id: usize, src: IpAddr, dst: IpAddr, from_initiator: bool, pkt: &SctpPacket) {
+        let info = &mut self.info;
+        let (sender, receiver) = if from_initiator {
+            (&mut self.initiator_addrs, &mut self.responder_addrs)
+        }
+        else {
+            (&mut self.responder_addrs, &mut self.initiator_addrs)
+        };
+        sender.insert(src);
+        receiver.insert(dst);
+
+        match &pkt.init {
+            Some(init) if !init.ack => {
+                info.init.get_or_insert(packet_id);
+                info.initiator_tag = Some(init.initiate_tag);
+                sender.extend(init.addrs.iter().copied());
+            },
+            Some(init) => {
+                info.init_ack.get_or_insert(packet_id);
+                info.responder_tag = Some(init.initiate_tag);
+                info.initiator_tag.get_or_insert(pkt.vtag);
+                sender.extend(init.addrs.iter().copied());
+            },
+            // joined mid-association: tags are learnt from the packets
+            None => {
+                let tag = if from_initiator != pkt.tag_reflected {
+                    &mut info.responder_tag
+                }
+                else {
+                    &mut info.initiator_tag
+                };
+                tag.get_or_insert(pkt.vtag);
+            },
+        }
+
+        if pkt.shutdown {
+            info.shutdown.get_or_insert(packet_id);
+        }
+        if pkt.abort {
+            info.abort.get_or_insert(packet_id);
+        }
+    }
+
+    fn finish(mut self) -> SctpAssociation {
+        let info = &mut self.info;
+        info.initiator_addrs = self.initiator_addrs.iter().map(|a| a.to_string()).collect();
+        info.responder_addrs = self.responder_addrs.iter().map(|a| a.to_string()).collect();
+
+        info.state = if info.abort.is_some() {
+            "Aborted"
+        }
+        else if info.shutdown.is_some() {
+            "Shut down"
+        }
+        else if info.init_ack.is_some() {
+            "Established"
+        }
+        else if info.init.is_some() {
+            "Setting up"
+        }
+        else {
+            "Established before the capture"
+        }.to_string();
+
+        self.info
+    }
+}
+
+
+/// Follows SCTP associations across a capture: tells them apart by port
+/// pair and verification tag, learns every address each side uses, and puts
+/// fragmented DATA/I-DATA back together per stream. Associations are
+/// numbered in the order they are first seen.
+pub struct SctpReassembler {
+    assocs: Vec<Association>,
+    by_ports: HashMap<(u16, u16), Vec<usize>>,
+    active: VecDeque<usize>,
+}
+
+impl SctpReassembler {
+    pub fn new() -> Self {
+        SctpReassembler {
+            assocs: Vec::new(),
+            by_ports: HashMap::new(),
+            active: VecDeque::new(),
+        }
+    }
+
+    fn create(&mut self, initiator_port: u16, responder_port: u16) -> usize {
+        if self.active.len() >= MAX_ASSOCIATIONS
+            && let Some(oldest) = self.active.pop_front() {
+            let assoc = &mut self.assocs[oldest];
+            let key = port_key(assoc.info.initiator_port, assoc.info.responder_port);
+            if let Some(ids) = self.by_ports.get_mut(&key) {
+                ids.retain(|&id| id != oldest);
+            }
+            assoc.from_initiator = Direction::new();
+            assoc.from_responder = Direction::new();
+        }
+
+        let id = self.assocs.len();
+        self.assocs.push(Association::new(id, initiator_port, responder_port));
+        self.by_ports.entry(port_key(initiator_port, responder_port)).or_default().push(id);
+        self.active.push_back(id);
+        id
+    }
+
+    // the association and whether the packet was sent by its initiator;
+    // the latest association on the ports wins
+    fn find(&self, src: (IpAddr, u16), dst: (IpAddr, u16), pkt: &SctpPacket) -> Option<(usize, bool)> {
+        let ids = self.by_ports.get(&port_key(src.1, dst.1))?;
+
+        if let Some(init) = pkt.init.as_ref().filter(|init| !init.ack) {
+            // a retransmitted INIT; any other starts a new association
+            return ids.iter().rev()
+                .find(|&&id| self.assocs[id].info.initiator_tag == Some(init.initiate_tag)
+                    && self.assocs[id].ports_match(true, src.1, dst.1))
+                .map(|&id| (id, true));
+        }
+
+        ids.iter().rev()
+            .find_map(|&id| self.assocs[id].direction_by_tag(src.1, dst.1, pkt).map(|d| (id, d)))
+            .or_else(|| ids.iter().rev()
+                .find_map(|&id| self.assocs[id].direction_by_addr(src, dst, pkt).map(|d| (id, d))))
+    }
+
+    /// Add the SCTP packet of `packet_id`. `data` is what the chunk offsets
+    /// point into: the frame, or the datagram it was reassembled into.
+    pub fn add(&mut self, packet_id: usize, flow: &FlowTuple, pkt: &SctpPacket, data: &[u8]) -> SctpResult {
+        let mut result = SctpResult::default();
+        let (Some(src_addr), Some(dst_addr)) = (flow.src_addr, flow.dst_addr) else {
+            return result;
+        };
+        let src = (src_addr, flow.src_port);
+        let dst = (dst_addr, flow.dst_port);
+
+        let (id, from_initiator) = match self.find(src, dst, pkt) {
+            Some(found) => found,
+            // an INIT ACK seen first is sent by the responder
+            None if pkt.init.as_ref().is_some_and(|init| init.ack) => (self.create(dst.1, src.1), false),
+            None => (self.create(src.1, dst.1), true),
+        };
+        result.assoc = Some(id);
+
+        let assoc = &mut self.assocs[id];
+        assoc.observe(packet_id, src_addr, dst_addr, from_initiator, pkt);
+
+        let direction = if from_initiator { &mut assoc.from_initiator } else { &mut assoc.from_responder };
+        for chunk in &pkt.data {
+            let payload = data.get(chunk.payload_at..chunk.payload_at + chunk.payload_len).unwrap_or_default();
+            result.messages.extend(direction.add(packet_id, chunk, payload));
+        }
+        assoc.info.messages += result.messages.len();
+
+        result
+    }
+
+    /// Every association, by id
+    pub fn finish(self) -> Vec<SctpAssociation> {
+        self.assocs.into_iter().map(|a| a.finish()).collect()
+    }
+}
+
+fn port_key(a: u16, b: u16) -> (u16, u16) {
+    (a.min(b), a.max(b))
+}
@@ -1,20 +1,164 @@
 use crate::ip::port::*;
 use crate::types::*;
+use crate::dissect::*;
+use crate::ip::checksum::*;
+use crate::l4::tcp_stream::TcpSegment;
 
-pub fn parse_tcp_simple(tcp: &[u8], packet: &mut PacketSummary) -> u16
+pub const TCP_FLAG_FIN: u8  = 0x01;
+pub const TCP_FLAG_SYN: u8  = 0x02;
+pub const TCP_FLAG_RST: u8  = 0x04;
+pub const TCP_FLAG_PSH: u8  = 0x08;
+pub const TCP_FLAG_ACK: u8  = 0x10;
+pub const TCP_FLAG_URG: u8  = 0x20;
+pub const TCP_FLAG_ECE: u8  = 0x40;
+pub const TCP_FLAG_CWR: u8  = 0x80;
+
+// [ https://www.iana.org/assignments/tcp-parameters ]
+pub const TCPOPT_EOL: u8        = 0;
+pub const TCPOPT_NOP: u8        = 1;
+pub const TCPOPT_MSS: u8        = 2;
+pub const TCPOPT_WSCALE: u8     = 3;
+pub const TCPOPT_SACK_PERM: u8  = 4;
+pub const TCPOPT_SACK: u8       = 5;
+pub const TCPOPT_TIMESTAMP: u8  = 8;
+pub const TCPOPT_MD5: u8        = 19;
+pub const TCPOPT_USER_TO: u8    = 28;
+pub const TCPOPT_AO: u8         = 29;
+pub const TCPOPT_MPTCP: u8      = 30;
+pub const TCPOPT_TFO: u8        = 34;
+
+// RFC 7323: larger shifts are used as 14
+const TCP_MAX_WSCALE: u8        = 14;
+
+
+pub fn tcp_option_to_str(kind: u8) -> String
 {
-    if tcp.len() < 20 {
-        println!( "TCP header too short");
+    match kind {
+        TCPOPT_EOL          => "End of Option List".to_string(),
+        TCPOPT_NOP          => "No-Operation".to_string(),
+        TCPOPT_MSS          => "Maximum Segment Size".to_string(),
+        TCPOPT_WSCALE       => "Window Scale".to_string(),
+        TCPOPT_SACK_PERM    => "SACK Permitted".to_string(),
+        TCPOPT_SACK         => "SACK".to_string(),
+        TCPOPT_TIMESTAMP    => "Timestamps".to_string(),
+        TCPOPT_MD5          => "MD5 Signature".to_string(),
+        TCPOPT_USER_TO      => "User Timeout".to_string(),
+        TCPOPT_AO           => "TCP Authentication Option".to_string(),
+        TCPOPT_MPTCP        => "Multipath TCP".to_string(),
+        TCPOPT_TFO          => "TCP Fast Open Cookie".to_string(),
+        253 | 254           => "Experimental".to_string(),
+        _                   => format!("Unknown ({})", kind),
     }
+}
+
 
-    let src_port = u16::from_be_bytes([tcp[0], tcp[1]]);
-    let dst_port = u16::from_be_bytes([tcp[2], tcp[3]]);
+fn tcp_option_value(kind: u8, body: &[u8]) -> TcpOptionValue
+{
+    let be32 = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
 
-    packet.src_port = src_port;
-    packet.dst_port = dst_port;
-    packet.l4_type = "TCP".to_string();
+    match (kind, body.len()) {
+        (TCPOPT_MSS, 2) => TcpOptionValue::Mss(u16::from_be_bytes([body[0], body[1]])),
 
-    dst_port
+        (TCPOPT_WSCALE, 1) => TcpOptionValue::WindowScale {
+            shift: body[0],
+            multiplier: 1 << body[0].min(TCP_MAX_WSCALE),
+        },
+
+        (TCPOPT_SACK, len) if len % 8 == 0 => TcpOptionValue::Sack(
+            body.chunks_exact(8)
+                .map(|c| SackBlock { left: be32(&c[..4]), right: be32(&c[4..]) })
+                .collect()
+        ),
+
+        (TCPOPT_TIMESTAMP, 8) => TcpOptionValue::Timestamp {
+            tsval: be32(&body[..4]),
+            tsecr: be32(&body[4..]),
+        },
+
+        (_, 0) => TcpOptionValue::None,
+        _ => TcpOptionValue::Raw(body.to_vec()),
+    }
+}
+
+
+/// Decode the options between the fixed header and the data offset into
+/// `list`. Offsets in the error are relative to `opts`.
+pub fn parse_tcp_options(opts: &[u8], list: &mut Vec<TcpOption>)
+-> Result<(), DecodeError>
+{
+    let mut pos = 0;
+
+    while pos < opts.len() {
+        let kind = opts[pos];
+
+        let mut opt = TcpOption {
+            kind,
+            name: tcp_option_to_str(kind),
+            length: 1,
+            value: TcpOptionValue::None,
+            raw: vec![kind],
+        };
+
+        // single octet options
+        if kind == TCPOPT_EOL || kind == TCPOPT_NOP {
+            list.push(opt);
+            pos += 1;
+            if kind == TCPOPT_EOL {
+                break;      // the rest is padding
+            }
+            continue;
+        }
+
+        let Some(&len) = opts.get(pos + 1) else {
+            return Err(DecodeError::Truncated { offset: pos + 1, needed: Some(pos + 2), available: opts.len() });
+        };
+        if len < 2 {
+            return Err(DecodeError::invalid(pos + 1,
+                format!("option {} length {} is below 2", opt.name, len)));
+        }
+
+        let end = pos + len as usize;
+        let Some(body) = opts.get(pos + 2..end) else {
+            return Err(DecodeError::Truncated { offset: pos, needed: Some(end), available: opts.len() });
+        };
+
+        opt.length = len;
+        opt.value = tcp_option_value(kind, body);
+        opt.raw = opts[pos..end].to_vec();
+        list.push(opt);
+
+        pos = end;
+    }
+
+    Ok(())
+}
+
+
+fn tcp_flags(flags: u8, ae: bool) -> TcpFlags
+{
+    TcpFlags {
+        ae,
+        cwr: flags & TCP_FLAG_CWR != 0,
+        ece: flags & TCP_FLAG_ECE != 0,
+        urg: flags & TCP_FLAG_URG != 0,
+        ack: flags & TCP_FLAG_ACK != 0,
+        psh: flags & TCP_FLAG_PSH != 0,
+        rst: flags & TCP_FLAG_RST != 0,
+        syn: flags & TCP_FLAG_SYN != 0,
+        fin: flags & TCP_FLAG_FIN != 0,
+    }
+}
+
+/// "SYN, ACK"; "<None>" when no bit is set
+pub fn tcp_flags_to_str(flags: &TcpFlags) -> String
+{
+    let names = [
+        (flags.fin, "FIN"), (flags.syn, "SYN"), (flags.rst, "RST"), (flags.psh, "PSH"),
+        (flags.ack, "ACK"), (flags.urg, "URG"), (flags.ece, "ECE"), (flags.cwr, "CWR"), (flags.ae, "AE"),
+    ];
+    let set: Vec<&str> = names.iter().filter(|(on, _)| *on).map(|(_, name)| *name).collect();
+
+    if set.is_empty() { "<None>".to_string() } else { set.join(", ") }
 }
 
 pub fn parse_single_tcp(tcp_buf: &[u8], tcp: & mut TcpInfo) -> u16
@@ -63,12 +207,100 @@ pub fn parse_single_tcp(tcp_buf: &[u8], tcp: & mut TcpInfo) -> u16
     tcp.seq             = seq_num;
     tcp.ack             = ack_num;
     tcp.flags           = flags;
+    tcp.named_flags     = tcp_flags(flags, tcp_buf[12] & 0x01 != 0);
+    tcp.flags_str       = tcp_flags_to_str(&tcp.named_flags);
     tcp.window          = win_size;
     tcp.header_sz       = data_offset;
     tcp.checksum        = chksum;
     tcp.urgent          = 0;
-    tcp.raw.extend_from_slice(&tcp_buf[0..20]);
-    tcp.payload = Some((tcp_buf[20..]).to_vec());
+    // options sit between the fixed header and the payload
+    let hdr_len = (data_offset as usize).clamp(TCP_HDR_LEN, tcp_buf.len());
+    tcp.raw.extend_from_slice(&tcp_buf[..hdr_len]);
+    tcp.payload = Some((tcp_buf[hdr_len..]).to_vec());
         
     dst_port
-}
\ No newline at end of file
+}
+
+
+pub struct TcpDissector;
+
+impl Dissector for TcpDissector {
+    fn name(&self) -> &'static str {
+        "TCP"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        need(data, TCP_HDR_LEN)?;
+
+        let mut tcp = TcpInfo::new();
+        parse_single_tcp(data, &mut tcp);
+
+        let status = l4_checksum_status(data, ctx.complete,
+            ctx.flow.src_addr, ctx.flow.dst_addr, PROTO_TYPE_TCP as u8);
+        tcp.checksum_status = ctx.check_checksum(status);
+
+        let hdr_len = (tcp.header_sz as usize).clamp(TCP_HDR_LEN, data.len());
+        let declared = tcp.header_sz as usize;
+        let (src, dst) = (tcp.src_port, tcp.dst_port);
+
+        ctx.flow.src_port = src;
+        ctx.flow.dst_port = dst;
+
+        ctx.summary.src_port = src;
+        ctx.summary.dst_port = dst;
+        ctx.summary.l4_type = "TCP".to_string();
+        ctx.summary.length = data.len() - hdr_len;
+
+        // ports are still worth showing when the rest of the header is bad
+        let header = if declared < TCP_HDR_LEN {
+            Err(DecodeError::invalid(12, format!("header length {} is below {}", declared, TCP_HDR_LEN)))
+        }
+        else {
+            need(data, declared)
+        };
+        if let Err(e) = header {
+            if ctx.wants_detail() {
+                ctx.detail.l4 = Layer4Info::TCP(tcp);
+            }
+            return Err(e);
+        }
+
+        // a bad option is reported, the payload is still decoded
+        if let Err(e) = parse_tcp_options(&data[TCP_HDR_LEN..declared], &mut tcp.options) {
+            ctx.mark_malformed(self.name(), &e.shifted(TCP_HDR_LEN));
+        }
+
+        if !ctx.in_quote {
+            let mut seg = TcpSegment {
+                seq: tcp.seq,
+                ack: tcp.ack,
+                flags: tcp.flags,
+                window: tcp.window,
+                mss: None,
+                window_scale: None,
+                sack_permitted: false,
+                payload_at: ctx.offset + hdr_len,
+                payload_len: data.len() - hdr_len,
+            };
+            for opt in &tcp.options {
+                match opt.value {
+                    TcpOptionValue::Mss(mss) => seg.mss = Some(mss),
+                    TcpOptionValue::WindowScale { shift, .. } => seg.window_scale = Some(shift),
+                    _ if opt.kind == TCPOPT_SACK_PERM => seg.sack_permitted = true,
+                    _ => {},
+                }
+            }
+            ctx.segment = Some(seg);
+        }
+
+        if ctx.wants_detail() {
+            ctx.detail.l4 = Layer4Info::TCP(tcp);
+        }
+
+        Ok(Some(NextLayer {
+            key: LayerKey::TcpPort { src, dst },
+            hdr_len,
+            len: None,
+        }))
+    }
+}
@@ -0,0 +1,452 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+
+use crate::types::{TcpAnalysis, TcpHandshake, TcpPdu};
+use crate::dissect::{Dissector, FlowTuple, StreamChunk};
+use crate::l4::tcp::*;
+
+// connections followed at once; the oldest is dropped beyond this
+const MAX_CONNECTIONS: usize    = 16384;
+// segments held per direction behind a missing one; past this the gap is skipped
+const MAX_HELD: usize           = 64;
+// longest PDU put back together
+const MAX_PDU: usize            = 1 << 20;
+
+
+/// A TCP segment found while dissecting a frame
+#[derive(Debug, Clone)]
+pub struct TcpSegment {
+    pub seq: u32,
+    pub ack: u32,
+    pub flags: u8,
+    pub window: u16,
+    pub mss: Option<u16>,           // options that matter for the handshake
+    pub window_scale: Option<u8>,
+    pub sack_permitted: bool,
+    pub payload_at: usize,      // offset of the payload in the frame, or in the reassembled datagram
+    pub payload_len: usize,
+}
+
+/// An application message cut out of a TCP byte stream
+#[derive(Debug, Clone)]
+pub struct StreamPdu {
+    pub data: Vec<u8>,
+    pub chunks: Vec<StreamChunk>,   // in stream order, the last one completed it
+}
+
+/// What the packet detail shows of a PDU made of `chunks`
+pub fn pdu_info(chunks: &[StreamChunk]) -> TcpPdu
+{
+    let mut packet_ids: Vec<usize> = chunks.iter().map(|c| c.packet_id).collect();
+    packet_ids.dedup();
+
+    TcpPdu {
+        len: chunks.iter().map(|c| c.len).sum(),
+        packet_ids,
+    }
+}
+
+/// What one segment meant for its connection
+#[derive(Debug, Default)]
+pub struct SegmentResult {
+    pub stream: Option<usize>,
+    pub analysis: Vec<TcpAnalysis>,
+    pub pdus: Vec<StreamPdu>,
+}
+
+
+/// Both directions of a connection map to the same key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ConnKey {
+    a: (IpAddr, u16),
+    b: (IpAddr, u16),
+}
+impl ConnKey {
+    // also tells whether src is the `a` side
+    fn new(src: (IpAddr, u16), dst: (IpAddr, u16)) -> (Self, bool) {
+        if src <= dst {
+            (ConnKey { a: src, b: dst }, true)
+        }
+        else {
+            (ConnKey { a: dst, b: src }, false)
+        }
+    }
+}
+
+// a segment waiting for the bytes before it
+struct Held {
+    packet_id: usize,
+    payload_at: usize,
+    seq_len: u32,       // payload plus one for FIN
+    data: Vec<u8>,      // only kept while PDUs are being cut
+}
+
+// one direction of a connection; offsets are relative to `base`
+struct HalfStream {
+    isn: Option<u32>,
+    base: Option<u32>,  // sequence number of the first payload byte
+    next: u32,          // next offset expected in order
+    max_end: u32,       // end of the highest segment seen
+    held: BTreeMap<u32, Held>,
+    buffer: Vec<u8>,    // in-order bytes not yet cut into PDUs
+    chunks: VecDeque<StreamChunk>,
+    broken: bool,       // lost its framing; no more PDUs are cut
+}
+impl HalfStream {
+    fn new() -> Self {
+        HalfStream {
+            isn: None,
+            base: None,
+            next: 0,
+            max_end: 0,
+            held: BTreeMap::new(),
+            buffer: Vec::new(),
+            chunks: VecDeque::new(),
+            broken: false,
+        }
+    }
+
+    fn deliver(&mut self, packet_id: usize, start: usize, data: &[u8], end: u32, buffering: bool) {
+        self.next = end;
+        if buffering && !data.is_empty() {
+            self.buffer.extend_from_slice(data);
+            self.chunks.push_back(StreamChunk { packet_id, start, len: data.len() });
+        }
+    }
+
+    // held segments the stream has caught up with
+    fn drain_held(&mut self, buffering: bool) {
+        while let Some(entry) = self.held.first_entry() {
+            let rel = *entry.key();
+            if seq_diff(rel, self.next) > 0 {
+                break;
+            }
+            let held = entry.remove();
+            let end = rel.wrapping_add(held.seq_len);
+            if seq_diff(end, self.next) <= 0 {
+                continue;
+            }
+            let skip = (seq_diff(self.next, rel) as usize).min(held.data.len());
+            self.deliver(held.packet_id, held.payload_at + skip, &held.data[skip..], end, buffering);
+        }
+    }
+
+    // give up on the bytes before the first held segment
+    fn skip_gap(&mut self, buffering: bool) {
+        let Some((&first, _)) = self.held.first_key_value() else {
+            return;
+        };
+        self.next = first;
+        if buffering {
+            // a PDU cut in two cannot be decoded, and the next one may start mid-segment
+            self.give_up();
+        }
+        self.drain_held(false);
+    }
+
+    fn cut_pdus(&mut self, framing: &dyn Dissector) -> Vec<StreamPdu> {
+        let mut pdus = Vec::new();
+
+        while !self.buffer.is_empty() {
+            let Some(len) = framing.pdu_len(&self.buffer) else {
+                if self.buffer.len() > MAX_PDU {
+                    self.give_up();
+                }
+                break;
+            };
+            if len == 0 || len > MAX_PDU {
+                self.give_up();
+                break;
+            }
+            if len > self.buffer.len() {
+                break;
+            }
+
+            let data: Vec<u8> = self.buffer.drain(..len).collect();
+            let mut chunks = Vec::new();
+            let mut left = len;
+            while left > 0 {
+                let Some(chunk) = self.chunks.front_mut() else {
+                    break;
+                };
+                if chunk.len <= left {
+                    left -= chunk.len;
+                    chunks.extend(self.chunks.pop_front());
+                }
+                else {
+                    chunks.push(StreamChunk { len: left, ..*chunk });
+                    chunk.start += left;
+                    chunk.len -= left;
+                    left = 0;
+                }
+            }
+
+            pdus.push(StreamPdu { data, chunks });
+        }
+
+        pdus
+    }
+
+    fn give_up(&mut self) {
+        self.buffer.clear();
+        self.chunks.clear();
+        self.broken = true;
+    }
+}
+
+/// Signed distance between two sequence numbers, modulo 2^32
+pub fn seq_diff(a: u32, b: u32) -> i32 {
+    a.wrapping_sub(b) as i32
+}
+
+
+// both directions of a connection and how it was opened and closed
+struct Connection {
+    a_is_client: bool,
+    a: HalfStream,
+    b: HalfStream,
+    handshake: TcpHandshake,
+    syn_ns: Option<i64>,
+    syn_ack_ns: Option<i64>,
+}
+impl Connection {
+    fn new(stream: usize, key: &ConnKey, a_is_client: bool) -> Self {
+        let (client, server) = if a_is_client { (key.a, key.b) } else { (key.b, key.a) };
+        let addr = |(ip, port): (IpAddr, u16)| SocketAddr::new(ip, port).to_string();
+
+        Connection {
+            a_is_client,
+            a: HalfStream::new(),
+            b: HalfStream::new(),
+            handshake: TcpHandshake::new(stream, addr(client), addr(server)),
+            syn_ns: None,
+            syn_ack_ns: None,
+        }
+    }
+
+    fn client_half(&self) -> &HalfStream {
+        if self.a_is_client { &self.a } else { &self.b }
+    }
+
+    // only the first of each step counts; retransmissions keep the original
+    fn observe(&mut self, packet_id: usize, time_ns: i64, from_client: bool, seg: &TcpSegment) {
+        let hs = &mut self.handshake;
+        let syn = seg.flags & TCP_FLAG_SYN != 0;
+        let ack = seg.flags & TCP_FLAG_ACK != 0;
+
+        if syn && !ack && from_client && hs.syn.is_none() {
+            hs.syn = Some(packet_id);
+            hs.client_mss = seg.mss;
+            hs.client_window_scale = seg.window_scale;
+            hs.sack_permitted = seg.sack_permitted;
+            self.syn_ns = Some(time_ns);
+        }
+        else if syn && ack && !from_client && hs.syn_ack.is_none() {
+            hs.syn_ack = Some(packet_id);
+            hs.server_mss = seg.mss;
+            hs.server_window_scale = seg.window_scale;
+            hs.sack_permitted &= seg.sack_permitted;
+            hs.syn_to_syn_ack_ns = self.syn_ns.map(|t| time_ns - t);
+            self.syn_ack_ns = Some(time_ns);
+        }
+        else if !syn && ack && from_client && hs.syn_ack.is_some() && hs.ack.is_none() {
+            hs.ack = Some(packet_id);
+            hs.syn_ack_to_ack_ns = self.syn_ack_ns.map(|t| time_ns - t);
+            hs.handshake_ns = self.syn_ns.map(|t| time_ns - t);
+        }
+
+        if seg.flags & TCP_FLAG_FIN != 0 {
+            let fin = if from_client { &mut hs.fin_client } else { &mut hs.fin_server };
+            fin.get_or_insert(packet_id);
+        }
+        if seg.flags & TCP_FLAG_RST != 0 && hs.rst.is_none() {
+            hs.rst = Some(packet_id);
+            hs.teardown = format!("Reset by {}", if from_client { "client" } else { "server" });
+        }
+    }
+
+    fn finish(mut self) -> TcpHandshake {
+        let hs = &mut self.handshake;
+
+        hs.mss = match (hs.client_mss, hs.server_mss) {
+            (Some(c), Some(s)) => Some(c.min(s)),
+            (c, s) => c.or(s),
+        };
+        hs.window_scaling = hs.client_window_scale.is_some() && hs.server_window_scale.is_some();
+        hs.sack_permitted &= hs.syn_ack.is_some();
+
+        if hs.rst.is_none() {
+            hs.teardown = match (hs.fin_client, hs.fin_server) {
+                (Some(c), Some(s)) => format!("Closed by {}", if c <= s { "client" } else { "server" }),
+                (Some(_), None) => "Half-closed by client".to_string(),
+                (None, Some(_)) => "Half-closed by server".to_string(),
+                (None, None) => "Open".to_string(),
+            };
+        }
+
+        self.handshake
+    }
+}
+
+
+/// Follows TCP connections across a capture: orders each direction by
+/// sequence number, flags what did not arrive in order and, for ports with a
+/// dissector, cuts the byte stream into PDUs with the packets they came from.
+/// Connections are numbered in the order they are first seen.
+pub struct TcpReassembler {
+    connections: HashMap<ConnKey, Connection>,
+    order: VecDeque<ConnKey>,
+    finished: Vec<TcpHandshake>,
+    next_stream: usize,
+}
+
+impl TcpReassembler {
+    pub fn new() -> Self {
+        TcpReassembler {
+            connections: HashMap::new(),
+            order: VecDeque::new(),
+            finished: Vec::new(),
+            next_stream: 0,
+        }
+    }
+
+    fn close(&mut self, key: &ConnKey) {
+        if let Some(conn) = self.connections.remove(key) {
+            self.order.retain(|k| k != key);
+            self.finished.push(conn.finish());
+        }
+    }
+
+    /// Add the segment of packet `packet_id`, captured at `time_ns`.
+    /// `framing` is the dissector its ports are decoded with; without one
+    /// only the analysis is done.
+    pub fn add(&mut self, packet_id: usize, time_ns: i64, flow: &FlowTuple, seg: &TcpSegment,
+        payload: &[u8], framing: Option<&dyn Dissector>)
+    -> SegmentResult
+    {
+        let mut result = SegmentResult::default();
+        let (Some(src), Some(dst)) = (flow.src_addr, flow.dst_addr) else {
+            return result;
+        };
+
+        let syn = seg.flags & TCP_FLAG_SYN != 0;
+        let ack = seg.flags & TCP_FLAG_ACK != 0;
+        let (key, forward) = ConnKey::new((src, flow.src_port), (dst, flow.dst_port));
+
+        // a SYN with a new ISN: the ports were reused by a new connection
+        if syn && !ack
+            && let Some(conn) = self.connections.get(&key)
+            && conn.client_half().isn.is_some_and(|isn| isn != seg.seq) {
+            self.close(&key);
+        }
+
+        if !self.connections.contains_key(&key) {
+            if self.order.len() >= MAX_CONNECTIONS
+                && let Some(oldest) = self.order.pop_front()
+                && let Some(conn) = self.connections.remove(&oldest) {
+                self.finished.push(conn.finish());
+            }
+            // a SYN-ACK seen first is sent by the server
+            let src_is_client = !(syn && ack);
+            self.order.push_back(key);
+            self.connections.insert(key, Connection::new(self.next_stream, &key, forward == src_is_client));
+            self.next_stream += 1;
+        }
+        let Some(conn) = self.connections.get_mut(&key) else {
+            return result;
+        };
+        result.stream = Some(conn.handshake.stream);
+
+        conn.observe(packet_id, time_ns, forward == conn.a_is_client, seg);
+        let half = if forward { &mut conn.a } else { &mut conn.b };
+
+        let fin = seg.flags & TCP_FLAG_FIN != 0;
+        let rst = seg.flags & TCP_FLAG_RST != 0;
+
+        if seg.window == 0 && !(syn || fin || rst) {
+            result.analysis.push(TcpAnalysis::ZeroWindow);
+        }
+
+        if syn {
+            match half.isn {
+                Some(isn) if isn == seg.seq => {
+                    result.analysis.push(TcpAnalysis::Retransmission);
+                    return result;
+                },
+                // the server answers a reused connection with a new ISN
+                Some(_) => *half = HalfStream::new(),
+                None => {},
+            }
+            half.isn = Some(seg.seq);
+            half.base = Some(seg.seq.wrapping_add(1));
+        }
+
+        let seq = if syn { seg.seq.wrapping_add(1) } else { seg.seq };
+        // joined mid-connection: the first segment seen starts the stream
+        let base = *half.base.get_or_insert(seq);
+        let rel = seq.wrapping_sub(base);
+        let seq_len = payload.len() as u32 + fin as u32;
+        if seq_len == 0 {
+            return result;
+        }
+        let end = rel.wrapping_add(seq_len);
+
+        let buffering = framing.is_some() && !half.broken;
+        let held_again = half.held.get(&rel).is_some_and(|h| h.seq_len >= seq_len);
+
+        if seq_diff(end, half.next) <= 0 || held_again {
+            result.analysis.push(TcpAnalysis::Retransmission);
+            return result;
+        }
+        if seq_diff(rel, half.max_end) > 0 {
+            result.analysis.push(TcpAnalysis::PreviousSegmentMissing);
+        }
+        else if seq_diff(rel, half.next) < 0 {
+            // partly seen before; the new bytes are still used
+            result.analysis.push(TcpAnalysis::Retransmission);
+        }
+        else if seq_diff(rel, half.max_end) < 0 {
+            // fills a gap left by later segments
+            result.analysis.push(TcpAnalysis::OutOfOrder);
+        }
+        if seq_diff(end, half.max_end) > 0 {
+            half.max_end = end;
+        }
+
+        if seq_diff(rel, half.next) > 0 {
+            half.held.insert(rel, Held {
+                packet_id,
+                payload_at: seg.payload_at,
+                seq_len,
+                data: if buffering { payload.to_vec() } else { Vec::new() },
+            });
+            if half.held.len() > MAX_HELD {
+                half.skip_gap(buffering);
+            }
+        }
+        else {
+            let skip = (seq_diff(half.next, rel) as usize).min(payload.len());
+            half.deliver(packet_id, seg.payload_at + skip, &payload[skip..], end, buffering);
+            half.drain_held(buffering);
+        }
+
+        if let Some(framing) = framing && !half.broken {
+            result.pdus = half.cut_pdus(framing);
+        }
+
+        result
+    }
+
+    /// Handshake and teardown of every connection, by stream index
+    pub fn finish(mut self) -> Vec<TcpHandshake> {
+        for key in self.order.drain(..) {
+            if let Some(conn) = self.connections.remove(&key) {
+                self.finished.push(conn.finish());
+            }
+        }
+
+        let mut handshakes = self.finished;
+        handshakes.sort_by_key(|hs| hs.stream);
+        handshakes
+    }
+}
@@ -0,0 +1,331 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::types::*;
+use crate::dissect::*;
+use crate::l4::{sctp::*, sctp_stream::*, tcp::*, tcp_stream::*};
+
+
+const CLIENT: (IpAddr, u16) = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 40000);
+const SERVER: (IpAddr, u16) = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 2123);
+
+fn flow(src: (IpAddr, u16), dst: (IpAddr, u16), protocol: u8) -> FlowTuple
+{
+    FlowTuple {
+        protocol,
+        src_addr: Some(src.0),
+        dst_addr: Some(dst.0),
+        src_port: src.1,
+        dst_port: dst.1,
+    }
+}
+
+
+// messages that start with their own length, as a 2-octet field
+struct LengthPrefixed;
+
+impl Dissector for LengthPrefixed {
+    fn name(&self) -> &'static str {
+        "Test"
+    }
+
+    fn dissect(&self, _data: &[u8], _ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        Ok(None)
+    }
+
+    fn pdu_len(&self, data: &[u8]) -> Option<usize> {
+        let len = data.get(0..2)?;
+        Some(u16::from_be_bytes([len[0], len[1]]) as usize)
+    }
+}
+
+fn segment(seq: u32, flags: u8, len: usize) -> TcpSegment
+{
+    TcpSegment {
+        seq,
+        ack: 0,
+        flags,
+        window: 1024,
+        mss: None,
+        window_scale: None,
+        sack_permitted: false,
+        payload_at: 54,
+        payload_len: len,
+    }
+}
+
+// one side of a connection; `at` is its next sequence number
+struct Sender {
+    from: (IpAddr, u16),
+    to: (IpAddr, u16),
+    at: u32,
+}
+
+impl Sender {
+    fn send(&mut self, streams: &mut TcpReassembler, packet_id: usize, flags: u8, payload: &[u8])
+    -> SegmentResult
+    {
+        let seg = segment(self.at, flags, payload.len());
+        self.at = self.at.wrapping_add(payload.len() as u32 + (flags & (TCP_FLAG_SYN | TCP_FLAG_FIN) != 0) as u32);
+        streams.add(packet_id, packet_id as i64 * 1000, &flow(self.from, self.to, 6), &seg,
+            payload, Some(&LengthPrefixed))
+    }
+}
+
+
+#[test]
+fn tcp_handshake_and_a_pdu_across_segments() {
+    let mut streams = TcpReassembler::new();
+    // the client's sequence numbers wrap around during the connection
+    let mut client = Sender { from: CLIENT, to: SERVER, at: 0xffff_fffa };
+    let mut server = Sender { from: SERVER, to: CLIENT, at: 1000 };
+
+    let mut syn = segment(client.at, TCP_FLAG_SYN, 0);
+    syn.mss = Some(1460);
+    syn.sack_permitted = true;
+    let result = streams.add(1, 0, &flow(CLIENT, SERVER, 6), &syn, &[], Some(&LengthPrefixed));
+    assert_eq!(result.stream, Some(0));
+    client.at = client.at.wrapping_add(1);
+
+    let mut syn_ack = segment(server.at, TCP_FLAG_SYN | TCP_FLAG_ACK, 0);
+    syn_ack.mss = Some(1400);
+    syn_ack.sack_permitted = true;
+    streams.add(2, 3000, &flow(SERVER, CLIENT, 6), &syn_ack, &[], Some(&LengthPrefixed));
+    server.at += 1;
+    assert!(client.send(&mut streams, 3, TCP_FLAG_ACK, &[]).pdus.is_empty());
+
+    // a 10-octet message split 4 + 6, then the first 3 octets of the next
+    assert!(client.send(&mut streams, 4, TCP_FLAG_ACK, &[0, 10, 1, 2]).pdus.is_empty());
+    let result = client.send(&mut streams, 5, TCP_FLAG_ACK, &[3, 4, 5, 6, 7, 8, 0, 5, 9]);
+    assert!(result.analysis.is_empty());
+    assert_eq!(result.pdus.len(), 1);
+    assert_eq!(result.pdus[0].data, vec![0, 10, 1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(result.pdus[0].chunks, vec![
+        StreamChunk { packet_id: 4, start: 54, len: 4 },
+        StreamChunk { packet_id: 5, start: 54, len: 6 },
+    ]);
+
+    let result = client.send(&mut streams, 6, TCP_FLAG_ACK | TCP_FLAG_FIN, &[1, 2]);
+    assert_eq!(result.pdus[0].data, vec![0, 5, 9, 1, 2]);
+    assert_eq!(pdu_info(&result.pdus[0].chunks).packet_ids, vec![5, 6]);
+    server.send(&mut streams, 7, TCP_FLAG_ACK | TCP_FLAG_FIN, &[]);
+
+    let handshakes = streams.finish();
+    assert_eq!(handshakes.len(), 1);
+    let hs = &handshakes[0];
+    assert_eq!((hs.client.as_str(), hs.server.as_str()), ("10.0.0.1:40000", "10.0.0.2:2123"));
+    assert_eq!((hs.syn, hs.syn_ack, hs.ack), (Some(1), Some(2), Some(3)));
+    assert_eq!((hs.syn_to_syn_ack_ns, hs.handshake_ns), (Some(3000), Some(3000)));
+    assert_eq!(hs.mss, Some(1400));
+    assert!(hs.sack_permitted);
+    assert_eq!(hs.teardown, "Closed by client");
+}
+
+#[test]
+fn tcp_out_of_order_and_retransmission() {
+    let mut streams = TcpReassembler::new();
+    let mut client = Sender { from: CLIENT, to: SERVER, at: 500 };
+
+    // joined mid-connection: the first segment seen starts the stream
+    let first = [0, 6, 1, 2];
+    let second = [3, 4, 0, 4];
+    let third = [5, 6];
+    assert!(client.send(&mut streams, 1, TCP_FLAG_ACK, &first).pdus.is_empty());
+    let resend_at = client.at;
+    client.at += second.len() as u32;
+
+    let result = client.send(&mut streams, 2, TCP_FLAG_ACK, &third);
+    assert_eq!(result.analysis, vec![TcpAnalysis::PreviousSegmentMissing]);
+    assert!(result.pdus.is_empty());
+
+    client.at = resend_at;
+    let result = client.send(&mut streams, 3, TCP_FLAG_ACK, &second);
+    assert_eq!(result.analysis, vec![TcpAnalysis::OutOfOrder]);
+    let pdus: Vec<_> = result.pdus.iter().map(|p| p.data.clone()).collect();
+    assert_eq!(pdus, vec![vec![0, 6, 1, 2, 3, 4], vec![0, 4, 5, 6]]);
+    assert_eq!(pdu_info(&result.pdus[1].chunks).packet_ids, vec![3, 2]);
+
+    client.at = resend_at;
+    let result = client.send(&mut streams, 4, TCP_FLAG_ACK, &second);
+    assert_eq!(result.analysis, vec![TcpAnalysis::Retransmission]);
+    assert!(result.pdus.is_empty());
+}
+
+#[test]
+fn tcp_without_framing_is_only_analysed() {
+    let mut streams = TcpReassembler::new();
+    let mut seg = segment(1, TCP_FLAG_ACK, 3);
+    seg.window = 0;
+
+    let result = streams.add(1, 0, &flow(CLIENT, SERVER, 6), &seg, &[1, 2, 3], None);
+    assert_eq!(result.analysis, vec![TcpAnalysis::ZeroWindow]);
+    assert!(result.pdus.is_empty());
+
+    // the other direction is the same connection; a new port pair is not
+    let back = streams.add(2, 0, &flow(SERVER, CLIENT, 6), &segment(9, TCP_FLAG_ACK, 0), &[], None);
+    assert_eq!(back.stream, Some(0));
+    let other = streams.add(3, 0, &flow((CLIENT.0, 40001), SERVER, 6), &segment(9, TCP_FLAG_ACK, 0), &[], None);
+    assert_eq!(other.stream, Some(1));
+}
+
+
+const INITIATOR: (IpAddr, u16) = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 36412);
+const RESPONDER: (IpAddr, u16) = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 36412);
+
+const B: u8 = 0x02;
+const E: u8 = 0x01;
+
+fn chunk(chunk_type: u8, flags: u8, body: &[u8]) -> Vec<u8>
+{
+    let mut out = vec![chunk_type, flags];
+    out.extend((4 + body.len() as u16).to_be_bytes());
+    out.extend(body);
+    out.resize(out.len().next_multiple_of(4), 0);
+    out
+}
+
+fn data_chunk(flags: u8, tsn: u32, stream_id: u16, ssn: u16, payload: &[u8]) -> Vec<u8>
+{
+    let mut body = tsn.to_be_bytes().to_vec();
+    body.extend(stream_id.to_be_bytes());
+    body.extend(ssn.to_be_bytes());
+    body.extend(18u32.to_be_bytes());
+    body.extend(payload);
+    chunk(SCTP_DATA, flags, &body)
+}
+
+// `ppid_fsn` is the PPID on the first fragment, the FSN on the others
+fn i_data_chunk(flags: u8, tsn: u32, stream_id: u16, mid: u32, ppid_fsn: u32, payload: &[u8]) -> Vec<u8>
+{
+    let mut body = tsn.to_be_bytes().to_vec();
+    body.extend(stream_id.to_be_bytes());
+    body.extend([0, 0]);
+    body.extend(mid.to_be_bytes());
+    body.extend(ppid_fsn.to_be_bytes());
+    body.extend(payload);
+    chunk(SCTP_I_DATA, flags, &body)
+}
+
+fn init_chunk(chunk_type: u8, initiate_tag: u32, addr: Option<Ipv4Addr>) -> Vec<u8>
+{
+    let mut body = initiate_tag.to_be_bytes().to_vec();
+    body.extend(65535u32.to_be_bytes());
+    body.extend([0, 10, 0, 10]);
+    body.extend(1u32.to_be_bytes());
+    if let Some(addr) = addr {
+        body.extend([0, 5, 0, 8]);
+        body.extend(addr.octets());
+    }
+    chunk(chunk_type, 0, &body)
+}
+
+// SCTP packet with `chunks`, decoded the way the dissector hands it on
+fn sctp_packet(vtag: u32, chunks: &[Vec<u8>]) -> (Vec<u8>, SctpPacket)
+{
+    let mut data = 36412u16.to_be_bytes().to_vec();
+    data.extend(36412u16.to_be_bytes());
+    data.extend(vtag.to_be_bytes());
+    data.extend([0; 4]);
+    data.extend(chunks.concat());
+
+    let mut pkt = SctpPacket::new(vtag);
+    parse_sctp_chunks(&data, 0, &mut SctpInfo::new(), &mut pkt).unwrap();
+    (data, pkt)
+}
+
+fn send(assocs: &mut SctpReassembler, packet_id: usize, src: (IpAddr, u16), dst: (IpAddr, u16),
+    vtag: u32, chunks: &[Vec<u8>])
+-> SctpResult
+{
+    let (data, pkt) = sctp_packet(vtag, chunks);
+    assocs.add(packet_id, &flow(src, dst, 132), &pkt, &data)
+}
+
+
+#[test]
+fn sctp_handshake_and_a_fragmented_message() {
+    let mut assocs = SctpReassembler::new();
+    let second_addr = Ipv4Addr::new(10, 0, 0, 3);
+
+    let init = send(&mut assocs, 1, INITIATOR, RESPONDER, 0, &[init_chunk(SCTP_INIT, 0x1111, Some(second_addr))]);
+    assert_eq!(init.assoc, Some(0));
+    send(&mut assocs, 2, RESPONDER, INITIATOR, 0x1111, &[init_chunk(SCTP_INIT_ACK, 0x2222, None)]);
+
+    // fragments B, E, then the middle one; E is sent from the second address
+    let first = send(&mut assocs, 3, INITIATOR, RESPONDER, 0x2222, &[data_chunk(B, 100, 1, 0, b"abc")]);
+    let last = send(&mut assocs, 4, (IpAddr::V4(second_addr), 36412), RESPONDER, 0x2222,
+        &[data_chunk(E, 102, 1, 0, b"gh")]);
+    assert!(first.messages.is_empty() && last.messages.is_empty());
+    assert_eq!(last.assoc, Some(0));
+
+    let result = send(&mut assocs, 5, INITIATOR, RESPONDER, 0x2222, &[data_chunk(0, 101, 1, 0, b"def")]);
+    assert_eq!(result.messages.len(), 1);
+    let msg = &result.messages[0];
+    assert_eq!(msg.data, b"abcdefgh");
+    assert_eq!((msg.stream_id, msg.ppid, msg.unordered), (1, 18, false));
+    // user data starts after the common header, the chunk header and 12 octets of DATA fields
+    assert_eq!(msg.chunks, vec![
+        StreamChunk { packet_id: 3, start: 28, len: 3 },
+        StreamChunk { packet_id: 5, start: 28, len: 3 },
+        StreamChunk { packet_id: 4, start: 28, len: 2 },
+    ]);
+    assert_eq!(message_info(0, msg).ppid_str.as_deref(), Some("S1AP"));
+
+    // a retransmitted TSN is not delivered twice
+    let again = send(&mut assocs, 6, INITIATOR, RESPONDER, 0x2222, &[data_chunk(B | E, 100, 1, 0, b"abc")]);
+    assert!(again.messages.is_empty());
+
+    let infos = assocs.finish();
+    assert_eq!(infos.len(), 1);
+    let info = &infos[0];
+    assert_eq!((info.initiator_tag, info.responder_tag), (Some(0x1111), Some(0x2222)));
+    assert_eq!((info.init, info.init_ack, info.messages), (Some(1), Some(2), 1));
+    assert_eq!(info.initiator_addrs, vec!["10.0.0.1".to_string(), "10.0.0.3".to_string()]);
+    assert_eq!(info.state, "Established");
+}
+
+#[test]
+fn sctp_interleaved_i_data_messages() {
+    let mut assocs = SctpReassembler::new();
+
+    // two messages with the same MID on different streams, their fragments interleaved
+    let result = send(&mut assocs, 1, INITIATOR, RESPONDER, 7, &[
+        i_data_chunk(B, 10, 1, 0, 60, b"one-"),
+        i_data_chunk(B, 11, 2, 0, 46, b"two-"),
+    ]);
+    assert!(result.messages.is_empty());
+
+    let result = send(&mut assocs, 2, INITIATOR, RESPONDER, 7, &[
+        i_data_chunk(E, 12, 2, 0, 1, b"b"),
+        i_data_chunk(0, 13, 1, 0, 1, b"a"),
+        i_data_chunk(E, 14, 1, 0, 2, b"c"),
+    ]);
+    let messages: Vec<_> = result.messages.iter()
+        .map(|m| (m.stream_id, m.ppid, m.data.clone()))
+        .collect();
+    assert_eq!(messages, vec![(2, 46, b"two-b".to_vec()), (1, 60, b"one-ac".to_vec())]);
+    assert_eq!(message_info(0, &result.messages[1]).packet_ids, vec![1, 2]);
+}
+
+#[test]
+fn sctp_new_init_starts_a_new_association() {
+    let mut assocs = SctpReassembler::new();
+
+    send(&mut assocs, 1, INITIATOR, RESPONDER, 0, &[init_chunk(SCTP_INIT, 0x1111, None)]);
+    assert_eq!(send(&mut assocs, 2, INITIATOR, RESPONDER, 0, &[init_chunk(SCTP_INIT, 0x1111, None)]).assoc, Some(0));
+    assert_eq!(send(&mut assocs, 3, INITIATOR, RESPONDER, 0, &[init_chunk(SCTP_INIT, 0x3333, None)]).assoc, Some(1));
+    // ABORT with the T bit carries the sender's own tag
+    assert_eq!(send(&mut assocs, 4, INITIATOR, RESPONDER, 0x3333, &[chunk(SCTP_ABORT, 0x01, &[])]).assoc, Some(1));
+
+    let states: Vec<_> = assocs.finish().into_iter().map(|a| a.state).collect();
+    assert_eq!(states, vec!["Setting up".to_string(), "Aborted".to_string()]);
+}
+
+#[test]
+fn sctp_truncated_chunk() {
+    let mut data = sctp_packet(1, &[]).0;
+    data.extend(data_chunk(B | E, 1, 0, 0, b"abcd"));
+    data.truncate(data.len() - 2);
+
+    let err = parse_sctp_chunks(&data, 0, &mut SctpInfo::new(), &mut SctpPacket::new(1)).unwrap_err();
+    assert_eq!(err.offset(), SCTP_HDR_LEN);
+}
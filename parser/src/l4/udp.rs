@@ -1,21 +1,8 @@
+use std::net::IpAddr;
 use crate::ip::port::*;
 use crate::types::*;
-
-pub fn parse_udp_simple (udp: &[u8], mut packet: &mut PacketSummary) -> u16
-{
-    let mut pos = 0;
-
-    let src_port = u16::from_be_bytes([udp[pos], udp[pos+1]]);
-    pos += 2;
-
-    let dst_port = u16::from_be_bytes([udp[pos], udp[pos+1]]);
-
-    packet.src_port = src_port;
-    packet.dst_port = dst_port;
-    packet.l4_type = "UDP".to_string();
-
-    dst_port
-}
+use crate::dissect::*;
+use crate::ip::checksum::*;
 
 pub fn get_udp_port(udp: &[u8]) -> (u16, u16)
 {
@@ -86,3 +73,73 @@ pub fn parse_single_udp(udp_buf: &[u8], udp: & mut UdpInfo) -> u16
 
     dst_port
 }
+
+
+fn udp_checksum_status(data: &[u8], udp_len: usize, ctx: &mut Dissection) -> ChecksumStatus
+{
+    let checksum = u16::from_be_bytes([data[6], data[7]]);
+    let ipv4 = matches!(ctx.flow.src_addr, Some(IpAddr::V4(_)));
+
+    // optional over IPv4; over IPv6 a zero checksum is only allowed for tunnels (RFC 6935)
+    if checksum == 0 {
+        let (src, dst) = get_udp_port(data);
+        if ipv4 || is_tunnel_port(src) || is_tunnel_port(dst) {
+            return ChecksumStatus::Unverified;
+        }
+        ctx.mark_malformed("UDP", &DecodeError::invalid(6, "zero checksum over IPv6"));
+        return ctx.check_checksum(ChecksumStatus::Bad);
+    }
+
+    let len = if udp_len == 0 { data.len() } else { udp_len };
+    let Some(segment) = data.get(..len) else {
+        return ChecksumStatus::Unverified;
+    };
+
+    let status = l4_checksum_status(segment, ctx.complete,
+        ctx.flow.src_addr, ctx.flow.dst_addr, PROTO_TYPE_UDP as u8);
+    ctx.check_checksum(status)
+}
+
+
+pub struct UdpDissector;
+
+impl Dissector for UdpDissector {
+    fn name(&self) -> &'static str {
+        "UDP"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        need(data, UDP_HDR_LEN)?;
+
+        let (src, dst) = get_udp_port(data);
+
+        ctx.flow.src_port = src;
+        ctx.flow.dst_port = dst;
+
+        ctx.summary.src_port = src;
+        ctx.summary.dst_port = dst;
+        ctx.summary.l4_type = "UDP".to_string();
+        ctx.summary.length = data.len() - UDP_HDR_LEN;
+
+        // 0 is allowed for IPv6 jumbograms
+        let udp_len = u16::from_be_bytes([data[4], data[5]]) as usize;
+        let status = udp_checksum_status(data, udp_len, ctx);
+
+        if ctx.wants_detail() {
+            let mut udp = UdpInfo::new();
+            parse_single_udp(data, &mut udp);
+            udp.checksum_status = status;
+            ctx.detail.l4 = Layer4Info::UDP(udp);
+        }
+
+        if udp_len != 0 && udp_len < UDP_HDR_LEN {
+            return Err(DecodeError::invalid(4, format!("length {} is below {}", udp_len, UDP_HDR_LEN)));
+        }
+
+        Ok(Some(NextLayer {
+            key: LayerKey::UdpPort { src, dst },
+            hdr_len: UDP_HDR_LEN,
+            len: (udp_len != 0).then_some(udp_len),
+        }))
+    }
+}
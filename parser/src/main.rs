@@ -11,16 +11,25 @@ use tower_http::cors::CorsLayer;
 use std::time::Duration;
 use serde::Deserialize;
 
+mod capture;
 mod file_manage;
 mod handlers;
+mod dissect;
 mod gtp;
 mod parse_pcap;
 mod ip;
+mod l2;
 mod types;
 mod l4;
 mod pfcp;
+mod tunnel;
 mod gtp_call_flow;
+mod nodes;
+mod conversations;
 mod call_flow_test;
+mod time_format;
+#[cfg(test)]
+mod test_util;
 
 use gtp_call_flow::*;
 use types::Cache;
@@ -37,6 +46,41 @@ pub struct AppState {
 pub struct CallflowRequest {
     pub file_id: u64,
     pub packet_id: usize,
+    pub tz: Option<String>,
+    pub ts_format: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DecodeAsRequest {
+    pub file_id: u64,
+    #[serde(default)]
+    pub rules: Vec<types::DecodeAsRule>,
+    #[serde(default)]
+    pub heuristics: bool,
+    pub tz: Option<String>,
+    pub ts_format: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NodeAliasRequest {
+    pub file_id: u64,
+    #[serde(default)]
+    pub aliases: Vec<types::NodeAlias>,
+}
+
+#[derive(Deserialize)]
+pub struct MergeFile {
+    pub file_id: u64,
+    #[serde(default)]
+    pub clock_offset_ns: i64,
+}
+
+#[derive(Deserialize)]
+pub struct MergeRequest {
+    pub files: Vec<MergeFile>,
+    pub name: Option<String>,
+    pub tz: Option<String>,
+    pub ts_format: Option<String>,
 }
 
 #[tokio::main]
@@ -69,6 +113,11 @@ async fn main()
         .route("/api/packet_detail", get(handle_single_packet))
         .route("/api/cleanup", get(handle_cleanup))
         .route("/api/gtp/callflow", post( handle_callflow))
+        .route("/api/merge", post(handle_merge))
+        .route("/api/decode_as", get(handle_get_decode_as).post(handle_decode_as))
+        .route("/api/nodes", get(handle_nodes))
+        .route("/api/nodes/aliases", post(handle_node_aliases))
+        .route("/api/conversations", get(handle_conversations))
         .with_state(state) //router에 의해 호출되는 모든 함수들에 전달되는 사용자 data.
         .layer(cors); 
 
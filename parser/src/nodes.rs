@@ -0,0 +1,360 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
+
+use crate::types::*;
+use crate::dissect::{Dissection, FlowTuple};
+use crate::gtp::{gtp_ie::*, gtpv2_types::*};
+use crate::pfcp::types::*;
+
+const ROLE_SMF: &str = "SMF";
+const ROLE_UPF: &str = "UPF";
+
+
+/// Roles and name learnt about one address from the capture itself
+#[derive(Debug, Clone)]
+pub struct InferredNode {
+    pub addr: IpAddr,
+    pub roles: Vec<&'static str>,
+    pub fqdn: Option<String>,       // PFCP Node ID sent from this address
+    pub evidence: Vec<String>,
+}
+impl InferredNode {
+    pub fn new(addr: IpAddr) -> Self {
+        InferredNode {
+            addr,
+            roles: Vec::new(),
+            fqdn: None,
+            evidence: Vec::new(),
+        }
+    }
+}
+
+
+/// Collects role evidence from GTPv2-C F-TEIDs and PFCP messages while the
+/// summary is parsed.
+pub struct NodeInference {
+    nodes: BTreeMap<IpAddr, InferredNode>,
+}
+
+impl NodeInference {
+    pub fn new() -> Self {
+        NodeInference {
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    fn node(&mut self, addr: IpAddr) -> &mut InferredNode {
+        self.nodes.entry(addr).or_insert_with(|| InferredNode::new(addr))
+    }
+
+    /// Only the first packet a role is seen in is kept as evidence
+    fn add_role(&mut self, addr: IpAddr, role: &'static str, evidence: impl FnOnce() -> String) {
+        let node = self.node(addr);
+        if !node.roles.contains(&role) {
+            node.roles.push(role);
+            node.evidence.push(format!("{}: {}", role, evidence()));
+        }
+    }
+
+    /// Application layer of packet `id`, decoded at `Depth::Flow` or deeper
+    pub fn observe(&mut self, id: usize, dissection: &Dissection) {
+        match &dissection.detail.app {
+            AppLayerInfo::GTP(gtp) => self.observe_gtp(id, &gtp.ies),
+            AppLayerInfo::PFCP(pfcp) => self.observe_pfcp(id, pfcp, &dissection.flow),
+            AppLayerInfo::None => {},
+        }
+    }
+
+    // F-TEIDs name the function that owns their address, bearer contexts included
+    fn observe_gtp(&mut self, id: usize, ies: &[GtpIe]) {
+        for ie in ies {
+            match &ie.ie_value {
+                IeValue::FTeid(fteid) => {
+                    let Some(role) = fteid_iface_to_role(fteid.iface_type) else {
+                        continue;
+                    };
+                    let addrs = [&fteid.ipv4, &fteid.ipv6];
+                    for addr in addrs.into_iter().flatten().filter_map(|a| a.parse().ok()) {
+                        self.add_role(addr, role, || format!("F-TEID interface type {} in #{}",
+                            fteid.iface_type, id));
+                    }
+                },
+                IeValue::SubIeList(sub) => self.observe_gtp(id, sub),
+                _ => {},
+            }
+        }
+    }
+
+    // session requests go from the control plane to the user plane, reports the other way
+    fn observe_pfcp(&mut self, id: usize, pfcp: &PfcpInfo, flow: &FlowTuple) {
+        let (Some(src), Some(dst)) = (flow.src_addr, flow.dst_addr) else {
+            return;
+        };
+
+        let roles = match pfcp.msg_type {
+            PFCP_SESSION_ESTABLISHMENT_REQUEST |
+            PFCP_SESSION_MODIFICATION_REQUEST |
+            PFCP_SESSION_DELETION_REQUEST |
+            PFCP_SESSION_REPORT_RESPONSE        => Some((ROLE_SMF, ROLE_UPF)),
+            PFCP_SESSION_ESTABLISHMENT_RESPONSE |
+            PFCP_SESSION_MODIFICATION_RESPONSE |
+            PFCP_SESSION_DELETION_RESPONSE |
+            PFCP_SESSION_REPORT_REQUEST         => Some((ROLE_UPF, ROLE_SMF)),
+            _ => None,
+        };
+
+        if let Some((src_role, dst_role)) = roles {
+            self.add_role(src, src_role, || format!("sent PFCP message type {} in #{}", pfcp.msg_type, id));
+            self.add_role(dst, dst_role, || format!("received PFCP message type {} in #{}", pfcp.msg_type, id));
+        }
+
+        // the Node ID is the sender's
+        for ie in pfcp.ies.iter().filter(|ie| ie.ie_type == PFCP_IE_NODE_ID) {
+            match &ie.ie_value {
+                IeValue::Utf8String(fqdn) => {
+                    self.node(src).fqdn.get_or_insert_with(|| fqdn.clone());
+                },
+                IeValue::Ipv4(addr) | IeValue::Ipv6(addr) => {
+                    if let (Ok(addr), Some((role, _))) = (addr.parse(), roles) {
+                        self.add_role(addr, role, || format!("PFCP Node ID in #{}", id));
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    pub fn finish(self) -> Vec<InferredNode> {
+        self.nodes.into_values().collect()
+    }
+}
+
+
+/// Parses "10.10.1.71", "10.10.1.0/24" or "2001:db8::/64" into network and prefix length
+pub fn parse_subnet(s: &str) -> Result<(IpAddr, u8), String>
+{
+    let (addr, prefix) = match s.trim().split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (s.trim(), None),
+    };
+
+    let addr: IpAddr = addr.parse()
+        .map_err(|_| format!("\"{}\" is not an IP address or subnet", s))?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+
+    let prefix = match prefix {
+        Some(p) => p.parse::<u8>().ok().filter(|&p| p <= max)
+            .ok_or_else(|| format!("\"{}\" has an invalid prefix length", s))?,
+        None => max,
+    };
+
+    Ok((addr, prefix))
+}
+
+pub fn in_subnet(net: IpAddr, prefix: u8, addr: IpAddr) -> bool
+{
+    match (net, addr) {
+        (IpAddr::V4(n), IpAddr::V4(a)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(n) & mask == u32::from(a) & mask
+        },
+        (IpAddr::V6(n), IpAddr::V6(a)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(n) & mask == u128::from(a) & mask
+        },
+        _ => false,
+    }
+}
+
+
+/// A file's alias map together with what was inferred from its packets.
+/// Aliases win over inferred names and roles; the longest prefix wins among aliases.
+pub struct NodeDirectory {
+    aliases: Vec<(IpAddr, u8, NodeAlias)>,
+    inferred: HashMap<IpAddr, InferredNode>,
+}
+
+impl NodeDirectory {
+    pub fn new(aliases: &[NodeAlias], inferred: &[InferredNode]) -> Result<Self, String> {
+        let mut parsed = Vec::with_capacity(aliases.len());
+        for alias in aliases {
+            let (net, prefix) = parse_subnet(&alias.addr)?;
+            parsed.push((net, prefix, alias.clone()));
+        }
+        parsed.sort_by_key(|(_, prefix, _)| std::cmp::Reverse(*prefix));
+
+        Ok(NodeDirectory {
+            aliases: parsed,
+            inferred: inferred.iter().map(|n| (n.addr, n.clone())).collect(),
+        })
+    }
+
+    fn alias_for(&self, addr: IpAddr) -> Option<&NodeAlias> {
+        self.aliases.iter()
+            .find(|(net, prefix, _)| in_subnet(*net, *prefix, addr))
+            .map(|(_, _, alias)| alias)
+    }
+
+    /// Everything known about `addr`, or None if it is neither aliased nor inferred
+    pub fn node(&self, addr: IpAddr) -> Option<NetworkNode> {
+        let alias = self.alias_for(addr);
+        let inferred = self.inferred.get(&addr);
+        if alias.is_none() && inferred.is_none_or(|n| n.roles.is_empty() && n.fqdn.is_none()) {
+            return None;
+        }
+
+        let inferred_roles: Vec<String> = inferred
+            .map(|n| n.roles.iter().map(|r| r.to_string()).collect())
+            .unwrap_or_default();
+        let joined = (!inferred_roles.is_empty()).then(|| inferred_roles.join("/"));
+
+        Some(NetworkNode {
+            addr: addr.to_string(),
+            name: alias.map(|a| a.name.clone()).or_else(|| inferred.and_then(|n| n.fqdn.clone())),
+            role: alias.and_then(|a| a.role.clone()).or(joined),
+            inferred_roles,
+            evidence: inferred.map(|n| n.evidence.clone()).unwrap_or_default(),
+            alias: alias.map(|a| a.addr.clone()),
+        })
+    }
+
+    /// Label shown next to an address: its name, else its role
+    pub fn name_of(&self, addr: &str) -> Option<String> {
+        let node = self.node(addr.parse().ok()?)?;
+        node.name.or(node.role)
+    }
+
+    pub fn name_packets(&self, packets: &mut [PacketSummary]) {
+        let mut names: HashMap<String, Option<String>> = HashMap::new();
+
+        for pkt in packets.iter_mut() {
+            pkt.src_name = names.entry(pkt.src_ip.clone())
+                .or_insert_with(|| self.name_of(&pkt.src_ip)).clone();
+            pkt.dst_name = names.entry(pkt.dst_ip.clone())
+                .or_insert_with(|| self.name_of(&pkt.dst_ip)).clone();
+        }
+    }
+
+    /// Known nodes among the addresses in `packets` and those only seen inside IEs
+    pub fn nodes(&self, packets: &[PacketSummary]) -> Vec<NetworkNode> {
+        let mut addrs: BTreeSet<IpAddr> = self.inferred.keys().copied().collect();
+        for pkt in packets {
+            addrs.extend(pkt.src_ip.parse::<IpAddr>().ok());
+            addrs.extend(pkt.dst_ip.parse::<IpAddr>().ok());
+        }
+
+        addrs.into_iter().filter_map(|addr| self.node(addr)).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dissect::{Depth, registry::*};
+    use crate::ip::port::*;
+    use crate::l2::link::*;
+    use crate::test_util::*;
+
+    const SMF: &str = "10.0.0.1";
+    const UPF: &str = "10.0.0.2";
+
+    fn alias(addr: &str, name: &str, role: Option<&str>) -> NodeAlias
+    {
+        NodeAlias { addr: addr.to_string(), name: name.to_string(), role: role.map(str::to_string) }
+    }
+
+    // PFCP session message with a Node ID IE, from 10.0.0.1 (or back to it when `reply`)
+    fn pfcp(msg_type: u8, node_id: &[u8], reply: bool) -> Vec<u8>
+    {
+        let mut ie = PFCP_IE_NODE_ID.to_be_bytes().to_vec();
+        ie.extend((node_id.len() as u16).to_be_bytes());
+        ie.extend(node_id);
+
+        let mut msg = vec![0x21, msg_type];
+        msg.extend((12 + ie.len() as u16).to_be_bytes());
+        msg.extend([0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 7, 0]);
+        msg.extend(ie);
+
+        let segment = udp(L4_PORT_PFCP, L4_PORT_PFCP, &msg);
+        match reply {
+            true => ipv4_reply(PROTO_TYPE_UDP as u8, &segment),
+            false => ipv4(PROTO_TYPE_UDP as u8, &segment),
+        }
+    }
+
+    fn infer(packets: &[Vec<u8>]) -> Vec<InferredNode>
+    {
+        let mut inference = NodeInference::new();
+        for (i, packet) in packets.iter().enumerate() {
+            let ctx = dissect_frame(default_table(), LINKTYPE_RAW, packet, Depth::Flow);
+            inference.observe(i + 1, &ctx);
+        }
+        inference.finish()
+    }
+
+    #[test]
+    fn pfcp_direction_gives_smf_and_upf() {
+        let fqdn = [&[2, 3][..], b"smf", &[7], b"example"].concat();
+        let inferred = infer(&[
+            pfcp(PFCP_SESSION_ESTABLISHMENT_REQUEST, &fqdn, false),
+            pfcp(PFCP_SESSION_ESTABLISHMENT_RESPONSE, &[0, 10, 0, 0, 2], true),
+        ]);
+        let nodes = NodeDirectory::new(&[], &inferred).unwrap();
+
+        let smf = nodes.node(SMF.parse().unwrap()).unwrap();
+        assert_eq!(smf.role.as_deref(), Some("SMF"));
+        // the FQDN in the request's Node ID is the sender's
+        assert_eq!(smf.name.as_deref(), Some("smf.example"));
+        assert_eq!(smf.evidence, ["SMF: sent PFCP message type 50 in #1"]);
+
+        let upf = nodes.node(UPF.parse().unwrap()).unwrap();
+        assert_eq!((upf.role.as_deref(), upf.name), (Some("UPF"), None));
+        assert_eq!(nodes.name_of(UPF).as_deref(), Some("UPF"));
+    }
+
+    #[test]
+    fn longest_alias_prefix_wins() {
+        let aliases = [
+            alias("10.0.0.0/8", "core", None),
+            alias(UPF, "upf-1", None),
+            alias("10.0.0.0/24", "lab", None),
+        ];
+        let nodes = NodeDirectory::new(&aliases, &[]).unwrap();
+
+        assert_eq!(nodes.name_of(UPF).as_deref(), Some("upf-1"));
+        assert_eq!(nodes.name_of("10.0.0.9").as_deref(), Some("lab"));
+        assert_eq!(nodes.name_of("10.9.9.9").as_deref(), Some("core"));
+        assert_eq!(nodes.name_of("192.168.0.1"), None);
+        assert_eq!(nodes.node("10.0.0.9".parse().unwrap()).unwrap().alias.as_deref(), Some("10.0.0.0/24"));
+    }
+
+    #[test]
+    fn alias_beats_inferred_roles() {
+        let inferred = infer(&[pfcp(PFCP_SESSION_ESTABLISHMENT_REQUEST, &[0, 10, 0, 0, 1], false)]);
+        let aliases = [alias(SMF, "pgw-c-1", Some("PGW-C"))];
+        let nodes = NodeDirectory::new(&aliases, &inferred).unwrap();
+
+        let node = nodes.node(SMF.parse().unwrap()).unwrap();
+        assert_eq!((node.name.as_deref(), node.role.as_deref()), (Some("pgw-c-1"), Some("PGW-C")));
+        assert_eq!(node.inferred_roles, ["SMF"]);
+    }
+
+    #[test]
+    fn subnets() {
+        assert_eq!(parse_subnet("10.0.0.0/8"), Ok(("10.0.0.0".parse().unwrap(), 8)));
+        assert_eq!(parse_subnet(" 2001:db8::1 "), Ok(("2001:db8::1".parse().unwrap(), 128)));
+        assert!(parse_subnet("10.0.0.0/33").is_err());
+        assert!(parse_subnet("2001:db8::/129").is_err());
+        assert!(parse_subnet("10.0.0.0/").is_err());
+        assert!(parse_subnet("mme-1").is_err());
+
+        let any4 = "0.0.0.0".parse().unwrap();
+        let any6 = "::".parse().unwrap();
+        assert!(in_subnet(any4, 0, "192.168.1.1".parse().unwrap()));
+        assert!(in_subnet(any6, 0, "2001:db8::1".parse().unwrap()));
+        assert!(!in_subnet(any4, 0, "2001:db8::1".parse().unwrap()));
+        assert!(in_subnet("10.0.0.0".parse().unwrap(), 31, "10.0.0.1".parse().unwrap()));
+        assert!(!in_subnet("10.0.0.0".parse().unwrap(), 32, "10.0.0.1".parse().unwrap()));
+    }
+}
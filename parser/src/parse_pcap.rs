@@ -1,222 +1,220 @@
-use std::process;
-use std::path::{Path, PathBuf};
-use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
-use pcap::{Capture, Packet};
-
-use crate::ip::{self, ipv4::*, ipv6::*, port::{self, *}};
-use crate::l4::{tcp::*, udp::*, icmp::*};
-use crate::gtp::{gtp::*, gtp_ie::*};
-use crate::pfcp::{pfcp::*, pfcp_ie::*};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::capture::{index::*, reader::*};
+
+use crate::l2::link::*;
+use crate::dissect::{*, registry::*};
+use crate::ip::reassembly::*;
+use crate::l4::{tcp_stream::*, sctp_stream::*};
 use crate::types::*;
+use crate::time_format::*;
+use crate::nodes::*;
+use crate::conversations::*;
 
-const NEXT_HDR_IPV4: usize = 0x0800;
-const NEXT_HDR_IPV6: usize = 0x86dd;
+// flows remembered for matching ICMP errors; the oldest is forgotten first
+const MAX_FLOWS: usize = 16384;
 
 
-fn format_timestamp(packet: &Packet) -> String
-{
-    let sec = packet.header.ts.tv_sec as i64;
-    let usec = packet.header.ts.tv_usec as u32; // microseconds
+// last packet seen on each flow, with and without its GTPv2-C/PFCP
+// sequence number, for ICMP errors that quote one
+struct FlowHistory {
+    last: HashMap<(FlowTuple, Option<u32>), usize>,
+    order: VecDeque<(FlowTuple, Option<u32>)>,
+}
 
-    // Create naive datetime from seconds + microseconds
-    let naive = NaiveDateTime::from_timestamp_opt(sec, usec * 1000)
-        .unwrap_or_else(|| NaiveDateTime::from_timestamp_opt(sec, 0).unwrap());
+impl FlowHistory {
+    fn new() -> Self
+    {
+        FlowHistory {
+            last: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
 
-    let dt: DateTime<Local> = Local.from_local_datetime(&naive).unwrap();
+    fn get(&self, flow: &FlowTuple, transaction: Option<u32>) -> Option<usize>
+    {
+        transaction
+            .and_then(|_| self.last.get(&(flow.clone(), transaction)))
+            .or_else(|| self.last.get(&(flow.clone(), None)))
+            .copied()
+    }
 
-    // dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
-    dt.format("%H:%M:%S%.3f").to_string()
+    fn insert(&mut self, flow: &FlowTuple, transaction: Option<u32>, idx: usize)
+    {
+        let key = (flow.clone(), transaction);
+        if !self.last.contains_key(&key) {
+            if self.order.len() >= MAX_FLOWS
+                && let Some(oldest) = self.order.pop_front() {
+                self.last.remove(&oldest);
+            }
+            self.order.push_back(key.clone());
+        }
+        self.last.insert(key, idx);
+    }
 }
 
 
-fn print_timestamp(idx:usize, packet: &Packet)
-    -> String
+fn frame_info(record: &CaptureRecord) -> FrameInfo
 {
-    let ts = format_timestamp(packet);
-    format!( "{}",ts).to_string()
+    FrameInfo {
+        interface_id: record.interface_id,
+        interface_name: record.interface_name.clone(),
+        link_type: record.link_type,
+        link_type_str: link_type_to_str(record.link_type).unwrap_or_default(),
+        cap_len: record.data.len(),
+        orig_len: record.orig_len,
+        flags: record.flags,
+        drop_count: record.drop_count,
+        comments: record.comments.clone(),
+    }
 }
 
 
-pub fn parse_ethernet(data: &[u8]) -> usize
+// decode packet `id`, together with the fragments before it if it completed a datagram
+fn dissect_one<R: Read + Seek>(file: &mut R, index: &CaptureIndex, table: &Arc<DissectorTable>,
+    id: usize, depth: Depth)
+-> Result<(CaptureRecord, Dissection, Option<Reassembled>), String>
 {
-    // let mut ethertype_str = String::from("N/A");
-    let mut offset = 0;
+    let packet = index.read_record(file, id)?;
 
-    let src_mac = format!("Src Mac: {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x} ",
-    data[offset+0], data[offset+1], data[offset+2],
-    data[offset+3], data[offset+4], data[offset+5]);
+    let mut frags = Reassembler::new();
+    for &frag_id in index.reassembled.get(&id).into_iter().flatten().filter(|&&f| f != id) {
+        let frag = index.read_record(file, frag_id)?;
+        dissect_frame_reassembled(table, &mut frags, frag_id,
+            frag.link_type, &frag.data, Depth::Summary);
+    }
+    let (dissection, datagram) = dissect_frame_reassembled(table, &mut frags, id,
+        packet.link_type, &packet.data, depth);
 
-    offset += 6;
+    Ok((packet, dissection, datagram))
+}
 
-    let dst_mac = format!("Dst Mac: {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x} ",
-    data[offset+0], data[offset+1], data[offset+2],
-    data[offset+3], data[offset+4], data[offset+5]);
 
-    offset += 6;
+// bytes of a message reassembled across packets; `own` is what packet `id` itself holds
+fn gather_chunks<R: Read + Seek>(file: &mut R, index: &CaptureIndex, table: &Arc<DissectorTable>,
+    id: usize, own: &[u8], chunks: &[StreamChunk])
+-> Result<Vec<u8>, String>
+{
+    let mut data = Vec::new();
 
-    let next_type = u16::from_be_bytes([data[offset], data[offset+1]]) as usize;
+    for chunk in chunks {
+        let range = chunk.start..chunk.start + chunk.len;
+        if chunk.packet_id == id {
+            data.extend_from_slice(own.get(range).unwrap_or_default());
+            continue;
+        }
+        let (packet, _, datagram) = dissect_one(file, index, table, chunk.packet_id, Depth::Summary)?;
+        let bytes = datagram.as_ref().map_or(&packet.data, |d| &d.payload);
+        data.extend_from_slice(bytes.get(range).unwrap_or_default());
+    }
 
-    next_type
+    Ok(data)
 }
 
 
-async fn parse_l3( next_type: usize, ip_hdr: &[u8],
-    parsed_packet: &mut PacketDetail)
--> (usize, usize)
+// rows of `ids` other than `idx` point at the packet they were put together in
+fn mark_reassembled_in(packets: &mut [PacketSummary], ids: &[usize], idx: usize)
 {
-    let mut ip= IpInfo::new();
-    let mut ip6= Ip6Info::new();
-
-    match next_type {
-        PROTO_TYPE_IPINIP | NEXT_HDR_IPV4 => {
-            let next_hdr_type = parse_ipv4(
-                ip_hdr, &mut ip);
-            parsed_packet.l3.push(Layer3Info::IP(ip));
-            (next_hdr_type, IP_HDR_LEN)
-        },
-
-        NEXT_HDR_IPV6 => {
-            let next_hdr_type = parse_ipv6(
-                ip_hdr, &mut ip6);
-            parsed_packet.l3.push(Layer3Info::IP6(ip6));
-            (next_hdr_type, IP6_HDR_LEN)
-        },
-
-        _ =>  (0,0)
+    for &id in ids.iter().filter(|&&id| id != idx) {
+        if let Ok(row) = packets.binary_search_by_key(&id, |p| p.id) {
+            packets[row].reassembled_in = Some(idx);
+        }
     }
 }
 
+// ids of the packets `chunks` came from, if more than this one
+fn chunk_packets<'a>(chunks: impl Iterator<Item = &'a StreamChunk>) -> Vec<usize>
+{
+    let mut ids: Vec<usize> = chunks.map(|c| c.packet_id).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.len() > 1 { ids } else { Vec::new() }
+}
+
 
-async fn parse_l4( next_type: usize,
-    data_buf: &[u8],
-    parsed_packet: &mut PacketDetail)
--> (u16, usize)
+/// Decode the given packets (1-based ids) through the index, in the order
+/// given. A packet that completes a fragmented datagram is decoded together
+/// with the fragments before it, and one that completes TCP PDUs or SCTP
+/// messages together with the segments or chunks they came from.
+pub fn dissect_indexed(path: &Path, index: &CaptureIndex, ids: &[usize], depth: Depth)
+-> Result<Vec<(usize, CaptureRecord, Dissection)>, String>
 {
-    match next_type {
-        PROTO_TYPE_TCP =>  {
-            let mut tcp = TcpInfo::new();
-            let result= parse_single_tcp( data_buf , &mut tcp);
+    let file = File::open(path)
+        .map_err(|e| format!("Failed to open pcap file {}: {}", path.to_string_lossy(), e))?;
+    let mut file = BufReader::new(file);
+    let mut table = default_table().with_decode_as(&index.decode_as)?;
+    table.set_tcp_streams(true);
+    let table = &Arc::new(table);
 
-            parsed_packet.l4 = Layer4Info::TCP(tcp);
-            (result, TCP_HDR_LEN)
-        },
+    let mut result = Vec::with_capacity(ids.len());
+    for &id in ids {
+        let (packet, mut dissection, datagram) = dissect_one(&mut file, index, table, id, depth)?;
 
-        PROTO_TYPE_UDP => {
-            let mut udp = UdpInfo::new();
-            let result = parse_single_udp( data_buf, &mut udp);
+        if let Some(pdus) = index.tcp_pdus.get(&id) {
+            let own = datagram.as_ref().map_or(&packet.data, |d| &d.payload);
 
-            parsed_packet.l4 = Layer4Info::UDP(udp);
-            (result, UDP_HDR_LEN)
-        },
+            let mut data = Vec::with_capacity(pdus.len());
+            for chunks in pdus {
+                data.push(gather_chunks(&mut file, index, table, id, own, chunks)?);
+            }
 
-        PROTO_TYPE_ICMP => {
-            let mut icmp = IcmpInfo::new();
-            let result = parse_single_icmp(data_buf, &mut icmp);
+            let data: Vec<&[u8]> = data.iter().map(|p| &p[..]).collect();
+            let info = pdus.iter().map(|chunks| pdu_info(chunks)).collect();
+            dissect_stream_pdus(table, &mut dissection, &data, info);
+        }
+        if let Some(messages) = index.sctp_messages.get(&id) {
+            let own = datagram.as_ref().map_or(&packet.data, |d| &d.payload);
 
-            parsed_packet.l4 = Layer4Info::ICMP(icmp);
-            (result, ICMP_HDR_LEN)
-        },
+            let mut data = Vec::with_capacity(messages.len());
+            for (msg, chunks) in messages {
+                data.push((msg.ppid, gather_chunks(&mut file, index, table, id, own, chunks)?));
+            }
 
-        _ =>  (0, 0)
+            let data: Vec<(u32, &[u8])> = data.iter().map(|(ppid, m)| (*ppid, &m[..])).collect();
+            let info = messages.iter().map(|(msg, _)| msg.clone()).collect();
+            dissect_sctp_messages(table, &mut dissection, &data, info);
+        }
 
+        result.push((id, packet, dissection));
     }
 
-}
-
-async fn parse_app(port_number: u16,
-    data_buf: &[u8],
-    parsed_packet: &mut PacketDetail)
-{
-    match port_number {
-        L4_PORT_GTPV2 => {
-            let (rest, mut gtpinfo) =
-                parse_gtpc_detail(data_buf)
-                    .map_err( |e| format!("GTP-C parse error: {:?}", e)).unwrap();
-
-            let result = match parse_all_ies(rest) {
-
-                Ok(v) => v,
-                Err(_) => {
-                    // return Err(format!("Parse All failed: {}", e));
-                    Vec::new()
-                }
-            };
-
-            gtpinfo.ies = result;
-            parsed_packet.app = AppLayerInfo::GTP(gtpinfo);
-        },
-
-        L4_PORT_PFCP => {
-            let (rest, mut pfcpinfo) =
-                parse_pfcp_detail(data_buf)
-                    .map_err( |e| format!("PFCP parse error: {:?}", e)).unwrap();
-
-            let result = match parse_all_pfcp_ies(rest) {
-                Ok(v) => v,
-                Err(_) => Vec::new(),
-            };
-            pfcpinfo.ies = result;
-            parsed_packet.app = AppLayerInfo::PFCP(pfcpinfo);
-        },
-
-        _ => {
-        },
-    };
+    Ok(result)
 }
 
 
 pub async fn
-parse_single_packet(path: &PathBuf, id: usize)
+parse_single_packet(path: &Path, index: &CaptureIndex, id: usize)
 -> Result<ParsedDetail, String>
 {
-    let mut offset: usize = 0;
-    let mut cap = Capture::from_file(path)
-        .map_err(|e| format!("Failed to open pcap file {}: {}", path.to_string_lossy(), e))?;
-
-    let mut idx: usize = 1;
-    let mut parsed_packet = PacketDetail::new();
-
-    let packet = loop {
-        match cap.next_packet() {
-            Ok(pkt) => {
-                if idx == id {
-                    break pkt; // 스코프 밖으로 packet 반환
-                }
-                idx += 1;
-            },
-            Err(_) => return Err("Packet not found".to_string()),
-        }
-    };
-
-    // --- Parse Layer 2 Ethernet ---
-    let mut next_type = if packet.data.len() >= MIN_ETH_HDR_LEN {
-        parse_ethernet(&packet.data)
-    } else {
+    // seek straight to the packet through the file's index
+    let (_, packet, dissection) = dissect_indexed(path, index, &[id], Depth::Detail)?
+        .pop()
+        .ok_or("Packet not found".to_string())?;
+    if dissection.layers.is_empty() && dissection.detail.malformed.is_empty() {
         return Err("Layer 2 parsing faile".to_string());
-    };
-    offset += MIN_ETH_HDR_LEN;
-
-    // --- Parse Layer 3 (IPv4, IPinIP or IPv6) ---
-    loop {
-        let (np, l3_hdr_len) =
-            parse_l3(next_type, &packet.data[offset..], &mut parsed_packet).await;
-
-        offset += l3_hdr_len;
-        next_type = np;
-
-        if next_type != PROTO_TYPE_IPINIP {
-            break;
-        }
     }
 
-    // --- Parse Layer 4 ---
-    let (port_number, l4_hdr_len) =
-        parse_l4(next_type, &packet.data[offset..], &mut parsed_packet).await;
-
-    offset += l4_hdr_len;
+    let mut parsed_packet = dissection.detail;
+    parsed_packet.frame = frame_info(&packet);
 
-    // --- Parse Application Layer ---
-    parse_app(port_number, &packet.data[offset..], &mut parsed_packet).await;
+    if let Layer4Info::ICMP(icmp) = &mut parsed_packet.l4
+        && let Some(quoted) = icmp.quoted.as_mut() {
+        quoted.original_packet = index.icmp_errors.get(&id).copied();
+    }
+    if let Layer4Info::TCP(tcp) = &mut parsed_packet.l4 {
+        tcp.analysis = index.tcp_analysis.get(&id).cloned().unwrap_or_default();
+        tcp.stream = index.tcp_streams.get(&id).copied();
+        tcp.handshake = tcp.stream.and_then(|s| index.tcp_handshakes.get(s)).cloned();
+    }
+    if let Layer4Info::Sctp(sctp) = &mut parsed_packet.l4 {
+        sctp.assoc_id = index.sctp_assocs.get(&id).copied();
+        sctp.association = sctp.assoc_id.and_then(|a| index.sctp_associations.get(a)).cloned();
+    }
+    parsed_packet.layers = dissection.layers;
 
     Ok(ParsedDetail {
         id,
@@ -225,139 +223,216 @@ parse_single_packet(path: &PathBuf, id: usize)
 }
 
 
-pub async fn simple_parse_pcap(path: &Path)
--> Result<ParsedResult, String> 
+/// Summary pass over a capture stream: a stored file, an in-memory buffer or
+/// an upload that is still being received.
+pub fn simple_parse_pcap<R: Read>(mut cap: CaptureReader<R>, file: String,
+    time_fmt: &TimeFormat, decode_as: &DecodeAs)
+-> Result<ParsedResult, String>
 {
+    let mut table = default_table().with_decode_as(decode_as)?;
+    table.set_tcp_streams(true);
+    let table = &Arc::new(table);
+
     //read pcap file line by line
-    let mut cap = match Capture::from_file(path) {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("Failed to open pcap file {}: {}", e, path.to_string_lossy());
-            process::exit(1);
-        }
-    };
+    let mut index = cap.new_index();
+    index.decode_as = decode_as.clone();
+    let mut times = TimeTracker::new();
+    let mut frags = Reassembler::new();
+    let mut streams = TcpReassembler::new();
+    let mut assocs = SctpReassembler::new();
+    let mut conversations = ConversationTracker::new();
+    let mut inference = NodeInference::new();
+    let mut last_on_flow = FlowHistory::new();
 
     let mut idx: usize = 1;
     let mut packets: Vec<PacketSummary> = Vec::new();
+    let mut truncated = None;
 
-    while let Ok(packet) = cap.next_packet() {
-
-        let mut hdr_len = 0;
-        let tot_len = packet.len();
-
-        // --- Parse TimeStamp ---
-        let mut parsed_packet : PacketSummary = PacketSummary::new();
-        parsed_packet.id = idx;
-        parsed_packet.ts = print_timestamp(idx, &packet);
-
-        // --- Parse Layer 2 Ethernet ---
-        let mut next_type = 0;
-        if packet.data.len() >= MIN_ETH_HDR_LEN {
-            //get next protocol from Ethernet
-            next_type = parse_ethernet(&packet.data);
+    loop {
+        // a capture cut off mid-write keeps what was read before the cut;
+        // only a file that does not get as far as its first packet fails
+        let packet = match cap.next_record() {
+            Ok(Some(packet)) => packet,
+            Ok(None) => break,
+            Err(e) if idx > 1 => {
+                truncated = Some(format!("{} after packet {}", e, idx - 1));
+                break;
+            },
+            Err(e) => return Err(e),
+        };
+        cap.index_record(&mut index, &packet);
+
+        // --- Decode addresses, ports and protocol ---
+        // GTPv2-C/PFCP IEs are decoded too, for the roles of the nodes
+        let (mut dissection, datagram) = dissect_frame_reassembled(table, &mut frags, idx,
+            packet.link_type, &packet.data, Depth::Flow);
+        let data = datagram.as_ref().map_or(&packet.data, |d| &d.payload);
+        let time_ns = to_ns(packet.ts_sec, packet.ts_nsec);
+        let tcp_segment = dissection.segment.clone();
+        let segment = follow_tcp_stream(table, &mut streams, idx, time_ns, &mut dissection, data);
+        let sctp = follow_sctp(table, &mut assocs, idx, &mut dissection, data);
+        inference.observe(idx, &dissection);
+
+        if let Some(stream) = segment.as_ref().and_then(|s| s.stream) {
+            index.tcp_streams.insert(idx, stream);
+        }
+        let pdus = segment.map(|s| s.pdus).unwrap_or_default();
+        if !pdus.is_empty() {
+            let seg_ids = chunk_packets(pdus.iter().flat_map(|p| &p.chunks));
+            mark_reassembled_in(&mut packets, &seg_ids, idx);
+            dissection.summary.tcp_segments = seg_ids;
+            index.tcp_pdus.insert(idx, pdus.into_iter().map(|p| p.chunks).collect());
+        }
+        if !dissection.summary.tcp_analysis.is_empty() {
+            index.tcp_analysis.insert(idx, dissection.summary.tcp_analysis.clone());
         }
 
-        if next_type != NEXT_HDR_IPV4 && next_type != NEXT_HDR_IPV6 {
-            idx += 1;
-            continue;
+        if let Some(SctpResult { assoc: Some(assoc), messages }) = sctp {
+            index.sctp_assocs.insert(idx, assoc);
+            if !messages.is_empty() {
+                let frag_ids = chunk_packets(messages.iter().flat_map(|m| &m.chunks));
+                mark_reassembled_in(&mut packets, &frag_ids, idx);
+                dissection.summary.sctp_fragments = frag_ids;
+                index.sctp_messages.insert(idx, messages.into_iter()
+                    .map(|m| (message_info(assoc, &m), m.chunks))
+                    .collect());
+            }
         }
 
-        hdr_len += MIN_ETH_HDR_LEN;
+        if let Some(datagram) = datagram {
+            mark_reassembled_in(&mut packets, &datagram.packet_ids, idx);
+            index.reassembled.insert(idx, datagram.packet_ids);
+        }
 
-        // --- Parse Layer 3 ---
-        let next_type= match next_type {
-            //IPv4
-            NEXT_HDR_IPV4 => {
-                parse_ipv4_simple(&packet.data[hdr_len..], &mut parsed_packet)
-            },
+        let mut parsed_packet = dissection.summary;
+        parsed_packet.id = idx;
 
-            //IPv6
-            NEXT_HDR_IPV6 => {
-                parse_ipv6_simple(&packet.data[hdr_len..], &mut parsed_packet)
+        if let Some(quoted) = dissection.quoted_flow {
+            parsed_packet.error_for = last_on_flow.get(&quoted, dissection.transaction);
+            if let Some(original) = parsed_packet.error_for {
+                index.icmp_errors.insert(idx, original);
             }
-            _       => {
-                idx+=1;
-                continue;
-            },
-        };
-
-        if next_type == 0 {
-            idx += 1;
-            continue;
         }
-
-        hdr_len += IP_HDR_LEN;
-
-        // --- Parse Layer 4 ---
-        let (port_number, l4_hdr_len) =
-            match next_type {
-                PROTO_TYPE_TCP   => {
-                    (
-                        parse_tcp_simple ( &packet.data[hdr_len..], &mut parsed_packet),
-                        TCP_HDR_LEN
-                    )
-                },
-
-                PROTO_TYPE_UDP   => {
-                    (
-                        parse_udp_simple ( &packet.data[hdr_len..], &mut parsed_packet),
-                        UDP_HDR_LEN
-                    )
-                },
-
-                PROTO_TYPE_ICMP   => {
-                    (
-                        parse_icmp_simple ( &packet.data[hdr_len..], &mut parsed_packet),
-                        ICMP_HDR_LEN
-                    )
-                },
-
-                _       => {
-                    idx+=1;
-                    packets.push(parsed_packet);
-                    continue;
-                },
-            };
-
-        if port_number == 0 || l4_hdr_len == 0 {
-            idx += 1;
-            packets.push(parsed_packet);
-            continue;
+        else if dissection.flow.src_addr.is_some() {
+            last_on_flow.insert(&dissection.flow, None, idx);
+            if dissection.transaction.is_some() {
+                last_on_flow.insert(&dissection.flow, dissection.transaction, idx);
+            }
         }
 
-        hdr_len += l4_hdr_len;
+        // --- Parse TimeStamp ---
+        parsed_packet.ts = time_fmt.format(packet.ts_sec, packet.ts_nsec);
+        parsed_packet.time = times.next(packet.ts_sec, packet.ts_nsec);
+        conversations.observe(&parsed_packet, &dissection.flow, packet.orig_len, tcp_segment.as_ref());
 
-        parsed_packet.length = tot_len - hdr_len;
-        // --- Parse Application Layer ---
-        match port_number {
-            L4_PORT_GTPV2   => {
-                parsed_packet.protocol = "GTP2-C".to_string();
-                let _ = parse_gtpc (
-                        &packet.data[hdr_len..],
-                        &mut parsed_packet);
-            },
+        idx += 1;
 
-            L4_PORT_PFCP => {
-                parsed_packet.protocol = "PCFP".to_string();
-                let _ = parse_pfcp( &packet.data[hdr_len..],
-                &mut parsed_packet);
-            },
+        // only IP traffic is listed, plus frames too broken to tell
+        if dissection.flow.src_addr.is_none() && !parsed_packet.malformed {
+            continue;
+        }
 
-            _ => {
-            },
-        };
+        // --- Capture Metadata (pcapng) ---
+        parsed_packet.interface_id = packet.interface_id;
+        parsed_packet.interface_name = packet.interface_name.clone();
+        parsed_packet.link_type = packet.link_type;
+        parsed_packet.flags = packet.flags;
+        parsed_packet.comments = packet.comments.clone();
 
-        idx += 1;
         packets.push(parsed_packet);
     }
 
+    index.tcp_handshakes = streams.finish();
+    index.sctp_associations = assocs.finish();
+    index.conversations = conversations.finish();
+    let inferred = inference.finish();
+    let nodes = NodeDirectory::new(&[], &inferred)?;
+    nodes.name_packets(&mut packets);
+
     let packet_len = packets.len();
 
     let result = ParsedResult {
-        file: path.to_string_lossy().to_string(),
+        file,
         total_packets: packet_len,
+        nodes: nodes.nodes(&packets),
         packets : packets,
+        truncated,
+        index,
+        inferred,
     };
 
     Ok (result)
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ip::port::*;
+    use crate::test_util::*;
+
+    fn parse(frames: &[Vec<u8>]) -> ParsedResult
+    {
+        let records: Vec<_> = frames.iter()
+            .enumerate()
+            .map(|(i, f)| (i as u32 + 1, 0, f.as_slice()))
+            .collect();
+        let file = pcap_file(false, false, &records);
+        let time_fmt = TimeFormat::from_query(None, None).unwrap();
+
+        simple_parse_pcap(CaptureReader::from_bytes(&file).unwrap(), "test.pcap".to_string(),
+            &time_fmt, &DecodeAs::default()).unwrap()
+    }
+
+    fn flow(port: u16) -> FlowTuple
+    {
+        FlowTuple { src_port: port, ..FlowTuple::new() }
+    }
+
+    #[test]
+    fn icmp_errors_link_to_the_quoted_packet() {
+        let sent = ipv4(PROTO_TYPE_UDP as u8, &udp(40000, 5000, &[1, 2, 3, 4]));
+        let other = ipv4(PROTO_TYPE_UDP as u8, &udp(40001, 5000, &[5]));
+        // port unreachable, quoting the header and the first 8 payload bytes
+        let unreach = [&[3, 3, 0, 0, 0, 0, 0, 0][..], &sent[..28]].concat();
+
+        let sent6 = ipv6(PROTO_TYPE_UDP as u8, &udp(40000, 5000, &[1, 2, 3, 4]));
+        let unreach6 = [&[1, 4, 0, 0, 0, 0, 0, 0][..], &sent6[..]].concat();
+
+        let parsed = parse(&[
+            eth(0x0800, &sent),
+            eth(0x86dd, &sent6),
+            eth(0x0800, &other),
+            eth(0x0800, &ipv4(PROTO_TYPE_ICMP as u8, &unreach)),
+            eth(0x86dd, &ipv6(PROTO_TYPE_ICMPV6 as u8, &unreach6)),
+        ]);
+
+        let error_for: Vec<_> = parsed.packets.iter().map(|p| p.error_for).collect();
+        assert_eq!(error_for, [None, None, None, Some(1), Some(2)]);
+        assert_eq!(parsed.index.icmp_errors.get(&4), Some(&1));
+        assert_eq!(parsed.index.icmp_errors.get(&5), Some(&2));
+    }
+
+    #[test]
+    fn flow_history_forgets_the_oldest_flow() {
+        let mut history = FlowHistory::new();
+        for port in 0..=MAX_FLOWS as u16 {
+            history.insert(&flow(port), None, port as usize);
+        }
+        // seeing a remembered flow again does not take a new slot
+        history.insert(&flow(MAX_FLOWS as u16), None, 99);
+
+        assert_eq!((history.last.len(), history.order.len()), (MAX_FLOWS, MAX_FLOWS));
+        assert_eq!(history.get(&flow(0), None), None);
+        assert_eq!(history.get(&flow(1), None), Some(1));
+        assert_eq!(history.get(&flow(MAX_FLOWS as u16), None), Some(99));
+
+        // a transaction falls back to the flow without one
+        let newest = flow(MAX_FLOWS as u16);
+        history.insert(&newest, Some(7), 100);
+        assert_eq!(history.get(&newest, Some(7)), Some(100));
+        assert_eq!(history.get(&newest, Some(8)), Some(99));
+        assert_eq!(history.get(&flow(1), None), None);
+        assert_eq!(history.last.len(), MAX_FLOWS);
+    }
+}
@@ -5,7 +5,9 @@ use nom::{
     bytes::complete::take,
 };
 use crate::types::*;
+use crate::dissect::*;
 use crate::pfcp::types::*;
+use crate::pfcp::pfcp_ie::*;
 
 
 #[derive(Debug)]
@@ -92,6 +94,11 @@ fn head_parser<'a>(input: &'a[u8])
     Ok((input, header))
 }
 
+fn pfcp_msg_type_str(msg_type: u8) -> &'static str
+{
+    PFCP_MSG_TYPES.get(msg_type as usize).copied().unwrap_or("Unknown")
+}
+
 pub fn parse_pfcp<'a>(input: &'a[u8], packet: &mut PacketSummary)
     -> IResult<&'a[u8], PfcpHeader>
 {
@@ -99,7 +106,7 @@ pub fn parse_pfcp<'a>(input: &'a[u8], packet: &mut PacketSummary)
     let (rest, head) = head_parser(input)?;
 
     packet.description = format!("{} [{}]",
-        PFCP_MSG_TYPES[head.msg_type as usize], head.msg_type).to_string();
+        pfcp_msg_type_str(head.msg_type), head.msg_type).to_string();
 
     Ok((rest, head))
 }
@@ -117,15 +124,76 @@ pub fn parse_pfcp_detail<'a>(input: &'a[u8])
         s_flag: head.s_flag,
 
         msg_type: head.msg_type,
-        msg_type_str: PFCP_MSG_TYPES[head.msg_type as usize].to_string(),
+        msg_type_str: pfcp_msg_type_str(head.msg_type).to_string(),
         msg_len: head.msg_len,
         seid: head.seid,
         seq: head.seq,
         mp: head.mp,
         ies: Vec::new(),
-        raw: input[..head.msg_len as usize].to_vec(),
+        raw: input[..gtp_msg_end(head.msg_len).min(input.len())].to_vec(),
     };
 
     Ok ((rest, info))
 
-}
\ No newline at end of file
+}
+
+
+pub struct PfcpDissector;
+
+impl Dissector for PfcpDissector {
+    fn name(&self) -> &'static str {
+        "PFCP"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        ctx.summary.protocol = "PFCP".to_string();
+        let (rest, head) = parse_pfcp(data, &mut ctx.summary)
+            .map_err(|e| DecodeError::from_nom(data, e))?;
+        ctx.transaction = Some(head.seq);
+
+        if head.version != 1 {
+            return Err(DecodeError::invalid(0, format!("PFCP version {} is not 1", head.version)));
+        }
+
+        if ctx.wants_app()
+            && let Ok((_, pfcpinfo)) = parse_pfcp_detail(data) {
+            ctx.detail.app = AppLayerInfo::PFCP(pfcpinfo);
+        }
+
+        let msg_end = gtp_msg_end(head.msg_len);
+        need(data, msg_end)?;
+
+        if ctx.wants_app() {
+            let ies_at = data.len() - rest.len();
+            if let AppLayerInfo::PFCP(pfcpinfo) = &mut ctx.detail.app {
+                let ies = rest.get(..msg_end.saturating_sub(ies_at)).unwrap_or_default();
+                parse_all_pfcp_ies(ies, &mut pfcpinfo.ies).map_err(|e| e.shifted(ies_at))?;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Version 1, spare bits clear, a known message type and a length that
+    /// covers the whole datagram (or leaves room for a follow-on message).
+    fn heuristic(&self, data: &[u8]) -> bool {
+        let Some(&[flags, msg_type, len_hi, len_lo]) = data.get(..4) else {
+            return false;
+        };
+        let fo_flag = flags & 0x04 != 0;
+        let s_flag = flags & 0x01 != 0;
+        let msg_end = gtp_msg_end(u16::from_be_bytes([len_hi, len_lo]));
+        let hdr_len = if s_flag { 16 } else { 8 };
+
+        let known = matches!(msg_type, PFCP_HEARTBEAT_REQUEST..=PFCP_SESSION_SET_MODIFCATION_RESPONSE |
+            PFCP_SESSION_ESTABLISHMENT_REQUEST..=PFCP_SESSION_REPORT_RESPONSE);
+        let len_ok = if fo_flag { msg_end <= data.len() } else { msg_end == data.len() };
+
+        flags >> 5 == 1 && flags & 0x18 == 0 && known && msg_end >= hdr_len && len_ok
+    }
+
+    fn pdu_len(&self, data: &[u8]) -> Option<usize> {
+        let len = data.get(2..4)?;
+        Some(gtp_msg_end(u16::from_be_bytes([len[0], len[1]])))
+    }
+}
@@ -9,6 +9,7 @@ use std::convert::TryInto;
 
 use crate::pfcp::types::*;
 use crate::gtp::gtp_ie::*;
+use crate::dissect::DecodeError;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct PfcpIe {
@@ -23,7 +24,7 @@ pub struct PfcpIe {
 pub struct FSeidValue {
     pub v4: bool,
     pub v6: bool,
-    pub seid: u32,
+    pub seid: u64,
     pub ipv4: Option<String>,
     pub ipv6: Option<String>,
 }
@@ -42,39 +43,39 @@ impl FSeidValue {
 fn decode_fseid<T>(input: &[u8])
     -> Result<IeValue<T>, String>
 {
-    if input.is_empty() {
-        return Err("Input is empty".into());
+    // flags(1) + SEID(8)
+    if input.len() < 9 {
+        return Err("F-SEID IE too short for SEID".into());
     }
 
     let mut pos = 0;
 
-    let v4 = input[pos] & 0x80 == 1;
-    let v6 = input[pos] & 0x40 == 1;
+    let v4 = input[pos] & 0x02 != 0;
+    let v6 = input[pos] & 0x01 != 0;
 
     pos += 1;
 
-    let seid = u32::from_be_bytes([
-        input[pos], input[pos+1],
-        input[pos+2], input[pos+2]
-    ]);
+    let seid = u64::from_be_bytes(input[pos..pos+8].try_into()
+        .map_err(|_| "F-SEID IE too short for SEID".to_string())?);
 
-    pos += 4;
+    pos += 8;
 
     let ipv4 = if v4 {
-        let addr = Ipv4Addr::from_octets(
-                input[pos..pos+4].try_into().unwrap());
+        let octets: [u8; 4] = input.get(pos..pos+4)
+            .and_then(|b| b.try_into().ok())
+            .ok_or("F-SEID IE too short for IPv4 address")?;
         pos += 4;
-        Some(addr.to_string())
+        Some(Ipv4Addr::from_octets(octets).to_string())
     }
     else {
         None
     };
 
     let ipv6 = if v6 {
-        let addr = Ipv6Addr::from_octets(
-                input[pos..pos+4].try_into().unwrap());
-        pos += 16;
-        Some(addr.to_string())
+        let octets: [u8; 16] = input.get(pos..pos+16)
+            .and_then(|b| b.try_into().ok())
+            .ok_or("F-SEID IE too short for IPv6 address")?;
+        Some(Ipv6Addr::from_octets(octets).to_string())
     }
     else {
         None
@@ -92,6 +93,44 @@ fn decode_fseid<T>(input: &[u8])
 }
 
 
+/// Node ID (TS 29.244 8.2.38): IPv4, IPv6 or an FQDN in DNS label encoding
+fn decode_node_id<T>(input: &[u8])
+    -> Result<IeValue<T>, String>
+{
+    let (&node_type, value) = input.split_first()
+        .ok_or("Node ID IE is empty")?;
+
+    match node_type & 0x0f {
+        0 => {
+            let octets: [u8; 4] = value.get(..4)
+                .and_then(|b| b.try_into().ok())
+                .ok_or("Node ID IE too short for IPv4 address")?;
+            Ok(IeValue::Ipv4(Ipv4Addr::from_octets(octets).to_string()))
+        },
+        1 => {
+            let octets: [u8; 16] = value.get(..16)
+                .and_then(|b| b.try_into().ok())
+                .ok_or("Node ID IE too short for IPv6 address")?;
+            Ok(IeValue::Ipv6(Ipv6Addr::from_octets(octets).to_string()))
+        },
+        2 => {
+            let mut labels = Vec::new();
+            let mut rest = value;
+            while let Some((&len, tail)) = rest.split_first() {
+                let label = tail.get(..len as usize)
+                    .ok_or("Node ID FQDN label runs past the IE")?;
+                if !label.is_empty() {
+                    labels.push(String::from_utf8_lossy(label).to_string());
+                }
+                rest = &tail[len as usize..];
+            }
+            Ok(IeValue::Utf8String(labels.join(".")))
+        },
+        t => Err(format!("Node ID type {} is unknown", t)),
+    }
+}
+
+
 fn parse_ie(input: &[u8])
     -> IResult<&[u8], PfcpIe>
 {
@@ -133,6 +172,8 @@ fn parse_ie(input: &[u8])
         let ie_value = match ie_type {
             PFCP_IE_F_SEID => 
                 decode_fseid::<PfcpIe>(raw_value).unwrap_or(IeValue::None),
+            PFCP_IE_NODE_ID =>
+                decode_node_id::<PfcpIe>(raw_value).unwrap_or(IeValue::None),
             _ =>
                 match ie_len {
                     1 => IeValue::Uint8(raw_value[0]),
@@ -152,26 +193,28 @@ fn parse_ie(input: &[u8])
     Ok((rest, ie))
 }
 
-pub fn parse_all_pfcp_ies(mut input: &[u8])
-    -> Result<Vec<PfcpIe>, String>
+/// Decodes IEs into `ies` until `input` is used up. On a broken IE the ones
+/// before it are kept and the error offset is relative to `input`.
+pub fn parse_all_pfcp_ies(input: &[u8], ies: &mut Vec<PfcpIe>)
+    -> Result<(), DecodeError>
 {
-    let mut result = Vec::new();
-
-    while !input.is_empty () {
-        match parse_ie(input) {
-            Ok((rest, ie)) => {
-                result.push(ie);
-                input = rest;
-                if input.len() < 4 {
+    let mut rest = input;
+
+    while !rest.is_empty () {
+        match parse_ie(rest) {
+            Ok((next, ie)) => {
+                ies.push(ie);
+                rest = next;
+                if rest.len() < 4 {
                     break;
                 }
             },
 
             Err(e) => {
-                return Err(format!("IE parse error: {}",e));
+                return Err(DecodeError::from_nom(input, e));
             }
         }
     }
 
-    Ok(result)
-}
\ No newline at end of file
+    Ok(())
+}
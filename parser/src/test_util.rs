@@ -0,0 +1,165 @@
+//! Frames and packets built byte by byte, shared by the dissector tests
+
+use crate::capture::pcap::*;
+
+pub const ETH_SRC: [u8; 6] = [0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb];
+pub const ETH_DST: [u8; 6] = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+
+
+/// One's complement sum of 16 bit words, folded and inverted
+pub fn internet_checksum(data: &[u8]) -> u16
+{
+    let mut sum: u32 = data.chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], c.get(1).copied().unwrap_or(0)]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+
+pub fn eth(ethertype: u16, rest: &[u8]) -> Vec<u8>
+{
+    let mut out = [ETH_DST, ETH_SRC].concat();
+    out.extend(ethertype.to_be_bytes());
+    out.extend(rest);
+    out
+}
+
+/// 802.1Q tag after its TPID: TCI, then the ethertype that follows
+pub fn vlan_tag(pcp: u8, dei: bool, vid: u16, next: u16) -> Vec<u8>
+{
+    let tci = ((pcp as u16) << 13) | ((dei as u16) << 12) | vid;
+    let mut out = tci.to_be_bytes().to_vec();
+    out.extend(next.to_be_bytes());
+    out
+}
+
+pub fn mpls_label(label: u32, tc: u8, bottom: bool, ttl: u8) -> Vec<u8>
+{
+    ((label << 12) | ((tc as u32) << 9) | ((bottom as u32) << 8) | ttl as u32).to_be_bytes().to_vec()
+}
+
+
+/// IPv4 header from 10.0.0.1 to 10.0.0.2 with a valid checksum, then
+/// `payload`. `offset` is in bytes.
+pub fn ipv4_fragment(id: u16, offset: u16, more: bool, protocol: u8, payload: &[u8]) -> Vec<u8>
+{
+    let flags_offset = ((more as u16) << 13) | (offset / 8);
+    let mut out = vec![0x45, 0x00];
+    out.extend((20 + payload.len() as u16).to_be_bytes());
+    out.extend(id.to_be_bytes());
+    out.extend(flags_offset.to_be_bytes());
+    out.extend([64, protocol, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+    let sum = internet_checksum(&out);
+    out[10..12].copy_from_slice(&sum.to_be_bytes());
+    out.extend(payload);
+    out
+}
+
+pub fn ipv4(protocol: u8, payload: &[u8]) -> Vec<u8>
+{
+    ipv4_fragment(1, 0, false, protocol, payload)
+}
+
+/// `ipv4()` the other way, from 10.0.0.2 to 10.0.0.1
+pub fn ipv4_reply(protocol: u8, payload: &[u8]) -> Vec<u8>
+{
+    let mut out = ipv4(protocol, payload);
+    let (src, dst) = out[12..20].split_at_mut(4);
+    src.swap_with_slice(dst);
+    out
+}
+
+/// IPv6 header from 2001:db8::1 to 2001:db8::2, then `payload`
+pub fn ipv6(next: u8, payload: &[u8]) -> Vec<u8>
+{
+    let mut out = vec![0x60, 0, 0, 0];
+    out.extend((payload.len() as u16).to_be_bytes());
+    out.extend([next, 64]);
+    out.extend([0x20, 0x01, 0x0d, 0xb8].iter().chain(&[0; 11]).chain(&[1]));
+    out.extend([0x20, 0x01, 0x0d, 0xb8].iter().chain(&[0; 11]).chain(&[2]));
+    out.extend(payload);
+    out
+}
+
+/// IPv6 Fragment header; `offset` is in bytes
+pub fn ipv6_fragment_header(next: u8, offset: u16, more: bool, id: u32) -> Vec<u8>
+{
+    let mut out = vec![next, 0];
+    out.extend((offset | more as u16).to_be_bytes());
+    out.extend(id.to_be_bytes());
+    out
+}
+
+/// Fill the UDP/TCP/ICMPv6 checksum at `field` (offset in the L4 header) of
+/// an `ipv4()`/`ipv6()` packet, pseudo header included.
+pub fn fill_l4_checksum(packet: &mut [u8], field: usize)
+{
+    let (l4_at, mut covered) = match packet[0] >> 4 {
+        4 => (20, packet[12..20].to_vec()),
+        _ => (40, packet[8..40].to_vec()),
+    };
+    let l4_len = (packet.len() - l4_at) as u32;
+    match packet[0] >> 4 {
+        4 => covered.extend([0, packet[9]].iter().chain(&(l4_len as u16).to_be_bytes())),
+        _ => covered.extend(l4_len.to_be_bytes().iter().chain(&[0, 0, 0, packet[6]])),
+    }
+
+    let field = l4_at + field;
+    packet[field..field + 2].fill(0);
+    covered.extend(&packet[l4_at..]);
+    let sum = internet_checksum(&covered);
+    packet[field..field + 2].copy_from_slice(&sum.to_be_bytes());
+}
+
+/// UDP header with a zero (unused) checksum, then `payload`
+pub fn udp(src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8>
+{
+    let mut out = src_port.to_be_bytes().to_vec();
+    out.extend(dst_port.to_be_bytes());
+    out.extend((8 + payload.len() as u16).to_be_bytes());
+    out.extend([0, 0]);
+    out.extend(payload);
+    out
+}
+
+/// TCP header without options and with a zero checksum, then `payload`
+pub fn tcp(src_port: u16, dst_port: u16, seq: u32, ack: u32, flags: u8, payload: &[u8]) -> Vec<u8>
+{
+    let mut out = src_port.to_be_bytes().to_vec();
+    out.extend(dst_port.to_be_bytes());
+    out.extend(seq.to_be_bytes());
+    out.extend(ack.to_be_bytes());
+    out.extend([0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+    out.extend(payload);
+    out
+}
+
+
+/// Ethernet pcap file of `(sec, fraction, frame)` records
+pub fn pcap_file(big_endian: bool, nanosecond: bool, records: &[(u32, u32, &[u8])]) -> Vec<u8>
+{
+    let u32b = |v: u32| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+    let u16b = |v: u16| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+
+    let magic = if nanosecond { PCAP_MAGIC_NSEC } else { PCAP_MAGIC_USEC };
+    let mut out = Vec::new();
+    out.extend(u32b(magic));
+    out.extend(u16b(2));
+    out.extend(u16b(4));
+    out.extend(u32b(0));
+    out.extend(u32b(0));
+    out.extend(u32b(65535));
+    out.extend(u32b(1));
+
+    for &(sec, frac, data) in records {
+        out.extend(u32b(sec));
+        out.extend(u32b(frac));
+        out.extend(u32b(data.len() as u32));
+        out.extend(u32b(data.len() as u32 + 4));
+        out.extend(data);
+    }
+    out
+}
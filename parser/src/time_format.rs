@@ -0,0 +1,170 @@
+use chrono::format::StrftimeItems;
+use chrono::{DateTime, FixedOffset, Local, Utc};
+
+use crate::types::*;
+
+pub const DEFAULT_TS_FORMAT: &str   = "%Y-%m-%d %H:%M:%S%.9f";
+const NSEC_PER_SEC: i64             = 1_000_000_000;
+
+
+#[derive(Debug, Clone)]
+enum TimeZoneOpt {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+/// How absolute packet times are rendered. UTC by default so the output does
+/// not depend on the server the parser runs on.
+#[derive(Debug, Clone)]
+pub struct TimeFormat {
+    tz: TimeZoneOpt,
+    format: String,
+}
+
+impl TimeFormat {
+    pub fn from_query(tz: Option<&str>, format: Option<&str>) -> Result<Self, String> {
+        let tz = match tz.map(|s| s.trim()) {
+            None | Some("") => TimeZoneOpt::Utc,
+            Some(s) if s.eq_ignore_ascii_case("utc") || s == "Z" => TimeZoneOpt::Utc,
+            Some(s) if s.eq_ignore_ascii_case("local") => TimeZoneOpt::Local,
+            Some(s) => {
+                let offset = s.parse::<FixedOffset>()
+                    .map_err(|_| format!("Invalid timezone '{}': use utc, local or +HH:MM", s))?;
+                TimeZoneOpt::Fixed(offset)
+            },
+        };
+
+        let format = match format {
+            None | Some("") => DEFAULT_TS_FORMAT.to_string(),
+            Some(f) => {
+                StrftimeItems::new(f).parse()
+                    .map_err(|_| format!("Invalid timestamp format '{}'", f))?;
+                f.to_string()
+            },
+        };
+
+        Ok(TimeFormat { tz, format })
+    }
+
+    pub fn from_time_query(query: &TimeQuery) -> Result<Self, String> {
+        TimeFormat::from_query(query.tz.as_deref(), query.ts_format.as_deref())
+    }
+
+    pub fn format(&self, sec: i64, nsec: u32) -> String {
+        let Some(utc) = DateTime::<Utc>::from_timestamp(sec, nsec) else {
+            return format!("{}.{:09}", sec, nsec);
+        };
+
+        match &self.tz {
+            TimeZoneOpt::Utc => utc.format(&self.format).to_string(),
+            TimeZoneOpt::Local => utc.with_timezone(&Local).format(&self.format).to_string(),
+            TimeZoneOpt::Fixed(offset) => utc.with_timezone(offset).format(&self.format).to_string(),
+        }
+    }
+}
+
+
+/// Seconds with nanosecond decimals, e.g. "-0.000120000"
+pub fn format_duration_ns(ns: i64) -> String
+{
+    let sign = if ns < 0 { "-" } else { "" };
+    let abs = ns.unsigned_abs();
+
+    format!("{}{}.{:09}", sign, abs / NSEC_PER_SEC as u64, abs % NSEC_PER_SEC as u64)
+}
+
+pub fn to_ns(sec: i64, nsec: u32) -> i64
+{
+    sec.saturating_mul(NSEC_PER_SEC).saturating_add(nsec as i64)
+}
+
+
+/// Fills relative and delta times for packets handed over in order.
+pub struct TimeTracker {
+    first: Option<i64>,
+    prev: Option<i64>,
+}
+
+impl TimeTracker {
+    pub fn new() -> Self {
+        TimeTracker {
+            first: None,
+            prev: None,
+        }
+    }
+
+    /// Relative times are measured from `sec`.`nsec` instead of the first packet seen.
+    pub fn with_base(sec: i64, nsec: u32) -> Self {
+        TimeTracker {
+            first: Some(to_ns(sec, nsec)),
+            prev: None,
+        }
+    }
+
+    pub fn next(&mut self, sec: i64, nsec: u32) -> PacketTime {
+        let now = to_ns(sec, nsec);
+        let first = *self.first.get_or_insert(now);
+        let prev = self.prev.replace(now).unwrap_or(now);
+
+        let relative_ns = now.saturating_sub(first);
+        let delta_ns = now.saturating_sub(prev);
+
+        PacketTime {
+            epoch_sec: sec,
+            epoch_nsec: nsec,
+            relative_ns,
+            delta_ns,
+            relative: format_duration_ns(relative_ns),
+            delta: format_duration_ns(delta_ns),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_query_zones() {
+        let utc = TimeFormat::from_query(Some("utc"), None).unwrap();
+        assert_eq!(utc.format(0, 5), "1970-01-01 00:00:00.000000005");
+
+        let tokyo = TimeFormat::from_query(Some("+09:00"), Some("%H:%M %z")).unwrap();
+        assert_eq!(tokyo.format(0, 0), "09:00 +0900");
+
+        let local = TimeFormat::from_query(Some(" Local "), None).unwrap();
+        assert!(matches!(local.tz, TimeZoneOpt::Local));
+        assert!(matches!(TimeFormat::from_query(None, None).unwrap().tz, TimeZoneOpt::Utc));
+    }
+
+    #[test]
+    fn from_query_rejects_invalid_values() {
+        assert!(TimeFormat::from_query(Some("Asia/Seoul"), None).is_err());
+        assert!(TimeFormat::from_query(Some("+25:00"), None).is_err());
+        assert!(TimeFormat::from_query(None, Some("%Q")).is_err());
+    }
+
+    #[test]
+    fn negative_durations() {
+        assert_eq!(format_duration_ns(-120_000), "-0.000120000");
+        assert_eq!(format_duration_ns(-1_500_000_000), "-1.500000000");
+        assert_eq!(format_duration_ns(0), "0.000000000");
+        assert_eq!(format_duration_ns(i64::MIN), "-9223372036.854775808");
+    }
+
+    #[test]
+    fn tracker_handles_packets_out_of_order_and_far_apart() {
+        let mut times = TimeTracker::new();
+        times.next(10, 0);
+
+        let back = times.next(9, 500_000_000);
+        assert_eq!((back.relative_ns, back.delta_ns), (-500_000_000, -500_000_000));
+        assert_eq!(back.delta, "-0.500000000");
+
+        let mut times = TimeTracker::with_base(i64::MAX, 0);
+        let far = times.next(i64::MIN, 0);
+        assert_eq!(far.relative_ns, i64::MIN);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::types::*;
+use crate::dissect::*;
+use crate::l2::ethernet::*;
+
+// GRE protocol types of the mirrored traffic
+pub const ETHERTYPE_ERSPAN_II: usize    = 0x88be;   // also type I, without a GRE sequence number
+pub const ETHERTYPE_ERSPAN_III: usize   = 0x22eb;
+
+pub const ERSPAN_II_HDR_LEN: usize      = 8;
+pub const ERSPAN_III_HDR_LEN: usize     = 12;
+const ERSPAN_III_SUBHDR_LEN: usize      = 8;        // platform specific, when O is set
+
+const ERSPAN_FT_ETHERNET: u8            = 0;
+const ERSPAN_FT_IP: u8                  = 2;
+
+
+/// Decode an ERSPAN type II or III header. Returns the header length.
+pub fn parse_erspan(erspan_type: u8, data: &[u8], erspan: &mut ErspanInfo)
+-> Result<usize, DecodeError>
+{
+    let hdr_len = if erspan_type == 2 { ERSPAN_II_HDR_LEN } else { ERSPAN_III_HDR_LEN };
+    need(data, hdr_len)?;
+
+    let w0 = u16::from_be_bytes([data[0], data[1]]);
+    let w1 = u16::from_be_bytes([data[2], data[3]]);
+
+    erspan.erspan_type = erspan_type;
+    erspan.version = (w0 >> 12) as u8;
+    erspan.vlan = w0 & 0x0fff;
+    erspan.cos = (w1 >> 13) as u8;
+    erspan.encap = ((w1 >> 11) & 0x3) as u8;
+    erspan.truncated = w1 & 0x0400 != 0;
+    erspan.session_id = w1 & 0x03ff;
+
+    let mut len = hdr_len;
+
+    if erspan_type == 2 {
+        let w = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        erspan.index = Some(w & 0x000f_ffff);
+    }
+    else {
+        erspan.timestamp = Some(u32::from_be_bytes([data[4], data[5], data[6], data[7]]));
+        erspan.sgt = Some(u16::from_be_bytes([data[8], data[9]]));
+
+        let w = u16::from_be_bytes([data[10], data[11]]);
+        erspan.frame_type = Some(((w >> 10) & 0x1f) as u8);
+        erspan.hw_id = Some(((w >> 4) & 0x3f) as u8);
+        erspan.direction = Some(((w >> 3) & 0x1) as u8);
+        erspan.granularity = Some(((w >> 1) & 0x3) as u8);
+
+        if w & 0x1 != 0 {
+            len += ERSPAN_III_SUBHDR_LEN;
+            need(data, len)?;
+        }
+    }
+
+    erspan.raw.extend_from_slice(&data[..len]);
+
+    Ok(len)
+}
+
+
+pub struct ErspanDissector {
+    pub erspan_type: u8,
+}
+
+impl Dissector for ErspanDissector {
+    fn name(&self) -> &'static str {
+        "ERSPAN"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        let mut erspan = ErspanInfo::new();
+        let parsed = parse_erspan(self.erspan_type, data, &mut erspan);
+
+        let frame_type = erspan.frame_type.unwrap_or(ERSPAN_FT_ETHERNET);
+
+        ctx.summary.protocol = "ERSPAN".to_string();
+        ctx.summary.description = format!("ERSPAN type {}, session {}", self.erspan_type, erspan.session_id);
+
+        if ctx.wants_detail() {
+            ctx.detail.l3.push(Layer3Info::Erspan(erspan));
+        }
+
+        let hdr_len = parsed?;
+
+        let next = match frame_type {
+            ERSPAN_FT_ETHERNET => ETHERTYPE_TEB,
+            // mirrored without its link layer
+            ERSPAN_FT_IP => match data.get(hdr_len).map(|b| b >> 4) {
+                Some(6) => NEXT_HDR_IPV6,
+                _ => NEXT_HDR_IPV4,
+            },
+            _ => return Err(DecodeError::invalid(10, format!("unknown ERSPAN frame type {}", frame_type))),
+        };
+
+        Ok(Some(NextLayer {
+            key: LayerKey::Ethertype(next as u16),
+            hdr_len,
+            len: None,
+        }))
+    }
+}
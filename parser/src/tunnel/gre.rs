@@ -0,0 +1,113 @@
+use crate::types::*;
+use crate::dissect::*;
+use crate::l2::ethernet::*;
+use crate::tunnel::erspan::*;
+
+pub const GRE_HDR_LEN: usize        = 4;
+
+const GRE_FLAG_CHECKSUM: u16        = 0x8000;
+const GRE_FLAG_ROUTING: u16         = 0x4000;
+const GRE_FLAG_KEY: u16             = 0x2000;
+const GRE_FLAG_SEQ: u16             = 0x1000;
+const GRE_FLAG_ACK: u16             = 0x0080;   // version 1
+const GRE_VERSION_MASK: u16         = 0x0007;
+
+pub const ETHERTYPE_PPP: u16        = 0x880b;
+
+
+pub fn gre_protocol_to_str(protocol: u16) -> String
+{
+    match protocol as usize {
+        NEXT_HDR_IPV4       => "IPv4".to_string(),
+        NEXT_HDR_IPV6       => "IPv6".to_string(),
+        ETHERTYPE_TEB       => "Transparent Ethernet Bridging".to_string(),
+        ETHERTYPE_ERSPAN_II => "ERSPAN".to_string(),
+        ETHERTYPE_ERSPAN_III => "ERSPAN Type III".to_string(),
+        _ if protocol == ETHERTYPE_PPP => "PPP".to_string(),
+        _ => format!("0x{:04x}", protocol),
+    }
+}
+
+
+/// Decode the GRE header and its optional fields. Returns the header length.
+pub fn parse_gre(data: &[u8], gre: &mut GreInfo) -> Result<usize, DecodeError>
+{
+    need(data, GRE_HDR_LEN)?;
+
+    let flags = u16::from_be_bytes([data[0], data[1]]);
+    let version = (flags & GRE_VERSION_MASK) as u8;
+
+    gre.flags = flags;
+    gre.version = version;
+    gre.protocol = u16::from_be_bytes([data[2], data[3]]);
+    gre.protocol_str = gre_protocol_to_str(gre.protocol);
+
+    if version > 1 {
+        return Err(DecodeError::invalid(1, format!("unknown GRE version {}", version)));
+    }
+    // source routing (RFC 1701) is deprecated and not decoded
+    if flags & GRE_FLAG_ROUTING != 0 {
+        return Err(DecodeError::invalid(0, "GRE source routing is not supported"));
+    }
+
+    // every optional field takes one 32 bit word
+    let mut pos = GRE_HDR_LEN;
+    let mut word = || -> Result<u32, DecodeError> {
+        need(data, pos + 4)?;
+        let v = u32::from_be_bytes([data[pos], data[pos+1], data[pos+2], data[pos+3]]);
+        pos += 4;
+        Ok(v)
+    };
+
+    if flags & GRE_FLAG_CHECKSUM != 0 {
+        // followed by 2 reserved bytes
+        gre.checksum = Some((word()? >> 16) as u16);
+    }
+    if flags & GRE_FLAG_KEY != 0 {
+        gre.key = Some(word()?);
+    }
+    if flags & GRE_FLAG_SEQ != 0 {
+        gre.seq = Some(word()?);
+    }
+    if version == 1 && flags & GRE_FLAG_ACK != 0 {
+        gre.ack = Some(word()?);
+    }
+
+    gre.raw.extend_from_slice(&data[..pos]);
+
+    Ok(pos)
+}
+
+
+pub struct GreDissector;
+
+impl Dissector for GreDissector {
+    fn name(&self) -> &'static str {
+        "GRE"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        let mut gre = GreInfo::new();
+        let parsed = parse_gre(data, &mut gre);
+
+        let protocol = gre.protocol;
+        // ERSPAN type I is sent without a sequence number and has no header
+        let erspan_type1 = protocol as usize == ETHERTYPE_ERSPAN_II && gre.seq.is_none();
+
+        ctx.summary.protocol = "GRE".to_string();
+        ctx.summary.description = format!("Encapsulated {}", gre.protocol_str);
+
+        if ctx.wants_detail() {
+            ctx.detail.l3.push(Layer3Info::Gre(gre));
+        }
+
+        let hdr_len = parsed?;
+        let next = if erspan_type1 { ETHERTYPE_TEB as u16 } else { protocol };
+
+        Ok(Some(NextLayer {
+            key: LayerKey::Ethertype(next),
+            hdr_len,
+            len: None,
+        }))
+    }
+}
@@ -0,0 +1,6 @@
+pub mod erspan;
+pub mod gre;
+pub mod vxlan;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,124 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::types::*;
+use crate::dissect::*;
+use crate::dissect::registry::*;
+use crate::ip::port::*;
+use crate::l2::link::*;
+use crate::test_util::*;
+
+
+const PROTO_UDP: u8 = PROTO_TYPE_UDP as u8;
+const PROTO_GRE: u8 = PROTO_TYPE_GRE as u8;
+
+// UDP from 10.0.0.1:40000 to 10.0.0.2:5000, the flow every tunnel carries
+fn inner() -> Vec<u8>
+{
+    ipv4(PROTO_UDP, &udp(40000, 5000, &[1, 2, 3, 4]))
+}
+
+fn dissect(frame: &[u8]) -> Dissection
+{
+    dissect_frame(default_table(), LINKTYPE_RAW, frame, Depth::Detail)
+}
+
+// the tunnel is decoded through to the inner flow, over an IPv6 outer header
+fn assert_inner_flow(ctx: &Dissection)
+{
+    assert_eq!(ctx.flow.src_addr, Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))));
+    assert_eq!(ctx.flow.dst_addr, Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))));
+    assert_eq!((ctx.flow.protocol, ctx.flow.src_port, ctx.flow.dst_port), (PROTO_UDP, 40000, 5000));
+    assert!(!ctx.summary.malformed, "{:?}", ctx.detail.malformed);
+}
+
+fn assert_malformed(ctx: &Dissection, protocol: &str)
+{
+    assert!(ctx.summary.malformed);
+    assert!(ctx.detail.malformed.iter().any(|m| m.protocol == protocol), "{:?}", ctx.detail.malformed);
+}
+
+
+#[test]
+fn gre_with_key_and_sequence_number() {
+    let mut gre = vec![0x30, 0x00, 0x08, 0x00];
+    gre.extend(0x0102_0304u32.to_be_bytes());
+    gre.extend(7u32.to_be_bytes());
+    let frame = ipv6(PROTO_GRE, &[gre.clone(), inner()].concat());
+
+    let ctx = dissect(&frame);
+    assert_inner_flow(&ctx);
+    let Some(Layer3Info::Gre(info)) = ctx.detail.l3.get(1) else {
+        panic!("expected GRE, got {:?}", ctx.detail.l3);
+    };
+    assert_eq!((info.key, info.seq, info.protocol_str.as_str()), (Some(0x0102_0304), Some(7), "IPv4"));
+
+    // the sequence number is cut off
+    let ctx = dissect(&frame[..40 + 10]);
+    assert_malformed(&ctx, "GRE");
+}
+
+#[test]
+fn erspan_type_ii() {
+    let gre = [0x10, 0x00, 0x88, 0xbe, 0, 0, 0, 1];
+    // version 1, VLAN 100, session 42, index 5
+    let erspan = [0x10, 0x64, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x05];
+    let frame = ipv6(PROTO_GRE, &[&gre[..], &erspan, &eth(0x0800, &inner())].concat());
+
+    let ctx = dissect(&frame);
+    assert_inner_flow(&ctx);
+    let Some(Layer3Info::Erspan(info)) = ctx.detail.l3.get(2) else {
+        panic!("expected ERSPAN, got {:?}", ctx.detail.l3);
+    };
+    assert_eq!((info.erspan_type, info.vlan, info.session_id, info.index), (2, 100, 42, Some(5)));
+
+    let ctx = dissect(&frame[..40 + gre.len() + 5]);
+    assert_malformed(&ctx, "ERSPAN");
+}
+
+#[test]
+fn erspan_type_iii() {
+    let gre = [0x10, 0x00, 0x22, 0xeb, 0, 0, 0, 1];
+    // version 2, session 7, timestamp, SGT 9, Ethernet frame, egress
+    let erspan = [0x20, 0x00, 0x00, 0x07, 0, 0, 0x10, 0, 0x00, 0x09, 0x00, 0x08];
+    let frame = ipv6(PROTO_GRE, &[&gre[..], &erspan, &eth(0x0800, &inner())].concat());
+
+    let ctx = dissect(&frame);
+    assert_inner_flow(&ctx);
+    let Some(Layer3Info::Erspan(info)) = ctx.detail.l3.get(2) else {
+        panic!("expected ERSPAN, got {:?}", ctx.detail.l3);
+    };
+    assert_eq!((info.erspan_type, info.session_id, info.sgt), (3, 7, Some(9)));
+    assert_eq!((info.timestamp, info.frame_type, info.direction), (Some(0x1000), Some(0), Some(1)));
+
+    let ctx = dissect(&frame[..40 + gre.len() + 10]);
+    assert_malformed(&ctx, "ERSPAN");
+}
+
+#[test]
+fn vxlan() {
+    let vxlan = [0x08, 0, 0, 0, 0x00, 0x01, 0x2c, 0];
+    let frame = ipv6(PROTO_UDP, &udp(50000, L4_PORT_VXLAN, &[&vxlan[..], &eth(0x0800, &inner())].concat()));
+
+    let ctx = dissect(&frame);
+    assert_inner_flow(&ctx);
+    let Some(Layer3Info::Vxlan(info)) = ctx.detail.l3.get(1) else {
+        panic!("expected VXLAN, got {:?}", ctx.detail.l3);
+    };
+    assert_eq!((info.vni, info.src_port, info.dst_port), (300, 50000, L4_PORT_VXLAN));
+
+    let ctx = dissect(&frame[..40 + 8 + 6]);
+    assert_malformed(&ctx, "VXLAN");
+}
+
+#[test]
+fn ip_in_ip() {
+    let frame = ipv6(PROTO_TYPE_IPINIP as u8, &inner());
+
+    let ctx = dissect(&frame);
+    assert_inner_flow(&ctx);
+    assert!(matches!(ctx.detail.l3[..], [Layer3Info::IP6(_), Layer3Info::IP(_)]));
+
+    // the inner IPv4 header is cut short
+    let ctx = dissect(&frame[..40 + 12]);
+    assert_malformed(&ctx, "IPv4");
+}
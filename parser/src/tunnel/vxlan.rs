@@ -0,0 +1,46 @@
+use crate::types::*;
+use crate::dissect::*;
+use crate::l2::ethernet::*;
+
+pub const VXLAN_HDR_LEN: usize      = 8;
+const VXLAN_FLAG_VNI: u8            = 0x08;
+
+
+pub struct VxlanDissector;
+
+impl Dissector for VxlanDissector {
+    fn name(&self) -> &'static str {
+        "VXLAN"
+    }
+
+    fn dissect(&self, data: &[u8], ctx: &mut Dissection) -> Result<Option<NextLayer>, DecodeError> {
+        need(data, VXLAN_HDR_LEN)?;
+
+        let mut vxlan = VxlanInfo::new();
+        vxlan.flags = data[0];
+        vxlan.vni = u32::from_be_bytes([0, data[4], data[5], data[6]]);
+        // the inner UDP/TCP header replaces the outer ports in the flow
+        vxlan.src_port = ctx.flow.src_port;
+        vxlan.dst_port = ctx.flow.dst_port;
+        vxlan.raw.extend_from_slice(&data[..VXLAN_HDR_LEN]);
+
+        let vni_valid = vxlan.flags & VXLAN_FLAG_VNI != 0;
+
+        ctx.summary.protocol = "VXLAN".to_string();
+        ctx.summary.description = format!("VXLAN, VNI {}", vxlan.vni);
+
+        if ctx.wants_detail() {
+            ctx.detail.l3.push(Layer3Info::Vxlan(vxlan));
+        }
+
+        if !vni_valid {
+            return Err(DecodeError::invalid(0, "VXLAN I flag is not set"));
+        }
+
+        Ok(Some(NextLayer {
+            key: LayerKey::Ethertype(ETHERTYPE_TEB as u16),
+            hdr_len: VXLAN_HDR_LEN,
+            len: None,
+        }))
+    }
+}
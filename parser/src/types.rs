@@ -5,6 +5,9 @@ use std::time::Instant;
 
 use crate::gtp::gtp_ie::*;
 use crate::pfcp::pfcp_ie::*;
+use crate::capture::index::*;
+use crate::dissect::LayerSpan;
+use crate::nodes::InferredNode;
 
 pub type Cache = Arc<RwLock<HashMap<String, FileInfo>>>;
 
@@ -30,6 +33,107 @@ pub struct PacketQuery {
     pub id: usize, // 프론트엔드에서 보내는 id
 }
 
+#[derive(serde::Deserialize)]
+pub struct FileQuery {
+    pub file_id: u64,
+}
+
+/// "Decode As" entry: UDP/TCP traffic on `port` (up to `port_end`, inclusive)
+/// is decoded as `protocol`, whatever the well-known ports say.
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+pub struct DecodeAsRule {
+    pub transport: String,      // "udp" or "tcp"
+    pub port: u16,
+    pub port_end: Option<u16>,
+    pub protocol: String,       // dissector name, e.g. "GTPv2-C", "PFCP"
+}
+
+/// Per-file decoding preferences, applied to the summary, the packet detail
+/// and the call flow alike.
+#[derive(Debug, Clone, Default, Serialize, serde::Deserialize)]
+pub struct DecodeAs {
+    #[serde(default)]
+    pub rules: Vec<DecodeAsRule>,
+    #[serde(default)]
+    pub heuristics: bool,       // try GTPv2-C/PFCP on UDP ports nothing is registered for
+}
+
+/// Operator supplied name for an address or a subnet
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+pub struct NodeAlias {
+    pub addr: String,           // "10.10.1.71" or "10.10.1.0/24"
+    pub name: String,
+    #[serde(default)]
+    pub role: Option<String>,   // MME, SGW-C, PGW-C, SMF, UPF, ...
+}
+
+/// An address seen in the capture, with what is known about it
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkNode {
+    pub addr: String,
+    pub name: Option<String>,       // alias name, else the PFCP Node ID FQDN
+    pub role: Option<String>,       // alias role, else the inferred roles
+    pub inferred_roles: Vec<String>,
+    pub evidence: Vec<String>,      // where each role was inferred from
+    pub alias: Option<String>,      // address or subnet of the matching alias
+}
+
+/// Conversation list options, e.g. `?file_id=1&protocol=tcp&addr=10.0.0.0/8&sort=bytes&order=desc`
+#[derive(serde::Deserialize)]
+pub struct ConversationQuery {
+    pub file_id: u64,
+    pub protocol: Option<String>,   // "tcp", "udp" or "sctp"
+    pub addr: Option<String>,       // address or subnet of either endpoint
+    pub port: Option<u16>,          // port of either endpoint
+    pub sort: Option<String>,       // see `sort_conversations`; capture order by default
+    pub order: Option<String>,      // "asc" (default) or "desc"
+}
+
+/// Traffic between two TCP/UDP/SCTP endpoints. A is the sender of the first packet seen.
+#[derive(Debug, Clone, Serialize)]
+pub struct Conversation {
+    pub id: usize,
+    pub protocol: String,
+    pub addr_a: String,
+    pub port_a: u16,
+    pub name_a: Option<String>,     // node alias or inferred role
+    pub addr_b: String,
+    pub port_b: u16,
+    pub name_b: Option<String>,
+    pub packets_a_to_b: usize,
+    pub bytes_a_to_b: usize,        // frame lengths on the wire
+    pub packets_b_to_a: usize,
+    pub bytes_b_to_a: usize,
+    pub packets: usize,
+    pub bytes: usize,
+    pub first_packet: usize,
+    pub last_packet: usize,
+    pub first_seen: String,         // absolute, as the packet list shows it
+    pub last_seen: String,
+    pub start_ns: i64,              // since the first packet of the capture
+    pub duration_ns: i64,
+    pub bps_a_to_b: Option<f64>,    // None when all packets share one timestamp
+    pub bps_b_to_a: Option<f64>,
+    pub tcp: Option<TcpConversation>,
+}
+
+/// TCP-only conversation statistics
+#[derive(Debug, Clone, Serialize)]
+pub struct TcpConversation {
+    pub retransmissions: usize,
+    pub rtt_samples: usize,         // segments acknowledged without being sent again
+    pub rtt_min_ns: Option<i64>,
+    pub rtt_avg_ns: Option<i64>,
+    pub rtt_max_ns: Option<i64>,
+}
+
+/// Timestamp display preference, e.g. `?tz=+09:00&ts_format=%H:%M:%S%.6f`
+#[derive(serde::Deserialize, Default)]
+pub struct TimeQuery {
+    pub tz: Option<String>,         // "utc" (default), "local" or "+HH:MM"
+    pub ts_format: Option<String>,  // chrono strftime
+}
+
 #[derive(serde::Serialize)]
 pub struct ParsedDetail {
     pub id: usize,
@@ -40,12 +144,42 @@ pub struct ParsedResult {
     pub file: String,
     pub total_packets: usize,
     pub packets: Vec<PacketSummary>,
+    pub nodes: Vec<NetworkNode>,
+    pub truncated: Option<String>,  // why reading stopped before the end; the packets read until then are kept
+    #[serde(skip)]
+    pub index: CaptureIndex,
+    #[serde(skip)]
+    pub inferred: Vec<InferredNode>,
+}
+
+/// Packet time at full capture precision
+#[derive(Debug, Clone, Serialize)]
+pub struct PacketTime {
+    pub epoch_sec: i64,
+    pub epoch_nsec: u32,
+    pub relative_ns: i64,   // since the first packet
+    pub delta_ns: i64,      // since the previous packet
+    pub relative: String,   // seconds, 9 decimals
+    pub delta: String,
+}
+impl PacketTime {
+    pub fn new() -> Self {
+        PacketTime {
+            epoch_sec: 0,
+            epoch_nsec: 0,
+            relative_ns: 0,
+            delta_ns: 0,
+            relative: String::new(),
+            delta: String::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize )]
 pub struct PacketSummary {
     pub id: usize,
-    pub ts: String,
+    pub ts: String,         // absolute time in the requested zone/format
+    pub time: PacketTime,
     pub src_ip: String,
     pub dst_ip: String,
     pub l4_type: String,
@@ -54,6 +188,23 @@ pub struct PacketSummary {
     pub protocol: String,
     pub length: usize,
     pub description: String,
+
+    pub interface_id: u32,
+    pub interface_name: String,
+    pub link_type: u16,
+    pub flags: Option<u32>,
+    pub comments: Vec<String>,
+    pub malformed: bool,    // a layer failed to decode, see PacketDetail.malformed
+    pub fragments: Vec<usize>,          // packets reassembled into this one, including itself
+    pub reassembled_in: Option<usize>,  // packet where this fragment's datagram, TCP PDU or SCTP message was completed
+    pub bad_checksum: bool, // an IPv4/UDP/TCP/ICMP checksum did not match
+    pub error_for: Option<usize>,       // packet quoted by this ICMP/ICMPv6 error
+    pub src_name: Option<String>,       // node alias or inferred role of src_ip
+    pub dst_name: Option<String>,
+    pub tcp_analysis: Vec<TcpAnalysis>,
+    pub tcp_segments: Vec<usize>,       // packets whose TCP payload went into the PDUs decoded here
+    pub sctp_fragments: Vec<usize>,     // packets whose DATA chunks went into the messages completed here
+    pub tunnel_teid: Option<u32>,       // GTP-U TEID of the tunnel the packet was carried in
 }
 
 impl PacketSummary{
@@ -61,6 +212,7 @@ impl PacketSummary{
         PacketSummary {
             id : 0,
             ts : String::new(),
+            time : PacketTime::new(),
             src_ip : String::new(),
             dst_ip : String::new(),
             l4_type : String::new(),
@@ -69,9 +221,129 @@ impl PacketSummary{
             protocol : String::new(),
             length: 0,
             description: String::new(),
+            interface_id: 0,
+            interface_name: String::new(),
+            link_type: 0,
+            flags: None,
+            comments: Vec::new(),
+            malformed: false,
+            fragments: Vec::new(),
+            reassembled_in: None,
+            bad_checksum: false,
+            error_for: None,
+            src_name: None,
+            dst_name: None,
+            tcp_analysis: Vec::new(),
+            tcp_segments: Vec::new(),
+            sctp_fragments: Vec::new(),
+            tunnel_teid: None,
+        }
+    }
+}
+
+/// Capture-level metadata of a frame (pcapng interface, flags, comments)
+#[derive(Serialize, Debug, Clone)]
+pub struct FrameInfo {
+    pub interface_id: u32,
+    pub interface_name: String,
+    pub link_type: u16,
+    pub link_type_str: String,
+    pub cap_len: usize,
+    pub orig_len: usize,
+    pub flags: Option<u32>,
+    pub drop_count: Option<u64>,
+    pub comments: Vec<String>,
+}
+impl FrameInfo {
+    pub fn new() -> Self {
+        FrameInfo {
+            interface_id: 0,
+            interface_name: String::new(),
+            link_type: 0,
+            link_type_str: String::new(),
+            cap_len: 0,
+            orig_len: 0,
+            flags: None,
+            drop_count: None,
+            comments: Vec::new(),
+        }
+    }
+}
+#[derive(Serialize, Debug, Clone)]
+pub struct VlanTag {
+    pub tpid: u16,
+    pub pcp: u8,
+    pub dei: bool,
+    pub vid: u16,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MplsLabel {
+    pub label: u32,
+    pub tc: u8,
+    pub s: bool,
+    pub ttl: u8,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Layer2Info {
+    pub link_type: u16,
+    pub src_mac: Option<String>,    // SLL/SLL2 only carry the sender's address
+    pub dst_mac: Option<String>,
+    pub src_vendor: Option<String>, // from the OUI table, see l2::oui
+    pub dst_vendor: Option<String>,
+    pub ethertype: u16,
+    pub ethertype_str: Option<String>,
+    pub frame_len: usize,           // captured bytes of the frame
+    pub vlans: Vec<VlanTag>,
+    pub mpls: Vec<MplsLabel>,
+}
+impl Layer2Info {
+    pub fn new() -> Self {
+        Layer2Info {
+            link_type: 0,
+            src_mac: None,
+            dst_mac: None,
+            src_vendor: None,
+            dst_vendor: None,
+            ethertype: 0,
+            ethertype_str: None,
+            frame_len: 0,
+            vlans: Vec::new(),
+            mpls: Vec::new(),
         }
     }
 }
+
+/// Option TLV of a Hop-by-Hop or Destination Options header
+#[derive(Serialize, Debug, Clone)]
+pub struct Ip6Option {
+    pub opt_type: u8,
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Segment Routing Header, routing type 4 (RFC 8754)
+#[derive(Serialize, Debug, Clone)]
+pub struct Srv6Header {
+    pub last_entry: u8,
+    pub flags: u8,
+    pub tag: u16,
+    pub segments: Vec<String>,      // segment list as sent, [0] is the final segment
+}
+
+/// One IPv6 extension header. `hdr_len` is the whole header in bytes.
+#[derive(Serialize, Debug, Clone)]
+pub enum Ip6ExtHeader {
+    HopByHop { next: u8, hdr_len: usize, options: Vec<Ip6Option> },
+    Routing { next: u8, hdr_len: usize, routing_type: u8, segments_left: u8,
+        srv6: Option<Srv6Header>, data: Vec<u8> },
+    Fragment { next: u8, offset: u16, more: bool, id: u32 },
+    DestOptions { next: u8, hdr_len: usize, options: Vec<Ip6Option> },
+    Ah { next: u8, hdr_len: usize, spi: u32, seq: u32, icv: Vec<u8> },
+    Esp { spi: u32, seq: u32 },    // the rest is encrypted
+}
+
 #[derive(Serialize, Debug)]
 pub struct Ip6Info {
     pub version: u8,
@@ -82,6 +354,8 @@ pub struct Ip6Info {
     pub hop: u8,
     pub src_addr: String,
     pub dst_addr: String,
+    pub ext_headers: Vec<Ip6ExtHeader>,
+    pub upper_proto: u8,    // next header after the extension chain
     pub raw: Vec<u8>,
 }
 impl Ip6Info {
@@ -95,11 +369,52 @@ impl Ip6Info {
             hop: 0,
             src_addr: String::new(),
             dst_addr: String::new(),
+            ext_headers: Vec::new(),
+            upper_proto: 0,
             raw: Vec::new(),
         }
     }
 }
 
+/// IPv4 option value, decoded for the options we know
+#[derive(Serialize, Debug, Clone)]
+pub enum Ipv4OptionValue {
+    None,
+    RouterAlert(u16),
+    Route { pointer: u8, addrs: Vec<String> },     // Record Route, LSRR, SSRR
+    Timestamp { pointer: u8, overflow: u8, flag: u8, entries: Vec<Ipv4Timestamp> },
+    Raw(Vec<u8>),
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Ipv4Timestamp {
+    pub addr: Option<String>,
+    pub timestamp: u32,     // ms since midnight UT
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Ipv4Option {
+    pub opt_type: u8,
+    pub name: String,
+    pub copied: bool,
+    pub class: u8,
+    pub number: u8,
+    pub length: u8,         // whole option, 1 for EOL/NOP
+    pub value: Ipv4OptionValue,
+    pub raw: Vec<u8>,
+}
+
+/// Result of checking a header checksum against the captured bytes.
+/// Unverified when the covered bytes were not all captured, or the sender
+/// left the checksum out (UDP over IPv4 with checksum 0).
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumStatus {
+    Good,
+    Bad,
+    Unverified,
+}
+
 #[derive(Serialize, Debug)]
 pub struct IpInfo {
     pub version: u8,
@@ -108,14 +423,16 @@ pub struct IpInfo {
     pub ecn: u8,
     pub total_length: u16,
     pub id: u16,
-    pub flags: u8,
-    pub fragment_offset: u16,
+    pub flags: u8,              // 0x2 DF, 0x1 MF
+    pub fragment_offset: u16,   // in bytes
     pub ttl: u8,
     pub protocol: u8,
     pub checksum: u16,
+    pub checksum_status: ChecksumStatus,
     pub src_addr: String,
     pub dst_addr: String,
     pub next: String,
+    pub options: Vec<Ipv4Option>,
     pub raw: Vec<u8>,
 }
 impl  IpInfo {
@@ -132,18 +449,189 @@ impl  IpInfo {
             ttl: 0,
             protocol: 0,
             checksum: 0,
+            checksum_status: ChecksumStatus::Unverified,
             src_addr: String::new(),
             dst_addr: String::new(),
             next: String::new(),
+            options: Vec::new(),
+            raw: Vec::new(),
+        }
+    }
+}
+
+/// GRE header (RFC 2784, RFC 2890; version 1 is PPTP, RFC 2637)
+#[derive(Serialize, Debug)]
+pub struct GreInfo {
+    pub flags: u16,             // first two bytes, version included
+    pub version: u8,
+    pub protocol: u16,          // ethertype of the payload
+    pub protocol_str: String,
+    pub checksum: Option<u16>,
+    pub key: Option<u32>,       // version 1: payload length and call ID
+    pub seq: Option<u32>,
+    pub ack: Option<u32>,       // version 1 only
+    pub raw: Vec<u8>,
+}
+impl GreInfo {
+    pub fn new() -> Self {
+        GreInfo {
+            flags: 0,
+            version: 0,
+            protocol: 0,
+            protocol_str: String::new(),
+            checksum: None,
+            key: None,
+            seq: None,
+            ack: None,
+            raw: Vec::new(),
+        }
+    }
+}
+
+/// ERSPAN header carried in GRE. Type I has no header and is not listed.
+#[derive(Serialize, Debug)]
+pub struct ErspanInfo {
+    pub erspan_type: u8,        // 2 or 3
+    pub version: u8,            // header field: 1 for type II, 2 for type III
+    pub vlan: u16,
+    pub cos: u8,
+    pub encap: u8,              // type II En, type III BSO
+    pub truncated: bool,
+    pub session_id: u16,
+    pub index: Option<u32>,     // type II
+    pub timestamp: Option<u32>, // type III, in units of `granularity`
+    pub sgt: Option<u16>,
+    pub frame_type: Option<u8>, // type III: 0 Ethernet, 2 IP
+    pub hw_id: Option<u8>,
+    pub direction: Option<u8>,  // type III: 0 ingress, 1 egress
+    pub granularity: Option<u8>,
+    pub raw: Vec<u8>,
+}
+impl ErspanInfo {
+    pub fn new() -> Self {
+        ErspanInfo {
+            erspan_type: 0,
+            version: 0,
+            vlan: 0,
+            cos: 0,
+            encap: 0,
+            truncated: false,
+            session_id: 0,
+            index: None,
+            timestamp: None,
+            sgt: None,
+            frame_type: None,
+            hw_id: None,
+            direction: None,
+            granularity: None,
+            raw: Vec::new(),
+        }
+    }
+}
+
+/// VXLAN header (RFC 7348) and the outer UDP ports it came in
+#[derive(Serialize, Debug)]
+pub struct VxlanInfo {
+    pub flags: u8,
+    pub vni: u32,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub raw: Vec<u8>,
+}
+impl VxlanInfo {
+    pub fn new() -> Self {
+        VxlanInfo {
+            flags: 0,
+            vni: 0,
+            src_port: 0,
+            dst_port: 0,
+            raw: Vec::new(),
+        }
+    }
+}
+
+/// GTPv1-U header. The optional fields are present when any of E/S/PN is set.
+#[derive(Serialize, Debug)]
+pub struct GtpuInfo {
+    pub flags: u8,
+    pub version: u8,
+    pub pt_flag: bool,      // 1 for GTP, 0 for GTP'
+    pub e_flag: bool,
+    pub s_flag: bool,
+    pub pn_flag: bool,
+    pub msg_type: u8,
+    pub msg_type_str: String,
+    pub msg_len: u16,       // bytes after the mandatory 8 octets
+    pub teid: u32,
+    pub seq: Option<u16>,
+    pub npdu: Option<u8>,
+    pub ext_headers: Vec<GtpuExtHeader>,
+    pub ies: Vec<GtpuIe>,   // Echo, Error Indication and the other signalling messages
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub raw: Vec<u8>,
+}
+impl GtpuInfo {
+    pub fn new() -> Self {
+        GtpuInfo {
+            flags: 0,
+            version: 0,
+            pt_flag: false,
+            e_flag: false,
+            s_flag: false,
+            pn_flag: false,
+            msg_type: 0,
+            msg_type_str: String::new(),
+            msg_len: 0,
+            teid: 0,
+            seq: None,
+            npdu: None,
+            ext_headers: Vec::new(),
+            ies: Vec::new(),
+            src_port: 0,
+            dst_port: 0,
             raw: Vec::new(),
         }
     }
 }
 
+#[derive(Serialize, Debug)]
+pub struct GtpuExtHeader {
+    pub ext_type: u8,
+    pub name: String,
+    pub length: usize,      // bytes, length and next type octets included
+    pub value: GtpuExtValue,
+}
+
+/// GTP-U extension header content, decoded for the headers we know
+#[derive(Serialize, Debug)]
+pub enum GtpuExtValue {
+    PduSession {
+        pdu_type: u8, pdu_type_str: String, qfi: u8,
+        rqi: Option<bool>, ppi: Option<u8>,     // downlink only
+    },
+    UdpPort(u16),
+    PdcpPduNumber(u32),     // 15 bits, 18 for the long form
+    Raw(Vec<u8>),
+}
+
+#[derive(Serialize, Debug)]
+pub struct GtpuIe {
+    pub ie_type: u8,
+    pub name: String,
+    pub value: String,
+}
+
+/// Network layer and encapsulation headers, outermost first
 #[derive(Serialize, Debug)]
 pub enum Layer3Info {
     IP(IpInfo),
     IP6(Ip6Info),
+    Gre(GreInfo),
+    Erspan(ErspanInfo),
+    Vxlan(VxlanInfo),
+    Gtpu(GtpuInfo),
+    Ethernet(Layer2Info),   // Ethernet frame inside a tunnel
     None,
 }
 
@@ -151,28 +639,101 @@ pub enum Layer3Info {
 pub enum Layer4Info {
     UDP(UdpInfo),
     TCP(TcpInfo),
+    #[serde(rename = "SCTP")]
+    Sctp(SctpInfo),     // serialized under the key the frontend reads
     ICMP(IcmpInfo),
     None,
 }
 
+/// Neighbor Discovery option value (RFC 4861 4.6, RFC 8106)
+#[derive(Serialize, Debug)]
+pub enum NdOptionValue {
+    LinkLayerAddr(String),
+    PrefixInfo {
+        prefix_len: u8,
+        on_link: bool,
+        autonomous: bool,
+        valid_lifetime: u32,
+        preferred_lifetime: u32,
+        prefix: String,
+    },
+    Mtu(u32),
+    Rdnss { lifetime: u32, servers: Vec<String> },
+    Raw(Vec<u8>),
+}
+
+#[derive(Serialize, Debug)]
+pub struct NdOption {
+    pub opt_type: u8,
+    pub name: String,
+    pub length: usize,          // in bytes, type and length included
+    pub value: NdOptionValue,
+}
+
+/// Fields of the ICMP/ICMPv6 message body that depend on its type
+#[derive(Serialize, Debug)]
+pub enum IcmpMessage {
+    None,
+    PacketTooBig { mtu: u32 },
+    ParamProblem { pointer: u32 },
+    RouterSolicit,
+    RouterAdvert {
+        hop_limit: u8,
+        managed: bool,
+        other: bool,
+        router_lifetime: u16,
+        reachable_time: u32,
+        retrans_timer: u32,
+    },
+    NeighborSolicit { target: String },
+    NeighborAdvert { router: bool, solicited: bool, override_flag: bool, target: String },
+    Redirect { target: String, dest: String },
+}
+
+/// Header of the packet an ICMP/ICMPv6 error quotes
+#[derive(Serialize, Debug)]
+pub struct IcmpQuoted {
+    pub src_addr: String,
+    pub dst_addr: String,
+    pub protocol: u8,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub description: String,
+    pub original_packet: Option<usize>,     // the quoted packet, if it is in the capture
+}
+
 #[derive(Serialize, Debug)]
 pub struct IcmpInfo {
+    pub version: u8,            // 4 ICMP, 6 ICMPv6
     pub icmp_type: u8,
+    pub type_str: String,
     pub code: u8,
+    pub code_str: String,
     pub checksum: u16,
+    pub checksum_status: ChecksumStatus,
     pub id: u16,
     pub seq: u16,
+    pub message: IcmpMessage,
+    pub nd_options: Vec<NdOption>,
+    pub quoted: Option<IcmpQuoted>,
     pub raw: Vec<u8>,
     pub payload: Option<Vec<u8>>,
 }
 impl  IcmpInfo {
     pub fn new() -> Self {
         IcmpInfo {
+            version: 4,
             icmp_type: 0,
+            type_str: String::new(),
             code: 0,
+            code_str: String::new(),
             checksum: 0,
+            checksum_status: ChecksumStatus::Unverified,
             id: 0,
             seq: 0,
+            message: IcmpMessage::None,
+            nd_options: Vec::new(),
+            quoted: None,
             raw: Vec::new(),
             payload: None,
         }
@@ -188,6 +749,7 @@ pub struct UdpInfo {
     pub str_dst_port: String,
     pub length: u16,
     pub checksum: u16,
+    pub checksum_status: ChecksumStatus,
     pub raw: Vec<u8>,
     pub payload: Option<Vec<u8>>,
 }
@@ -201,6 +763,7 @@ impl  UdpInfo {
             str_dst_port: String::new(),
             length: 0,
             checksum: 0,
+            checksum_status: ChecksumStatus::Unverified,
             raw: Vec::new(),
             payload: None,
         }
@@ -219,9 +782,16 @@ pub struct TcpInfo {
     pub flags: u8,
     pub window: u16,
     pub checksum: u16,
+    pub checksum_status: ChecksumStatus,
     pub urgent: u16,
     pub raw: Vec<u8>,
     pub payload: Option<Vec<u8>>,
+    pub analysis: Vec<TcpAnalysis>,
+    pub named_flags: TcpFlags,
+    pub flags_str: String,      // e.g. "SYN, ACK"
+    pub options: Vec<TcpOption>,
+    pub stream: Option<usize>,  // connection index in the capture
+    pub handshake: Option<TcpHandshake>,
 }
 impl  TcpInfo {
     pub fn new() -> Self {
@@ -236,13 +806,233 @@ impl  TcpInfo {
             flags: 0,
             window: 0,
             checksum: 0,
+            checksum_status: ChecksumStatus::Unverified,
             urgent: 0,
             raw: Vec::new(),
             payload: None,
+            analysis: Vec::new(),
+            named_flags: TcpFlags::default(),
+            flags_str: String::new(),
+            options: Vec::new(),
+            stream: None,
+            handshake: None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct TcpFlags {
+    pub ae: bool,       // Accurate ECN, formerly NS
+    pub cwr: bool,
+    pub ece: bool,
+    pub urg: bool,
+    pub ack: bool,
+    pub psh: bool,
+    pub rst: bool,
+    pub syn: bool,
+    pub fin: bool,
+}
+
+/// TCP option value, decoded for the options we know
+#[derive(Serialize, Debug, Clone)]
+pub enum TcpOptionValue {
+    None,
+    Mss(u16),
+    WindowScale { shift: u8, multiplier: u32 },
+    Sack(Vec<SackBlock>),
+    Timestamp { tsval: u32, tsecr: u32 },
+    Raw(Vec<u8>),
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SackBlock {
+    pub left: u32,
+    pub right: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TcpOption {
+    pub kind: u8,
+    pub name: String,
+    pub length: u8,         // whole option, 1 for EOL/NOP
+    pub value: TcpOptionValue,
+    pub raw: Vec<u8>,
+}
+
+/// How a connection was opened and closed. The client is the side that sent
+/// the SYN, or the first packet seen when the handshake was not captured.
+#[derive(Serialize, Debug, Clone)]
+pub struct TcpHandshake {
+    pub stream: usize,
+    pub client: String,     // "addr:port"
+    pub server: String,
+    pub syn: Option<usize>,         // packet ids
+    pub syn_ack: Option<usize>,
+    pub ack: Option<usize>,
+    pub syn_to_syn_ack_ns: Option<i64>,
+    pub syn_ack_to_ack_ns: Option<i64>,
+    pub handshake_ns: Option<i64>,  // SYN to ACK
+    pub client_mss: Option<u16>,
+    pub server_mss: Option<u16>,
+    pub mss: Option<u16>,           // the lower of the two
+    pub client_window_scale: Option<u8>,
+    pub server_window_scale: Option<u8>,
+    pub window_scaling: bool,       // both sides sent the option, so both scale
+    pub sack_permitted: bool,       // both sides sent SACK-permitted
+    pub fin_client: Option<usize>,  // first FIN from each side
+    pub fin_server: Option<usize>,
+    pub rst: Option<usize>,         // first RST
+    pub teardown: String,           // e.g. "Closed by client", "Reset by server", "Open"
+}
+impl TcpHandshake {
+    pub fn new(stream: usize, client: String, server: String) -> Self {
+        TcpHandshake {
+            stream,
+            client,
+            server,
+            syn: None,
+            syn_ack: None,
+            ack: None,
+            syn_to_syn_ack_ns: None,
+            syn_ack_to_ack_ns: None,
+            handshake_ns: None,
+            client_mss: None,
+            server_mss: None,
+            mss: None,
+            client_window_scale: None,
+            server_window_scale: None,
+            window_scaling: false,
+            sack_permitted: false,
+            fin_client: None,
+            fin_server: None,
+            rst: None,
+            teardown: String::new(),
+        }
+    }
+}
+
+/// What TCP sequence analysis found wrong with a segment
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TcpAnalysis {
+    Retransmission,             // carries only bytes already seen
+    OutOfOrder,                 // fills a gap left before later segments
+    ZeroWindow,                 // receive window advertised as 0
+    PreviousSegmentMissing,     // bytes before it were not captured (yet)
+}
+
+/// A PDU reassembled from a TCP stream
+#[derive(Serialize, Debug, Clone)]
+pub struct TcpPdu {
+    pub len: usize,
+    pub packet_ids: Vec<usize>,     // in stream order, the last one completed it
+}
+
+#[derive(Serialize, Debug)]
+pub struct SctpInfo {
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub vtag: u32,
+    pub checksum: u32,
+    pub checksum_status: ChecksumStatus,    // CRC32c
+    pub chunks: Vec<SctpChunk>,
+    pub assoc_id: Option<usize>,            // association index in the capture
+    pub association: Option<SctpAssociation>,
+}
+impl SctpInfo {
+    pub fn new() -> Self {
+        SctpInfo {
+            src_port: 0,
+            dst_port: 0,
+            vtag: 0,
+            checksum: 0,
+            checksum_status: ChecksumStatus::Unverified,
+            chunks: Vec::new(),
+            assoc_id: None,
+            association: None,
         }
     }
 }
 
+#[derive(Serialize, Debug, Clone)]
+pub struct SctpChunk {
+    pub chunk_type: u8,
+    pub name: String,
+    pub flags: u8,
+    pub length: u16,        // without padding
+    pub value: SctpChunkValue,
+}
+
+/// SCTP chunk value, decoded for the chunks we know
+#[derive(Serialize, Debug, Clone)]
+pub enum SctpChunkValue {
+    None,
+    Data {
+        tsn: u32, stream_id: u16, stream_seq: u16, ppid: u32, ppid_str: Option<String>,
+        unordered: bool, begin: bool, end: bool, data_len: usize,
+    },
+    IData {
+        tsn: u32, stream_id: u16, mid: u32, ppid: Option<u32>, fsn: u32,    // first fragment: PPID, the others: FSN
+        unordered: bool, begin: bool, end: bool, data_len: usize,
+    },
+    Init {      // INIT, INIT ACK
+        initiate_tag: u32, a_rwnd: u32, out_streams: u16, in_streams: u16, initial_tsn: u32,
+        addresses: Vec<String>, params: Vec<SctpParam>,
+    },
+    Sack { cum_tsn: u32, a_rwnd: u32, gap_blocks: Vec<SctpGapBlock>, dup_tsns: Vec<u32> },
+    Heartbeat { info_len: usize },          // HEARTBEAT, HEARTBEAT ACK
+    Shutdown { cum_tsn: u32 },
+    Error { causes: Vec<SctpParam> },       // ABORT, ERROR
+    Raw(Vec<u8>),
+}
+
+/// Gap Ack Block, offsets from the cumulative TSN
+#[derive(Serialize, Debug, Clone)]
+pub struct SctpGapBlock {
+    pub start: u16,
+    pub end: u16,
+}
+
+/// INIT parameter or ABORT/ERROR cause
+#[derive(Serialize, Debug, Clone)]
+pub struct SctpParam {
+    pub param_type: u16,
+    pub name: String,
+    pub length: u16,
+    pub value: Option<String>,      // addresses and host names
+}
+
+/// One SCTP association. The initiator is the side that sent the INIT, or
+/// of the first packet seen when the setup was not captured.
+#[derive(Serialize, Debug, Clone)]
+pub struct SctpAssociation {
+    pub id: usize,
+    pub initiator_port: u16,
+    pub responder_port: u16,
+    pub initiator_tag: Option<u32>,     // verification tag of packets sent to the initiator
+    pub responder_tag: Option<u32>,
+    pub initiator_addrs: Vec<String>,   // every address used or announced, for multihoming
+    pub responder_addrs: Vec<String>,
+    pub init: Option<usize>,            // packet ids
+    pub init_ack: Option<usize>,
+    pub shutdown: Option<usize>,
+    pub abort: Option<usize>,
+    pub messages: usize,                // user messages delivered
+    pub state: String,                  // "Established", "Shut down", "Aborted", ...
+}
+
+/// A user message put back together from DATA/I-DATA chunks
+#[derive(Serialize, Debug, Clone)]
+pub struct SctpUserMessage {
+    pub assoc_id: usize,
+    pub stream_id: u16,
+    pub ppid: u32,
+    pub ppid_str: Option<String>,
+    pub unordered: bool,
+    pub len: usize,
+    pub packet_ids: Vec<usize>,     // in fragment order
+}
+
 #[derive(Serialize, Debug)]
 pub enum AppLayerInfo {
     GTP(GtpInfo),
@@ -308,20 +1098,42 @@ pub struct PfcpInfo {
     pub raw: Vec<u8>,
 }
 
+/// A layer that could not be decoded completely
+#[derive(Serialize, Debug, Clone)]
+pub struct Malformed {
+    pub protocol: String,
+    pub offset: usize,      // frame offset where decoding stopped
+    pub reason: String,
+}
+
 #[derive(Serialize, Debug)]
 pub struct PacketDetail {
     pub id: usize,
+    pub frame: FrameInfo,
+    pub l2: Layer2Info,
     pub l3: Vec<Layer3Info>,
     pub l4: Layer4Info,
     pub app: AppLayerInfo,
+    pub layers: Vec<LayerSpan>,     // protocol stack with byte ranges in the frame
+    pub malformed: Vec<Malformed>,
+    pub fragments: Vec<usize>,      // packets reassembled into this one, including itself
+    pub tcp_pdus: Vec<TcpPdu>,      // PDUs completed by this packet's TCP segment
+    pub sctp_messages: Vec<SctpUserMessage>,    // user messages completed by this packet's DATA chunks
 }
 impl PacketDetail{
     pub fn new() -> Self {
         PacketDetail {
             id: 0,
+            frame: FrameInfo::new(),
+            l2: Layer2Info::new(),
             l3: Vec::new(),
             l4: Layer4Info::None,
             app: AppLayerInfo::None,
+            layers: Vec::new(),
+            malformed: Vec::new(),
+            fragments: Vec::new(),
+            tcp_pdus: Vec::new(),
+            sctp_messages: Vec::new(),
         }
     }
 }
\ No newline at end of file
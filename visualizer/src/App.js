@@ -4,6 +4,7 @@ import "./App.css";
 
 import PacketTable from "./PacketTable"
 import CallFlowView from "./components/callflow/CallFlowView"
+import ConversationsView from "./components/conversations/ConversationsView"
 
 
 function App() {
@@ -18,6 +19,7 @@ function App() {
   const [showCallFlow, setShowCallFlow] = useState(false);
   const [loadingFlow, setLoadingFlow] = useState(false);
   const [flowError, setFlowError] = useState(null);
+  const [showConversations, setShowConversations] = useState(false);
 
   // 파일 input ref 생성
   const fileInputRef = useRef(null);
@@ -169,6 +171,15 @@ function App() {
 
               {/* Table Panel */}
               <div className="panel table-panel">
+                <button className="btn btn-sm btn-outline-primary mb-2"
+                  onClick={() => setShowConversations(true)}>
+                  Conversations
+                </button>
+                {result.truncated && (
+                  <div className="alert alert-warning py-2">
+                    Capture is truncated ({result.truncated}); showing the packets read before it.
+                  </div>
+                )}
                 <PacketTable packets={result.packets} fileId={fileId}
                     onCallFlow={fetchCallFlow}
                     showCallFlow={showCallFlow} />
@@ -186,6 +197,15 @@ function App() {
           </div>
         )}
 
+        <Modal show={showConversations} onHide={() => setShowConversations(false)} centered size="xl">
+          <Modal.Header closeButton>
+            <Modal.Title>Conversations</Modal.Title>
+          </Modal.Header>
+          <Modal.Body>
+            {showConversations && <ConversationsView fileId={fileId} />}
+          </Modal.Body>
+        </Modal>
+
         <Modal show={selectedPacket !== null} onHide={() => setSelectedPacket(null)} centered size="lg">
           <Modal.Header closeButton>
             <Modal.Title>Packet Detail (ID: {selectedPacket?.id}) </Modal.Title>
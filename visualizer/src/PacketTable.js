@@ -4,8 +4,10 @@ import "./App.css";
 
 import OverlayTrigger from "react-bootstrap/OverlayTrigger";
 import Tooltip from "react-bootstrap/Tooltip";
+import Layer2Header from "./components/headers/Layer2Header";
 import Layer3Header from "./components/headers/Layer3Header";
 import Layer4Header from "./components/headers/Layer4Header";
+import { analysisToStr } from "./components/headers/TcpHeader";
 import GtpHeader from "./components/headers/GtpHeader";
 
 
@@ -282,18 +284,48 @@ function PacketTable({ packets, fileId, ShowCallFlow, onCallFlow})  {
           {filteredPackets || filteredPackets.length > 0 ? (
             filteredPackets.map((pkt) => (
               <tr key={pkt.id}
+                className={pkt.malformed ? "table-danger" : pkt.bad_checksum ? "table-warning" : undefined}
                 onClick={() => fetchPacketDetail(pkt.id) }
                 style={{ cursor: "pointer" }}>
 
                 <td>{pkt.id}</td>
                 <td>{pkt.ts}</td>
-                <td>{pkt.src_ip}</td>
-                <td>{pkt.dst_ip}</td>
+                <td>
+                  {pkt.src_ip}
+                  {pkt.src_name && <span className="text-muted"> ({pkt.src_name})</span>}
+                </td>
+                <td>
+                  {pkt.dst_ip}
+                  {pkt.dst_name && <span className="text-muted"> ({pkt.dst_name})</span>}
+                </td>
                 {/* <td>{pkt.src_port}</td> */}
                 {/* <td>{pkt.dst_port}</td> */}
                 <td>{pkt.protocol}</td>
                 <td>{pkt.length}</td>
-                <td>{pkt.description}</td>
+                <td>
+                  {pkt.description}
+                  {pkt.reassembled_in && (
+                    <span className="text-muted"> [Reassembled in #{pkt.reassembled_in}]</span>
+                  )}
+                  {pkt.bad_checksum && (
+                    <span className="text-danger"> [Bad checksum]</span>
+                  )}
+                  {pkt.tcp_analysis?.length > 0 && (
+                    <span className="text-danger"> [{analysisToStr(pkt.tcp_analysis)}]</span>
+                  )}
+                  {pkt.tcp_segments?.length > 0 && (
+                    <span className="text-muted"> [{pkt.tcp_segments.length} Reassembled TCP Segments]</span>
+                  )}
+                  {pkt.tunnel_teid != null && (
+                    <span className="text-muted"> [TEID 0x{pkt.tunnel_teid.toString(16).padStart(8, "0")}]</span>
+                  )}
+                  {pkt.sctp_fragments?.length > 0 && (
+                    <span className="text-muted"> [Reassembled from {pkt.sctp_fragments.length} SCTP packets]</span>
+                  )}
+                  {pkt.error_for && (
+                    <span className="text-muted"> [Error for #{pkt.error_for}]</span>
+                  )}
+                </td>
                 <td>
                   {pkt.description === "Create Session Request [32]" && onCallFlow && (
                     <button
@@ -338,6 +370,51 @@ function PacketTable({ packets, fileId, ShowCallFlow, onCallFlow})  {
                   </h5>
                 </div>
 
+                {/* IP reassembly */}
+                {selectedPacket.packet.fragments?.length > 0 && (
+                  <div className="alert alert-info py-2">
+                    Reassembled from packets {selectedPacket.packet.fragments.map((id) => `#${id}`).join(", ")}
+                  </div>
+                )}
+
+                {/* TCP reassembly */}
+                {selectedPacket.packet.tcp_pdus?.length > 0 && (
+                  <div className="alert alert-info py-2">
+                    {selectedPacket.packet.tcp_pdus.map((pdu, idx) => (
+                      <div key={idx}>
+                        PDU of {pdu.len} bytes from packets {pdu.packet_ids.map((id) => `#${id}`).join(", ")}
+                      </div>
+                    ))}
+                  </div>
+                )}
+
+                {/* SCTP reassembly */}
+                {selectedPacket.packet.sctp_messages?.length > 0 && (
+                  <div className="alert alert-info py-2">
+                    {selectedPacket.packet.sctp_messages.map((msg, idx) => (
+                      <div key={idx}>
+                        {msg.ppid_str || `PPID ${msg.ppid}`} message of {msg.len} bytes on stream {msg.stream_id}
+                        {" "}from packets {msg.packet_ids.map((id) => `#${id}`).join(", ")}
+                      </div>
+                    ))}
+                  </div>
+                )}
+
+                {/* Malformed layers */}
+                {selectedPacket.packet.malformed?.length > 0 && (
+                  <div className="alert alert-danger py-2">
+                    {selectedPacket.packet.malformed.map((m, idx) => (
+                      <div key={idx}>
+                        [Malformed {m.protocol}] offset {m.offset}: {m.reason}
+                      </div>
+                    ))}
+                  </div>
+                )}
+
+                {/* Link Layer */}
+                <Layer2Header l2={selectedPacket.packet.l2}
+                  linkType={selectedPacket.packet.frame?.link_type_str} />
+
                 {/* IP Section */}
                 {selectedPacket.packet.l3.map((l3, idx) => (
                   <Layer3Header key={idx} l3={l3} idx={idx} />
@@ -434,6 +434,13 @@ function CallFlowGraph({ data, step }) {
     nodeX[node] = padding + idx * span;
   });
 
+  // 노드 alias 또는 추론된 역할 (MME, SGW-C ...)
+  const nodeName = {};
+  data.forEach(p => {
+    if (p.src_name) nodeName[p.src_addr] = p.src_name;
+    if (p.dst_name) nodeName[p.dst_addr] = p.dst_name;
+  });
+
   const visibleFlows = data.slice(0, step);
   const bearerHistory = useBearerState(data); // 전체 히스토리 미리 계산
   const currentNodeState = bearerHistory[step - 1] || {};
@@ -444,7 +451,10 @@ function CallFlowGraph({ data, step }) {
       {nodes.map(node => (
         <g key={node}>
           <line x1={nodeX[node]} y1={70} x2={nodeX[node]} y2={height - 200} stroke="#aaa" />
-          <text x={nodeX[node]} y={50} textAnchor="middle" fontWeight="bold">{node}</text>
+          {nodeName[node] && (
+            <text x={nodeX[node]} y={30} textAnchor="middle" fontWeight="bold">{nodeName[node]}</text>
+          )}
+          <text x={nodeX[node]} y={50} textAnchor="middle" fontWeight={nodeName[node] ? "normal" : "bold"}>{node}</text>
         </g>
       ))}
 
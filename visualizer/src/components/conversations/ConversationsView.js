@@ -0,0 +1,115 @@
+import React, { useEffect, useState } from "react";
+
+// 정렬 가능한 컬럼: [서버 sort 키, 제목]
+const COLUMNS = [
+  ["id", "#"],
+  ["addr", "Endpoints"],
+  ["packets", "Packets A→B / B→A"],
+  ["bytes", "Bytes A→B / B→A"],
+  ["start", "Start"],
+  ["duration", "Duration"],
+  ["bps", "bps A→B / B→A"],
+  ["rtt", "RTT avg (min-max)"],
+  ["retransmissions", "Retrans."],
+];
+
+const secs = (ns) => (ns / 1e9).toFixed(6);
+const ms = (ns) => (ns == null ? "-" : `${(ns / 1e6).toFixed(3)} ms`);
+const bps = (v) => (v == null ? "-" : Math.round(v).toLocaleString());
+const endpoint = (addr, port, name) => `${name ? `${name} ` : ""}${addr}:${port}`;
+
+
+export default function ConversationsView({ fileId }) {
+  const [conversations, setConversations] = useState([]);
+  const [error, setError] = useState(null);
+  const [protocol, setProtocol] = useState("");
+  const [addr, setAddr] = useState("");
+  const [port, setPort] = useState("");
+  const [sort, setSort] = useState("id");
+  const [order, setOrder] = useState("asc");
+
+  useEffect(() => {
+    if (fileId == null) return;
+
+    const params = new URLSearchParams({ file_id: fileId, sort, order });
+    if (protocol) params.set("protocol", protocol);
+    if (addr) params.set("addr", addr);
+    if (port) params.set("port", port);
+
+    fetch(`/api/conversations?${params}`)
+      .then(async (res) => {
+        if (!res.ok) throw new Error(await res.text());
+        return res.json();
+      })
+      .then((json) => {
+        setConversations(json.conversations);
+        setError(null);
+      })
+      .catch((err) => setError(String(err.message || err)));
+  }, [fileId, protocol, addr, port, sort, order]);
+
+  // 같은 컬럼을 다시 누르면 방향만 바꾼다
+  const sortBy = (key) => {
+    if (key === sort) {
+      setOrder(order === "asc" ? "desc" : "asc");
+    } else {
+      setSort(key);
+      setOrder(key === "id" || key === "addr" ? "asc" : "desc");
+    }
+  };
+
+  return (
+    <div>
+      <div className="d-flex gap-2 mb-3" style={{ fontSize: "14px" }}>
+        <select className="form-select form-select-sm" style={{ width: "120px" }}
+          value={protocol} onChange={(e) => setProtocol(e.target.value)}>
+          <option value="">All</option>
+          <option value="tcp">TCP</option>
+          <option value="udp">UDP</option>
+          <option value="sctp">SCTP</option>
+        </select>
+        <input className="form-control form-control-sm" style={{ width: "200px" }}
+          placeholder="Address or subnet" value={addr}
+          onChange={(e) => setAddr(e.target.value.trim())} />
+        <input className="form-control form-control-sm" style={{ width: "100px" }}
+          placeholder="Port" value={port}
+          onChange={(e) => setPort(e.target.value.replace(/\D/g, ""))} />
+      </div>
+
+      {error && <div className="alert alert-warning py-2">{error}</div>}
+
+      <table className="table table-bordered table-sm table-hover" style={{ fontSize: "13px" }}>
+        <thead>
+          <tr>
+            <th>Proto</th>
+            {COLUMNS.map(([key, title]) => (
+              <th key={key} style={{ cursor: "pointer" }} onClick={() => sortBy(key)}>
+                {title}{sort === key && (order === "asc" ? " ▲" : " ▼")}
+              </th>
+            ))}
+          </tr>
+        </thead>
+        <tbody>
+          {conversations.map((c) => (
+            <tr key={c.id}>
+              <td>{c.protocol}</td>
+              <td>{c.id}</td>
+              <td>
+                {endpoint(c.addr_a, c.port_a, c.name_a)} ↔ {endpoint(c.addr_b, c.port_b, c.name_b)}
+              </td>
+              <td>{c.packets_a_to_b} / {c.packets_b_to_a}</td>
+              <td>{c.bytes_a_to_b} / {c.bytes_b_to_a}</td>
+              <td title={c.first_seen}>{secs(c.start_ns)} (#{c.first_packet})</td>
+              <td title={c.last_seen}>{secs(c.duration_ns)}</td>
+              <td>{bps(c.bps_a_to_b)} / {bps(c.bps_b_to_a)}</td>
+              <td>
+                {c.tcp ? `${ms(c.tcp.rtt_avg_ns)} (${ms(c.tcp.rtt_min_ns)} - ${ms(c.tcp.rtt_max_ns)})` : "-"}
+              </td>
+              <td>{c.tcp ? c.tcp.retransmissions : "-"}</td>
+            </tr>
+          ))}
+        </tbody>
+      </table>
+    </div>
+  );
+}
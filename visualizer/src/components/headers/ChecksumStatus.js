@@ -0,0 +1,18 @@
+import React from "react";
+
+
+// checksum_status from the parser: "good" | "bad" | "unverified"
+const LABELS = {
+  good: { text: "correct", className: "text-success" },
+  bad: { text: "incorrect", className: "text-danger fw-bold" },
+  unverified: { text: "unverified", className: "text-muted" },
+};
+
+function ChecksumStatus({ status }) {
+  const label = LABELS[status];
+  if (!label) return null;
+
+  return <span className={label.className}> [{label.text}]</span>;
+}
+
+export default ChecksumStatus;
@@ -1,6 +1,23 @@
 import React, { useState } from "react";
 import "./ip.css";
 import HexDump from '../hex-dump/HexDump';
+import ChecksumStatus from './ChecksumStatus';
+
+
+// serde 의 externally tagged enum ({ Variant: {...} }) 을 [name, fields] 로
+function variantOf(value) {
+  if (!value || typeof value !== "object") return [value, null];
+  const [name] = Object.keys(value);
+  return [name, value[name]];
+}
+
+function fieldsToStr(fields) {
+  if (fields == null) return "";
+  if (typeof fields !== "object") return String(fields);
+  return Object.entries(fields)
+    .map(([k, v]) => `${k}: ${Array.isArray(v) ? v.join(", ") : v}`)
+    .join(", ");
+}
 
 
 export default function IcmpHeader({ icmp }) {
@@ -8,6 +25,9 @@ export default function IcmpHeader({ icmp }) {
 
   if (!icmp) return null;
 
+  const title = icmp.version === 6 ? "ICMPv6 Header" : "ICMP Header";
+  const [msgName, msgFields] = variantOf(icmp.message);
+
   return (
     <div className="card mb-3">
 
@@ -42,18 +62,26 @@ export default function IcmpHeader({ icmp }) {
                 <tbody>
                   <tr>
                     <th colSpan="2" style={{textAlign: "Center"}}>
-                      <b>ICMP Header</b>
+                      <b>{title}</b>
                     </th>
                   </tr>
 
                   <tr>
                     <th>ICMP Type</th>
-                    <td> {icmp.icmp_type} [{icmp.icmp_type}] </td>
+                    <td> {icmp.type_str || icmp.icmp_type} [{icmp.icmp_type}] </td>
                   </tr>
 
                   <tr>
                     <th>ICMP Code</th>
-                    <td> {icmp.code} </td>
+                    <td> {icmp.code_str ? `${icmp.code_str} [${icmp.code}]` : icmp.code} </td>
+                  </tr>
+
+                  <tr>
+                    <th>Checksum</th>
+                    <td>
+                      0x{icmp.checksum != null ? icmp.checksum.toString(16).toUpperCase().padStart(4, "0") : "-"}
+                      <ChecksumStatus status={icmp.checksum_status} />
+                    </td>
                   </tr>
 
                   <tr>
@@ -88,7 +116,7 @@ export default function IcmpHeader({ icmp }) {
               }}>
 
               <div style={{ flex: "1 1 auto", overflowX: "auto" }}>
-                <div style={{ fontWeight: "bold", marginBottom: "5px" }}>ICMP Header</div>
+                <div style={{ fontWeight: "bold", marginBottom: "5px" }}>{title}</div>
                 <HexDump raw={icmp.raw} /> 
               </div>
 
@@ -110,7 +138,7 @@ export default function IcmpHeader({ icmp }) {
             <tbody>
               <tr>
                 <th colSpan="33" style={{ textAlign: "center" }}>
-                  <b>ICMP Header</b>
+                  <b>{title}</b>
                 </th>
               </tr>
 
@@ -132,9 +160,11 @@ export default function IcmpHeader({ icmp }) {
 
               <tr>
                 <th>0</th>
-                <td colSpan="8"><i>Type: </i> {icmp.icmp_type} [{icmp.icmp_type}] </td>
-                <td colSpan="8"><i>Code: </i> {icmp.code} </td>
-                <td colSpan="16"><i>Checksum: </i> {icmp.checksum} </td>
+                <td colSpan="8"><i>Type: </i> {icmp.type_str || icmp.icmp_type} [{icmp.icmp_type}] </td>
+                <td colSpan="8"><i>Code: </i> {icmp.code_str || icmp.code} [{icmp.code}] </td>
+                <td colSpan="16"><i>Checksum: </i> {icmp.checksum}
+                  <ChecksumStatus status={icmp.checksum_status} />
+                </td>
               </tr>
 
               <tr>
@@ -147,6 +177,37 @@ export default function IcmpHeader({ icmp }) {
                 </td>
               </tr>
 
+              {msgName && msgName !== "None" && (
+                <tr>
+                  <th>32</th>
+                  <td colSpan="32"><i>{msgName}: </i> {fieldsToStr(msgFields)}</td>
+                </tr>
+              )}
+
+              {icmp.nd_options?.map((opt, idx) => (
+                <tr key={`nd-${idx}`}>
+                  <th>Option</th>
+                  <td colSpan="32">
+                    <i>{opt.name || `Type ${opt.opt_type}`} ({opt.length} bytes): </i>
+                    {fieldsToStr(variantOf(opt.value)[1])}
+                  </td>
+                </tr>
+              ))}
+
+              {icmp.quoted && (
+                <tr>
+                  <th>Quoted</th>
+                  <td colSpan="32">
+                    <i>Original: </i>
+                    {icmp.quoted.src_addr}:{icmp.quoted.src_port} &gt; {icmp.quoted.dst_addr}:{icmp.quoted.dst_port}
+                    {icmp.quoted.description && ` [${icmp.quoted.description}]`}
+                    {icmp.quoted.original_packet != null && (
+                      <span className="text-muted"> (original packet #{icmp.quoted.original_packet})</span>
+                    )}
+                  </td>
+                </tr>
+              )}
+
               {icmp.payload ? (
                 <>
                   <tr>
@@ -1,6 +1,23 @@
 import React, { useState } from "react";
 import "./ip.css";
 import HexDump from '../hex-dump/HexDump';
+import ChecksumStatus from './ChecksumStatus';
+
+
+function formatOption(opt) {
+  const v = opt.value;
+  let detail = "";
+  if (v?.RouterAlert !== undefined) {
+    detail = `value ${v.RouterAlert}`;
+  } else if (v?.Route) {
+    detail = `pointer ${v.Route.pointer}, ${v.Route.addrs.join(", ")}`;
+  } else if (v?.Timestamp) {
+    detail = v.Timestamp.entries
+      .map((e) => (e.addr ? `${e.addr}@${e.timestamp}` : `${e.timestamp}`))
+      .join(", ");
+  }
+  return `${opt.name} [${opt.opt_type}] len ${opt.length}${detail ? ": " + detail : ""}`;
+}
 
 
 export default function IpHeader({ ip, depth }) {
@@ -99,6 +116,7 @@ export default function IpHeader({ ip, depth }) {
                     <th>Checksum</th>
                     <td>
                       0x{ip.checksum != null ? ip.checksum.toString(16).toUpperCase() : "-"}
+                      <ChecksumStatus status={ip.checksum_status} />
                     </td>
                   </tr>
 
@@ -111,6 +129,13 @@ export default function IpHeader({ ip, depth }) {
                     <th>Destination Address</th>
                     <td>{ip.dst_addr}</td>
                   </tr>
+
+                  {ip.options?.map((opt, idx) => (
+                    <tr key={idx}>
+                      <th>Option</th>
+                      <td>{formatOption(opt)}</td>
+                    </tr>
+                  ))}
                 </tbody>
               </table>
             </div>
@@ -176,6 +201,7 @@ export default function IpHeader({ ip, depth }) {
                 <td colSpan="16"><i>Header Checksum:0x</i>
                   {/* {ip.checksum} */}
                   {ip.checksum != null ? ip.checksum.toString(16).toUpperCase() : "-"}
+                  <ChecksumStatus status={ip.checksum_status} />
                 </td>
               </tr>
 
@@ -192,6 +218,19 @@ export default function IpHeader({ ip, depth }) {
                 <th>128</th>
                 <td colSpan="32"><i>Destination Address:</i> {ip.dst_addr}</td>
               </tr>
+
+              {/* Options (IHL > 5) */}
+              {ip.options?.length > 0 && (
+                <tr>
+                  <th>160</th>
+                  <td colSpan="32">
+                    <i>Options:</i>
+                    {ip.options.map((opt, idx) => (
+                      <div key={idx}>{formatOption(opt)}</div>
+                    ))}
+                  </td>
+                </tr>
+              )}
             </tbody>
           </table>
         )}
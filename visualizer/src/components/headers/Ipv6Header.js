@@ -3,6 +3,27 @@ import "./ip.css";
 import HexDump from '../hex-dump/HexDump';
 
 
+function formatExtHeader(ext) {
+  const [kind, h] = Object.entries(ext)[0];
+  switch (kind) {
+    case "HopByHop":
+    case "DestOptions":
+      return `${kind} (next ${h.next}, ${h.hdr_len} bytes): ${h.options.map((o) => o.name).join(", ")}`;
+    case "Routing":
+      return `Routing type ${h.routing_type} (next ${h.next}, segments left ${h.segments_left})`
+        + (h.srv6 ? `: SRv6 [${h.srv6.segments.join(", ")}]` : "");
+    case "Fragment":
+      return `Fragment (next ${h.next}, offset ${h.offset}, ${h.more ? "more" : "last"}, ID 0x${h.id.toString(16)})`;
+    case "Ah":
+      return `AH (next ${h.next}, SPI 0x${h.spi.toString(16)}, seq ${h.seq})`;
+    case "Esp":
+      return `ESP (SPI 0x${h.spi.toString(16)}, seq ${h.seq})`;
+    default:
+      return kind;
+  }
+}
+
+
 export default function Ipv6Header({ ip, depth }) {
   const [viewMode, setViewMode] = useState("decoded");
 
@@ -79,6 +100,13 @@ export default function Ipv6Header({ ip, depth }) {
                     <td>{ip.dst_addr}</td>
                   </tr>
 
+                  {ip.ext_headers?.map((ext, idx) => (
+                    <tr key={idx}>
+                      <th>Extension Header</th>
+                      <td>{formatExtHeader(ext)}</td>
+                    </tr>
+                  ))}
+
                 </tbody>
               </table>
             </div>
@@ -159,6 +187,19 @@ export default function Ipv6Header({ ip, depth }) {
               <tr>
                 <th>288</th>
               </tr>
+
+              {/* Extension header chain */}
+              {ip.ext_headers?.length > 0 && (
+                <tr>
+                  <th>320</th>
+                  <td colSpan="32">
+                    <i>Extension Headers:</i>
+                    {ip.ext_headers.map((ext, idx) => (
+                      <div key={idx}>{formatExtHeader(ext)}</div>
+                    ))}
+                  </td>
+                </tr>
+              )}
             </tbody>
           </table>
         )}
@@ -0,0 +1,47 @@
+import React from "react";
+import "./ip.css";
+
+
+const hex = (v, width) => v != null ? "0x" + v.toString(16).toUpperCase().padStart(width, "0") : "-";
+
+// "00:00:0c:01:02:03 (Cisco)"
+export function macWithVendor(mac, vendor) {
+  if (!mac) return "-";
+  return vendor ? `${mac} (${vendor})` : mac;
+}
+
+// VLAN/MPLS 태그와 MAC 주소 행 (Tunnel 안의 Ethernet 도 같이 사용)
+export function layer2Rows(l2) {
+  return [
+    ["Destination", macWithVendor(l2.dst_mac, l2.dst_vendor)],
+    ["Source", macWithVendor(l2.src_mac, l2.src_vendor)],
+    ["Ethertype", l2.ethertype_str ? `${l2.ethertype_str} (${hex(l2.ethertype, 4)})` : hex(l2.ethertype, 4)],
+    ...l2.vlans.map((v) => ["VLAN", `${v.vid} (PCP ${v.pcp})`]),
+    ...l2.mpls.map((m) => ["MPLS", `label ${m.label}, TC ${m.tc}, TTL ${m.ttl}`]),
+  ];
+}
+
+
+export default function Layer2Header({ l2, linkType }) {
+  if (!l2) return null;
+
+  return (
+    <div className="card mb-3">
+      <div className="card-header ip-header">
+        <strong>Layer 2 ({linkType || "Link"}, {l2.frame_len} bytes)</strong>
+      </div>
+      <div className="card-body ip-card-body">
+        <table className="table table-bordered table-sm" style={{ fontSize: "14px", maxWidth: "600px" }}>
+          <tbody>
+            {layer2Rows(l2).map(([label, value], idx) => (
+              <tr key={idx}>
+                <th>{label}</th>
+                <td>{value}</td>
+              </tr>
+            ))}
+          </tbody>
+        </table>
+      </div>
+    </div>
+  );
+}
@@ -1,9 +1,16 @@
 import IpHeader from "./IpHeader";
-// import Ip6Header from "./Ip6Header";
+import Ipv6Header from "./Ipv6Header";
+import TunnelHeader from "./TunnelHeader";
 
 export default function Layer3Header({ l3, idx }) {
   if (l3.IP) return <IpHeader ip={l3.IP} depth={idx} />;
-//   if (l3.IP6) return <Ip6Header ip6={l3.IP6} />;
+  if (l3.IP6) return <Ipv6Header ip={l3.IP6} depth={idx} />;
+
+  // unit variants such as "None" arrive as plain strings
+  if (typeof l3 !== "object") return null;
+
+  const [kind, header] = Object.entries(l3)[0] ?? [];
+  if (kind) return <TunnelHeader kind={kind} header={header} />;
 
 return null;
-}
\ No newline at end of file
+}
@@ -1,6 +1,7 @@
 import React from "react";
 import TcpHeader from "./TcpHeader";
 import UdpHeader from "./UdpHeader";
+import SctpHeader from "./SctpHeader";
 import IcmpHeader from "./IcmpHeader";
 
 export default function Layer4Header({ l4 }) {
@@ -8,6 +9,8 @@ export default function Layer4Header({ l4 }) {
 
   if (l4.UDP) return <UdpHeader udp={l4.UDP} />;
 
+  if (l4.SCTP) return <SctpHeader sctp={l4.SCTP} />;
+
   if (l4.ICMP) return <IcmpHeader icmp={l4.ICMP} />;
 
   return <p>Unknown Layer 4 Protocol</p>;
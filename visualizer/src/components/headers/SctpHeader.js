@@ -0,0 +1,170 @@
+import React from "react";
+import "./ip.css";
+import ChecksumStatus from './ChecksumStatus';
+
+const pktRef = (id) => (id == null ? "-" : `#${id}`);
+const hex32 = (v) => (v == null ? "-" : "0x" + v.toString(16).toUpperCase().padStart(8, "0"));
+
+// DATA 의 B/E/U 플래그
+const dataFlags = (d) =>
+  [d.unordered && "U", d.begin && "B", d.end && "E"].filter(Boolean).join("") || "-";
+
+const paramsToStr = (params) =>
+  (params || []).map((p) => (p.value ? `${p.name} ${p.value}` : p.name)).join(", ");
+
+function chunkToStr(chunk) {
+  const v = chunk.value;
+  if (v?.Data) {
+    const d = v.Data;
+    return `TSN=${d.tsn} SID=${d.stream_id} SSN=${d.stream_seq} PPID=${d.ppid}` +
+      `${d.ppid_str ? ` (${d.ppid_str})` : ""} [${dataFlags(d)}] ${d.data_len} bytes`;
+  }
+  if (v?.IData) {
+    const d = v.IData;
+    return `TSN=${d.tsn} SID=${d.stream_id} MID=${d.mid} ` +
+      `${d.ppid != null ? `PPID=${d.ppid}` : `FSN=${d.fsn}`} [${dataFlags(d)}] ${d.data_len} bytes`;
+  }
+  if (v?.Init) {
+    const i = v.Init;
+    return `Initiate Tag=${hex32(i.initiate_tag)} a_rwnd=${i.a_rwnd} OS=${i.out_streams} ` +
+      `MIS=${i.in_streams} Initial TSN=${i.initial_tsn}` +
+      `${i.params?.length ? `, ${paramsToStr(i.params)}` : ""}`;
+  }
+  if (v?.Sack) {
+    const s = v.Sack;
+    return `Cumulative TSN=${s.cum_tsn} a_rwnd=${s.a_rwnd}` +
+      `${s.gap_blocks.length ? `, Gaps ${s.gap_blocks.map((g) => `${g.start}-${g.end}`).join(", ")}` : ""}` +
+      `${s.dup_tsns.length ? `, Dup TSNs ${s.dup_tsns.join(", ")}` : ""}`;
+  }
+  if (v?.Heartbeat) return `Heartbeat Info ${v.Heartbeat.info_len} bytes`;
+  if (v?.Shutdown) return `Cumulative TSN=${v.Shutdown.cum_tsn}`;
+  if (v?.Error) return paramsToStr(v.Error.causes) || "-";
+  if (v?.Raw) return `${v.Raw.length} bytes`;
+  return "-";
+}
+
+function SctpAssociation({ assoc }) {
+  if (!assoc) return null;
+
+  return (
+    <table className="table table-bordered table-sm mt-3" style={{ fontSize: "14px" }}>
+      <tbody>
+        <tr>
+          <th colSpan="2" style={{textAlign: "Center"}}>
+            <b>Association {assoc.id}</b>
+          </th>
+        </tr>
+        <tr>
+          <th>Initiator</th>
+          <td>
+            {assoc.initiator_addrs.join(", ")} port {assoc.initiator_port}, tag {hex32(assoc.initiator_tag)}
+          </td>
+        </tr>
+        <tr>
+          <th>Responder</th>
+          <td>
+            {assoc.responder_addrs.join(", ")} port {assoc.responder_port}, tag {hex32(assoc.responder_tag)}
+          </td>
+        </tr>
+        <tr>
+          <th>Setup</th>
+          <td>INIT {pktRef(assoc.init)}, INIT ACK {pktRef(assoc.init_ack)}</td>
+        </tr>
+        <tr>
+          <th>State</th>
+          <td>
+            {assoc.state}
+            {assoc.shutdown != null && ` (SHUTDOWN ${pktRef(assoc.shutdown)})`}
+            {assoc.abort != null && ` (ABORT ${pktRef(assoc.abort)})`}
+          </td>
+        </tr>
+        <tr>
+          <th>User Messages</th>
+          <td>{assoc.messages}</td>
+        </tr>
+      </tbody>
+    </table>
+  );
+}
+
+
+export default function SctpHeader({ sctp }) {
+  if (!sctp) return null;
+
+  return (
+    <div className="card mb-3">
+      <div className="card-header udp-header d-flex justify-content-between align-items-center">
+        <strong>Layer 4 (Transport)</strong>
+      </div>
+
+      <div className="card-body udp-card-body">
+        <table className="ip-table ">
+          <tbody>
+            <tr>
+              <th colSpan="33" style={{ textAlign: "center" }}>
+                <b>SCTP Common Header</b>
+              </th>
+            </tr>
+
+            {/* Header Row */}
+            <tr>
+              <th style={{ borderLeft: "" }}>Octet</th>
+              <th colSpan="8">0</th>
+              <th colSpan="8">1</th>
+              <th colSpan="8">2</th>
+              <th colSpan="8">3</th>
+            </tr>
+
+            <tr>
+              <th>Bit</th>
+              {[...Array(32)].map((_, i) => (
+                <th key={i}>{i}</th>
+              ))}
+            </tr>
+
+            <tr>
+              <th>0</th>
+              <td colSpan="16"><i>Source Port:</i> {sctp.src_port}</td>
+              <td colSpan="16"><i>Destination Port:</i> {sctp.dst_port}</td>
+            </tr>
+
+            <tr>
+              <th>32</th>
+              <td colSpan="32"><i>Verification Tag:</i> {hex32(sctp.vtag)}</td>
+            </tr>
+
+            <tr>
+              <th>64</th>
+              <td colSpan="32"><i>Checksum (CRC32c):</i> {hex32(sctp.checksum)}
+                <ChecksumStatus status={sctp.checksum_status} />
+              </td>
+            </tr>
+          </tbody>
+        </table>
+
+        <table className="table table-bordered table-sm mt-3" style={{ fontSize: "14px" }}>
+          <thead>
+            <tr>
+              <th>Chunk</th>
+              <th>Flags</th>
+              <th>Length</th>
+              <th>Value</th>
+            </tr>
+          </thead>
+          <tbody>
+            {sctp.chunks.map((chunk, idx) => (
+              <tr key={idx}>
+                <td>{chunk.name} [{chunk.chunk_type}]</td>
+                <td>0x{chunk.flags.toString(16).padStart(2, "0")}</td>
+                <td>{chunk.length}</td>
+                <td>{chunkToStr(chunk)}</td>
+              </tr>
+            ))}
+          </tbody>
+        </table>
+
+        <SctpAssociation assoc={sctp.association} />
+      </div>
+    </div>
+  );
+}
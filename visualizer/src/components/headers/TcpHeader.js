@@ -1,6 +1,80 @@
 import React, { useState } from "react";
 import "./ip.css";
 import HexDump from '../hex-dump/HexDump';
+import ChecksumStatus from './ChecksumStatus';
+
+export const TCP_ANALYSIS = {
+  retransmission: "TCP Retransmission",
+  out_of_order: "TCP Out-Of-Order",
+  zero_window: "TCP ZeroWindow",
+  previous_segment_missing: "TCP Previous segment not captured",
+};
+
+export const analysisToStr = (analysis) =>
+  (analysis || []).map((a) => TCP_ANALYSIS[a] || a).join(", ");
+
+// "MSS=1460", "WS=128", "SACK 200-300", "TSval=1 TSecr=0"
+const optionToStr = (opt) => {
+  const v = opt.value;
+  if (v?.Mss !== undefined) return `MSS=${v.Mss}`;
+  if (v?.WindowScale) return `WS=${v.WindowScale.multiplier} (shift ${v.WindowScale.shift})`;
+  if (v?.Sack) return "SACK " + v.Sack.map((b) => `${b.left}-${b.right}`).join(", ");
+  if (v?.Timestamp) return `TSval=${v.Timestamp.tsval} TSecr=${v.Timestamp.tsecr}`;
+  return opt.name;
+};
+
+const optionsToStr = (options) =>
+  (options || []).filter((o) => o.kind > 1).map(optionToStr).join(", ");
+
+const nsToStr = (ns) => (ns == null ? "-" : `${(ns / 1e6).toFixed(3)} ms`);
+const pktRef = (id) => (id == null ? "-" : `#${id}`);
+
+function TcpHandshake({ hs }) {
+  if (!hs) return null;
+
+  return (
+    <table className="table table-bordered table-sm mt-3" style={{ fontSize: "14px" }}>
+      <tbody>
+        <tr>
+          <th colSpan="2" style={{textAlign: "Center"}}>
+            <b>Connection (stream {hs.stream})</b>
+          </th>
+        </tr>
+        <tr>
+          <th>Client / Server</th>
+          <td>{hs.client} → {hs.server}</td>
+        </tr>
+        <tr>
+          <th>Handshake</th>
+          <td>
+            SYN {pktRef(hs.syn)}, SYN-ACK {pktRef(hs.syn_ack)} (+{nsToStr(hs.syn_to_syn_ack_ns)}),
+            ACK {pktRef(hs.ack)} (+{nsToStr(hs.syn_ack_to_ack_ns)}), total {nsToStr(hs.handshake_ns)}
+          </td>
+        </tr>
+        <tr>
+          <th>MSS</th>
+          <td>{hs.mss ?? "-"} (client {hs.client_mss ?? "-"}, server {hs.server_mss ?? "-"})</td>
+        </tr>
+        <tr>
+          <th>Window Scale</th>
+          <td>
+            {hs.window_scaling ? "on" : "off"} (client {hs.client_window_scale ?? "-"}, server {hs.server_window_scale ?? "-"})
+            {hs.sack_permitted && ", SACK permitted"}
+          </td>
+        </tr>
+        <tr>
+          <th>Teardown</th>
+          <td>
+            {hs.teardown}
+            {hs.rst != null && ` (RST ${pktRef(hs.rst)})`}
+            {(hs.fin_client != null || hs.fin_server != null) &&
+              ` (FIN client ${pktRef(hs.fin_client)}, server ${pktRef(hs.fin_server)})`}
+          </td>
+        </tr>
+      </tbody>
+    </table>
+  );
+}
 
 
 export default function TcpHeader({ tcp }) {
@@ -75,18 +149,7 @@ export default function TcpHeader({ tcp }) {
                     <tr>
                       <th>Flags</th>
                       <td>
-                        {[
-                          tcp.flags & 0x01 ? "FIN " : "",
-                          tcp.flags & 0x02 ? "SYN " : "",
-                          tcp.flags & 0x04 ? "RST " : "",
-                          tcp.flags & 0x08 ? "PSH " : "",
-
-                          tcp.flags & 0x10 ? "ACK " : "",
-                          tcp.flags & 0x20 ? "URG " : "",
-                          tcp.flags & 0x40 ? "ECE " : "",
-                          tcp.flags & 0x80 ? "CWR " : "",
-                        ].filter(Boolean).join(" ") || "0"
-                        }
+                        {tcp.flags_str} (0x{tcp.flags.toString(16).padStart(2, "0")})
                       </td>
                     </tr>
 
@@ -101,6 +164,7 @@ export default function TcpHeader({ tcp }) {
                         0x{tcp.ack != null ? tcp.checksum.toString(16)
                         .toUpperCase().padStart(4, "0")
                         : "-"}
+                        <ChecksumStatus status={tcp.checksum_status} />
                       </td>
                     </tr>
 
@@ -109,6 +173,20 @@ export default function TcpHeader({ tcp }) {
                       <td>{tcp.urgent}</td>
                     </tr>
 
+                    {tcp.options?.length > 0 && (
+                      <tr>
+                        <th>Options</th>
+                        <td>{optionsToStr(tcp.options) || "-"}</td>
+                      </tr>
+                    )}
+
+                    {tcp.analysis?.length > 0 && (
+                      <tr>
+                        <th>Analysis</th>
+                        <td className="text-danger">{analysisToStr(tcp.analysis)}</td>
+                      </tr>
+                    )}
+
                   {tcp.payload ? (
                     <>
                       <tr>
@@ -126,6 +204,7 @@ export default function TcpHeader({ tcp }) {
 
                   </tbody>
                 </table>
+                <TcpHandshake hs={tcp.handshake} />
               </div>
 
               <div style={{ 
@@ -204,18 +283,7 @@ export default function TcpHeader({ tcp }) {
                   <td colSpan="4"><i>Header Size:</i> {tcp.header_sz}</td>
                   <td colSpan="4"><i>Reserved</i></td>
                   <td colSpan="8"><i></i>{" "}
-                    {[
-                    tcp.flags & 0x01 ? "FIN " : "",
-                    tcp.flags & 0x02 ? "SYN " : "",
-                    tcp.flags & 0x04 ? "RST " : "",
-                    tcp.flags & 0x08 ? "PSH " : "",
-
-                    tcp.flags & 0x10 ? "ACK " : "",
-                    tcp.flags & 0x20 ? "URG " : "",
-                    tcp.flags & 0x40 ? "ECE " : "",
-                    tcp.flags & 0x80 ? "CWR " : "",
-                    ].filter(Boolean).join(" ") || "0"
-                    }
+                    {tcp.flags_str}
                   </td>
                   <td colSpan="16"><i>Window:</i> {tcp.window}</td>
                 </tr>
@@ -227,6 +295,7 @@ export default function TcpHeader({ tcp }) {
                     0x{tcp.ack != null ? tcp.checksum.toString(16)
                     .toUpperCase().padStart(4, "0")
                     : "-"}
+                    <ChecksumStatus status={tcp.checksum_status} />
                   </td>
 
                   <td colSpan="16"><i>Urgent Point:</i> 
@@ -234,6 +303,20 @@ export default function TcpHeader({ tcp }) {
                   </td>
                 </tr>
 
+                {tcp.options?.length > 0 && (
+                  <tr>
+                    <th>160</th>
+                    <td colSpan="32"><i>Options:</i> {optionsToStr(tcp.options) || "-"}</td>
+                  </tr>
+                )}
+
+                {tcp.analysis?.length > 0 && (
+                  <tr>
+                    <th></th>
+                    <td colSpan="32" className="text-danger"><i>Analysis:</i> {analysisToStr(tcp.analysis)}</td>
+                  </tr>
+                )}
+
                 {tcp.payload ? (
                   <>
                     <tr>
@@ -249,6 +332,7 @@ export default function TcpHeader({ tcp }) {
               </tbody>
             </table>
           )}
+          {viewMode !== "raw" && <TcpHandshake hs={tcp.handshake} />}
         </div>
     </div>
   );
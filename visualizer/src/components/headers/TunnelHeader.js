@@ -0,0 +1,119 @@
+import React from "react";
+import "./ip.css";
+import HexDump from '../hex-dump/HexDump';
+import { layer2Rows } from './Layer2Header';
+
+
+const hex = (v, width) => v != null ? "0x" + v.toString(16).toUpperCase().padStart(width, "0") : "-";
+
+// GTP-U 확장 헤더 값
+function gtpuExtToStr(ext) {
+  const v = ext.value;
+  if (v?.PduSession) {
+    const p = v.PduSession;
+    return `${p.pdu_type_str}, QFI ${p.qfi}` +
+      `${p.rqi != null ? `, RQI ${p.rqi ? 1 : 0}` : ""}${p.ppi != null ? `, PPI ${p.ppi}` : ""}`;
+  }
+  if (v?.UdpPort != null) return `Port ${v.UdpPort}`;
+  if (v?.PdcpPduNumber != null) return `${v.PdcpPduNumber}`;
+  if (v?.Raw) return `${v.Raw.length} bytes`;
+  return "-";
+}
+
+// rows to show for each encapsulation header, as [label, value]
+function tunnelRows(kind, h) {
+  switch (kind) {
+    case "Gre":
+      return [
+        ["Version", h.version],
+        ["Protocol", `${h.protocol_str} (${hex(h.protocol, 4)})`],
+        ["Checksum", hex(h.checksum, 4)],
+        ["Key", hex(h.key, 8)],
+        ["Sequence", h.seq ?? "-"],
+        ...(h.version === 1 ? [["Acknowledgment", h.ack ?? "-"]] : []),
+      ];
+    case "Erspan":
+      return [
+        ["Type", h.erspan_type],
+        ["Session ID", h.session_id],
+        ["VLAN", h.vlan],
+        ["COS", h.cos],
+        ["Truncated", h.truncated ? "yes" : "no"],
+        ...(h.erspan_type === 2
+          ? [["Index", h.index]]
+          : [
+              ["Timestamp", h.timestamp],
+              ["SGT", h.sgt],
+              ["Frame Type", h.frame_type === 2 ? "IP" : "Ethernet"],
+              ["Hardware ID", h.hw_id],
+              ["Direction", h.direction ? "Egress" : "Ingress"],
+            ]),
+      ];
+    case "Vxlan":
+      return [
+        ["Flags", hex(h.flags, 2)],
+        ["VNI", h.vni],
+        ["UDP Ports", `${h.src_port} → ${h.dst_port}`],
+      ];
+    case "Gtpu":
+      return [
+        ["Version", h.version],
+        ["Flags", `${hex(h.flags, 2)} (${[h.e_flag && "E", h.s_flag && "S", h.pn_flag && "PN"].filter(Boolean).join("") || "-"})`],
+        ["Message Type", `${h.msg_type_str} (${h.msg_type})`],
+        ["Length", h.msg_len],
+        ["TEID", hex(h.teid, 8)],
+        ["Sequence", h.seq ?? "-"],
+        ["N-PDU Number", h.npdu ?? "-"],
+        ...h.ext_headers.map((ext) => [`${ext.name} (${hex(ext.ext_type, 2)})`, gtpuExtToStr(ext)]),
+        ...h.ies.map((ie) => [`${ie.name} [${ie.ie_type}]`, ie.value]),
+        ["UDP Ports", `${h.src_port} → ${h.dst_port}`],
+      ];
+    case "Ethernet":
+      return layer2Rows(h);
+    default:
+      return [];
+  }
+}
+
+const TITLES = {
+  Gre: "GRE",
+  Erspan: "ERSPAN",
+  Vxlan: "VXLAN",
+  Gtpu: "GTP-U",
+  Ethernet: "Inner Ethernet",
+};
+
+
+export default function TunnelHeader({ kind, header }) {
+  if (!header) return null;
+
+  return (
+    <div className="card mb-3">
+      <div className="card-header ip-header">
+        <strong>Tunnel ({TITLES[kind]})</strong>
+      </div>
+      <div className="card-body ip-card-body">
+        <div style={{ display: "flex", gap: "15px" }}>
+          <div style={{ flex: "0 0 600px" }}>
+            <table className="table table-bordered table-sm" style={{ fontSize: "14px" }}>
+              <tbody>
+                {tunnelRows(kind, header).map(([label, value], idx) => (
+                  <tr key={idx}>
+                    <th>{label}</th>
+                    <td>{value}</td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+
+          {header.raw && (
+            <div style={{ flex: "1 1 auto", overflowX: "auto" }}>
+              <HexDump raw={header.raw} />
+            </div>
+          )}
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -1,6 +1,7 @@
 import React, { useState } from "react";
 import "./ip.css";
 import HexDump from '../hex-dump/HexDump';
+import ChecksumStatus from './ChecksumStatus';
 
 
 export default function UdpHeader({ udp }) {
@@ -66,6 +67,7 @@ export default function UdpHeader({ udp }) {
                     <th>Checksum</th>
                     <td>
                       0x{udp.checksum != null ? udp.checksum.toString(16).toUpperCase().padStart(4, "0") : "-"}
+                      <ChecksumStatus status={udp.checksum_status} />
                     </td>
                   </tr>
 
@@ -146,6 +148,7 @@ export default function UdpHeader({ udp }) {
                 <td colSpan="16"><i>Checksum:0x</i>
                   {/* {udp.checksum} */}
                   {udp.checksum != null ? udp.checksum.toString(16).toUpperCase().padStart(4, "0") : "-"}
+                  <ChecksumStatus status={udp.checksum_status} />
                 </td>
               </tr>
 